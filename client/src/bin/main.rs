@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use cicero::views::category::WithCountResponse;
+use cicero_client::directory::{TemplateDir, METADATA_FILE};
+use cicero_client::{Client, Error, Result};
+use cicero_dsl::compiler::compile_types;
+use clap::{Parser, Subcommand};
+
+/// Pushes templates kept in git to a Cicero server and pulls them back.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Origin of the server.
+    #[arg(
+        long,
+        global = true,
+        env = "CICERO_SERVER",
+        default_value = "http://localhost:5150"
+    )]
+    server: String,
+    /// API token with the `templates:read` and `templates:write` scopes.
+    #[arg(long, global = true, env = "CICERO_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Creates or updates the template of the directory.
+    Push { dir: PathBuf },
+    /// Downloads the template into the directory.
+    Pull { id: i32, dir: PathBuf },
+    /// Compiles the DSL file without a server.
+    Validate { dsl: PathBuf },
+    /// Lists the categories with their IDs for the metadata.
+    Categories,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            if let Error::Api { error, .. } = &err {
+                for field in &error.fields {
+                    eprintln!("  {}: {}", field.field, field.message);
+                }
+            }
+            ExitCode::FAILURE
+        },
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let mut client = Client::new(cli.server);
+    if let Some(token) = cli.token {
+        client = client.with_token(token);
+    }
+
+    match cli.command {
+        Command::Push { dir } => {
+            let mut template = TemplateDir::read(&dir)?;
+            let is_new = template.metadata.id.is_none();
+            let id = template.push(&client).await?;
+            // the next push updates the same template from this revision
+            template.write_metadata(&dir)?;
+            if is_new {
+                println!(
+                    "Created the template {id}, its ID is saved to {}",
+                    dir.join(METADATA_FILE).display()
+                );
+            } else {
+                println!("Updated the template {id}");
+            }
+        },
+        Command::Pull { id, dir } => {
+            TemplateDir::pull(&client, id).await?.write(&dir)?;
+            println!("Pulled the template {id} into {}", dir.display());
+        },
+        Command::Validate { dsl } => {
+            let source = fs::read_to_string(&dsl).map_err(|source| {
+                Error::Io {
+                    path: dsl.clone(),
+                    source,
+                }
+            })?;
+            let vars = compile_types(&source).map_err(Error::InvalidDsl)?;
+            for name in vars.keys() {
+                println!("{name}");
+            }
+        },
+        Command::Categories => {
+            let categories = client.list_categories().await?;
+            let mut children = BTreeMap::<Option<i32>, Vec<&WithCountResponse>>::new();
+            for category in &categories {
+                children
+                    .entry(category.parent_id)
+                    .or_default()
+                    .push(category);
+            }
+            print_categories(&children, None, 0);
+        },
+    }
+
+    Ok(())
+}
+
+/// Prints the tree of the categories, subcategories are indented.
+fn print_categories(
+    children: &BTreeMap<Option<i32>, Vec<&WithCountResponse>>,
+    parent_id: Option<i32>,
+    depth: usize,
+) {
+    for category in children.get(&parent_id).into_iter().flatten() {
+        println!(
+            "{:>5}  {}{} ({})",
+            category.id,
+            "  ".repeat(depth),
+            category.name,
+            category.template_count
+        );
+        print_categories(children, Some(category.id), depth + 1);
+    }
+}
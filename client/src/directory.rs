@@ -0,0 +1,217 @@
+//! Templates as directories, so authors keep them in git and deploy them
+//! from CI:
+//!
+//! ```text
+//! lease/
+//!   template.toml  # name, description, categories and publicity
+//!   template.docx
+//!   template.dsl
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use cicero::controllers::templates::{CreateTemplateParams, PublicityParams};
+use cicero::views::template::{PublicityResponse, WithCategoriesResponse};
+use cicero_dsl::compiler::{compile_types, VarEnv};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Error, Result};
+
+pub const METADATA_FILE: &str = "template.toml";
+pub const DOCX_FILE: &str = "template.docx";
+pub const DSL_FILE: &str = "template.dsl";
+
+/// Contents of [`METADATA_FILE`], e.g.
+///
+/// ```toml
+/// id = 12
+/// revision = 3
+/// name = "Договор аренды"
+/// description = ""
+/// categories = [1]
+/// publicity = "private"
+/// viewers = ["lawyer@example.com"]
+/// ```
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Metadata {
+    /// The template on the server, that is updated by the push. Without it
+    /// the push creates a new template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    /// The revision on the server, that the files are based on. The push is
+    /// rejected, if someone changed the template since.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i32>,
+    #[serde(flatten)]
+    pub params: CreateTemplateParams,
+}
+
+#[derive(Debug)]
+pub struct TemplateDir {
+    pub metadata: Metadata,
+    pub docx: Vec<u8>,
+    pub dsl: String,
+}
+
+impl TemplateDir {
+    /// # Errors
+    ///
+    /// When a file is missing or the metadata is invalid
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(METADATA_FILE);
+        let metadata = toml::from_str(&read_to_string(&path)?)
+            .map_err(|source| Error::InvalidMetadata { path, source })?;
+
+        let path = dir.join(DOCX_FILE);
+        let docx = fs::read(&path).map_err(|source| Error::Io { path, source })?;
+
+        let dsl = read_to_string(&dir.join(DSL_FILE))?;
+
+        Ok(Self {
+            metadata,
+            docx,
+            dsl,
+        })
+    }
+
+    /// Writes the files, the directory is created if needed.
+    ///
+    /// # Errors
+    ///
+    /// When the files can't be written
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).map_err(|source| {
+            Error::Io {
+                path: dir.to_path_buf(),
+                source,
+            }
+        })?;
+
+        self.write_metadata(dir)?;
+        for (path, contents) in [
+            (dir.join(DOCX_FILE), self.docx.as_slice()),
+            (dir.join(DSL_FILE), self.dsl.as_bytes()),
+        ] {
+            fs::write(&path, contents).map_err(|source| Error::Io { path, source })?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes only [`METADATA_FILE`], e.g. with the new revision after a
+    /// push.
+    ///
+    /// # Errors
+    ///
+    /// When the file can't be written
+    pub fn write_metadata(&self, dir: &Path) -> Result<()> {
+        let metadata = toml::to_string(&self.metadata)?;
+        let path = dir.join(METADATA_FILE);
+        fs::write(&path, metadata).map_err(|source| Error::Io { path, source })
+    }
+
+    /// Compiles the DSL locally, the same way the server does on upload.
+    ///
+    /// # Errors
+    ///
+    /// When the DSL is invalid
+    pub fn validate(&self) -> Result<VarEnv> {
+        compile_types(&self.dsl).map_err(Error::InvalidDsl)
+    }
+
+    /// Creates or updates the template and returns its ID. The DSL is
+    /// validated first, so a broken template isn't uploaded. The metadata
+    /// gets the ID and the revision of the pushed template.
+    ///
+    /// # Errors
+    ///
+    /// When the DSL is invalid, the revision is missing or outdated, the
+    /// server rejects the template or the request failed
+    pub async fn push(&mut self, client: &Client) -> Result<i32> {
+        self.validate()?;
+
+        let docx = self.docx.clone();
+        let (id, revision) = match self.metadata.id {
+            Some(id) => {
+                let revision = self.metadata.revision.ok_or(Error::MissingRevision)?;
+                let template = client
+                    .update_template(id, revision, &self.metadata.params, docx, &self.dsl)
+                    .await?;
+                (template.id, template.revision)
+            },
+            None => {
+                let created = client
+                    .create_template(&self.metadata.params, docx, &self.dsl)
+                    .await?;
+                (created.id, created.revision)
+            },
+        };
+
+        self.metadata.id = Some(id);
+        self.metadata.revision = Some(revision);
+        Ok(id)
+    }
+
+    /// Downloads the template, so pushing it back updates it.
+    ///
+    /// # Errors
+    ///
+    /// When the template is not found or the request failed
+    pub async fn pull(client: &Client, id: i32) -> Result<Self> {
+        let template = client.get_template(id).await?;
+        let docx = client.download_docx(id).await?;
+        let dsl = client.download_dsl(id).await?;
+
+        Ok(Self {
+            metadata: Metadata::new(template),
+            docx,
+            dsl,
+        })
+    }
+}
+
+impl Metadata {
+    #[must_use]
+    pub fn new(template: WithCategoriesResponse) -> Self {
+        let publicity = match template.publicity {
+            PublicityResponse::Public => PublicityParams::Public,
+            PublicityResponse::Private {
+                viewers,
+                organizations,
+            } => {
+                PublicityParams::Private {
+                    viewers: viewers.into_iter().map(|viewer| viewer.email).collect(),
+                    organizations: organizations
+                        .into_iter()
+                        .map(|organization| organization.id)
+                        .collect(),
+                }
+            },
+        };
+
+        Self {
+            id: Some(template.id),
+            revision: Some(template.revision),
+            params: CreateTemplateParams {
+                name: template.name,
+                description: template.description,
+                categories: template
+                    .categories
+                    .into_iter()
+                    .map(|category| category.id)
+                    .collect(),
+                publicity,
+            },
+        }
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|source| {
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    })
+}
@@ -0,0 +1,298 @@
+//! Typed client for the Cicero REST API. Requests and responses are the
+//! serde types of the server, so both sides change together.
+
+use std::path::PathBuf;
+
+use cicero::controllers::auth::RefreshParams;
+use cicero::controllers::templates::{CreateTemplateParams, ListParams};
+use cicero::models::users::LoginParams;
+use cicero::views::auth::LoginResponse;
+use cicero::views::category::WithCountResponse;
+use cicero::views::error::ErrorResponse;
+use cicero::views::template::{CreateResponse, PageResponse, WithCategoriesResponse};
+use cicero_dsl::compiler::VarEnv;
+use cicero_dsl::types::Var;
+use reqwest::header::IF_MATCH;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+pub mod directory;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The server rejected the request, e.g. with a validation error.
+    #[error("{status}: {}", error.message)]
+    Api {
+        status: StatusCode,
+        error: ErrorResponse,
+    },
+    /// The server is unreachable or responded with something unexpected.
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// The tokens can be refreshed only after [`Client::login`].
+    #[error("no refresh token, log in first")]
+    NotLoggedIn,
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    InvalidMetadata {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error(transparent)]
+    Toml(#[from] toml::ser::Error),
+    /// The DSL doesn't compile, checked before the upload.
+    #[error("invalid DSL: {0}")]
+    InvalidDsl(String),
+    /// The metadata refers to a template, but not to its revision, so the
+    /// push could overwrite changes on the server.
+    #[error("the metadata has an `id`, but no `revision`, pull the template first")]
+    MissingRevision,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    refresh_token: Option<String>,
+}
+
+impl Client {
+    /// The `base_url` is the origin of the server, e.g.
+    /// `https://cicero.example.com`.
+    #[must_use]
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url: String = base_url.into();
+
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+            refresh_token: None,
+        }
+    }
+
+    /// Authenticates the requests with the access token or the API token,
+    /// so services don't need the password of the user.
+    #[must_use]
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    #[must_use]
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Starts a session, the following requests use its access token.
+    ///
+    /// # Errors
+    ///
+    /// When the credentials or the two-factor code are invalid or the request
+    /// failed
+    pub async fn login(&mut self, params: &LoginParams) -> Result<LoginResponse> {
+        let response: LoginResponse =
+            Self::json(self.request(Method::POST, "/api/auth/login").json(params)).await?;
+
+        self.set_session(&response);
+        Ok(response)
+    }
+
+    /// Replaces the tokens of the session with new ones, e.g. when the access
+    /// token has expired.
+    ///
+    /// # Errors
+    ///
+    /// When the client isn't logged in, the session is revoked or the request
+    /// failed
+    pub async fn refresh(&mut self) -> Result<LoginResponse> {
+        let refresh_token = self.refresh_token.clone().ok_or(Error::NotLoggedIn)?;
+
+        let response: LoginResponse = Self::json(
+            self.request(Method::POST, "/api/auth/refresh")
+                .json(&RefreshParams { refresh_token }),
+        )
+        .await?;
+
+        self.set_session(&response);
+        Ok(response)
+    }
+
+    /// # Errors
+    ///
+    /// When the cursor is invalid or the request failed
+    pub async fn list_templates(&self, params: &ListParams) -> Result<PageResponse> {
+        Self::json(self.request(Method::GET, "/api/templates").query(params)).await
+    }
+
+    /// The categories with the number of visible templates in each.
+    ///
+    /// # Errors
+    ///
+    /// When the request failed
+    pub async fn list_categories(&self) -> Result<Vec<WithCountResponse>> {
+        Self::json(self.request(Method::GET, "/api/categories")).await
+    }
+
+    /// # Errors
+    ///
+    /// When the template is not found or not visible or the request failed
+    pub async fn get_template(&self, id: i32) -> Result<WithCategoriesResponse> {
+        Self::json(self.request(Method::GET, &format!("/api/templates/{id}"))).await
+    }
+
+    /// # Errors
+    ///
+    /// When the params or the DSL are invalid or the request failed
+    pub async fn create_template(
+        &self,
+        params: &CreateTemplateParams,
+        docx: Vec<u8>,
+        dsl: &str,
+    ) -> Result<CreateResponse> {
+        let form = template_form(params, docx, dsl)?;
+
+        Self::json(self.request(Method::POST, "/api/templates").multipart(form)).await
+    }
+
+    /// Replaces the template, unless it was changed since the `revision`.
+    ///
+    /// # Errors
+    ///
+    /// When the template is not found or was changed, the params or the DSL
+    /// are invalid or the request failed
+    pub async fn update_template(
+        &self,
+        id: i32,
+        revision: i32,
+        params: &CreateTemplateParams,
+        docx: Vec<u8>,
+        dsl: &str,
+    ) -> Result<WithCategoriesResponse> {
+        let form = template_form(params, docx, dsl)?;
+
+        Self::json(
+            self.request(Method::PUT, &format!("/api/templates/{id}"))
+                .header(IF_MATCH, format!("\"{revision}\""))
+                .multipart(form),
+        )
+        .await
+    }
+
+    /// Removes the template, unless it was changed since the `revision`.
+    ///
+    /// # Errors
+    ///
+    /// When the template is not found or was changed or the request failed
+    pub async fn delete_template(&self, id: i32, revision: i32) -> Result<()> {
+        Self::send(
+            self.request(Method::DELETE, &format!("/api/templates/{id}"))
+                .header(IF_MATCH, format!("\"{revision}\"")),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// When the template is not found or the request failed
+    pub async fn download_docx(&self, id: i32) -> Result<Vec<u8>> {
+        let response =
+            Self::send(self.request(Method::GET, &format!("/api/templates/{id}/docx"))).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// # Errors
+    ///
+    /// When the template is not found or the request failed
+    pub async fn download_dsl(&self, id: i32) -> Result<String> {
+        let response =
+            Self::send(self.request(Method::GET, &format!("/api/templates/{id}/dsl"))).await?;
+        Ok(response.text().await?)
+    }
+
+    /// The variables of the template, in the order of the DSL.
+    ///
+    /// # Errors
+    ///
+    /// When the template is not found or the request failed
+    pub async fn dsl_types(&self, id: i32) -> Result<Vec<Var>> {
+        Self::json(self.request(Method::GET, &format!("/api/templates/{id}/dsl/types"))).await
+    }
+
+    /// Compiles the DSL without saving it.
+    ///
+    /// # Errors
+    ///
+    /// When the DSL is invalid, [`ErrorResponse::fields`] point to the
+    /// problems, or the request failed
+    pub async fn validate(&self, dsl: &str) -> Result<VarEnv> {
+        let form = Form::new().text("dsl", dsl.to_string());
+
+        Self::json(
+            self.request(Method::POST, "/api/templates/validate")
+                .multipart(form),
+        )
+        .await
+    }
+
+    fn set_session(&mut self, response: &LoginResponse) {
+        self.token = Some(response.token.clone());
+        self.refresh_token = Some(response.refresh_token.clone());
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{path}", self.base_url));
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        // errors of the proxy or of axum itself aren't JSON
+        let text = response.text().await?;
+        let error = serde_json::from_str(&text).unwrap_or_else(|_| {
+            ErrorResponse {
+                code: status.as_str().to_string(),
+                message: text,
+                fields: Vec::new(),
+            }
+        });
+
+        Err(Error::Api { status, error })
+    }
+
+    async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        Ok(Self::send(request).await?.json().await?)
+    }
+}
+
+/// The multipart form, that the server expects on create and update.
+fn template_form(params: &CreateTemplateParams, docx: Vec<u8>, dsl: &str) -> Result<Form> {
+    let form = Form::new()
+        .text("json", serde_json::to_string(params)?)
+        .part("docx", Part::bytes(docx).file_name("template.docx"))
+        .text("dsl", dsl.to_string());
+
+    Ok(form)
+}
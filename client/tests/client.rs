@@ -0,0 +1,285 @@
+use std::io::{Cursor, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+
+use axum_test::TestServer;
+use cicero::app::App;
+use cicero::controllers::templates::{CreateTemplateParams, ListParams, PublicityParams};
+use cicero::models::users::{self, LoginParams, RegisterParams};
+use cicero_client::directory::{TemplateDir, DSL_FILE, METADATA_FILE};
+use cicero_client::{Client, Error};
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use reqwest::StatusCode;
+use sea_orm::IntoActiveModel;
+use serial_test::serial;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+";
+
+const UPDATED_DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+    /// Арендодатель
+    let landlord: String;
+";
+
+/// A docx with a single paragraph of the text.
+fn docx(text: &str) -> Vec<u8> {
+    let document = format!(
+        r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body><w:p><w:r><w:t>{text}</w:t></w:r></w:p></w:body></w:document>"#
+    );
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("word/document.xml", SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(document.as_bytes()).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+/// Serves the app over HTTP, so the client talks to it like to a deployed
+/// server.
+async fn serve() -> (TestServer, loco_rs::app::AppContext) {
+    // the config and the template files are relative to the workspace root
+    std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("..")).unwrap();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let server = TestServer::builder()
+        .http_transport()
+        .build(
+            boot.router
+                .unwrap()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .unwrap();
+
+    (server, boot.app_context)
+}
+
+async fn register(ctx: &loco_rs::app::AppContext) {
+    let params = RegisterParams {
+        email: "test@loco.com".to_string(),
+        password: "1234".to_string(),
+        name: "Тест".to_string(),
+    };
+    users::Model::create_with_password(&ctx.db, &params)
+        .await
+        .unwrap()
+        .into_active_model()
+        .verified(&ctx.db)
+        .await
+        .unwrap();
+}
+
+fn params(name: &str) -> CreateTemplateParams {
+    CreateTemplateParams {
+        name: name.to_string(),
+        description: String::new(),
+        categories: Vec::new(),
+        publicity: PublicityParams::Public,
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_manage_templates() {
+    let (server, ctx) = serve().await;
+    register(&ctx).await;
+
+    let mut client = Client::new(server.server_address().unwrap().as_str());
+    let login = client
+        .login(&LoginParams {
+            email: "test@loco.com".to_string(),
+            password: "1234".to_string(),
+            code: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(client.token(), Some(login.token.as_str()));
+
+    let created = client
+        .create_template(&params("Договор аренды"), docx("Договор"), DSL)
+        .await
+        .unwrap();
+
+    let page = client
+        .list_templates(&ListParams {
+            search: Some("аренды".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.templates[0].id, created.id);
+
+    let updated = client
+        .update_template(
+            created.id,
+            created.revision,
+            &params("Договор субаренды"),
+            docx("Договор {{landlord}}"),
+            UPDATED_DSL,
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.name, "Договор субаренды");
+    assert_eq!(
+        client.get_template(created.id).await.unwrap().name,
+        "Договор субаренды"
+    );
+
+    assert_eq!(
+        client.download_docx(created.id).await.unwrap(),
+        docx("Договор {{landlord}}")
+    );
+    assert_eq!(client.download_dsl(created.id).await.unwrap(), UPDATED_DSL);
+    let types = client.dsl_types(created.id).await.unwrap();
+    assert_eq!(
+        types
+            .iter()
+            .map(|var| var.name.as_str())
+            .collect::<Vec<_>>(),
+        ["tenant", "landlord"]
+    );
+
+    // the refreshed access token replaces the old one
+    let refreshed = client.refresh().await.unwrap();
+    assert_ne!(refreshed.refresh_token, login.refresh_token);
+    assert_eq!(client.token(), Some(refreshed.token.as_str()));
+
+    client
+        .delete_template(created.id, updated.revision)
+        .await
+        .unwrap();
+    let Err(Error::Api { status, .. }) = client.get_template(created.id).await else {
+        panic!("the template isn't deleted");
+    };
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[serial]
+async fn reports_api_errors() {
+    let (server, ctx) = serve().await;
+    register(&ctx).await;
+
+    let mut client = Client::new(server.server_address().unwrap().as_str());
+    let Err(Error::NotLoggedIn) = client.refresh().await else {
+        panic!("refreshed without a session");
+    };
+    let Err(Error::Api { status, error }) = client
+        .login(&LoginParams {
+            email: "test@loco.com".to_string(),
+            password: "wrong".to_string(),
+            code: None,
+        })
+        .await
+    else {
+        panic!("logged in with a wrong password");
+    };
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(!error.code.is_empty());
+
+    client
+        .login(&LoginParams {
+            email: "test@loco.com".to_string(),
+            password: "1234".to_string(),
+            code: None,
+        })
+        .await
+        .unwrap();
+
+    let types = client.validate(DSL).await.unwrap();
+    assert!(types.contains_key("tenant"));
+
+    let Err(Error::Api { status, error }) = client.validate("let tenant: Strin;").await else {
+        panic!("invalid DSL is accepted");
+    };
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(!error.fields.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn can_push_and_pull_directories() {
+    let (server, ctx) = serve().await;
+    App::seed(&ctx.db, Path::new("src/fixtures/test"))
+        .await
+        .unwrap();
+    register(&ctx).await;
+
+    let mut client = Client::new(server.server_address().unwrap().as_str());
+    client
+        .login(&LoginParams {
+            email: "test@loco.com".to_string(),
+            password: "1234".to_string(),
+            code: None,
+        })
+        .await
+        .unwrap();
+
+    let categories = client.list_categories().await.unwrap();
+    assert!(!categories.is_empty());
+
+    let dir = tempfile::tempdir().unwrap();
+    let metadata = format!(
+        r#"
+            name = "Договор аренды"
+            description = ""
+            categories = [{}]
+            publicity = "private"
+            viewers = []
+        "#,
+        categories[0].id
+    );
+    std::fs::write(dir.path().join(METADATA_FILE), metadata).unwrap();
+    std::fs::write(dir.path().join("template.docx"), docx("Договор")).unwrap();
+    std::fs::write(dir.path().join(DSL_FILE), DSL).unwrap();
+
+    let mut template = TemplateDir::read(dir.path()).unwrap();
+    let id = template.push(&client).await.unwrap();
+    assert_eq!(template.metadata.id, Some(id));
+
+    template.dsl = UPDATED_DSL.to_string();
+    assert_eq!(template.push(&client).await.unwrap(), id);
+    assert_eq!(template.metadata.revision, Some(2));
+
+    // a push from an outdated copy doesn't overwrite the server
+    let mut outdated = TemplateDir::read(dir.path()).unwrap();
+    outdated.metadata.id = Some(id);
+    outdated.metadata.revision = Some(1);
+    let Err(Error::Api { status, .. }) = outdated.push(&client).await else {
+        panic!("the outdated copy is pushed");
+    };
+    assert_eq!(status, 412);
+
+    // the pulled directory pushes back to the same template
+    let pulled_dir = tempfile::tempdir().unwrap();
+    TemplateDir::pull(&client, id)
+        .await
+        .unwrap()
+        .write(pulled_dir.path())
+        .unwrap();
+    let pulled = TemplateDir::read(pulled_dir.path()).unwrap();
+    assert_eq!(pulled.metadata.id, Some(id));
+    assert_eq!(pulled.metadata.params.name, "Договор аренды");
+    assert_eq!(pulled.metadata.params.categories, [categories[0].id]);
+    assert!(matches!(
+        pulled.metadata.params.publicity,
+        PublicityParams::Private { .. }
+    ));
+    assert_eq!(pulled.docx, docx("Договор"));
+    assert_eq!(pulled.dsl, UPDATED_DSL);
+
+    // the broken DSL isn't uploaded
+    template.dsl = "let tenant: Strin;".to_string();
+    let Err(Error::InvalidDsl(_)) = template.push(&client).await else {
+        panic!("invalid DSL is pushed");
+    };
+    assert_eq!(client.download_dsl(id).await.unwrap(), UPDATED_DSL);
+}
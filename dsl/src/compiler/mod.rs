@@ -16,6 +16,10 @@ use crate::types;
 
 pub type VarEnv = IndexMap<String, types::Var>;
 
+/// Version of the compiler output. Bump it, when the same source compiles to
+/// different types, so types cached by the server are compiled again.
+pub const VERSION: i32 = 1;
+
 mod ast;
 mod grammar;
 mod lexer;
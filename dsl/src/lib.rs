@@ -10,4 +10,6 @@
  */
 
 pub mod compiler;
+pub mod path;
 pub mod types;
+pub mod validation;
@@ -0,0 +1,305 @@
+/*
+ * Copyright (C) 2024 Kirill Lukashev <kirill.lukashev.sic@gmail.com>,
+ * Gleb Krylov <gleb_cry@mail.ru>
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+//! Paths to single values in the data, e.g. `tenant.Individual.name`.
+//!
+//! A path starts with a variable name and continues with names of struct
+//! fields and enum variants, the same way as paths in
+//! [`ValidationError`]s. A value is filled from plain text, so the path must
+//! end with a string, an integer, an enum (the text is the name of the
+//! variant) or an array of them (the elements are separated with `;`).
+
+use serde_json::{Map, Value};
+
+use crate::compiler::VarEnv;
+use crate::types::EntityType;
+use crate::validation::{ValidationError, DISCRIMINANT, DISCRIMINANT_FIELD};
+
+/// Separator of array elements in the text.
+pub const ARRAY_SEPARATOR: char = ';';
+
+enum Step<'a> {
+    Field(&'a str),
+    Variant(&'a str),
+}
+
+fn resolve<'a>(env: &'a VarEnv, path: &'a str) -> Result<(Vec<Step<'a>>, &'a EntityType), String> {
+    let mut segments = path.split('.');
+
+    let name = segments.next().unwrap_or_default();
+    let var = env
+        .get(name)
+        .ok_or_else(|| format!("unknown variable `{name}`"))?;
+
+    let mut steps = vec![Step::Field(name)];
+    let mut ty = &var.ty.ty;
+
+    for segment in segments {
+        ty = match ty {
+            EntityType::Struct(ty) => {
+                let field = ty
+                    .get_field(segment)
+                    .ok_or_else(|| format!("unknown field `{segment}`"))?;
+                steps.push(Step::Field(segment));
+                &field.entity.ty
+            },
+            EntityType::Enum(ty) => {
+                let variant = ty
+                    .variants
+                    .iter()
+                    .find(|variant| variant.name == segment)
+                    .ok_or_else(|| format!("unknown variant `{segment}`"))?;
+                let field = variant
+                    .field
+                    .as_ref()
+                    .ok_or_else(|| format!("variant `{segment}` has no field"))?;
+                steps.push(Step::Variant(segment));
+                &field.ty
+            },
+            _ => return Err(format!("`{segment}` is inside of a value")),
+        };
+    }
+
+    if !is_fillable(ty) {
+        return Err(format!("`{path}` is not a single value"));
+    }
+
+    Ok((steps, ty))
+}
+
+fn is_fillable(ty: &EntityType) -> bool {
+    match ty {
+        EntityType::String
+        | EntityType::Integer
+        | EntityType::PhoneNumber
+        | EntityType::Date
+        | EntityType::Place
+        | EntityType::Enum(_) => true,
+        EntityType::Array(array) => {
+            !matches!(*array.ty, EntityType::Array(_)) && is_fillable(&array.ty)
+        },
+        EntityType::Struct(_) => false,
+    }
+}
+
+/// Checks that the path leads to a value, that can be filled from text.
+///
+/// # Errors
+///
+/// Returns the reason, why the path is invalid.
+pub fn check(env: &VarEnv, path: &str) -> Result<(), String> {
+    resolve(env, path).map(|_| ())
+}
+
+/// Parses the text as the value at the path and puts it into the data. Empty
+/// text is skipped, so the value stays missing.
+///
+/// # Errors
+///
+/// When the path is invalid, the text can't be parsed, or the path chooses
+/// another variant of an enum than the data already has.
+pub fn set(env: &VarEnv, data: &mut Value, path: &str, text: &str) -> Result<(), ValidationError> {
+    let error = |message: String| {
+        ValidationError {
+            path: path.to_string(),
+            message,
+        }
+    };
+
+    let (steps, ty) = resolve(env, path).map_err(error)?;
+
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let value = parse(ty, text).map_err(error)?;
+
+    let mut current = data;
+    for step in steps {
+        let object = object_mut(current);
+
+        current = match step {
+            Step::Field(name) => object.entry(name).or_insert(Value::Null),
+            Step::Variant(name) => {
+                if let Some(chosen) = object.get(DISCRIMINANT).and_then(Value::as_str) {
+                    if chosen != name {
+                        return Err(error(format!("variant `{chosen}` is already chosen")));
+                    }
+                }
+                object.insert(DISCRIMINANT.to_string(), Value::String(name.to_string()));
+                object.entry(DISCRIMINANT_FIELD).or_insert(Value::Null)
+            },
+        };
+    }
+
+    match value {
+        // the variant is merged with its field, that may be filled by another path
+        Value::Object(variant) => {
+            let object = object_mut(current);
+            if let Some(chosen) = object.get(DISCRIMINANT).and_then(Value::as_str) {
+                if Some(chosen) != variant.get(DISCRIMINANT).and_then(Value::as_str) {
+                    return Err(error(format!("variant `{chosen}` is already chosen")));
+                }
+            }
+            object.extend(variant);
+        },
+        value => *current = value,
+    }
+
+    Ok(())
+}
+
+fn object_mut(value: &mut Value) -> &mut Map<String, Value> {
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+
+    match value {
+        Value::Object(object) => object,
+        _ => unreachable!("the value was replaced with an object"),
+    }
+}
+
+fn parse(ty: &EntityType, text: &str) -> Result<Value, String> {
+    match ty {
+        EntityType::String | EntityType::PhoneNumber | EntityType::Date | EntityType::Place => {
+            Ok(Value::String(text.to_string()))
+        },
+        EntityType::Integer => {
+            text.parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("`{text}` is not an integer"))
+        },
+        EntityType::Enum(ty) => {
+            let variant = ty
+                .variants
+                .iter()
+                .find(|variant| variant.name == text)
+                .ok_or_else(|| format!("unknown variant `{text}`"))?;
+
+            let mut object = Map::new();
+            object.insert(
+                DISCRIMINANT.to_string(),
+                Value::String(variant.name.clone()),
+            );
+            Ok(Value::Object(object))
+        },
+        EntityType::Array(array) => {
+            text.split(ARRAY_SEPARATOR)
+                .map(str::trim)
+                .filter(|element| !element.is_empty())
+                .map(|element| parse(&array.ty, element))
+                .collect::<Result<_, _>>()
+                .map(Value::Array)
+        },
+        EntityType::Struct(_) => Err(format!("`{text}` can't be parsed as a struct")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::compiler::compile_types;
+
+    const SOURCE: &str = r"
+        /// Person
+        struct Person {
+            /// Name
+            name: String,
+            /// Age
+            age: Integer?,
+            /// Phones
+            phones: [PhoneNumber],
+        }
+
+        /// Kind
+        enum Kind {
+            /// Individual
+            Individual(Person),
+            /// Company
+            Company,
+        }
+
+        /// Tenant
+        let tenant: Kind;
+    ";
+
+    #[test]
+    fn check_test() {
+        let env = compile_types(SOURCE).unwrap();
+
+        assert_eq!(check(&env, "tenant"), Ok(()));
+        assert_eq!(check(&env, "tenant.Individual.phones"), Ok(()));
+        assert_eq!(
+            check(&env, "tenant.Individual"),
+            Err("`tenant.Individual` is not a single value".to_string())
+        );
+        assert_eq!(
+            check(&env, "tenant.Company.name"),
+            Err("variant `Company` has no field".to_string())
+        );
+        assert_eq!(
+            check(&env, "landlord"),
+            Err("unknown variable `landlord`".to_string())
+        );
+    }
+
+    #[test]
+    fn set_test() {
+        let env = compile_types(SOURCE).unwrap();
+        let mut data = json!({});
+
+        set(&env, &mut data, "tenant", "Individual").unwrap();
+        set(&env, &mut data, "tenant.Individual.name", "Иван").unwrap();
+        set(&env, &mut data, "tenant.Individual.age", " 30 ").unwrap();
+        set(&env, &mut data, "tenant.Individual.phones", "+7999; +7888").unwrap();
+
+        assert_eq!(
+            data,
+            json!({
+                "tenant": {
+                    "_discriminant": "Individual",
+                    "_discriminantField": {
+                        "name": "Иван",
+                        "age": 30,
+                        "phones": ["+7999", "+7888"],
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn set_errors_test() {
+        let env = compile_types(SOURCE).unwrap();
+        let mut data = json!({});
+
+        assert_eq!(
+            set(&env, &mut data, "tenant.Individual.age", "тридцать"),
+            Err(ValidationError {
+                path: "tenant.Individual.age".to_string(),
+                message: "`тридцать` is not an integer".to_string(),
+            })
+        );
+
+        set(&env, &mut data, "tenant", "Company").unwrap();
+        assert_eq!(
+            set(&env, &mut data, "tenant.Individual.name", "Иван"),
+            Err(ValidationError {
+                path: "tenant.Individual.name".to_string(),
+                message: "variant `Company` is already chosen".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,397 @@
+/*
+ * Copyright (C) 2024 Kirill Lukashev <kirill.lukashev.sic@gmail.com>,
+ * Gleb Krylov <gleb_cry@mail.ru>
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+//! Validation of the data, that is filled by the user for the scenario.
+//!
+//! The data is a JSON object with a key for each variable. Values of enums
+//! are objects with the name of the variant in `_discriminant` and the value
+//! of the variant's field in `_discriminantField`.
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::compiler::VarEnv;
+use crate::types::{Entity, EntityType, Enum, Struct};
+
+pub const DISCRIMINANT: &str = "_discriminant";
+pub const DISCRIMINANT_FIELD: &str = "_discriminantField";
+
+/// How strictly the data is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Values may be missing, even the required ones.
+    Draft,
+    /// All required values must be present.
+    Complete,
+}
+
+/// A single problem with the data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Path to the value, e.g. `tenant.passport[0]`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Checks that the data matches the types of the variables.
+///
+/// # Errors
+///
+/// Returns all found problems, if there are any.
+pub fn validate(env: &VarEnv, data: &Value, mode: Mode) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+
+    let Some(object) = data.as_object() else {
+        errors.push(ValidationError {
+            path: String::new(),
+            message: "expected an object with variables".to_string(),
+        });
+        return Err(errors);
+    };
+
+    for key in object.keys() {
+        if !env.contains_key(key) {
+            errors.push(error(key, "unknown variable"));
+        }
+    }
+
+    for (name, var) in env {
+        validate_entity(&var.ty, object.get(name), name, mode, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Keeps only the values, that still match the types of the variables, e.g.
+/// after the scenario was changed.
+#[must_use]
+pub fn retain_compatible(env: &VarEnv, data: &Value) -> Value {
+    let Some(object) = data.as_object() else {
+        return Value::Object(Map::new());
+    };
+
+    let retained = env
+        .iter()
+        .filter_map(|(name, var)| {
+            let value = object.get(name)?;
+            let value = retain_entity(&var.ty.ty, value)?;
+            Some((name.clone(), value))
+        })
+        .collect();
+
+    Value::Object(retained)
+}
+
+fn error(path: &str, message: &str) -> ValidationError {
+    ValidationError {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+const fn is_missing(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(string)) => string.is_empty(),
+        _ => false,
+    }
+}
+
+fn validate_entity(
+    entity: &Entity,
+    value: Option<&Value>,
+    path: &str,
+    mode: Mode,
+    errors: &mut Vec<ValidationError>,
+) {
+    if is_missing(value) {
+        if mode == Mode::Complete && entity.is_required {
+            errors.push(error(path, "value is required"));
+        }
+        return;
+    }
+    let Some(value) = value else {
+        return;
+    };
+
+    match &entity.ty {
+        EntityType::String | EntityType::PhoneNumber | EntityType::Date | EntityType::Place => {
+            if !value.is_string() {
+                errors.push(error(path, "expected a string"));
+            }
+        },
+        EntityType::Integer => {
+            if !value.is_i64() {
+                errors.push(error(path, "expected an integer"));
+            }
+        },
+        EntityType::Struct(ty) => validate_struct(ty, value, path, mode, errors),
+        EntityType::Enum(ty) => validate_enum(ty, value, path, mode, errors),
+        EntityType::Array(array) => {
+            let Some(elements) = value.as_array() else {
+                errors.push(error(path, "expected an array"));
+                return;
+            };
+
+            let element = Entity {
+                ty: (*array.ty).clone(),
+                is_required: true,
+            };
+            for (i, value) in elements.iter().enumerate() {
+                validate_entity(&element, Some(value), &format!("{path}[{i}]"), mode, errors);
+            }
+        },
+    }
+}
+
+fn validate_struct(
+    ty: &Struct,
+    value: &Value,
+    path: &str,
+    mode: Mode,
+    errors: &mut Vec<ValidationError>,
+) {
+    let Some(object) = value.as_object() else {
+        errors.push(error(path, "expected an object"));
+        return;
+    };
+
+    for key in object.keys() {
+        if ty.get_field(key).is_none() {
+            errors.push(error(&format!("{path}.{key}"), "unknown field"));
+        }
+    }
+
+    let mut current = Some(ty);
+    while let Some(ty) = current {
+        for field in &ty.fields {
+            validate_entity(
+                &field.entity,
+                object.get(&field.name),
+                &format!("{path}.{}", field.name),
+                mode,
+                errors,
+            );
+        }
+        current = ty.parent.as_deref();
+    }
+}
+
+fn validate_enum(
+    ty: &Enum,
+    value: &Value,
+    path: &str,
+    mode: Mode,
+    errors: &mut Vec<ValidationError>,
+) {
+    let Some(object) = value.as_object() else {
+        errors.push(error(path, "expected an object with a variant"));
+        return;
+    };
+
+    for key in object.keys() {
+        if key != DISCRIMINANT && key != DISCRIMINANT_FIELD {
+            errors.push(error(&format!("{path}.{key}"), "unknown field"));
+        }
+    }
+
+    let Some(name) = object.get(DISCRIMINANT).and_then(Value::as_str) else {
+        errors.push(error(path, "variant is not chosen"));
+        return;
+    };
+
+    let Some(variant) = ty.variants.iter().find(|variant| variant.name == name) else {
+        errors.push(error(path, "unknown variant"));
+        return;
+    };
+
+    if let Some(field) = &variant.field {
+        validate_entity(
+            field,
+            object.get(DISCRIMINANT_FIELD),
+            &format!("{path}.{name}"),
+            mode,
+            errors,
+        );
+    }
+}
+
+fn retain_entity(ty: &EntityType, value: &Value) -> Option<Value> {
+    match ty {
+        EntityType::String | EntityType::PhoneNumber | EntityType::Date | EntityType::Place => {
+            value.is_string().then(|| value.clone())
+        },
+        EntityType::Integer => value.is_i64().then(|| value.clone()),
+        EntityType::Struct(ty) => {
+            let object = value.as_object()?;
+
+            let retained = object
+                .iter()
+                .filter_map(|(key, value)| {
+                    let field = ty.get_field(key)?;
+                    let value = retain_entity(&field.entity.ty, value)?;
+                    Some((key.clone(), value))
+                })
+                .collect();
+
+            Some(Value::Object(retained))
+        },
+        EntityType::Enum(ty) => {
+            let object = value.as_object()?;
+            let name = object.get(DISCRIMINANT)?.as_str()?;
+            let variant = ty.variants.iter().find(|variant| variant.name == name)?;
+
+            let mut retained = Map::new();
+            retained.insert(DISCRIMINANT.to_string(), Value::String(name.to_string()));
+
+            if let (Some(field), Some(value)) = (&variant.field, object.get(DISCRIMINANT_FIELD)) {
+                if let Some(value) = retain_entity(&field.ty, value) {
+                    retained.insert(DISCRIMINANT_FIELD.to_string(), value);
+                }
+            }
+
+            Some(Value::Object(retained))
+        },
+        EntityType::Array(array) => {
+            let elements = value.as_array()?;
+
+            Some(Value::Array(
+                elements
+                    .iter()
+                    .filter_map(|value| retain_entity(&array.ty, value))
+                    .collect(),
+            ))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::compiler::compile_types;
+
+    const SOURCE: &str = r"
+        /// Person
+        struct Person {
+            /// Name
+            name: String,
+            /// Age
+            age: Integer?,
+            /// Phones
+            phones: [PhoneNumber],
+        }
+
+        /// Kind
+        enum Kind {
+            /// Individual
+            Individual(Person),
+            /// Company
+            Company,
+        }
+
+        /// Tenant
+        let tenant: Kind;
+        /// City
+        let city: Place?;
+    ";
+
+    #[test]
+    fn valid_data_test() {
+        let env = compile_types(SOURCE).unwrap();
+        let data = json!({
+            "tenant": {
+                "_discriminant": "Individual",
+                "_discriminantField": {
+                    "name": "Иван",
+                    "age": 30,
+                    "phones": ["+79990000000"],
+                },
+            },
+        });
+
+        assert_eq!(validate(&env, &data, Mode::Complete), Ok(()));
+    }
+
+    #[test]
+    fn draft_allows_missing_values_test() {
+        let env = compile_types(SOURCE).unwrap();
+        let data = json!({ "tenant": { "_discriminant": "Individual", "_discriminantField": {} } });
+
+        assert_eq!(validate(&env, &data, Mode::Draft), Ok(()));
+        assert_eq!(
+            validate(&env, &data, Mode::Complete),
+            Err(vec![
+                error("tenant.Individual.name", "value is required"),
+                error("tenant.Individual.phones", "value is required"),
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_data_test() {
+        let env = compile_types(SOURCE).unwrap();
+        let data = json!({
+            "tenant": {
+                "_discriminant": "Individual",
+                "_discriminantField": { "name": 1, "age": "30", "phones": [1], "extra": "" },
+            },
+            "unknown": "",
+        });
+
+        assert_eq!(
+            validate(&env, &data, Mode::Draft),
+            Err(vec![
+                error("unknown", "unknown variable"),
+                error("tenant.Individual.extra", "unknown field"),
+                error("tenant.Individual.name", "expected a string"),
+                error("tenant.Individual.age", "expected an integer"),
+                error("tenant.Individual.phones[0]", "expected a string"),
+            ])
+        );
+    }
+
+    #[test]
+    fn retain_compatible_test() {
+        let env = compile_types(SOURCE).unwrap();
+        let data = json!({
+            "tenant": {
+                "_discriminant": "Individual",
+                "_discriminantField": { "name": "Иван", "age": "30", "removed": "" },
+            },
+            "city": 1,
+            "removed": "",
+        });
+
+        assert_eq!(
+            retain_compatible(&env, &data),
+            json!({
+                "tenant": {
+                    "_discriminant": "Individual",
+                    "_discriminantField": { "name": "Иван" },
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,7 @@
+import type { TemplateWithCategoriesDto } from "./TemplateWithCategories.dto.ts";
+
+export interface TemplatesPageDto {
+  templates: TemplateWithCategoriesDto[];
+  total: number;
+  nextCursor: string | null;
+}
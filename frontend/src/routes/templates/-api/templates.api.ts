@@ -4,6 +4,7 @@ import { appAxios } from "../../../api/config.ts";
 import type { CategoryWithTemplatesDto } from "./dtos/CategoryWithTemplates.dto.ts";
 import type { TemplateCategoryDto } from "./dtos/TemplateCategory.dto.ts";
 import type { TemplateWithCategoriesDto } from "./dtos/TemplateWithCategories.dto.ts";
+import type { TemplatesPageDto } from "./dtos/TemplatesPage.dto.ts";
 
 class _TemplatesApi {
   readonly baseQueryKey = ["Templates"] as const;
@@ -16,8 +17,11 @@ class _TemplatesApi {
     return queryOptions({
       queryKey: [...this.baseQueryKey, "templates"],
       queryFn: async (): Promise<CategoryWithTemplatesDto[]> => {
-        const { data } =
-          await appAxios.get<TemplateWithCategoriesDto[]>("/templates");
+        const {
+          data: { templates: data },
+        } = await appAxios.get<TemplatesPageDto>("/templates", {
+          params: { limit: 100 },
+        });
 
         const categoriesMap = new Map<number, CategoryWithTemplatesDto>();
 
@@ -53,25 +57,9 @@ class _TemplatesApi {
       queryKey: [...this.baseQueryKey, "categories"],
       queryFn: async (): Promise<TemplateCategoryDto[]> => {
         const { data } =
-          await appAxios.get<TemplateWithCategoriesDto[]>("/templates");
-
-        const categoriesMap = new Map<number, TemplateCategoryDto>();
-
-        for (const template of data) {
-          for (const category of template.categories) {
-            if (!categoriesMap.has(category.id)) {
-              categoriesMap.set(category.id, {
-                id: category.id,
-                name: category.name,
-              });
-            }
-          }
-        }
+          await appAxios.get<TemplateCategoryDto[]>("/categories");
 
-        let categories = Array.from(categoriesMap.values());
-        categories = categories.sort((a, b) => b.id - a.id);
-
-        return categories;
+        return data;
       },
     } as const);
   }
@@ -7,6 +7,23 @@ mod m20240827_164857_templates;
 mod m20240827_165252_categories;
 mod m20240827_165502_templates_categories;
 mod m20240827_171617_users_visible_templates;
+mod m20241202_101500_templates_search;
+mod m20241203_143000_categories_parent;
+mod m20241205_120000_documents;
+mod m20241206_090000_merge_jobs;
+mod m20241207_100000_user_roles;
+mod m20241208_100000_organizations;
+mod m20241209_100000_template_invitations;
+mod m20241210_100000_sessions;
+mod m20241211_100000_users_totp;
+mod m20241212_100000_user_identities;
+mod m20241213_100000_users_pending_email;
+mod m20241214_100000_api_tokens;
+mod m20241215_100000_templates_dsl_types;
+mod m20241216_100000_templates_files;
+mod m20241217_100000_templates_revision;
+mod m20241218_100000_used_refresh_tokens;
+mod m20241219_100000_merge_jobs_row_numbers;
 
 pub struct Migrator;
 
@@ -19,6 +36,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20240827_165252_categories::Migration),
             Box::new(m20240827_165502_templates_categories::Migration),
             Box::new(m20240827_171617_users_visible_templates::Migration),
+            Box::new(m20241202_101500_templates_search::Migration),
+            Box::new(m20241203_143000_categories_parent::Migration),
+            Box::new(m20241205_120000_documents::Migration),
+            Box::new(m20241206_090000_merge_jobs::Migration),
+            Box::new(m20241207_100000_user_roles::Migration),
+            Box::new(m20241208_100000_organizations::Migration),
+            Box::new(m20241209_100000_template_invitations::Migration),
+            Box::new(m20241210_100000_sessions::Migration),
+            Box::new(m20241211_100000_users_totp::Migration),
+            Box::new(m20241212_100000_user_identities::Migration),
+            Box::new(m20241213_100000_users_pending_email::Migration),
+            Box::new(m20241214_100000_api_tokens::Migration),
+            Box::new(m20241215_100000_templates_dsl_types::Migration),
+            Box::new(m20241216_100000_templates_files::Migration),
+            Box::new(m20241217_100000_templates_revision::Migration),
+            Box::new(m20241218_100000_used_refresh_tokens::Migration),
+            Box::new(m20241219_100000_merge_jobs_row_numbers::Migration),
         ]
     }
 }
@@ -0,0 +1,178 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(integer(Templates::Downloads).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(text(Templates::SearchText).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        // lowercased in Rust, because SQLite's `LOWER` only knows ASCII
+        let db = manager.get_connection();
+        let builder = db.get_database_backend();
+        let rows = db
+            .query_all(
+                builder.build(
+                    Query::select()
+                        .columns([Templates::Id, Templates::Name, Templates::Description])
+                        .from(Templates::Table),
+                ),
+            )
+            .await?;
+
+        for row in rows {
+            let id: i32 = row.try_get("", "id")?;
+            let name: String = row.try_get("", "name")?;
+            let description: String = row.try_get("", "description")?;
+
+            db.execute(
+                builder.build(
+                    Query::update()
+                        .table(Templates::Table)
+                        .value(
+                            Templates::SearchText,
+                            format!("{name}\n{description}").to_lowercase(),
+                        )
+                        .and_where(Expr::col(Templates::Id).eq(id)),
+                ),
+            )
+            .await?;
+        }
+
+        for (name, column) in [
+            ("idx-templates-created_at", Templates::CreatedAt),
+            ("idx-templates-updated_at", Templates::UpdatedAt),
+            ("idx-templates-name", Templates::Name),
+            ("idx-templates-downloads", Templates::Downloads),
+            ("idx-templates-user_id", Templates::UserId),
+            ("idx-templates-is_public", Templates::IsPublic),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Templates::Table)
+                        .col(column)
+                        .col(Templates::Id)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-templates_categories-category_id")
+                    .table(TemplatesCategories::Table)
+                    .col(TemplatesCategories::CategoryId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-users_visible_templates-user_id")
+                    .table(UsersVisibleTemplates::Table)
+                    .col(UsersVisibleTemplates::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-users_visible_templates-user_id")
+                    .table(UsersVisibleTemplates::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-templates_categories-category_id")
+                    .table(TemplatesCategories::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for name in [
+            "idx-templates-created_at",
+            "idx-templates-updated_at",
+            "idx-templates-name",
+            "idx-templates-downloads",
+            "idx-templates-user_id",
+            "idx-templates-is_public",
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).table(Templates::Table).to_owned())
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .drop_column(Templates::SearchText)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .drop_column(Templates::Downloads)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Id,
+    Name,
+    Description,
+    UserId,
+    IsPublic,
+    Downloads,
+    SearchText,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TemplatesCategories {
+    Table,
+    CategoryId,
+}
+
+#[derive(DeriveIden)]
+enum UsersVisibleTemplates {
+    Table,
+    UserId,
+}
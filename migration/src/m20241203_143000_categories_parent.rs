@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Categories::Table)
+                    .add_column(integer_null(Categories::ParentId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // SQLite can't add foreign keys to existing tables, subtrees are deleted
+        // by the application anyway
+        if manager.get_database_backend() != DatabaseBackend::Sqlite {
+            manager
+                .create_foreign_key(
+                    ForeignKey::create()
+                        .name("fk-categories-parent")
+                        .from(Categories::Table, Categories::ParentId)
+                        .to(Categories::Table, Categories::Id)
+                        .on_delete(ForeignKeyAction::Cascade)
+                        .on_update(ForeignKeyAction::Cascade)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-categories-parent_id")
+                    .table(Categories::Table)
+                    .col(Categories::ParentId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-categories-parent_id")
+                    .table(Categories::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        if manager.get_database_backend() != DatabaseBackend::Sqlite {
+            manager
+                .drop_foreign_key(
+                    ForeignKey::drop()
+                        .name("fk-categories-parent")
+                        .table(Categories::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Categories::Table)
+                    .drop_column(Categories::ParentId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Categories {
+    Table,
+    Id,
+    ParentId,
+}
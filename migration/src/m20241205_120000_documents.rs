@@ -0,0 +1,100 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(integer(Templates::Version).default(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                table_auto_tz(Documents::Table)
+                    .col(pk_auto(Documents::Id))
+                    .col(string(Documents::Name))
+                    .col(integer(Documents::UserId))
+                    .col(integer(Documents::TemplateId))
+                    .col(integer(Documents::TemplateVersion))
+                    .col(json(Documents::Data))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-documents-users")
+                            .from(Documents::Table, Documents::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-documents-templates")
+                            .from(Documents::Table, Documents::TemplateId)
+                            .to(Templates::Table, Templates::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-documents-user_id")
+                    .table(Documents::Table)
+                    .col(Documents::UserId)
+                    .col(Documents::UpdatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Documents::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .drop_column(Templates::Version)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Documents {
+    Table,
+    Id,
+    Name,
+    UserId,
+    TemplateId,
+    TemplateVersion,
+    Data,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Id,
+    Version,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,81 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(MergeJobs::Table)
+                    .col(pk_auto(MergeJobs::Id))
+                    .col(integer(MergeJobs::UserId))
+                    .col(integer(MergeJobs::TemplateId))
+                    .col(string_len(MergeJobs::Status, 16))
+                    .col(json(MergeJobs::Mapping))
+                    .col(json(MergeJobs::Headers))
+                    .col(json(MergeJobs::Rows))
+                    .col(integer(MergeJobs::TotalRows))
+                    .col(integer(MergeJobs::ProcessedRows).default(0))
+                    .col(integer(MergeJobs::FailedRows).default(0))
+                    .col(json(MergeJobs::RowErrors))
+                    .col(text_null(MergeJobs::Error))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-merge_jobs-users")
+                            .from(MergeJobs::Table, MergeJobs::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-merge_jobs-templates")
+                            .from(MergeJobs::Table, MergeJobs::TemplateId)
+                            .to(Templates::Table, Templates::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MergeJobs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MergeJobs {
+    Table,
+    Id,
+    UserId,
+    TemplateId,
+    Status,
+    Mapping,
+    Headers,
+    Rows,
+    TotalRows,
+    ProcessedRows,
+    FailedRows,
+    RowErrors,
+    Error,
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,175 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Organizations::Table)
+                    .col(pk_auto(Organizations::Id))
+                    .col(string(Organizations::Name))
+                    .col(integer_null(Organizations::ParentId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-organizations-parent")
+                            .from(Organizations::Table, Organizations::ParentId)
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-organizations-parent_id")
+                    .table(Organizations::Table)
+                    .col(Organizations::ParentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                table_auto_tz(OrganizationMembers::Table)
+                    .primary_key(
+                        Index::create()
+                            .name("idx-organization_members-refs-pk")
+                            .table(OrganizationMembers::Table)
+                            .col(OrganizationMembers::OrganizationId)
+                            .col(OrganizationMembers::UserId),
+                    )
+                    .col(integer(OrganizationMembers::OrganizationId))
+                    .col(integer(OrganizationMembers::UserId))
+                    .col(string_len(OrganizationMembers::Role, 16).default("member"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-organization_members-organizations")
+                            .from(
+                                OrganizationMembers::Table,
+                                OrganizationMembers::OrganizationId,
+                            )
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-organization_members-users")
+                            .from(OrganizationMembers::Table, OrganizationMembers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // visibility of templates is checked by the user
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-organization_members-user_id")
+                    .table(OrganizationMembers::Table)
+                    .col(OrganizationMembers::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                table_auto_tz(TemplatesOrganizations::Table)
+                    .primary_key(
+                        Index::create()
+                            .name("idx-templates_organizations-refs-pk")
+                            .table(TemplatesOrganizations::Table)
+                            .col(TemplatesOrganizations::TemplateId)
+                            .col(TemplatesOrganizations::OrganizationId),
+                    )
+                    .col(integer(TemplatesOrganizations::TemplateId))
+                    .col(integer(TemplatesOrganizations::OrganizationId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-templates_organizations-templates")
+                            .from(
+                                TemplatesOrganizations::Table,
+                                TemplatesOrganizations::TemplateId,
+                            )
+                            .to(Templates::Table, Templates::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-templates_organizations-organizations")
+                            .from(
+                                TemplatesOrganizations::Table,
+                                TemplatesOrganizations::OrganizationId,
+                            )
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(TemplatesOrganizations::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(OrganizationMembers::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Organizations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Organizations {
+    Table,
+    Id,
+    Name,
+    ParentId,
+}
+
+#[derive(DeriveIden)]
+enum OrganizationMembers {
+    Table,
+    OrganizationId,
+    UserId,
+    Role,
+}
+
+#[derive(DeriveIden)]
+enum TemplatesOrganizations {
+    Table,
+    TemplateId,
+    OrganizationId,
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,74 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(TemplateInvitations::Table)
+                    .col(pk_auto(TemplateInvitations::Id))
+                    .col(integer(TemplateInvitations::TemplateId))
+                    .col(string(TemplateInvitations::Email))
+                    .col(timestamp_with_time_zone_null(TemplateInvitations::SentAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-template_invitations-templates")
+                            .from(TemplateInvitations::Table, TemplateInvitations::TemplateId)
+                            .to(Templates::Table, Templates::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-template_invitations-template_id-email")
+                    .table(TemplateInvitations::Table)
+                    .col(TemplateInvitations::TemplateId)
+                    .col(TemplateInvitations::Email)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // invitations are accepted by the email of the verified user
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-template_invitations-email")
+                    .table(TemplateInvitations::Table)
+                    .col(TemplateInvitations::Email)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TemplateInvitations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TemplateInvitations {
+    Table,
+    Id,
+    TemplateId,
+    Email,
+    SentAt,
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Id,
+}
@@ -0,0 +1,69 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Sessions::Table)
+                    .col(pk_auto(Sessions::Id))
+                    .col(uuid_uniq(Sessions::Pid))
+                    .col(integer(Sessions::UserId))
+                    .col(string_uniq(Sessions::RefreshToken))
+                    .col(string_null(Sessions::UserAgent))
+                    .col(timestamp_with_time_zone(Sessions::LastUsedAt))
+                    .col(timestamp_with_time_zone(Sessions::ExpiresAt))
+                    .col(timestamp_with_time_zone_null(Sessions::RevokedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-sessions-users")
+                            .from(Sessions::Table, Sessions::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sessions-user_id")
+                    .table(Sessions::Table)
+                    .col(Sessions::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Sessions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+    Pid,
+    UserId,
+    RefreshToken,
+    UserAgent,
+    LastUsedAt,
+    ExpiresAt,
+    RevokedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column per statement
+        let columns = [
+            string_null(Users::TotpSecret),
+            timestamp_with_time_zone_null(Users::TotpEnabledAt),
+            big_integer_null(Users::TotpLastStep),
+            json_null(Users::TotpRecoveryCodes),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            Users::TotpSecret,
+            Users::TotpEnabledAt,
+            Users::TotpLastStep,
+            Users::TotpRecoveryCodes,
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    TotpSecret,
+    TotpEnabledAt,
+    TotpLastStep,
+    TotpRecoveryCodes,
+}
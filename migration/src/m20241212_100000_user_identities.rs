@@ -0,0 +1,101 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(UserIdentities::Table)
+                    .col(pk_auto(UserIdentities::Id))
+                    .col(integer(UserIdentities::UserId))
+                    .col(string(UserIdentities::Provider))
+                    .col(string(UserIdentities::Subject))
+                    .col(string_null(UserIdentities::Email))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_identities-users")
+                            .from(UserIdentities::Table, UserIdentities::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user_identities-provider-subject")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::Provider)
+                    .col(UserIdentities::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user_identities-user_id")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // logins started at the provider, until it redirects back
+        manager
+            .create_table(
+                table_auto_tz(OidcLogins::Table)
+                    .col(pk_auto(OidcLogins::Id))
+                    .col(string(OidcLogins::Provider))
+                    .col(string_uniq(OidcLogins::State))
+                    .col(string(OidcLogins::CodeVerifier))
+                    .col(string(OidcLogins::Nonce))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OidcLogins::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UserIdentities::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserIdentities {
+    Table,
+    Id,
+    UserId,
+    Provider,
+    Subject,
+    Email,
+}
+
+#[derive(DeriveIden)]
+enum OidcLogins {
+    Table,
+    Id,
+    Provider,
+    State,
+    CodeVerifier,
+    Nonce,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // existing templates are compiled on the first request of their types
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(json_null(Templates::DslTypes))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(integer_null(Templates::DslCompilerVersion))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(string_null(Templates::DslHash))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Templates::DslTypes,
+            Templates::DslCompilerVersion,
+            Templates::DslHash,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Templates::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    DslTypes,
+    DslCompilerVersion,
+    DslHash,
+}
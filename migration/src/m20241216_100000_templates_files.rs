@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // hashes of existing files are computed on their first download
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(string_null(Templates::DocxHash))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(timestamp_with_time_zone_null(Templates::FilesUpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Templates::DocxHash, Templates::FilesUpdatedAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Templates::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    DocxHash,
+    FilesUpdatedAt,
+}
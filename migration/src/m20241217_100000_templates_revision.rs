@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // unlike `version`, every change of the template moves the revision
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .add_column(integer(Templates::Revision).default(1))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Templates::Table)
+                    .drop_column(Templates::Revision)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Revision,
+}
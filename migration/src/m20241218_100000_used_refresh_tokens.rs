@@ -0,0 +1,49 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(UsedRefreshTokens::Table)
+                    .col(pk_auto(UsedRefreshTokens::Id))
+                    .col(integer(UsedRefreshTokens::SessionId))
+                    .col(string_uniq(UsedRefreshTokens::RefreshToken))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-used_refresh_tokens-sessions")
+                            .from(UsedRefreshTokens::Table, UsedRefreshTokens::SessionId)
+                            .to(Sessions::Table, Sessions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UsedRefreshTokens::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UsedRefreshTokens {
+    Table,
+    Id,
+    SessionId,
+    RefreshToken,
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // rows of earlier jobs are numbered as if the table had no blank rows
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MergeJobs::Table)
+                    .add_column(json_null(MergeJobs::RowNumbers))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MergeJobs::Table)
+                    .drop_column(MergeJobs::RowNumbers)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MergeJobs {
+    Table,
+    RowNumbers,
+}
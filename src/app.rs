@@ -2,7 +2,7 @@ use std::path::Path;
 
 use async_trait::async_trait;
 use loco_rs::app::{AppContext, Hooks, Initializer};
-use loco_rs::bgworker::Queue;
+use loco_rs::bgworker::{BackgroundWorker, Queue};
 use loco_rs::boot::{create_app, BootResult, StartMode};
 use loco_rs::controller::AppRoutes;
 use loco_rs::db::{self, truncate_table};
@@ -14,7 +14,8 @@ use sea_orm::DatabaseConnection;
 
 use crate::models::_entities::users;
 use crate::models::{categories, templates, templates_categories};
-use crate::{controllers, tasks};
+use crate::workers::mail_merge::MailMergeWorker;
+use crate::{controllers, initializers, tasks};
 
 pub struct App;
 #[async_trait]
@@ -38,20 +39,35 @@ impl Hooks for App {
     }
 
     async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
-        Ok(vec![])
+        Ok(vec![Box::new(
+            initializers::rate_limit::RateLimitInitializer,
+        )])
     }
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
         AppRoutes::with_default_routes() // controller routes below
             .add_route(controllers::auth::routes())
             .add_route(controllers::user::routes())
+            .add_route(controllers::api_tokens::routes())
             .add_route(controllers::templates::routes())
+            .add_route(controllers::bundles::routes())
+            .add_route(controllers::categories::routes())
+            .add_route(controllers::documents::routes())
+            .add_route(controllers::merges::routes())
+            .add_route(controllers::organizations::routes())
+            .add_route(controllers::admin::routes())
+            .add_route(controllers::docs::routes())
     }
-    async fn connect_workers(_ctx: &AppContext, _queue: &Queue) -> Result<()> {
+    async fn connect_workers(ctx: &AppContext, queue: &Queue) -> Result<()> {
+        queue.register(MailMergeWorker::build(ctx)).await?;
         Ok(())
     }
     fn register_tasks(tasks: &mut Tasks) {
         tasks.register(tasks::seed::SeedData);
+        tasks.register(tasks::set_role::SetRole);
+        tasks.register(tasks::export_templates::ExportTemplates);
+        tasks.register(tasks::import_templates::ImportTemplates);
+        tasks.register(tasks::recompile_templates::RecompileTemplates);
         // tasks-inject (do not remove)
     }
     async fn truncate(db: &DatabaseConnection) -> Result<()> {
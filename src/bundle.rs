@@ -0,0 +1,219 @@
+//! Template bundles, ZIP archives that move templates between Cicero
+//! instances, e.g. from staging to production:
+//!
+//! ```text
+//! manifest.json
+//! templates/0.docx
+//! templates/0.dsl
+//! templates/1.docx
+//! ...
+//! ```
+
+use std::io::{Cursor, Read, Write};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Bumped on incompatible changes of the manifest.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The largest file of a bundle after decompression, so that a small
+/// archive can't take up all memory.
+pub const MAX_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("invalid bundle: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("invalid bundle: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("the bundle format {0} is not supported")]
+    FormatVersion(u32),
+    #[error("the checksum of {0} doesn't match the manifest")]
+    Checksum(String),
+    #[error("{0} is not valid UTF-8")]
+    Encoding(String),
+    #[error("{0} is larger than {MAX_FILE_SIZE} bytes")]
+    TooLarge(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub format_version: u32,
+    pub templates: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    #[serde(flatten)]
+    pub template: TemplateManifest,
+    pub docx: BundleFile,
+    pub dsl: BundleFile,
+}
+
+/// Where a file of the template is in the archive.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFile {
+    pub path: String,
+    /// Hex of the SHA-256 of the contents.
+    pub sha256: String,
+}
+
+/// What a template is on the exporting instance. Categories are referenced
+/// by name and viewers by email, because IDs differ between instances.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateManifest {
+    pub name: String,
+    pub description: String,
+    /// Names of the categories with their parents, from the root down to
+    /// the category itself.
+    pub categories: Vec<Vec<String>>,
+    #[serde(flatten)]
+    pub publicity: BundlePublicity,
+    /// Version of the template, when it was exported.
+    pub version: i32,
+    /// Values of a document, that show how the template is filled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_data: Option<serde_json::Value>,
+}
+
+/// Organizations are left out, they don't exist on other instances.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "publicity")]
+pub enum BundlePublicity {
+    Public,
+    Private { viewers: Vec<String> },
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleTemplate {
+    pub manifest: TemplateManifest,
+    pub docx: Vec<u8>,
+    pub dsl: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bundle {
+    pub templates: Vec<BundleTemplate>,
+}
+
+fn sha256(contents: &[u8]) -> String {
+    hex::encode(Sha256::digest(contents))
+}
+
+impl Bundle {
+    /// Packs the templates with the manifest and the checksums.
+    ///
+    /// # Errors
+    ///
+    /// When writing the archive fails
+    pub fn write(&self) -> Result<Vec<u8>, BundleError> {
+        let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+        let mut entries = Vec::with_capacity(self.templates.len());
+
+        for (i, template) in self.templates.iter().enumerate() {
+            let docx = BundleFile {
+                path: format!("templates/{i}.docx"),
+                sha256: sha256(&template.docx),
+            };
+            archive.start_file(docx.path.as_str(), SimpleFileOptions::default())?;
+            archive.write_all(&template.docx)?;
+
+            let dsl = BundleFile {
+                path: format!("templates/{i}.dsl"),
+                sha256: sha256(template.dsl.as_bytes()),
+            };
+            archive.start_file(dsl.path.as_str(), SimpleFileOptions::default())?;
+            archive.write_all(template.dsl.as_bytes())?;
+
+            entries.push(ManifestEntry {
+                template: template.manifest.clone(),
+                docx,
+                dsl,
+            });
+        }
+
+        let manifest = Manifest {
+            format_version: FORMAT_VERSION,
+            templates: entries,
+        };
+        archive.start_file(MANIFEST_FILE, SimpleFileOptions::default())?;
+        serde_json::to_writer_pretty(&mut archive, &manifest)?;
+
+        Ok(archive.finish()?.into_inner())
+    }
+
+    /// Unpacks the templates and checks the files against the checksums of
+    /// the manifest.
+    ///
+    /// # Errors
+    ///
+    /// When the archive or the manifest is malformed, the format is newer
+    /// than [`FORMAT_VERSION`] or a file is missing or corrupted
+    pub fn read(bundle: &[u8]) -> Result<Self, BundleError> {
+        let mut archive = ZipArchive::new(Cursor::new(bundle))?;
+
+        let manifest: Manifest = serde_json::from_slice(&read_entry(&mut archive, MANIFEST_FILE)?)?;
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(BundleError::FormatVersion(manifest.format_version));
+        }
+
+        let templates = manifest
+            .templates
+            .into_iter()
+            .map(|entry| {
+                let docx = read_file(&mut archive, &entry.docx)?;
+                let dsl = String::from_utf8(read_file(&mut archive, &entry.dsl)?)
+                    .map_err(|_| BundleError::Encoding(entry.dsl.path.clone()))?;
+
+                Ok(BundleTemplate {
+                    manifest: entry.template,
+                    docx,
+                    dsl,
+                })
+            })
+            .collect::<Result<_, BundleError>>()?;
+
+        Ok(Self { templates })
+    }
+}
+
+fn read_file(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    file: &BundleFile,
+) -> Result<Vec<u8>, BundleError> {
+    let contents = read_entry(archive, &file.path)?;
+
+    if !sha256(&contents).eq_ignore_ascii_case(&file.sha256) {
+        return Err(BundleError::Checksum(file.path.clone()));
+    }
+
+    Ok(contents)
+}
+
+/// Decompresses the entry up to [`MAX_FILE_SIZE`]. The size in the archive
+/// is checked first, but it may lie, so the reading is limited too.
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> Result<Vec<u8>, BundleError> {
+    let entry = archive.by_name(path)?;
+    if entry.size() > MAX_FILE_SIZE {
+        return Err(BundleError::TooLarge(path.to_string()));
+    }
+
+    let mut contents = Vec::new();
+    entry.take(MAX_FILE_SIZE + 1).read_to_end(&mut contents)?;
+    if contents.len() as u64 > MAX_FILE_SIZE {
+        return Err(BundleError::TooLarge(path.to_string()));
+    }
+
+    Ok(contents)
+}
@@ -0,0 +1,166 @@
+//! Conditional requests for templates. Validators of the files come from
+//! the metadata stored with the template, so a client with a current copy
+//! gets `304 Not Modified` before the file is read. Writes are conditioned
+//! on the revision of the template, see [`expected_revision`].
+
+use std::ops::RangeInclusive;
+use std::time::SystemTime;
+
+use axum::http::header::{
+    CACHE_CONTROL,
+    ETAG,
+    IF_MATCH,
+    IF_MODIFIED_SINCE,
+    IF_NONE_MATCH,
+    IF_RANGE,
+    LAST_MODIFIED,
+    RANGE,
+};
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use httpdate::HttpDate;
+use sea_orm::prelude::DateTimeWithTimeZone;
+
+use crate::errors::{AppError, AppResult};
+
+/// `ETag` and `Last-Modified` of a file, see RFC 9110 section 8.8.
+#[derive(Debug, Clone)]
+pub struct Validators {
+    etag: String,
+    last_modified: HttpDate,
+    is_public: bool,
+}
+
+/// Part of a file requested with `Range`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ByteRange {
+    Full,
+    Partial(RangeInclusive<u64>),
+    Unsatisfiable,
+}
+
+impl Validators {
+    /// The `tag` is a hash of the file, or of whatever the response is
+    /// derived from. Private files must not be kept by shared caches.
+    #[must_use]
+    pub fn new(tag: &str, last_modified: DateTimeWithTimeZone, is_public: bool) -> Self {
+        Self {
+            etag: format!("\"{tag}\""),
+            last_modified: SystemTime::from(last_modified).into(),
+            is_public,
+        }
+    }
+
+    /// Whether the copy of the client is current. `If-Modified-Since` is
+    /// ignored, when `If-None-Match` is sent, see RFC 9110 section 13.2.2.
+    #[must_use]
+    pub fn is_fresh(&self, headers: &HeaderMap) -> bool {
+        if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+            // weak comparison, a `W/` prefix added by a proxy still matches
+            return if_none_match.to_str().is_ok_and(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.etag)
+            });
+        }
+
+        headers
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|since| since.to_str().ok()?.parse::<HttpDate>().ok())
+            .is_some_and(|since| self.last_modified <= since)
+    }
+
+    /// The part of a file of `size` bytes, that is requested. Malformed and
+    /// multiple ranges get the full file, and so do ranges conditioned with
+    /// `If-Range` on an outdated copy.
+    #[must_use]
+    pub fn range(&self, headers: &HeaderMap, size: u64) -> ByteRange {
+        let Some(range) = headers.get(RANGE).and_then(|range| range.to_str().ok()) else {
+            return ByteRange::Full;
+        };
+
+        if let Some(if_range) = headers.get(IF_RANGE) {
+            // `If-Range` needs a strong match, or the exact date
+            let is_current = if_range.to_str().is_ok_and(|if_range| {
+                if_range == self.etag
+                    || if_range
+                        .parse::<HttpDate>()
+                        .is_ok_and(|date| date == self.last_modified)
+            });
+            if !is_current {
+                return ByteRange::Full;
+            }
+        }
+
+        let Ok(parsed) = http_range_header::parse_range_header(range) else {
+            return ByteRange::Full;
+        };
+        if size == 0 {
+            return ByteRange::Unsatisfiable;
+        }
+        match parsed.validate(size) {
+            Ok(ranges) if ranges.len() == 1 => ByteRange::Partial(ranges[0].clone()),
+            Ok(_) => ByteRange::Full,
+            Err(_) => ByteRange::Unsatisfiable,
+        }
+    }
+
+    /// Headers, that are sent with every response of the file.
+    #[must_use]
+    pub fn headers(&self) -> [(HeaderName, String); 3] {
+        let cache_control = if self.is_public {
+            "no-cache"
+        } else {
+            "private, no-cache"
+        };
+
+        [
+            (ETAG, self.etag.clone()),
+            (LAST_MODIFIED, self.last_modified.to_string()),
+            (CACHE_CONTROL, cache_control.to_string()),
+        ]
+    }
+
+    #[must_use]
+    pub fn not_modified(&self) -> Response {
+        (StatusCode::NOT_MODIFIED, self.headers()).into_response()
+    }
+}
+
+/// The revision of the template, that a change is based on. It's sent as
+/// `If-Match: "3"` or as the `revision` of the JSON. `If-Match: *` changes
+/// any revision, `None` is returned then.
+///
+/// # Errors
+///
+/// When neither is sent, or `If-Match` isn't a revision or differs from the
+/// JSON
+pub fn expected_revision(headers: &HeaderMap, revision: Option<i32>) -> AppResult<Option<i32>> {
+    let Some(if_match) = headers.get(IF_MATCH) else {
+        return revision.map(Some).ok_or_else(|| {
+            AppError::precondition_required(
+                "revision_required",
+                "Send the revision of the template in `If-Match` or `revision`",
+            )
+        });
+    };
+
+    let if_match = if_match.to_str().unwrap_or_default().trim();
+    if if_match == "*" {
+        return Ok(revision);
+    }
+
+    match if_match.trim_matches('"').parse() {
+        Ok(expected) if revision.is_none_or(|revision| revision == expected) => Ok(Some(expected)),
+        _ => Err(revision_conflict()),
+    }
+}
+
+/// The template was changed by someone else since the given revision.
+#[must_use]
+pub fn revision_conflict() -> AppError {
+    AppError::precondition_failed(
+        "revision_conflict",
+        "The template was changed since the revision, reload it",
+    )
+}
@@ -0,0 +1,121 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::categories::CategoryParams;
+use crate::errors::{AppError, AppResult};
+use crate::middlewares::permission::{ManageCategories, ManageRoles, ModerateTemplates};
+use crate::middlewares::{Json, JwtWithPermission};
+use crate::models::roles::UserRole;
+use crate::models::{categories, templates, users};
+use crate::views::category::DetailsResponse;
+use crate::views::user::WithRoleResponse;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleParams {
+    pub role: UserRole,
+}
+
+#[debug_handler]
+async fn list_users(
+    _jwt: JwtWithPermission<ManageRoles>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let users = users::Model::find_all(&ctx.db).await?;
+
+    Ok(format::json(
+        users.iter().map(WithRoleResponse::new).collect::<Vec<_>>(),
+    )?)
+}
+
+#[debug_handler]
+async fn set_role(
+    jwt: JwtWithPermission<ManageRoles>,
+    Path(pid): Path<String>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<RoleParams>,
+) -> AppResult<Response> {
+    if Uuid::parse_str(&pid).is_err() {
+        return Err(AppError::not_found("User not found"));
+    }
+    let user = users::Model::find_by_pid(&ctx.db, &pid).await?;
+
+    // otherwise the last admin could lock everybody out
+    if user.id == jwt.user.id {
+        return Err(AppError::conflict(
+            "own_role",
+            "Admins can't change their own role",
+        ));
+    }
+
+    let user = user
+        .into_active_model()
+        .set_role(&ctx.db, params.role)
+        .await?;
+
+    Ok(format::json(WithRoleResponse::new(&user))?)
+}
+
+#[debug_handler]
+async fn remove_template(
+    jwt: JwtWithPermission<ModerateTemplates>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    // moderation doesn't wait for the author to finish editing
+    templates::Model::delete_template(&ctx.db, id, None, &jwt.user).await?;
+
+    Ok(format::json(())?)
+}
+
+#[debug_handler]
+async fn create_category(
+    jwt: JwtWithPermission<ManageCategories>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CategoryParams>,
+) -> AppResult<Response> {
+    let category = categories::Model::create(&ctx.db, &params, &jwt.user).await?;
+
+    Ok(format::json(DetailsResponse::new(&category))?)
+}
+
+#[debug_handler]
+async fn update_category(
+    jwt: JwtWithPermission<ManageCategories>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CategoryParams>,
+) -> AppResult<Response> {
+    let category = categories::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .update_category(&ctx.db, &params, &jwt.user)
+        .await?;
+
+    Ok(format::json(DetailsResponse::new(&category))?)
+}
+
+#[debug_handler]
+async fn remove_category(
+    jwt: JwtWithPermission<ManageCategories>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    categories::Model::delete_category(&ctx.db, id, &jwt.user).await?;
+
+    Ok(format::json(())?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/admin")
+        .add("/users", get(list_users))
+        .add("/users/:pid/role", put(set_role))
+        .add("/templates/:id", delete(remove_template))
+        .add("/categories", post(create_category))
+        .add("/categories/:id", put(update_category))
+        .add("/categories/:id", delete(remove_category))
+}
@@ -0,0 +1,148 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::errors::AppResult;
+use crate::middlewares::{Json, JwtWithUser};
+use crate::models::api_tokens::{self, ApiScope};
+use crate::views::api_token::{Response as ApiTokenResponse, WithSecretResponse};
+use crate::views::error::ErrorResponse;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = CreateApiTokenParams)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateParams {
+    /// What the token is for, e.g. the name of the pipeline.
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    /// The token never expires without it.
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub expires_at: Option<DateTimeWithTimeZone>,
+}
+
+/// Lists the tokens of the user, the tokens themselves are shown only once.
+#[utoipa::path(
+    get,
+    path = "/api/user/tokens",
+    tag = "api tokens",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = Vec<ApiTokenResponse>),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn list(jwt: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let api_tokens = api_tokens::Model::find_for_user(&ctx.db, jwt.user.id).await?;
+
+    Ok(format::json(
+        api_tokens
+            .iter()
+            .map(ApiTokenResponse::new)
+            .collect::<Vec<_>>(),
+    )?)
+}
+
+/// Tokens are managed only with access tokens, so a leaked token can't issue
+/// new ones.
+#[utoipa::path(
+    post,
+    path = "/api/user/tokens",
+    tag = "api tokens",
+    security(("jwt" = [])),
+    request_body = CreateParams,
+    responses(
+        (status = 200, body = WithSecretResponse),
+        (status = 422, description = "Invalid values", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn create(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> AppResult<Response> {
+    jwt.user.require_verified()?;
+
+    let (api_token, token) = api_tokens::Model::create(
+        &ctx.db,
+        jwt.user.id,
+        &params.name,
+        &params.scopes,
+        params.expires_at,
+    )
+    .await?;
+
+    Ok(format::json(WithSecretResponse::new(&api_token, token))?)
+}
+
+/// Issues a new token instead of the old one, that stops working at once.
+#[utoipa::path(
+    post,
+    path = "/api/user/tokens/{pid}/rotate",
+    tag = "api tokens",
+    security(("jwt" = [])),
+    params(("pid" = Uuid, Path)),
+    responses(
+        (status = 200, body = WithSecretResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn rotate(
+    jwt: JwtWithUser,
+    Path(pid): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let (api_token, token) = api_tokens::Model::find_by_pid_for_user(&ctx.db, pid, jwt.user.id)
+        .await?
+        .into_active_model()
+        .rotate(&ctx.db)
+        .await?;
+
+    Ok(format::json(WithSecretResponse::new(&api_token, token))?)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/user/tokens/{pid}",
+    tag = "api tokens",
+    security(("jwt" = [])),
+    params(("pid" = Uuid, Path)),
+    responses(
+        (status = 200, description = "The token is removed"),
+        (status = 404, body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn remove(
+    jwt: JwtWithUser,
+    Path(pid): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    api_tokens::Model::find_by_pid_for_user(&ctx.db, pid, jwt.user.id)
+        .await?
+        .delete(&ctx.db)
+        .await?;
+
+    Ok(format::json(())?)
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(list, create, remove, rotate,))]
+pub struct ApiDoc;
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/user/tokens")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:pid", delete(remove))
+        .add("/:pid/rotate", post(rotate))
+}
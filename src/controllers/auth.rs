@@ -1,146 +1,732 @@
-use axum::debug_handler;
+use std::sync::Arc;
+
+use axum::http::{header, HeaderMap};
+use axum::{debug_handler, Extension};
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use utoipa::{OpenApi, ToSchema};
 
-// use crate::mailers::auth::AuthMailer;
+use crate::errors::{AppError, AppResult};
+use crate::mailers::auth::AuthMailer;
+use crate::middlewares::rate_limit::too_many_requests;
+use crate::middlewares::{Json, JwtWithUser};
 use crate::models::_entities::users;
 use crate::models::users::{LoginParams, RegisterParams};
-use crate::views::auth::LoginResponse;
+use crate::models::{oidc_logins, sessions, template_invitations, user_identities};
+use crate::oidc::{self, IdTokenClaims};
+use crate::rate_limit::{normalize_account, RateLimiter};
+use crate::settings::{OidcProvider, Settings};
+use crate::totp;
+use crate::views::auth::{LoginResponse, OidcAuthorizeResponse};
+use crate::views::error::{ErrorResponse, FieldError};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct VerifyParams {
     pub token: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ForgotParams {
     pub email: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ResetParams {
     pub token: String,
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshParams {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcCallbackParams {
+    pub code: String,
+    pub state: String,
+    /// The one-time or a recovery code of the account, if the identity is
+    /// linked to an account with the two-factor authentication.
+    #[serde(default)]
+    pub totp_code: Option<String>,
+}
+
+/// Starts a session of the user and responds with its tokens.
+async fn create_session(
+    ctx: &AppContext,
+    user: &users::Model,
+    headers: &HeaderMap,
+) -> AppResult<Response> {
+    let settings = Settings::from_config(&ctx.config)?;
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string);
+    let (session, refresh_token) = sessions::Model::create(
+        &ctx.db,
+        user.id,
+        user_agent,
+        settings.auth.refresh_token_ttl(),
+    )
+    .await?;
+
+    session_response(ctx, user, &session, &refresh_token)
+}
+
+/// Responds with a new access token of the session and its refresh token.
+fn session_response(
+    ctx: &AppContext,
+    user: &users::Model,
+    session: &sessions::Model,
+    refresh_token: &str,
+) -> AppResult<Response> {
+    let jwt_secret = ctx.config.get_jwt_config()?;
+
+    let token = user
+        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration, session)
+        .map_err(AppError::internal)?;
+
+    Ok(format::json(LoginResponse::new(
+        user,
+        &token,
+        refresh_token,
+    ))?)
+}
+
+/// Responds not earlier than the minimal response time since the start, so
+/// the timing doesn't tell, whether the email is registered.
+async fn respond_after(ctx: &AppContext, started: Instant) -> AppResult<Response> {
+    let settings = Settings::from_config(&ctx.config)?;
+    tokio::time::sleep_until(started + settings.auth.min_response_time()).await;
+    Ok(format::json(())?)
+}
+
 /// Register function creates a new user with the given parameters and sends a
 /// welcome email to the user
+#[utoipa::path(
+    post,
+    path = "/api/auth/register",
+    tag = "auth",
+    request_body = RegisterParams,
+    responses(
+        (status = 200, description = "The verification email is sent, unless the email is taken"),
+        (status = 422, description = "Invalid values", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn register(
     State(ctx): State<AppContext>,
     Json(params): Json<RegisterParams>,
-) -> Result<Response> {
+) -> AppResult<Response> {
+    let started = Instant::now();
     let res = users::Model::create_with_password(&ctx.db, &params).await;
 
-    let user = match res {
+    let user = match res.map_err(AppError::from) {
         Ok(user) => user,
+        // invalid values are reported, but we don't want to expose, that the
+        // email is already registered
+        Err(err @ AppError::Validation { .. }) => return Err(err),
         Err(err) => {
             tracing::info!(
                 message = err.to_string(),
                 user_email = &params.email,
                 "could not register user",
             );
-            return format::json(());
+            return respond_after(&ctx, started).await;
         },
     };
 
-    let _user = user
+    let (user, token) = user
         .into_active_model()
         .set_email_verification_sent(&ctx.db)
         .await?;
 
-    // AuthMailer::send_welcome(&ctx, &user).await?;
+    // a failed mail must not reveal, that the email is registered
+    if let Err(err) = AuthMailer::send_welcome(&ctx, &user, &token).await {
+        tracing::error!(
+            pid = user.pid.to_string(),
+            error = %err,
+            "verification mail can't be sent"
+        );
+    }
 
-    format::json(())
+    respond_after(&ctx, started).await
 }
 
 /// Verify register user. if the user not verified his email, he can't login to
 /// the system.
+#[utoipa::path(
+    post,
+    path = "/api/auth/verify",
+    tag = "auth",
+    request_body = VerifyParams,
+    responses(
+        (status = 200, description = "The email is verified"),
+        (status = 422, description = "The token is invalid or expired", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn verify(
     State(ctx): State<AppContext>,
     Json(params): Json<VerifyParams>,
-) -> Result<Response> {
-    let user = users::Model::find_by_verification_token(&ctx.db, &params.token).await?;
+) -> AppResult<Response> {
+    let user = match users::Model::find_by_verification_token(&ctx.db, &params.token).await {
+        Ok(user) => user,
+        Err(ModelError::EntityNotFound) => {
+            return Err(AppError::validation(
+                "invalid_token",
+                "The verification token is invalid",
+                vec![FieldError::new("token", "is not found")],
+            ));
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    let settings = Settings::from_config(&ctx.config)?;
+    if user.is_verification_token_expired(settings.auth.verification_token_ttl()) {
+        return Err(AppError::validation(
+            "token_expired",
+            "The verification token is expired",
+            vec![FieldError::new("token", "is expired")],
+        ));
+    }
+
+    if user.pending_email.is_some() {
+        let user = user.into_active_model().change_email(&ctx.db).await?;
+        tracing::info!(pid = user.pid.to_string(), "email changed");
 
-    if user.email_verified_at.is_some() {
+        template_invitations::Model::accept_for_user(&ctx.db, &user).await?;
+    } else if user.email_verified_at.is_some() {
         tracing::info!(pid = user.pid.to_string(), "user already verified");
     } else {
         let active_model = user.into_active_model();
         let user = active_model.verified(&ctx.db).await?;
         tracing::info!(pid = user.pid.to_string(), "user verified");
+
+        template_invitations::Model::accept_for_user(&ctx.db, &user).await?;
     }
 
-    format::json(())
+    Ok(format::json(())?)
 }
 
 /// In case the user forgot his password  this endpoints generate a forgot token
 /// and send email to the user. In case the email not found in our DB, we are
 /// returning a valid request for for security reasons (not exposing users DB
 /// list).
+#[utoipa::path(
+    post,
+    path = "/api/auth/forgot",
+    tag = "auth",
+    request_body = ForgotParams,
+    responses(
+        (status = 200, description = "The reset email is sent, if the email is registered"),
+        (status = 429, description = "Too many requests", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn forgot(
     State(ctx): State<AppContext>,
     Json(params): Json<ForgotParams>,
-) -> Result<Response> {
+) -> AppResult<Response> {
+    let started = Instant::now();
     let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
         // we don't want to expose our users email. if the email is invalid we still
         // returning success to the caller
-        return format::json(());
+        return respond_after(&ctx, started).await;
     };
 
-    // AuthMailer::forgot_password(&ctx, &user).await?;
-
-    user.into_active_model()
+    let (user, token) = user
+        .into_active_model()
         .set_forgot_password_sent(&ctx.db)
         .await?;
 
-    format::json(())
+    AuthMailer::forgot_password(&ctx, &user, &token).await?;
+
+    respond_after(&ctx, started).await
+}
+
+/// Sends the verification email again with a new token. Like `forgot`, it
+/// succeeds for unknown and already verified emails, so registered emails
+/// aren't exposed.
+#[utoipa::path(
+    post,
+    path = "/api/auth/resend-verification",
+    tag = "auth",
+    request_body = ForgotParams,
+    responses(
+        (status = 200, description = "The verification email is sent, if the email is not verified"),
+        (status = 429, description = "Too many requests", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn resend_verification(
+    State(ctx): State<AppContext>,
+    Json(params): Json<ForgotParams>,
+) -> AppResult<Response> {
+    let started = Instant::now();
+    let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
+        return respond_after(&ctx, started).await;
+    };
+
+    // a recently sent email isn't sent again, but the response is the same,
+    // so it doesn't tell that the email is registered
+    if user.email_verified_at.is_some() || !user.can_resend_verification() {
+        return respond_after(&ctx, started).await;
+    }
+
+    let (user, token) = user
+        .into_active_model()
+        .set_email_verification_sent(&ctx.db)
+        .await?;
+
+    // a failed mail must not reveal, that the email is registered
+    if let Err(err) = AuthMailer::send_welcome(&ctx, &user, &token).await {
+        tracing::error!(
+            pid = user.pid.to_string(),
+            error = %err,
+            "verification mail can't be sent"
+        );
+    }
+
+    respond_after(&ctx, started).await
 }
 
 /// reset user password by the given parameters
+#[utoipa::path(
+    post,
+    path = "/api/auth/reset",
+    tag = "auth",
+    request_body = ResetParams,
+    responses(
+        (status = 200, description = "The password is changed and the user is logged out everywhere"),
+        (status = 422, description = "The token is invalid or expired", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
-async fn reset(State(ctx): State<AppContext>, Json(params): Json<ResetParams>) -> Result<Response> {
+async fn reset(
+    State(ctx): State<AppContext>,
+    Json(params): Json<ResetParams>,
+) -> AppResult<Response> {
     let Ok(user) = users::Model::find_by_reset_token(&ctx.db, &params.token).await else {
         // we don't want to expose our users email. if the email is invalid we still
         // returning success to the caller
         tracing::info!("reset token not found");
 
-        return format::json(());
+        return Ok(format::json(())?);
     };
-    user.into_active_model()
+
+    let settings = Settings::from_config(&ctx.config)?;
+    if user.is_reset_token_expired(settings.auth.reset_token_ttl()) {
+        return Err(AppError::validation(
+            "token_expired",
+            "The reset token is expired",
+            vec![FieldError::new("token", "is expired")],
+        ));
+    }
+
+    // resetting the password clears the token, so it can't be used again
+    let user = user
+        .into_active_model()
         .reset_password(&ctx.db, &params.password)
         .await?;
+    // whoever knew the old password is logged out
+    sessions::Model::revoke_all_for_user(&ctx.db, user.id).await?;
 
-    format::json(())
+    Ok(format::json(())?)
 }
 
 /// Creates a user login and returns a token
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginParams,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 401, description = "Wrong credentials or the one-time code is required", body = ErrorResponse),
+        (status = 429, description = "Too many failed logins", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
-async fn login(State(ctx): State<AppContext>, Json(params): Json<LoginParams>) -> Result<Response> {
-    let user = users::Model::find_by_email(&ctx.db, &params.email).await?;
+async fn login(
+    State(ctx): State<AppContext>,
+    Extension(limiter): Extension<Arc<RateLimiter>>,
+    headers: HeaderMap,
+    Json(params): Json<LoginParams>,
+) -> AppResult<Response> {
+    let invalid_credentials =
+        || AppError::unauthorized("invalid_credentials", "The email or the password is wrong");
+
+    // unknown emails are locked too, so the lock doesn't expose accounts
+    let account = normalize_account(&params.email);
+    if let Some(retry_after) = limiter.locked_for(&account).await? {
+        return Ok(too_many_requests(
+            "account_locked",
+            "Too many failed logins, try again later",
+            retry_after,
+        ));
+    }
+
+    let user = match users::Model::find_by_email(&ctx.db, &params.email).await {
+        Ok(user) => user,
+        Err(ModelError::EntityNotFound) => {
+            // takes as long as checking the password of a registered user
+            users::Model::verify_dummy_password(&params.password);
+            limiter.record_failure(&account).await?;
+            return Err(invalid_credentials());
+        },
+        Err(err) => return Err(err.into()),
+    };
 
     let valid = user.verify_password(&params.password);
 
     if !valid {
-        return unauthorized("unauthorized!");
+        limiter.record_failure(&account).await?;
+        return Err(invalid_credentials());
     }
 
-    let jwt_secret = ctx.config.get_jwt_config()?;
+    user.require_verified()?;
 
-    let token = user
-        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration)
-        .or_else(|_| unauthorized("unauthorized!"))?;
+    // the second step of the login with the two-factor authentication
+    let user = if user.has_totp() {
+        let Some(code) = params.code.as_deref() else {
+            return Err(AppError::unauthorized(
+                "totp_required",
+                "The one-time code is required",
+            ));
+        };
+        match user.check_second_factor(&ctx.db, code, totp::now()).await {
+            Ok(user) => user,
+            Err(err) => {
+                limiter.record_failure(&account).await?;
+                return Err(err);
+            },
+        }
+    } else {
+        user
+    };
 
-    format::json(LoginResponse::new(&user, &token))
+    // the user remembers the password, so the requested reset is not needed
+    let user = if user.reset_token.is_some() {
+        user.into_active_model().clear_reset_token(&ctx.db).await?
+    } else {
+        user
+    };
+
+    limiter.reset_failures(&account).await?;
+
+    create_session(&ctx, &user, &headers).await
+}
+
+fn find_provider<'a>(settings: &'a Settings, name: &str) -> AppResult<&'a OidcProvider> {
+    settings
+        .oidc
+        .providers
+        .get(name)
+        .ok_or_else(|| AppError::not_found("The identity provider is not found"))
+}
+
+/// Starts the single sign-on with the identity provider. The client sends the
+/// user to the URL and keeps the state, the provider redirects back with the
+/// same state and the code for the callback. The client must check, that the
+/// state is the one it started with, so a login can't be injected.
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/{provider}/authorize",
+    tag = "auth",
+    params(("provider" = String, Path, description = "Name of the configured identity provider")),
+    responses(
+        (status = 200, body = OidcAuthorizeResponse),
+        (status = 404, description = "The provider is not configured", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn oidc_authorize(
+    State(ctx): State<AppContext>,
+    Path(provider): Path<String>,
+) -> AppResult<Response> {
+    let settings = Settings::from_config(&ctx.config)?;
+    let client = oidc::Client::discover(find_provider(&settings, &provider)?).await?;
+
+    let (login, state) =
+        oidc_logins::Model::start(&ctx.db, &provider, settings.oidc.login_ttl()).await?;
+    let url = client.authorization_url(&state, &login.nonce, &login.code_verifier)?;
+
+    Ok(format::json(OidcAuthorizeResponse { url, state })?)
+}
+
+/// Finishes the single sign-on: exchanges the code for the ID token of the
+/// user and logs in the user, that the identity is linked to. The provider
+/// takes care of the second factor, so the one-time code is not required,
+/// except for linking an account with the two-factor authentication. The
+/// login is started again with the code then.
+#[utoipa::path(
+    post,
+    path = "/api/auth/oidc/{provider}/callback",
+    tag = "auth",
+    params(("provider" = String, Path, description = "Name of the configured identity provider")),
+    request_body = OidcCallbackParams,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 401, description = "The code or the ID token is invalid, or the one-time code is required", body = ErrorResponse),
+        (status = 403, description = "The identity can't be linked to an account", body = ErrorResponse),
+        (status = 422, description = "The state is invalid or expired", body = ErrorResponse),
+        (status = 429, description = "Too many failed one-time codes", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn oidc_callback(
+    State(ctx): State<AppContext>,
+    Extension(limiter): Extension<Arc<RateLimiter>>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Json(params): Json<OidcCallbackParams>,
+) -> AppResult<Response> {
+    let settings = Settings::from_config(&ctx.config)?;
+    let config = find_provider(&settings, &provider)?;
+
+    let login =
+        oidc_logins::Model::finish(&ctx.db, &provider, &params.state, settings.oidc.login_ttl())
+            .await?
+            .ok_or_else(|| {
+                AppError::validation("invalid_state", "The login is unknown or expired", vec![
+                    FieldError::new("state", "is not found"),
+                ])
+            })?;
+
+    let client = oidc::Client::discover(config).await?;
+    let claims = client
+        .exchange_code(&params.code, &login.code_verifier, &login.nonce)
+        .await?;
+
+    let user = match user_identities::Model::find_user(&ctx.db, &provider, &claims.sub).await? {
+        Some(user) => user,
+        None => {
+            link_identity(
+                &ctx,
+                &limiter,
+                &provider,
+                config,
+                &claims,
+                params.totp_code.as_deref(),
+            )
+            .await?
+        },
+    };
+
+    create_session(&ctx, &user, &headers).await
 }
 
+/// Links the new identity to the account of its email, the account is
+/// created if needed. Only verified emails of the domains, that the provider
+/// is trusted with, are linked. The provider only vouches for the email, so
+/// accounts with the two-factor authentication are linked with their code.
+async fn link_identity(
+    ctx: &AppContext,
+    limiter: &RateLimiter,
+    provider: &str,
+    config: &OidcProvider,
+    claims: &IdTokenClaims,
+    totp_code: Option<&str>,
+) -> AppResult<users::Model> {
+    let email = claims
+        .email
+        .as_deref()
+        .filter(|email| claims.email_verified && config.can_provision(email))
+        .ok_or_else(|| {
+            AppError::Forbidden {
+                code: "identity_not_linked",
+                message: "No account is linked to the identity".to_string(),
+            }
+        })?;
+
+    let user = match users::Model::find_by_email(&ctx.db, email).await {
+        Ok(user) if user.has_totp() => confirm_second_factor(ctx, limiter, user, totp_code).await?,
+        Ok(user) if user.email_verified_at.is_some() => user,
+        // whoever registered the email didn't prove to own it, so the
+        // password is replaced
+        Ok(user) => {
+            let user = user
+                .into_active_model()
+                .reset_password(&ctx.db, &uuid::Uuid::new_v4().to_string())
+                .await?;
+            let user = user.into_active_model().verified(&ctx.db).await?;
+            template_invitations::Model::accept_for_user(&ctx.db, &user).await?;
+            user
+        },
+        Err(ModelError::EntityNotFound) => {
+            let user =
+                users::Model::create_from_identity(&ctx.db, email, claims.name.as_deref()).await?;
+            tracing::info!(pid = user.pid.to_string(), provider, "user provisioned");
+            template_invitations::Model::accept_for_user(&ctx.db, &user).await?;
+            user
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    user_identities::Model::link(
+        &ctx.db,
+        &user,
+        provider,
+        &claims.sub,
+        Some(email.to_string()),
+    )
+    .await?;
+    tracing::info!(pid = user.pid.to_string(), provider, "identity linked");
+
+    Ok(user)
+}
+
+/// Checks the one-time code of the account, that the identity is linked to.
+/// Wrong codes count as failed logins of the account.
+async fn confirm_second_factor(
+    ctx: &AppContext,
+    limiter: &RateLimiter,
+    user: users::Model,
+    code: Option<&str>,
+) -> AppResult<users::Model> {
+    let Some(code) = code else {
+        return Err(AppError::unauthorized(
+            "totp_required",
+            "The one-time code is required",
+        ));
+    };
+
+    let account = normalize_account(&user.email);
+    if limiter.locked_for(&account).await?.is_some() {
+        return Err(AppError::too_many_requests(
+            "account_locked",
+            "Too many failed logins, try again later",
+        ));
+    }
+
+    match user.check_second_factor(&ctx.db, code, totp::now()).await {
+        Ok(user) => {
+            limiter.reset_failures(&account).await?;
+            Ok(user)
+        },
+        Err(err) => {
+            limiter.record_failure(&account).await?;
+            Err(err)
+        },
+    }
+}
+
+/// Issues a new access token for the refresh token. The refresh token is
+/// rotated, so the used one can't be used again.
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    tag = "auth",
+    request_body = RefreshParams,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 401, description = "The refresh token is invalid or expired", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn refresh(
+    State(ctx): State<AppContext>,
+    Json(params): Json<RefreshParams>,
+) -> AppResult<Response> {
+    let invalid_token = || {
+        AppError::unauthorized(
+            "invalid_refresh_token",
+            "The refresh token is invalid or expired",
+        )
+    };
+
+    let Some(session) =
+        sessions::Model::find_active_by_refresh_token(&ctx.db, &params.refresh_token).await?
+    else {
+        // a replaced token is used again, either by a thief or by the client,
+        // after a thief refreshed first, so nobody can refresh the session
+        if let Some(session) =
+            sessions::Model::find_by_used_refresh_token(&ctx.db, &params.refresh_token).await?
+        {
+            session.into_active_model().revoke(&ctx.db).await?;
+        }
+        return Err(invalid_token());
+    };
+    let user = users::Entity::find_by_id(session.user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(invalid_token)?;
+
+    let settings = Settings::from_config(&ctx.config)?;
+    let (session, refresh_token) = session
+        .into_active_model()
+        .rotate(&ctx.db, settings.auth.refresh_token_ttl())
+        .await?;
+
+    session_response(&ctx, &user, &session, &refresh_token)
+}
+
+/// Revokes the current session, its access and refresh tokens stop working.
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    tag = "auth",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The session is revoked"),
+        (status = 401, body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn logout(jwt: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    jwt.session.into_active_model().revoke(&ctx.db).await?;
+    Ok(format::json(())?)
+}
+
+/// Revokes all sessions of the user, including the current one.
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout-all",
+    tag = "auth",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "All sessions are revoked"),
+        (status = 401, body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn logout_all(jwt: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    sessions::Model::revoke_all_for_user(&ctx.db, jwt.user.id).await?;
+    Ok(format::json(())?)
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    register,
+    verify,
+    resend_verification,
+    login,
+    refresh,
+    logout,
+    logout_all,
+    forgot,
+    reset,
+    oidc_authorize,
+    oidc_callback,
+))]
+pub struct ApiDoc;
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("api/auth")
         .add("/register", post(register))
         .add("/verify", post(verify))
+        .add("/resend-verification", post(resend_verification))
         .add("/login", post(login))
+        .add("/refresh", post(refresh))
+        .add("/logout", post(logout))
+        .add("/logout-all", post(logout_all))
         .add("/forgot", post(forgot))
         .add("/reset", post(reset))
+        .add("/oidc/:provider/authorize", get(oidc_authorize))
+        .add("/oidc/:provider/callback", post(oidc_callback))
 }
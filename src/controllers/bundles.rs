@@ -0,0 +1,132 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use axum::extract::{Multipart, Query};
+use axum_extra::response::Attachment;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::bundle::Bundle;
+use crate::controllers::templates::comma_separated;
+use crate::errors::{AppError, AppResult};
+use crate::middlewares::api_token::{TemplatesRead, TemplatesWrite};
+use crate::middlewares::TokenWithScope;
+use crate::models::bundles::{self, ConflictPolicy};
+use crate::views::bundle::ImportResponse;
+use crate::views::error::{ErrorResponse, FieldError};
+
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParams {
+    /// Comma-separated list of template IDs.
+    #[serde(default, with = "comma_separated")]
+    #[param(value_type = String)]
+    pub ids: Vec<i32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportParams {
+    /// What happens to templates, whose names the user already has.
+    #[serde(default)]
+    pub conflicts: ConflictPolicy,
+}
+
+/// Parts of the multipart form, that imports the bundle.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BundleForm {
+    /// The ZIP from the export.
+    #[schema(value_type = String, format = Binary)]
+    pub bundle: Vec<u8>,
+}
+
+/// Bundles the templates with their files, categories and sample data, so
+/// they can be imported on another instance.
+#[utoipa::path(
+    get,
+    path = "/api/templates/export",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:read"])),
+    params(ExportParams),
+    responses(
+        (status = 200, description = "The bundle", content_type = "application/zip"),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "A template is private", body = ErrorResponse),
+        (status = 404, description = "A template is not found", body = ErrorResponse),
+        (status = 422, description = "No templates are given", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn export(
+    token: TokenWithScope<TemplatesRead>,
+    State(ctx): State<AppContext>,
+    Query(params): Query<ExportParams>,
+) -> AppResult<impl IntoResponse> {
+    let bundle = bundles::export(&ctx.db, &params.ids, &token.user).await?;
+    let bundle = bundle.write().map_err(AppError::internal)?;
+
+    let response = Attachment::new(bundle)
+        .filename("templates.zip")
+        .content_type("application/zip");
+
+    Ok(response)
+}
+
+/// Imports the templates of the bundle as templates of the user. Categories
+/// are matched by name and created, if missing.
+#[utoipa::path(
+    post,
+    path = "/api/templates/import",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    params(ImportParams),
+    request_body(content = BundleForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = ImportResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 422, description = "Invalid bundle, DSL or sample data", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn import(
+    token: TokenWithScope<TemplatesWrite>,
+    State(ctx): State<AppContext>,
+    Query(params): Query<ImportParams>,
+    mut multipart: Multipart,
+) -> AppResult<Response> {
+    token.user.require_verified()?;
+
+    let mut bundle = None;
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+
+        match name.as_str() {
+            "bundle" => bundle = Some(field.bytes().await?),
+            _ => return Err(AppError::unexpected_part(&name)),
+        }
+    }
+
+    let bundle = bundle.ok_or_else(|| AppError::missing_part("bundle"))?;
+    let bundle = Bundle::read(&bundle).map_err(|err| {
+        AppError::validation("invalid_bundle", "The bundle can't be read", vec![
+            FieldError::new("bundle", err.to_string()),
+        ])
+    })?;
+
+    let report = bundles::import(&ctx.db, &bundle, &token.user, params.conflicts).await?;
+
+    Ok(format::json(ImportResponse::new(&report))?)
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(export, import), components(schemas(ConflictPolicy)))]
+pub struct ApiDoc;
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/templates")
+        .add("/export", get(export))
+        .add("/import", post(import))
+}
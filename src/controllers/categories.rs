@@ -0,0 +1,121 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use axum::extract::Query;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::templates::ListParams;
+use crate::errors::AppResult;
+use crate::middlewares::{Json, JwtWithUser, MaybeJwtWithUser};
+use crate::models::loaders::TemplateRelations;
+use crate::models::{categories, templates};
+use crate::views::category::{DetailsResponse, WithCountResponse};
+use crate::views::template::PageResponse;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryParams {
+    pub name: String,
+    /// Parent category, `None` for a top-level one.
+    pub parent_id: Option<i32>,
+}
+
+#[debug_handler]
+async fn list(
+    MaybeJwtWithUser(maybe_jwt): MaybeJwtWithUser,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_jwt.map(|jwt| jwt.user.id);
+
+    let categories = categories::Model::find_all(&ctx.db).await?;
+    let counts = categories::Model::count_visible_templates(&ctx.db, maybe_user_id).await?;
+
+    let response = categories
+        .iter()
+        .map(|category| {
+            WithCountResponse::new(category, counts.get(&category.id).copied().unwrap_or(0))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(format::json(response)?)
+}
+
+#[debug_handler]
+async fn get_one(Path(id): Path<i32>, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let category = categories::Model::find_by_id(&ctx.db, id).await?;
+
+    Ok(format::json(DetailsResponse::new(&category))?)
+}
+
+#[debug_handler]
+async fn get_templates(
+    MaybeJwtWithUser(maybe_jwt): MaybeJwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Query(mut params): Query<ListParams>,
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_jwt.map(|jwt| jwt.user.id);
+
+    let cursor = params.decode_cursor()?;
+    params.categories = categories::Model::find_subtree_ids(&ctx.db, id).await?;
+
+    let page = templates::Model::find_visible_page(&ctx.db, maybe_user_id, &params, cursor).await?;
+
+    let response = TemplateRelations::load_responses(&ctx.db, &page.templates).await?;
+
+    Ok(format::json(PageResponse {
+        templates: response,
+        total: page.total,
+        next_cursor: page.next_cursor,
+    })?)
+}
+
+#[debug_handler]
+async fn create(
+    jwt_with_user: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CategoryParams>,
+) -> AppResult<Response> {
+    let category = categories::Model::create(&ctx.db, &params, &jwt_with_user.user).await?;
+
+    Ok(format::json(DetailsResponse::new(&category))?)
+}
+
+#[debug_handler]
+async fn update(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CategoryParams>,
+) -> AppResult<Response> {
+    let category = categories::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .update_category(&ctx.db, &params, &jwt_with_user.user)
+        .await?;
+
+    Ok(format::json(DetailsResponse::new(&category))?)
+}
+
+#[debug_handler]
+async fn remove(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    categories::Model::delete_category(&ctx.db, id, &jwt_with_user.user).await?;
+
+    Ok(format::json(())?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/categories")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:id", get(get_one))
+        .add("/:id", put(update))
+        .add("/:id", delete(remove))
+        .add("/:id/templates", get(get_templates))
+}
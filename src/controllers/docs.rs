@@ -0,0 +1,27 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use axum::response::Html;
+use loco_rs::prelude::*;
+use utoipa_scalar::Scalar;
+
+use crate::errors::AppResult;
+use crate::openapi;
+
+#[debug_handler]
+async fn spec() -> AppResult<Response> {
+    Ok(format::json(openapi::document())?)
+}
+
+/// The API reference, that renders the document.
+#[debug_handler]
+async fn reference() -> Html<String> {
+    Html(Scalar::new(openapi::document()).to_html())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api")
+        .add("/openapi.json", get(spec))
+        .add("/docs", get(reference))
+}
@@ -0,0 +1,116 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppResult;
+use crate::middlewares::{Json, JwtWithUser};
+use crate::models::documents;
+use crate::views::document::{DetailsResponse, ListResponse};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDocumentParams {
+    pub template_id: i32,
+    /// Name of the draft, the template's name by default.
+    pub name: Option<String>,
+    /// Values filled so far, keyed by variable name.
+    #[serde(default = "empty_data")]
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveDocumentParams {
+    pub name: String,
+    pub data: serde_json::Value,
+}
+
+fn empty_data() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+#[debug_handler]
+async fn list(jwt_with_user: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let documents =
+        documents::Model::find_with_templates_for_user(&ctx.db, jwt_with_user.user.id).await?;
+
+    let response = documents
+        .iter()
+        .map(|(document, template)| ListResponse::new(document, template))
+        .collect::<Vec<_>>();
+
+    Ok(format::json(response)?)
+}
+
+#[debug_handler]
+async fn create(
+    jwt_with_user: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateDocumentParams>,
+) -> AppResult<Response> {
+    let document = documents::Model::create(&ctx.db, &params, jwt_with_user.user.id).await?;
+
+    Ok(format::json(DetailsResponse::new(&document))?)
+}
+
+#[debug_handler]
+async fn get_one(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let document = documents::Model::reopen(&ctx.db, id, jwt_with_user.user.id).await?;
+
+    Ok(format::json(DetailsResponse::new(&document))?)
+}
+
+#[debug_handler]
+async fn save(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<SaveDocumentParams>,
+) -> AppResult<Response> {
+    let document = documents::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .save_document(&ctx.db, &params, jwt_with_user.user.id)
+        .await?;
+
+    Ok(format::json(DetailsResponse::new(&document))?)
+}
+
+#[debug_handler]
+async fn duplicate(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let document = documents::Model::duplicate(&ctx.db, id, jwt_with_user.user.id).await?;
+
+    Ok(format::json(DetailsResponse::new(&document))?)
+}
+
+#[debug_handler]
+async fn remove(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    documents::Model::delete_document(&ctx.db, id, jwt_with_user.user.id).await?;
+
+    Ok(format::json(())?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/documents")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:id", get(get_one))
+        .add("/:id", put(save))
+        .add("/:id", delete(remove))
+        .add("/:id/duplicate", post(duplicate))
+}
@@ -0,0 +1,119 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use axum::extract::Multipart;
+use axum_extra::response::Attachment;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{AppError, AppResult};
+use crate::middlewares::api_token::Render;
+use crate::middlewares::TokenWithScope;
+use crate::models::merge_jobs::{self, Mapping};
+use crate::spreadsheet::{Format, Table};
+use crate::views::error::FieldError;
+use crate::views::merge::DetailsResponse;
+use crate::workers::mail_merge::{MailMergeWorker, MailMergeWorkerArgs};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeParams {
+    pub template_id: i32,
+    /// Column headers mapped to paths of variables, e.g.
+    /// `{ "ФИО": "tenant.Individual.name" }`.
+    pub mapping: Mapping,
+}
+
+async fn extract_multipart(mut multipart: Multipart) -> AppResult<(MergeParams, Table)> {
+    let mut params: Option<MergeParams> = None;
+    let mut table: Option<Table> = None;
+
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+
+        match name.as_str() {
+            "json" => {
+                let json = field.text().await?;
+                params = Some(
+                    serde_json::from_str(&json)
+                        .map_err(|err| AppError::invalid_json_part("json", &err))?,
+                );
+            },
+            "file" => {
+                let format = field
+                    .file_name()
+                    .and_then(Format::from_file_name)
+                    .ok_or_else(|| {
+                        AppError::validation(
+                            "unsupported_file",
+                            "Expected a CSV or XLSX file",
+                            vec![FieldError::new("file", "must be a .csv or .xlsx file")],
+                        )
+                    })?;
+                let bytes = field.bytes().await?;
+                table = Some(Table::parse(&bytes, format).map_err(|err| {
+                    AppError::validation("invalid_table", "The table can't be read", vec![
+                        FieldError::new("file", err.to_string()),
+                    ])
+                })?);
+            },
+            _ => return Err(AppError::unexpected_part(&name)),
+        }
+    }
+
+    let params = params.ok_or_else(|| AppError::missing_part("json"))?;
+    let table = table.ok_or_else(|| AppError::missing_part("file"))?;
+
+    Ok((params, table))
+}
+
+#[debug_handler]
+async fn create(
+    token: TokenWithScope<Render>,
+    State(ctx): State<AppContext>,
+    multipart: Multipart,
+) -> AppResult<Response> {
+    let (params, table) = extract_multipart(multipart).await?;
+
+    let job = merge_jobs::Model::create(&ctx.db, &params, token.user.id, &table).await?;
+
+    MailMergeWorker::perform_later(&ctx, MailMergeWorkerArgs { job_id: job.id }).await?;
+
+    Ok(format::json(DetailsResponse::new(&job)?)?)
+}
+
+#[debug_handler]
+async fn get_one(
+    token: TokenWithScope<Render>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let job = merge_jobs::Model::find_by_id_for_user(&ctx.db, id, token.user.id).await?;
+
+    Ok(format::json(DetailsResponse::new(&job)?)?)
+}
+
+#[debug_handler]
+async fn get_result(
+    token: TokenWithScope<Render>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<impl IntoResponse> {
+    let job = merge_jobs::Model::find_by_id_for_user(&ctx.db, id, token.user.id).await?;
+
+    let archive = job.find_result().await?;
+
+    let response = Attachment::new(archive)
+        .filename(format!("merge-{}.zip", job.id))
+        .content_type("application/zip");
+
+    Ok(response)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/merges")
+        .add("/", post(create))
+        .add("/:id", get(get_one))
+        .add("/:id/result", get(get_result))
+}
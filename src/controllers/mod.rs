@@ -1,4 +1,12 @@
+pub mod admin;
+pub mod api_tokens;
 pub mod auth;
+pub mod bundles;
+pub mod categories;
+pub mod docs;
+pub mod documents;
+pub mod merges;
+pub mod organizations;
 pub mod user;
 
 pub mod templates;
@@ -0,0 +1,154 @@
+#![allow(clippy::unused_async)]
+
+use axum::debug_handler;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppResult;
+use crate::middlewares::{Json, JwtWithUser};
+use crate::models::_entities::sea_orm_active_enums::OrganizationRole;
+use crate::models::organizations;
+use crate::views::organization::{DetailsResponse, MemberResponse, WithRoleResponse};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationParams {
+    pub name: String,
+    /// Organization of the new team, `None` for a new organization.
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameParams {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberParams {
+    pub email: String,
+    #[serde(default)]
+    pub role: OrganizationRole,
+}
+
+#[debug_handler]
+async fn list(jwt_with_user: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let organizations = organizations::Model::find_for_user(&ctx.db, jwt_with_user.user.id).await?;
+
+    Ok(format::json(
+        organizations
+            .iter()
+            .map(|(organization, role)| WithRoleResponse::new(organization, *role))
+            .collect::<Vec<_>>(),
+    )?)
+}
+
+#[debug_handler]
+async fn get_one(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let organization =
+        organizations::Model::find_by_id_for_member(&ctx.db, id, jwt_with_user.user.id).await?;
+    let members = organization.find_members(&ctx.db).await?;
+    let teams = organization.find_teams(&ctx.db).await?;
+
+    Ok(format::json(DetailsResponse::new(
+        &organization,
+        &members,
+        &teams,
+    ))?)
+}
+
+#[debug_handler]
+async fn create(
+    jwt_with_user: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<OrganizationParams>,
+) -> AppResult<Response> {
+    let organization =
+        organizations::Model::create(&ctx.db, &params, jwt_with_user.user.id).await?;
+    let members = organization.find_members(&ctx.db).await?;
+
+    Ok(format::json(DetailsResponse::new(
+        &organization,
+        &members,
+        &[],
+    ))?)
+}
+
+#[debug_handler]
+async fn update(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<RenameParams>,
+) -> AppResult<Response> {
+    let organization = organizations::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .rename(&ctx.db, &params, jwt_with_user.user.id)
+        .await?;
+    let members = organization.find_members(&ctx.db).await?;
+    let teams = organization.find_teams(&ctx.db).await?;
+
+    Ok(format::json(DetailsResponse::new(
+        &organization,
+        &members,
+        &teams,
+    ))?)
+}
+
+#[debug_handler]
+async fn remove(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    organizations::Model::delete_organization(&ctx.db, id, jwt_with_user.user.id).await?;
+
+    Ok(format::json(())?)
+}
+
+#[debug_handler]
+async fn set_member(
+    jwt_with_user: JwtWithUser,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<MemberParams>,
+) -> AppResult<Response> {
+    let organization = organizations::Model::find_by_id(&ctx.db, id).await?;
+    let (member, user) = organization
+        .set_member(&ctx.db, &params, jwt_with_user.user.id)
+        .await?;
+
+    Ok(format::json(MemberResponse::new(&member, &user))?)
+}
+
+#[debug_handler]
+async fn remove_member(
+    jwt_with_user: JwtWithUser,
+    Path((id, pid)): Path<(i32, String)>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let organization = organizations::Model::find_by_id(&ctx.db, id).await?;
+    organization
+        .remove_member(&ctx.db, &pid, jwt_with_user.user.id)
+        .await?;
+
+    Ok(format::json(())?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/organizations")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:id", get(get_one))
+        .add("/:id", put(update))
+        .add("/:id", delete(remove))
+        .add("/:id/members", put(set_member))
+        .add("/:id/members/:pid", delete(remove_member))
+}
@@ -1,18 +1,28 @@
 #![allow(clippy::unused_async)]
 
 use axum::debug_handler;
-use axum::extract::Multipart;
+use axum::extract::{Multipart, Query};
+use axum::http::header::{ACCEPT_RANGES, CONTENT_RANGE};
+use axum::http::{HeaderMap, StatusCode};
 use axum_extra::response::Attachment;
-use cicero_dsl::compiler::compile_types;
-use loco_rs::prelude::auth::JWTWithUser;
+use cicero_dsl::compiler::{self, compile_types};
 use loco_rs::prelude::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-
-use crate::middlewares::MaybeJwtWithUser;
-use crate::models::{categories, templates, users};
-use crate::views::template::{CreateResponse, WithCategoriesResponse};
-
-#[derive(Debug, Deserialize, Serialize)]
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::conditional::{self, ByteRange, Validators};
+use crate::errors::{AppError, AppResult};
+use crate::mailers::template::TemplateMailer;
+use crate::middlewares::api_token::{TemplatesRead, TemplatesWrite};
+use crate::middlewares::{Json, MaybeTokenWithScope, TokenWithScope};
+use crate::models::loaders::TemplateRelations;
+use crate::models::templates::Cursor;
+use crate::models::{template_invitations, templates, users};
+use crate::views::error::ErrorResponse;
+use crate::views::template::{CreateResponse, PageResponse, WithCategoriesResponse};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateTemplateParams {
     pub name: String,
@@ -22,273 +32,848 @@ pub struct CreateTemplateParams {
     pub publicity: PublicityParams,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTemplateParams {
+    #[serde(flatten)]
+    pub template: CreateTemplateParams,
+    /// The revision, that the changes are based on. It's required, unless
+    /// `If-Match` is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i32>,
+}
+
+/// Metadata to change, the rest of the template is kept. The publicity is
+/// sent as in [`CreateTemplateParams`] and replaces the viewers and
+/// organizations too.
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase", try_from = "PatchTemplateJson")]
+pub struct PatchTemplateParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<i32>>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub publicity: Option<PublicityParams>,
+    /// See [`UpdateTemplateParams::revision`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i32>,
+}
+
+/// A flattened `Option` swallows errors of the publicity, so it's
+/// deserialized from the rest of the fields by hand.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatchTemplateJson {
+    name: Option<String>,
+    description: Option<String>,
+    categories: Option<Vec<i32>>,
+    revision: Option<i32>,
+    #[serde(flatten)]
+    rest: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TryFrom<PatchTemplateJson> for PatchTemplateParams {
+    type Error = serde_json::Error;
+
+    fn try_from(json: PatchTemplateJson) -> Result<Self, Self::Error> {
+        let has_publicity = ["publicity", "viewers", "organizations"]
+            .iter()
+            .any(|field| json.rest.contains_key(*field));
+        let publicity = has_publicity
+            .then(|| serde_json::from_value(serde_json::Value::Object(json.rest)))
+            .transpose()?;
+
+        Ok(Self {
+            name: json.name,
+            description: json.description,
+            categories: json.categories,
+            publicity,
+            revision: json.revision,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase", tag = "publicity")]
 pub enum PublicityParams {
     Public,
-    Private { viewers: Vec<String> },
+    Private {
+        viewers: Vec<String>,
+        /// Organizations and teams of the author, whose members can see the
+        /// template.
+        #[serde(default)]
+        organizations: Vec<i32>,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ListParams {
+    /// Case-insensitive substring of the name or the description.
+    pub search: Option<String>,
+    /// Comma-separated list of category IDs, matches any of them.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        with = "comma_separated"
+    )]
+    #[param(value_type = Option<String>)]
+    pub categories: Vec<i32>,
+    /// PID of the author.
+    pub author: Option<Uuid>,
+    pub publicity: Option<PublicityFilter>,
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub created_from: Option<DateTimeWithTimeZone>,
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub created_to: Option<DateTimeWithTimeZone>,
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub updated_from: Option<DateTimeWithTimeZone>,
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub updated_to: Option<DateTimeWithTimeZone>,
+    #[serde(default)]
+    pub sort: SortOrder,
+    /// Opaque cursor from the `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PublicityFilter {
+    Public,
+    Private,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    #[default]
+    Newest,
+    Name,
+    /// Most downloaded `docx` first.
+    Popularity,
+}
+
+impl ListParams {
+    /// Decodes the cursor for the requested sort order.
+    ///
+    /// # Errors
+    ///
+    /// When the cursor is malformed or was issued for another sort order
+    pub fn decode_cursor(&self) -> AppResult<Option<Cursor>> {
+        self.cursor
+            .as_deref()
+            .map(|cursor| {
+                Cursor::decode(cursor, self.sort)
+                    .ok_or_else(|| AppError::bad_request("invalid_cursor", "The cursor is invalid"))
+            })
+            .transpose()
+    }
+}
+
+/// IDs in the query string, e.g. `categories=1,2`.
+pub(crate) mod comma_separated {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(ids: &[i32], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = ids
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        serializer.serialize_str(&value)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<i32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse().map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ValidateParams {
     dsl: String,
 }
 
-async fn extract_multipart(
+/// Parts of the multipart form, that creates the template.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct TemplateForm {
+    /// The JSON of [`CreateTemplateParams`].
+    pub json: CreateTemplateParams,
+    #[schema(value_type = String, format = Binary)]
+    pub docx: Vec<u8>,
+    /// The source of the template variables.
+    pub dsl: String,
+}
+
+/// Parts of the multipart form, that replaces the template.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdateTemplateForm {
+    /// The JSON of [`UpdateTemplateParams`].
+    pub json: UpdateTemplateParams,
+    #[schema(value_type = String, format = Binary)]
+    pub docx: Vec<u8>,
+    /// The source of the template variables.
+    pub dsl: String,
+}
+
+/// The multipart form, that replaces only the docx.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DocxForm {
+    #[schema(value_type = String, format = Binary)]
+    pub docx: Vec<u8>,
+}
+
+async fn extract_multipart<T: DeserializeOwned>(
     mut multipart: Multipart,
-) -> Result<(CreateTemplateParams, Vec<u8>, String)> {
-    let mut params: Option<CreateTemplateParams> = None;
+) -> AppResult<(T, Vec<u8>, String)> {
+    let mut params: Option<T> = None;
     let mut docx: Option<Vec<u8>> = None;
     let mut dsl: Option<String> = None;
 
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|_| Error::BadRequest("Invalid multipart".into()))?
-    {
-        let name = field
-            .name()
-            .ok_or_else(|| Error::BadRequest("Invalid multipart".into()))?;
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
 
-        match name {
+        match name.as_str() {
             "json" => {
-                let json = field
-                    .text()
-                    .await
-                    .map_err(|_| Error::BadRequest("Invalid multipart".into()))?;
-                params = Some(serde_json::from_str(&json)?);
-            },
-            "docx" => {
-                docx =
-                    Some(Vec::from(field.bytes().await.map_err(|_| {
-                        Error::BadRequest("Invalid multipart".into())
-                    })?));
-            },
-            "dsl" => {
-                dsl = Some(
-                    field
-                        .text()
-                        .await
-                        .map_err(|_| Error::BadRequest("Invalid multipart".into()))?,
+                let json = field.text().await?;
+                params = Some(
+                    serde_json::from_str(&json)
+                        .map_err(|err| AppError::invalid_json_part("json", &err))?,
                 );
             },
-            _ => return Err(Error::BadRequest("Invalid multipart".into())),
+            "docx" => docx = Some(Vec::from(field.bytes().await?)),
+            "dsl" => dsl = Some(field.text().await?),
+            _ => return Err(AppError::unexpected_part(&name)),
         }
     }
 
-    let params = params.ok_or_else(|| Error::BadRequest("Invalid multipart".into()))?;
-    let docx = docx.ok_or_else(|| Error::BadRequest("Invalid multipart".into()))?;
-    let dsl = dsl.ok_or_else(|| Error::BadRequest("Invalid multipart".into()))?;
+    let params = params.ok_or_else(|| AppError::missing_part("json"))?;
+    let docx = docx.ok_or_else(|| AppError::missing_part("docx"))?;
+    let dsl = dsl.ok_or_else(|| AppError::missing_part("dsl"))?;
 
     Ok((params, docx, dsl))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/templates",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    request_body(content = TemplateForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = CreateResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 422, description = "Invalid values, DSL or parts", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn create_template(
-    jwt_with_user: JWTWithUser<users::Model>,
+    token: TokenWithScope<TemplatesWrite>,
     State(ctx): State<AppContext>,
     multipart: Multipart,
-) -> Result<Response> {
+) -> AppResult<Response> {
+    token.user.require_verified()?;
+
     let (params, docx, dsl) = extract_multipart(multipart).await?;
 
     let template = templates::Model::create(
         &ctx.db,
         &params,
-        jwt_with_user.user.id,
+        token.user.id,
         docx.as_slice(),
         dsl.as_str(),
     )
     .await?;
 
+    send_invitations(&ctx, &template, &token.user).await;
+
     let response = CreateResponse::new(&template);
 
-    format::json(response)
+    Ok(format::json(response)?)
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/templates/{id}",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = Option<String>, Header, description = "The revision, e.g. `\"3\"`, or `*` for any"),
+    ),
+    request_body(content = UpdateTemplateForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = WithCategoriesResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+        (status = 412, description = "The template was changed since the revision", body = ErrorResponse),
+        (status = 422, description = "Invalid values, DSL or parts", body = ErrorResponse),
+        (status = 428, description = "The revision is not sent", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn update_template(
-    jwt_with_user: JWTWithUser<users::Model>,
+    token: TokenWithScope<TemplatesWrite>,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
+    headers: HeaderMap,
     multipart: Multipart,
-) -> Result<Response> {
-    let (params, docx, dsl) = extract_multipart(multipart).await?;
+) -> AppResult<Response> {
+    let (mut params, docx, dsl) = extract_multipart::<UpdateTemplateParams>(multipart).await?;
+    params.revision = conditional::expected_revision(&headers, params.revision)?;
 
-    let template = templates::Model::find_by_id_for_user(&ctx.db, id, jwt_with_user.user.id)
+    let template = templates::Model::find_by_id(&ctx.db, id)
         .await?
         .into_active_model()
         .update_template(
             &ctx.db,
             &params,
             id,
-            jwt_with_user.user.id,
+            &token.user,
             docx.as_slice(),
             dsl.as_str(),
         )
         .await?;
 
-    let author = users::Model::find_template_author(&ctx.db, template.user_id).await?;
-    let categories = categories::Model::find_for_template(&ctx.db, id).await?;
-    let viewers = users::Model::find_template_viewers(&ctx.db, id).await?;
+    send_invitations(&ctx, &template, &token.user).await;
+
+    let response = TemplateRelations::load(&ctx.db, std::slice::from_ref(&template))
+        .await?
+        .response(&template)?;
+
+    Ok(format::json(response)?)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/templates/{id}",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = Option<String>, Header, description = "The revision, e.g. `\"3\"`, or `*` for any"),
+    ),
+    request_body = PatchTemplateParams,
+    responses(
+        (status = 200, body = WithCategoriesResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+        (status = 412, description = "The template was changed since the revision", body = ErrorResponse),
+        (status = 422, description = "Invalid values", body = ErrorResponse),
+        (status = 428, description = "The revision is not sent", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn patch_template(
+    token: TokenWithScope<TemplatesWrite>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(mut params): Json<PatchTemplateParams>,
+) -> AppResult<Response> {
+    params.revision = conditional::expected_revision(&headers, params.revision)?;
+
+    let template = templates::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .patch_template(&ctx.db, &params, id, &token.user)
+        .await?;
+
+    send_invitations(&ctx, &template, &token.user).await;
+
+    let response = TemplateRelations::load(&ctx.db, std::slice::from_ref(&template))
+        .await?
+        .response(&template)?;
+
+    Ok(format::json(response)?)
+}
+
+/// Reads the only part of the multipart form, that replaces a file.
+async fn extract_file_part(mut multipart: Multipart, part: &str) -> AppResult<Vec<u8>> {
+    let mut file = None;
+
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+        if name != part {
+            return Err(AppError::unexpected_part(&name));
+        }
+        file = Some(Vec::from(field.bytes().await?));
+    }
+
+    file.ok_or_else(|| AppError::missing_part(part))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/templates/{id}/docx",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = String, Header, description = "The revision, e.g. `\"3\"`, or `*` for any"),
+    ),
+    request_body(content = DocxForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = WithCategoriesResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+        (status = 412, description = "The template was changed since the revision", body = ErrorResponse),
+        (status = 422, description = "Invalid docx, or it uses variables, that the DSL doesn't declare", body = ErrorResponse),
+        (status = 428, description = "`If-Match` is not sent", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn replace_docx(
+    token: TokenWithScope<TemplatesWrite>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> AppResult<Response> {
+    let revision = conditional::expected_revision(&headers, None)?;
+    let docx = extract_file_part(multipart, "docx").await?;
+
+    let template = templates::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .replace_docx(&ctx.db, id, revision, &token.user, docx.as_slice())
+        .await?;
+
+    let response = TemplateRelations::load(&ctx.db, std::slice::from_ref(&template))
+        .await?
+        .response(&template)?;
 
-    let response = WithCategoriesResponse::new(&template, &author, &categories, viewers.as_ref());
+    Ok(format::json(response)?)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/templates/{id}/dsl",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = String, Header, description = "The revision, e.g. `\"3\"`, or `*` for any"),
+    ),
+    request_body(content = ValidateParams, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = WithCategoriesResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+        (status = 412, description = "The template was changed since the revision", body = ErrorResponse),
+        (status = 422, description = "Invalid DSL, or it doesn't declare variables of the docx", body = ErrorResponse),
+        (status = 428, description = "`If-Match` is not sent", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn replace_dsl(
+    token: TokenWithScope<TemplatesWrite>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> AppResult<Response> {
+    let revision = conditional::expected_revision(&headers, None)?;
+    let dsl = String::from_utf8(extract_file_part(multipart, "dsl").await?)
+        .map_err(|err| AppError::invalid_dsl("dsl", err.to_string()))?;
 
-    format::json(response)
+    let template = templates::Model::find_by_id(&ctx.db, id)
+        .await?
+        .into_active_model()
+        .replace_dsl(&ctx.db, id, revision, &token.user, dsl.as_str())
+        .await?;
+
+    let response = TemplateRelations::load(&ctx.db, std::slice::from_ref(&template))
+        .await?
+        .response(&template)?;
+
+    Ok(format::json(response)?)
 }
 
+/// Mails invitations to viewers of the template, that don't have an account
+/// yet. Each invitation is mailed once. The template is saved already, so
+/// failures are only logged, and the unsent invitations are mailed on the
+/// next save.
+async fn send_invitations(ctx: &AppContext, template: &templates::Model, inviter: &users::Model) {
+    let result: AppResult<()> = async {
+        for invitation in template_invitations::Model::find_unsent(&ctx.db, template.id).await? {
+            TemplateMailer::send_invitation(ctx, &invitation, template, inviter).await?;
+            invitation.into_active_model().set_sent(&ctx.db).await?;
+        }
+        Ok(())
+    }
+    .await;
+
+    if let Err(err) = result {
+        tracing::error!(
+            template_id = template.id,
+            error = %err,
+            "invitations can't be sent"
+        );
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates/validate",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    request_body(content = ValidateParams, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Types of the DSL by name", body = serde_json::Value),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 422, description = "Invalid DSL", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn validate(
-    _jwt_with_user: JWTWithUser<users::Model>,
+    _token: TokenWithScope<TemplatesWrite>,
     State(_ctx): State<AppContext>,
     mut multipart: Multipart,
-) -> Result<Response> {
-    if let Some(field) = multipart
+) -> AppResult<Response> {
+    let field = multipart
         .next_field()
-        .await
-        .map_err(|_| Error::BadRequest("Invalid multipart".into()))?
-    {
-        let name = field
-            .name()
-            .ok_or_else(|| Error::BadRequest("Invalid multipart".into()))?;
+        .await?
+        .ok_or_else(|| AppError::missing_part("dsl"))?;
 
-        if name != "dsl" {
-            return Err(Error::BadRequest("Invalid multipart".into()));
-        }
+    let name = field.name().unwrap_or_default().to_string();
+    if name != "dsl" {
+        return Err(AppError::unexpected_part(&name));
+    }
 
-        let dsl = field
-            .text()
-            .await
-            .map_err(|_| Error::BadRequest("Invalid multipart".into()))?;
+    let dsl = field.text().await?;
 
-        let types = match compile_types(dsl.as_str()) {
-            Ok(types) => types,
-            Err(err) => return Err(Error::BadRequest(err)),
-        };
+    let types = compile_types(dsl.as_str()).map_err(|err| AppError::invalid_dsl("dsl", err))?;
 
-        return format::json(types);
-    };
-
-    Err(Error::BadRequest("Invalid multipart".into()))
+    Ok(format::json(types)?)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/templates",
+    tag = "templates",
+    security((), ("jwt" = []), ("api_token" = ["templates:read"])),
+    params(ListParams),
+    responses(
+        (status = 200, body = PageResponse),
+        (status = 400, description = "Invalid cursor", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn get_visible(
-    MaybeJwtWithUser(maybe_jwt): MaybeJwtWithUser<users::Model>,
+    MaybeTokenWithScope(maybe_token): MaybeTokenWithScope<TemplatesRead>,
     State(ctx): State<AppContext>,
-) -> Result<Response> {
-    let maybe_user_id = maybe_jwt.map(|jwt| jwt.user.id);
+    Query(params): Query<ListParams>,
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_token.map(|token| token.user.id);
 
-    let templates = templates::Model::find_visible(&ctx.db, maybe_user_id).await?;
+    let cursor = params.decode_cursor()?;
 
-    let mut response = Vec::with_capacity(templates.len());
+    let page = templates::Model::find_visible_page(&ctx.db, maybe_user_id, &params, cursor).await?;
 
-    for template in templates {
-        let author = users::Model::find_template_author(&ctx.db, template.user_id).await?;
-        let categories = categories::Model::find_for_template(&ctx.db, template.id).await?;
-        let viewers = users::Model::find_template_viewers(&ctx.db, template.id).await?;
-
-        response.push(WithCategoriesResponse::new(
-            &template,
-            &author,
-            &categories,
-            viewers.as_ref(),
-        ));
-    }
+    let response = TemplateRelations::load_responses(&ctx.db, &page.templates).await?;
 
-    format::json(response)
+    Ok(format::json(PageResponse {
+        templates: response,
+        total: page.total,
+        next_cursor: page.next_cursor,
+    })?)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}",
+    tag = "templates",
+    security((), ("jwt" = []), ("api_token" = ["templates:read"])),
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = WithCategoriesResponse),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn get_one(
-    MaybeJwtWithUser(maybe_jwt): MaybeJwtWithUser<users::Model>,
+    MaybeTokenWithScope(maybe_token): MaybeTokenWithScope<TemplatesRead>,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
-) -> Result<Response> {
-    let maybe_user_id = maybe_jwt.map(|jwt| jwt.user.id);
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_token.map(|token| token.user.id);
 
     let template = templates::Model::find_visible_by_id(&ctx.db, id, maybe_user_id).await?;
-    let author = users::Model::find_template_author(&ctx.db, template.user_id).await?;
-    let categories = categories::Model::find_for_template(&ctx.db, id).await?;
-    let viewers = users::Model::find_template_viewers(&ctx.db, id).await?;
-
-    let response = WithCategoriesResponse::new(&template, &author, &categories, viewers.as_ref());
+    let response = TemplateRelations::load(&ctx.db, std::slice::from_ref(&template))
+        .await?
+        .response(&template)?;
 
-    format::json(response)
+    Ok(format::json(response)?)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/docx",
+    tag = "templates",
+    security((), ("jwt" = []), ("api_token" = ["templates:read"])),
+    params(
+        ("id" = i32, Path),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of the cached copy"),
+        ("If-Modified-Since" = Option<String>, Header, description = "`Last-Modified` of the cached copy"),
+        ("Range" = Option<String>, Header, description = "A single range of bytes, e.g. `bytes=1024-`"),
+        ("If-Range" = Option<String>, Header, description = "The range is sent only for this `ETag` or date"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The docx file",
+            content_type = "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        ),
+        (
+            status = 206,
+            description = "The requested range of the docx file",
+            content_type = "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        ),
+        (status = 304, description = "The cached copy is current"),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+        (status = 416, description = "The range is out of the file"),
+    )
+)]
 #[debug_handler]
 async fn get_docx(
-    MaybeJwtWithUser(maybe_jwt): MaybeJwtWithUser<users::Model>,
+    MaybeTokenWithScope(maybe_token): MaybeTokenWithScope<TemplatesRead>,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
-) -> Result<impl IntoResponse> {
-    let maybe_user_id = maybe_jwt.map(|jwt| jwt.user.id);
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_token.map(|token| token.user.id);
     let template = templates::Model::find_visible_by_id(&ctx.db, id, maybe_user_id).await?;
 
-    let docx = templates::Model::find_docx(template.id).await?;
+    let validators = Validators::new(
+        &template.find_docx_hash(&ctx.db).await?,
+        template.files_updated_at(),
+        template.is_public,
+    );
+    if validators.is_fresh(&headers) {
+        return Ok(validators.not_modified());
+    }
 
-    let response = Attachment::new(docx)
-        .filename(format!("{}.docx", template.id))
-        .content_type("application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+    let size = templates::Model::find_docx_size(template.id).await?;
+    let range = validators.range(&headers, size);
+
+    // a resumed download is counted once, by its first part
+    if matches!(&range, ByteRange::Full)
+        || matches!(&range, ByteRange::Partial(bytes) if *bytes.start() == 0)
+    {
+        templates::Model::increment_downloads(&ctx.db, template.id).await?;
+    }
+
+    let attachment = |docx: Vec<u8>| {
+        Attachment::new(docx)
+            .filename(format!("{}.docx", template.id))
+            .content_type("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+    };
+    let accept_ranges = [(ACCEPT_RANGES, "bytes")];
+
+    let response = match range {
+        ByteRange::Full => {
+            let docx = templates::Model::find_docx(template.id).await?;
+            (validators.headers(), accept_ranges, attachment(docx)).into_response()
+        },
+        ByteRange::Partial(bytes) => {
+            let content_range = format!("bytes {}-{}/{size}", bytes.start(), bytes.end());
+            let docx = templates::Model::find_docx_range(template.id, bytes).await?;
+            (
+                StatusCode::PARTIAL_CONTENT,
+                validators.headers(),
+                accept_ranges,
+                [(CONTENT_RANGE, content_range)],
+                attachment(docx),
+            )
+                .into_response()
+        },
+        ByteRange::Unsatisfiable => {
+            (StatusCode::RANGE_NOT_SATISFIABLE, validators.headers(), [(
+                CONTENT_RANGE,
+                format!("bytes */{size}"),
+            )])
+                .into_response()
+        },
+    };
 
     Ok(response)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/dsl",
+    tag = "templates",
+    security((), ("jwt" = []), ("api_token" = ["templates:read"])),
+    params(
+        ("id" = i32, Path),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of the cached copy"),
+        ("If-Modified-Since" = Option<String>, Header, description = "`Last-Modified` of the cached copy"),
+    ),
+    responses(
+        (status = 200, description = "The DSL", body = String, content_type = "text/plain"),
+        (status = 304, description = "The cached copy is current"),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn get_dsl(
-    MaybeJwtWithUser(maybe_jwt): MaybeJwtWithUser<users::Model>,
+    MaybeTokenWithScope(maybe_token): MaybeTokenWithScope<TemplatesRead>,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
-) -> Result<impl IntoResponse> {
-    let maybe_user_id = maybe_jwt.map(|jwt| jwt.user.id);
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_token.map(|token| token.user.id);
     let template = templates::Model::find_visible_by_id(&ctx.db, id, maybe_user_id).await?;
 
+    let validators = Validators::new(
+        &template.find_dsl_hash(&ctx.db).await?,
+        template.files_updated_at(),
+        template.is_public,
+    );
+    if validators.is_fresh(&headers) {
+        return Ok(validators.not_modified());
+    }
+
     let dsl = templates::Model::find_dsl(template.id).await?;
 
-    let response = Attachment::new(dsl)
+    let attachment = Attachment::new(dsl)
         .filename(format!("{}.dsl", template.id))
         .content_type("text/plain;encoding=utf-8");
 
-    Ok(response)
+    Ok((validators.headers(), attachment).into_response())
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/dsl/types",
+    tag = "templates",
+    security((), ("jwt" = []), ("api_token" = ["templates:read"])),
+    params(
+        ("id" = i32, Path),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of the cached copy"),
+        ("If-Modified-Since" = Option<String>, Header, description = "`Last-Modified` of the cached copy"),
+    ),
+    responses(
+        (status = 200, description = "Types of the DSL", body = Vec<serde_json::Value>),
+        (status = 304, description = "The cached copy is current"),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn get_dsl_types(
-    MaybeJwtWithUser(maybe_jwt_with_user): MaybeJwtWithUser<users::Model>,
+    MaybeTokenWithScope(maybe_token): MaybeTokenWithScope<TemplatesRead>,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
-) -> Result<Response> {
-    let maybe_user_id = maybe_jwt_with_user.map(|jwt| jwt.user.id);
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let maybe_user_id = maybe_token.map(|token| token.user.id);
 
     let template = templates::Model::find_visible_by_id(&ctx.db, id, maybe_user_id).await?;
-    let dsl = templates::Model::find_dsl(template.id).await?;
 
-    let types = compile_types(dsl.as_str()).map_err(Error::BadRequest)?;
+    // the types change with the DSL, or with the compiler
+    let dsl_hash = template.find_dsl_hash(&ctx.db).await?;
+    let validators = Validators::new(
+        &format!("{dsl_hash}-{}", compiler::VERSION),
+        template.files_updated_at(),
+        template.is_public,
+    );
+    if validators.is_fresh(&headers) {
+        return Ok(validators.not_modified());
+    }
+
+    let types = template.find_dsl_types(&ctx.db).await?;
 
-    format::json(types.into_values().collect::<Vec<_>>())
+    Ok((validators.headers(), format::json(types)?).into_response())
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/templates/{id}",
+    tag = "templates",
+    security(("jwt" = []), ("api_token" = ["templates:write"])),
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = String, Header, description = "The revision, e.g. `\"3\"`, or `*` for any"),
+    ),
+    responses(
+        (status = 200, description = "The template is removed"),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+        (status = 403, description = "The API token doesn't have the scope", body = ErrorResponse),
+        (status = 404, description = "The template is not found or not visible", body = ErrorResponse),
+        (status = 412, description = "The template was changed since the revision", body = ErrorResponse),
+        (status = 428, description = "`If-Match` is not sent", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 async fn delete_template(
-    jwt_with_user: JWTWithUser<users::Model>,
+    token: TokenWithScope<TemplatesWrite>,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
-) -> Result<Response> {
-    templates::Model::delete_template(&ctx.db, id, jwt_with_user.user.id).await?;
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let revision = conditional::expected_revision(&headers, None)?;
+    templates::Model::delete_template(&ctx.db, id, revision, &token.user).await?;
 
-    format::json(())
+    Ok(format::json(())?)
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_visible,
+        create_template,
+        update_template,
+        patch_template,
+        get_one,
+        delete_template,
+        get_docx,
+        replace_docx,
+        get_dsl,
+        replace_dsl,
+        get_dsl_types,
+        validate,
+    ),
+    components(schemas(PublicityFilter, SortOrder))
+)]
+pub struct ApiDoc;
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("api/templates")
         .add("/", get(get_visible))
         .add("/", post(create_template))
         .add("/:id", put(update_template))
+        .add("/:id", patch(patch_template))
         .add("/:id", get(get_one))
         .add("/:id", delete(delete_template))
         .add("/:id/docx", get(get_docx))
+        .add("/:id/docx", put(replace_docx))
         .add("/:id/dsl", get(get_dsl))
+        .add("/:id/dsl", put(replace_dsl))
         .add("/:id/dsl/types", get(get_dsl_types))
         .add("/validate", post(validate))
 }
@@ -1,17 +1,446 @@
 use axum::debug_handler;
 use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
 
-use crate::models::_entities::users;
-use crate::views::user::Response as UserResponse;
+use crate::errors::{AppError, AppResult};
+use crate::mailers::auth::AuthMailer;
+use crate::middlewares::{Json, JwtWithUser};
+use crate::models::loaders::TemplateRelations;
+use crate::models::{
+    api_tokens,
+    categories,
+    documents,
+    merge_jobs,
+    organizations,
+    sessions,
+    template_invitations,
+    templates,
+    user_identities,
+};
+use crate::totp;
+use crate::views::api_token::Response as ApiTokenResponse;
+use crate::views::category::DetailsResponse as CategoryResponse;
+use crate::views::document::DetailsResponse as DocumentResponse;
+use crate::views::error::ErrorResponse;
+use crate::views::export::{AccountResponse, ExportResponse, IdentityResponse, TemplateResponse};
+use crate::views::merge::DetailsResponse as MergeResponse;
+use crate::views::organization::WithRoleResponse as OrganizationResponse;
+use crate::views::session::Response as SessionResponse;
+use crate::views::user::{RecoveryCodesResponse, TotpEnrollmentResponse, WithRoleResponse};
 
+/// Issuer, that authenticator apps show next to the codes.
+const TOTP_ISSUER: &str = "Cicero";
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct TotpParams {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProfileParams {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordParams {
+    /// The current password.
+    pub password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ChangeEmailParams {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteAccountParams {
+    pub password: String,
+    #[serde(flatten)]
+    pub templates: TemplatesDisposal,
+}
+
+/// What happens to the templates of the deleted account, there is no
+/// default, so the user has to choose.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase", tag = "templates")]
+pub enum TemplatesDisposal {
+    /// Templates and the documents of other users from them are deleted.
+    Delete,
+    /// Templates and categories are given to the user with the email.
+    Transfer {
+        #[serde(rename = "transferTo")]
+        transfer_to: String,
+    },
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/current",
+    tag = "user",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = WithRoleResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn current(jwt: JwtWithUser, State(_ctx): State<AppContext>) -> AppResult<Response> {
+    Ok(format::json(WithRoleResponse::new(&jwt.user))?)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/user/profile",
+    tag = "user",
+    security(("jwt" = [])),
+    request_body = ProfileParams,
+    responses(
+        (status = 200, body = WithRoleResponse),
+        (status = 422, description = "Invalid name", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn update_profile(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ProfileParams>,
+) -> AppResult<Response> {
+    let user = jwt
+        .user
+        .into_active_model()
+        .set_name(&ctx.db, &params.name)
+        .await?;
+
+    Ok(format::json(WithRoleResponse::new(&user))?)
+}
+
+/// Changes the password, other devices are logged out.
+#[utoipa::path(
+    post,
+    path = "/api/user/password",
+    tag = "user",
+    security(("jwt" = [])),
+    request_body = ChangePasswordParams,
+    responses(
+        (status = 200, description = "The password is changed"),
+        (status = 422, description = "The current password is wrong", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn change_password(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ChangePasswordParams>,
+) -> AppResult<Response> {
+    jwt.user.confirm_password(&params.password)?;
+
+    jwt.user
+        .into_active_model()
+        .reset_password(&ctx.db, &params.new_password)
+        .await?;
+    jwt.session.revoke_others(&ctx.db).await?;
+
+    Ok(format::json(())?)
+}
+
+/// Starts changing the email, the verification is sent to the new email.
+/// The current email is used, until the new one is verified.
+#[utoipa::path(
+    post,
+    path = "/api/user/email",
+    tag = "user",
+    security(("jwt" = [])),
+    request_body = ChangeEmailParams,
+    responses(
+        (status = 200, body = WithRoleResponse),
+        (status = 409, description = "The email is taken", body = ErrorResponse),
+        (status = 422, description = "The password is wrong or the email is invalid", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn change_email(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ChangeEmailParams>,
+) -> AppResult<Response> {
+    jwt.user.confirm_password(&params.password)?;
+
+    let (user, token) = jwt
+        .user
+        .request_email_change(&ctx.db, params.email.trim())
+        .await?;
+
+    AuthMailer::send_email_change(&ctx, &user, &token).await?;
+
+    Ok(format::json(WithRoleResponse::new(&user))?)
+}
+
+/// Downloads everything, that is stored about the user.
+#[utoipa::path(
+    get,
+    path = "/api/user/export",
+    tag = "user",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = ExportResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
-async fn current(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
-    format::json(UserResponse::new(&user))
+async fn export(jwt: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let user = &jwt.user;
+
+    let own_templates = templates::Model::find_for_author(&ctx.db, user.id).await?;
+    let shared_templates = templates::Model::find_shared_with_user(&ctx.db, user.id).await?;
+    let template_ids = own_templates
+        .iter()
+        .map(|template| template.id)
+        .collect::<Vec<_>>();
+    let invitations =
+        template_invitations::Model::find_for_templates(&ctx.db, &template_ids).await?;
+
+    let relations = TemplateRelations::load(&ctx.db, &own_templates).await?;
+    let mut templates = Vec::with_capacity(own_templates.len());
+    for template in &own_templates {
+        templates.push(TemplateResponse::new(
+            template,
+            relations.response(template)?,
+            &invitations,
+            templates::Model::find_dsl(template.id).await.ok(),
+            templates::Model::find_docx(template.id)
+                .await
+                .ok()
+                .as_deref(),
+        ));
+    }
+
+    let sessions = sessions::Model::find_active_for_user(&ctx.db, user.id).await?;
+    let api_tokens = api_tokens::Model::find_for_user(&ctx.db, user.id).await?;
+    let identities = user_identities::Model::find_for_user(&ctx.db, user.id).await?;
+    let organizations = organizations::Model::find_for_user(&ctx.db, user.id).await?;
+    let categories = categories::Model::find_for_user(&ctx.db, user.id).await?;
+    let documents = documents::Model::find_with_templates_for_user(&ctx.db, user.id).await?;
+    let merges = merge_jobs::Model::find_for_user(&ctx.db, user.id).await?;
+
+    Ok(format::json(ExportResponse {
+        account: AccountResponse::new(user),
+        sessions: sessions
+            .iter()
+            .map(|session| SessionResponse::new(session, &jwt.session))
+            .collect(),
+        api_tokens: api_tokens.iter().map(ApiTokenResponse::new).collect(),
+        identities: identities.iter().map(IdentityResponse::new).collect(),
+        organizations: organizations
+            .iter()
+            .map(|(organization, role)| OrganizationResponse::new(organization, *role))
+            .collect(),
+        categories: categories.iter().map(CategoryResponse::new).collect(),
+        templates,
+        shared_with_me: TemplateRelations::load_responses(&ctx.db, &shared_templates).await?,
+        documents: documents
+            .iter()
+            .map(|(document, _)| DocumentResponse::new(document))
+            .collect(),
+        merges: merges
+            .iter()
+            .map(MergeResponse::new)
+            .collect::<AppResult<_>>()?,
+    })?)
+}
+
+/// Deletes the account, the templates are transferred or deleted as chosen.
+#[utoipa::path(
+    delete,
+    path = "/api/user/account",
+    tag = "user",
+    security(("jwt" = [])),
+    request_body = DeleteAccountParams,
+    responses(
+        (status = 200, description = "The account is deleted"),
+        (status = 409, description = "The user is the last owner of an organization", body = ErrorResponse),
+        (status = 422, description = "The password is wrong or the recipient is not found", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn delete_account(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<DeleteAccountParams>,
+) -> AppResult<Response> {
+    jwt.user.confirm_password(&params.password)?;
+
+    let pid = jwt.user.pid;
+    jwt.user.delete_account(&ctx.db, &params.templates).await?;
+    tracing::info!(pid = pid.to_string(), "account deleted");
+
+    Ok(format::json(())?)
+}
+
+/// Lists the devices, that the user is logged in on.
+#[utoipa::path(
+    get,
+    path = "/api/user/sessions",
+    tag = "user",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = Vec<SessionResponse>),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn list_sessions(jwt: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let sessions = sessions::Model::find_active_for_user(&ctx.db, jwt.user.id).await?;
+
+    Ok(format::json(
+        sessions
+            .iter()
+            .map(|session| SessionResponse::new(session, &jwt.session))
+            .collect::<Vec<_>>(),
+    )?)
 }
 
+/// Logs out on another device.
+#[utoipa::path(
+    delete,
+    path = "/api/user/sessions/{pid}",
+    tag = "user",
+    security(("jwt" = [])),
+    params(("pid" = String, Path)),
+    responses(
+        (status = 200, description = "The session is revoked"),
+        (status = 404, body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn revoke_session(
+    jwt: JwtWithUser,
+    Path(pid): Path<String>,
+    State(ctx): State<AppContext>,
+) -> AppResult<Response> {
+    let sessions = sessions::Model::find_active_for_user(&ctx.db, jwt.user.id).await?;
+    let session = sessions
+        .into_iter()
+        .find(|session| session.pid.to_string() == pid)
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    session.into_active_model().revoke(&ctx.db).await?;
+
+    Ok(format::json(())?)
+}
+
+/// Starts enabling the two-factor authentication. It's enabled, once a
+/// code of the secret is confirmed.
+#[utoipa::path(
+    post,
+    path = "/api/user/totp",
+    tag = "user",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, body = TotpEnrollmentResponse),
+        (status = 409, description = "The two-factor authentication is enabled", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn start_totp(jwt: JwtWithUser, State(ctx): State<AppContext>) -> AppResult<Response> {
+    let user = jwt.user.start_totp(&ctx.db).await?;
+    let secret = user.totp_secret.as_deref().unwrap_or_default();
+
+    Ok(format::json(TotpEnrollmentResponse::new(
+        secret,
+        totp::provisioning_uri(secret, TOTP_ISSUER, &user.email),
+    ))?)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/totp/confirm",
+    tag = "user",
+    security(("jwt" = [])),
+    request_body = TotpParams,
+    responses(
+        (status = 200, body = RecoveryCodesResponse),
+        (status = 422, description = "The code is wrong", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn confirm_totp(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<TotpParams>,
+) -> AppResult<Response> {
+    let (_, recovery_codes) = jwt
+        .user
+        .confirm_totp(&ctx.db, &params.code, totp::now())
+        .await?;
+
+    Ok(format::json(RecoveryCodesResponse { recovery_codes })?)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/totp/disable",
+    tag = "user",
+    security(("jwt" = [])),
+    request_body = TotpParams,
+    responses(
+        (status = 200, description = "The two-factor authentication is disabled"),
+        (status = 422, description = "The code is wrong", body = ErrorResponse),
+        (status = 401, description = "Not logged in", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+async fn disable_totp(
+    jwt: JwtWithUser,
+    State(ctx): State<AppContext>,
+    Json(params): Json<TotpParams>,
+) -> AppResult<Response> {
+    jwt.user
+        .disable_totp(&ctx.db, &params.code, totp::now())
+        .await?;
+
+    Ok(format::json(())?)
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    current,
+    update_profile,
+    change_password,
+    change_email,
+    export,
+    delete_account,
+    list_sessions,
+    revoke_session,
+    start_totp,
+    confirm_totp,
+    disable_totp,
+))]
+pub struct ApiDoc;
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("api/user")
         .add("/current", get(current))
+        .add("/profile", put(update_profile))
+        .add("/password", post(change_password))
+        .add("/email", post(change_email))
+        .add("/export", get(export))
+        .add("/account", delete(delete_account))
+        .add("/sessions", get(list_sessions))
+        .add("/sessions/:pid", delete(revoke_session))
+        .add("/totp", post(start_totp))
+        .add("/totp/confirm", post(confirm_totp))
+        .add("/totp/disable", post(disable_totp))
 }
@@ -0,0 +1,491 @@
+//! Server-side rendering of `docx` templates.
+//!
+//! Supports the same syntax as the constructor, that renders templates with
+//! docxtemplater in the browser: `{{ path.to.value }}` tags, sections
+//! `{{#path}}...{{/path}}` (loops over arrays, conditions on other values),
+//! inverted sections `{{^path}}...{{/path}}` and comparisons like
+//! `{{#tenant._discriminant == "Individual"}}`. Missing values are rendered
+//! as [`MISSING_VALUE`].
+
+use std::collections::BTreeSet;
+use std::io::{Cursor, Read, Write};
+
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Placeholder for values, that are not filled.
+pub const MISSING_VALUE: &str = "_______";
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("invalid docx: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("invalid docx: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid template: {0}")]
+    Template(String),
+}
+
+/// Fills the `docx` template with the data.
+///
+/// # Errors
+///
+/// When the `docx` can't be read or the tags in it are malformed
+pub fn render(docx: &[u8], data: &Value) -> Result<Vec<u8>, RenderError> {
+    let mut archive = ZipArchive::new(Cursor::new(docx))?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        if !is_content_part(file.name()) {
+            writer.raw_copy_file(file)?;
+            continue;
+        }
+
+        let name = file.name().to_string();
+        let mut xml = String::new();
+        file.read_to_string(&mut xml)?;
+
+        let rendered = render_xml(&xml, data)?;
+
+        writer.start_file(name, SimpleFileOptions::default())?;
+        writer.write_all(rendered.as_bytes())?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Names of the variables, that the tags of the template refer to. Inside
+/// of loops and sections over objects a name may be a field of the element,
+/// so only tags outside of them are collected.
+///
+/// # Errors
+///
+/// When the `docx` can't be read or the tags in it are malformed
+pub fn variables(docx: &[u8]) -> Result<BTreeSet<String>, RenderError> {
+    let mut archive = ZipArchive::new(Cursor::new(docx))?;
+    let mut names = BTreeSet::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !is_content_part(file.name()) {
+            continue;
+        }
+
+        let mut xml = String::new();
+        file.read_to_string(&mut xml)?;
+
+        let xml = merge_split_tags(&xml);
+        let nodes = parse(&mut tokenize(&xml)?.into_iter(), None)?;
+        collect_variables(&nodes, &mut names);
+    }
+
+    Ok(names)
+}
+
+fn collect_variables(nodes: &[Node<'_>], names: &mut BTreeSet<String>) {
+    for node in nodes {
+        let (expr, children) = match node {
+            Node::Text(_) => continue,
+            Node::Value(expr) => (expr, None),
+            Node::Section {
+                expr,
+                inverted,
+                children,
+            } => {
+                // comparisons and inverted sections keep the scope
+                let keeps_scope = *inverted || expr.comparison.is_some();
+                (expr, keeps_scope.then_some(children))
+            },
+        };
+
+        if let Some(name) = expr.path.first() {
+            names.insert(name.clone());
+        }
+        if let Some(children) = children {
+            collect_variables(children, names);
+        }
+    }
+}
+
+/// Parts of the document, that may contain tags.
+fn is_content_part(name: &str) -> bool {
+    let Some(part) = name
+        .strip_prefix("word/")
+        .and_then(|name| name.strip_suffix(".xml"))
+    else {
+        return false;
+    };
+
+    ["document", "header", "footer", "footnotes", "endnotes"]
+        .iter()
+        .any(|prefix| part.starts_with(prefix) && !part.contains('/'))
+}
+
+fn render_xml(xml: &str, data: &Value) -> Result<String, RenderError> {
+    let xml = merge_split_tags(xml);
+    let tokens = tokenize(&xml)?;
+    let nodes = parse(&mut tokens.into_iter(), None)?;
+
+    let mut output = String::with_capacity(xml.len());
+    render_nodes(&nodes, &[data], &mut output);
+    Ok(output)
+}
+
+/// Position of the text of a `<w:t>` element in the XML.
+struct TextNode {
+    start: usize,
+    content_start: usize,
+    content_end: usize,
+}
+
+fn find_text_nodes(xml: &str) -> Vec<TextNode> {
+    let mut nodes = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = xml[pos..].find("<w:t") {
+        let start = pos + found;
+        pos = start + "<w:t".len();
+
+        // skip `<w:tab>`, `<w:tbl>` and other elements with the same prefix
+        if !matches!(xml[pos..].chars().next(), Some('>' | ' ')) {
+            continue;
+        }
+
+        let Some(open_end) = xml[pos..].find('>').map(|i| pos + i) else {
+            break;
+        };
+        pos = open_end + 1;
+
+        if xml[..open_end].ends_with('/') {
+            continue;
+        }
+
+        let Some(content_end) = xml[pos..].find("</w:t>").map(|i| pos + i) else {
+            break;
+        };
+
+        nodes.push(TextNode {
+            start,
+            content_start: pos,
+            content_end,
+        });
+        pos = content_end + "</w:t>".len();
+    }
+
+    nodes
+}
+
+/// Word splits text into runs arbitrarily, e.g. when a part of a tag was
+/// edited later, so a tag may be spread over several `<w:t>` elements. Moves
+/// every tag entirely into the element, where it starts.
+fn merge_split_tags(xml: &str) -> String {
+    let nodes = find_text_nodes(xml);
+
+    let text = nodes
+        .iter()
+        .map(|node| &xml[node.content_start..node.content_end])
+        .collect::<String>();
+
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = text[pos..].find(OPEN).map(|i| pos + i) {
+        let Some(end) = text[start..].find(CLOSE).map(|i| start + i + CLOSE.len()) else {
+            break;
+        };
+        tags.push(start..end);
+        pos = end;
+    }
+
+    if tags.is_empty() {
+        return xml.to_string();
+    }
+
+    let mut output = String::with_capacity(xml.len());
+    let mut copied = 0;
+    let mut offset = 0;
+    let mut tag = 0;
+
+    for node in &nodes {
+        let content = &xml[node.content_start..node.content_end];
+
+        let mut merged = String::with_capacity(content.len());
+        for (i, char) in content.char_indices() {
+            let pos = offset + i;
+            while tags.get(tag).is_some_and(|range| range.end <= pos) {
+                tag += 1;
+            }
+
+            match tags.get(tag) {
+                Some(range) if range.contains(&pos) => {
+                    if range.start == pos {
+                        merged.push_str(&text[range.clone()]);
+                    }
+                },
+                _ => merged.push(char),
+            }
+        }
+        offset += content.len();
+
+        // values may start or end with spaces, that Word drops by default
+        if merged == content && !merged.contains(OPEN) {
+            continue;
+        }
+
+        output.push_str(&xml[copied..node.start]);
+        output.push_str(r#"<w:t xml:space="preserve">"#);
+        output.push_str(&merged);
+        copied = node.content_end;
+    }
+
+    output.push_str(&xml[copied..]);
+    output
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Tag(String),
+}
+
+fn tokenize(xml: &str) -> Result<Vec<Token<'_>>, RenderError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while let Some(start) = xml[pos..].find(OPEN).map(|i| pos + i) {
+        let end = xml[start..]
+            .find(CLOSE)
+            .map(|i| start + i)
+            .ok_or_else(|| RenderError::Template("unclosed tag".into()))?;
+
+        tokens.push(Token::Text(&xml[pos..start]));
+        tokens.push(Token::Tag(unescape(&xml[start + OPEN.len()..end])));
+        pos = end + CLOSE.len();
+    }
+
+    tokens.push(Token::Text(&xml[pos..]));
+    Ok(tokens)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        // Word replaces quotes with typographic ones while typing
+        .replace(['“', '”', '„', '«', '»'], "\"")
+        .replace(['‘', '’'], "'")
+        .trim()
+        .to_string()
+}
+
+enum Node<'a> {
+    Text(&'a str),
+    Value(Expr),
+    Section {
+        expr: Expr,
+        inverted: bool,
+        children: Vec<Self>,
+    },
+}
+
+fn parse<'a>(
+    tokens: &mut impl Iterator<Item = Token<'a>>,
+    section: Option<&str>,
+) -> Result<Vec<Node<'a>>, RenderError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            },
+            Token::Tag(tag) => tag,
+        };
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            return match section {
+                Some(section) if name.is_empty() || name == section => Ok(nodes),
+                _ => {
+                    Err(RenderError::Template(format!(
+                        "unexpected `{{{{/{name}}}}}`"
+                    )))
+                },
+            };
+        }
+
+        let (inverted, source) = match (tag.strip_prefix('#'), tag.strip_prefix('^')) {
+            (Some(source), _) => (false, source.trim()),
+            (_, Some(source)) => (true, source.trim()),
+            _ => {
+                nodes.push(Node::Value(Expr::parse(&tag)?));
+                continue;
+            },
+        };
+
+        let expr = Expr::parse(source)?;
+        let children = parse(tokens, Some(source))?;
+        nodes.push(Node::Section {
+            expr,
+            inverted,
+            children,
+        });
+    }
+
+    match section {
+        Some(section) => {
+            Err(RenderError::Template(format!(
+                "unclosed `{{{{#{section}}}}}`"
+            )))
+        },
+        None => Ok(nodes),
+    }
+}
+
+/// A path to a value, optionally compared with a literal.
+struct Expr {
+    path: Vec<String>,
+    comparison: Option<(bool, Value)>,
+}
+
+impl Expr {
+    fn parse(source: &str) -> Result<Self, RenderError> {
+        let (path, comparison) = if let Some((path, literal)) = source.split_once("==") {
+            (path, Some((true, parse_literal(literal)?)))
+        } else if let Some((path, literal)) = source.split_once("!=") {
+            (path, Some((false, parse_literal(literal)?)))
+        } else {
+            (source, None)
+        };
+
+        let path = path.trim();
+        let path = if path.is_empty() || path == "." || path == "this" {
+            Vec::new()
+        } else {
+            path.split('.')
+                .map(|name| name.trim().to_string())
+                .collect()
+        };
+
+        if path.iter().any(String::is_empty) {
+            return Err(RenderError::Template(format!("invalid tag `{source}`")));
+        }
+
+        Ok(Self { path, comparison })
+    }
+
+    fn evaluate(&self, scopes: &[&Value]) -> Option<Value> {
+        let value = lookup(scopes, &self.path);
+
+        match &self.comparison {
+            None => value.cloned(),
+            Some((equal, literal)) => Some(Value::Bool((value == Some(literal)) == *equal)),
+        }
+    }
+}
+
+fn parse_literal(literal: &str) -> Result<Value, RenderError> {
+    let literal = literal.trim();
+
+    for quote in ['"', '\''] {
+        if let Some(string) = literal
+            .strip_prefix(quote)
+            .and_then(|literal| literal.strip_suffix(quote))
+        {
+            return Ok(Value::String(string.to_string()));
+        }
+    }
+
+    serde_json::from_str(literal)
+        .map_err(|_| RenderError::Template(format!("invalid literal `{literal}`")))
+}
+
+/// Finds the value in the innermost scope, that has the first name of the
+/// path, like docxtemplater does inside of loops.
+fn lookup<'a>(scopes: &[&'a Value], path: &[String]) -> Option<&'a Value> {
+    let Some((first, rest)) = path.split_first() else {
+        return scopes.last().copied();
+    };
+
+    let value = scopes.iter().rev().find_map(|scope| scope.get(first))?;
+
+    rest.iter().try_fold(value, |value, name| value.get(name))
+}
+
+const fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::String(string) => !string.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Number(_) | Value::Object(_) => true,
+    }
+}
+
+fn render_nodes(nodes: &[Node<'_>], scopes: &[&Value], output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value(expr) => push_value(expr.evaluate(scopes).as_ref(), output),
+            Node::Section {
+                expr,
+                inverted,
+                children,
+            } => {
+                let value = expr.evaluate(scopes).unwrap_or(Value::Null);
+
+                if *inverted {
+                    if !is_truthy(&value) {
+                        render_nodes(children, scopes, output);
+                    }
+                    continue;
+                }
+
+                match &value {
+                    Value::Array(elements) => {
+                        for element in elements {
+                            render_nodes(children, &[scopes, &[element]].concat(), output);
+                        }
+                    },
+                    Value::Object(_) => {
+                        render_nodes(children, &[scopes, &[&value]].concat(), output);
+                    },
+                    value if is_truthy(value) => render_nodes(children, scopes, output),
+                    _ => {},
+                }
+            },
+        }
+    }
+}
+
+fn push_value(value: Option<&Value>, output: &mut String) {
+    let text = match value {
+        None | Some(Value::Null) => return output.push_str(MISSING_VALUE),
+        Some(Value::String(string)) => string.clone(),
+        Some(Value::Object(object)) => {
+            // an enum is printed as its variant
+            match object.get(cicero_dsl::validation::DISCRIMINANT) {
+                Some(Value::String(variant)) => variant.clone(),
+                _ => String::new(),
+            }
+        },
+        Some(Value::Array(_)) => String::new(),
+        Some(value) => value.to_string(),
+    };
+
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;");
+
+    output.push_str(&escaped.replace('\n', r#"</w:t><w:br/><w:t xml:space="preserve">"#));
+}
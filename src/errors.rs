@@ -0,0 +1,362 @@
+//! Errors of the API, that are returned to the client as
+//! `{ "code": ..., "message": ..., "fields": [...] }` with the matching HTTP
+//! status.
+
+use std::collections::BTreeMap;
+
+use axum::extract::multipart::MultipartError;
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use cicero_dsl::validation::ValidationError;
+use loco_rs::controller::ErrorDetail;
+use loco_rs::model::ModelError;
+use loco_rs::validation::ModelValidationMessage;
+use sea_orm::DbErr;
+
+use crate::views::error::{ErrorResponse, FieldError};
+
+pub type AppResult<T, E = AppError> = std::result::Result<T, E>;
+
+/// An error with a machine-readable `code`, e.g. `invalid_dsl`, and a human
+/// readable `message`.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    /// 400, the request is malformed.
+    #[error("{message}")]
+    BadRequest { code: &'static str, message: String },
+    /// 401, the user is not logged in or the credentials are wrong.
+    #[error("{message}")]
+    Unauthorized { code: &'static str, message: String },
+    /// 403, the user is known, but has no access.
+    #[error("{message}")]
+    Forbidden { code: &'static str, message: String },
+    /// 404
+    #[error("{message}")]
+    NotFound { code: &'static str, message: String },
+    /// 409, e.g. the name is already taken.
+    #[error("{message}")]
+    Conflict { code: &'static str, message: String },
+    /// 412, the resource was changed since the client has seen it.
+    #[error("{message}")]
+    PreconditionFailed { code: &'static str, message: String },
+    /// 428, the request must say, which version of the resource it's based
+    /// on.
+    #[error("{message}")]
+    PreconditionRequired { code: &'static str, message: String },
+    /// 429, the request is repeated too often.
+    #[error("{message}")]
+    TooManyRequests { code: &'static str, message: String },
+    /// 422, the request is well-formed, but some values are invalid.
+    #[error("{message}")]
+    Validation {
+        code: &'static str,
+        message: String,
+        fields: Vec<FieldError>,
+    },
+    /// 500, the details are only logged.
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl AppError {
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::BadRequest {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn unauthorized(code: &'static str, message: impl Into<String>) -> Self {
+        Self::Unauthorized {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::Forbidden {
+            code: "forbidden",
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound {
+            code: "not_found",
+            message: message.into(),
+        }
+    }
+
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self::Conflict {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn precondition_failed(code: &'static str, message: impl Into<String>) -> Self {
+        Self::PreconditionFailed {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn precondition_required(code: &'static str, message: impl Into<String>) -> Self {
+        Self::PreconditionRequired {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn too_many_requests(code: &'static str, message: impl Into<String>) -> Self {
+        Self::TooManyRequests {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn validation(
+        code: &'static str,
+        message: impl Into<String>,
+        fields: Vec<FieldError>,
+    ) -> Self {
+        Self::Validation {
+            code,
+            message: message.into(),
+            fields,
+        }
+    }
+
+    pub fn internal(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Internal(err.into())
+    }
+
+    /// The DSL doesn't compile, the message of the compiler is in the field.
+    pub fn invalid_dsl(field: &str, message: impl Into<String>) -> Self {
+        Self::validation("invalid_dsl", "The DSL is invalid", vec![FieldError::new(
+            field, message,
+        )])
+    }
+
+    /// The docx can't be read, or its tags don't match the DSL.
+    pub fn invalid_docx(field: &str, message: impl Into<String>) -> Self {
+        Self::validation("invalid_docx", "The docx is invalid", vec![
+            FieldError::new(field, message),
+        ])
+    }
+
+    /// The data doesn't match the DSL, paths of the values are prefixed with
+    /// the `field`.
+    #[must_use]
+    pub fn invalid_data(field: &str, errors: &[ValidationError]) -> Self {
+        let fields = errors
+            .iter()
+            .map(|error| {
+                let path = if error.path.is_empty() {
+                    field.to_string()
+                } else {
+                    format!("{field}.{}", error.path)
+                };
+                FieldError::new(&path, error.message.clone())
+            })
+            .collect();
+
+        Self::validation("invalid_data", "The data doesn't match the DSL", fields)
+    }
+
+    /// A part of a multipart request is missing.
+    #[must_use]
+    pub fn missing_part(name: &str) -> Self {
+        Self::validation(
+            "missing_part",
+            format!("The `{name}` part is missing"),
+            vec![FieldError::new(name, "is required")],
+        )
+    }
+
+    /// A multipart request has a part, that isn't expected.
+    #[must_use]
+    pub fn unexpected_part(name: &str) -> Self {
+        Self::bad_request("unexpected_part", format!("Unexpected part `{name}`"))
+    }
+
+    /// The JSON part of a multipart request can't be parsed.
+    #[must_use]
+    pub fn invalid_json_part(name: &str, err: &serde_json::Error) -> Self {
+        Self::validation("invalid_json", "The JSON is invalid", vec![
+            FieldError::new(name, err.to_string()),
+        ])
+    }
+
+    #[must_use]
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            Self::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            Self::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Self::NotFound { .. } => StatusCode::NOT_FOUND,
+            Self::Conflict { .. } => StatusCode::CONFLICT,
+            Self::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+            Self::PreconditionRequired { .. } => StatusCode::PRECONDITION_REQUIRED,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest { code, .. }
+            | Self::Unauthorized { code, .. }
+            | Self::Forbidden { code, .. }
+            | Self::NotFound { code, .. }
+            | Self::Conflict { code, .. }
+            | Self::PreconditionFailed { code, .. }
+            | Self::PreconditionRequired { code, .. }
+            | Self::TooManyRequests { code, .. }
+            | Self::Validation { code, .. } => code,
+            Self::Internal(_) => "internal_server_error",
+        }
+    }
+
+    #[must_use]
+    pub fn response(&self) -> ErrorResponse {
+        match self {
+            Self::Validation {
+                code,
+                message,
+                fields,
+            } => {
+                ErrorResponse {
+                    code: (*code).to_string(),
+                    message: message.clone(),
+                    fields: fields.clone(),
+                }
+            },
+            // don't leak the details of internal errors
+            Self::Internal(_) => {
+                ErrorResponse {
+                    code: self.code().to_string(),
+                    message: "Internal server error".to_string(),
+                    fields: Vec::new(),
+                }
+            },
+            _ => {
+                ErrorResponse {
+                    code: self.code().to_string(),
+                    message: self.to_string(),
+                    fields: Vec::new(),
+                }
+            },
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        match &self {
+            Self::Internal(err) => {
+                tracing::error!(error.msg = %err, error.details = ?err, "controller_error");
+            },
+            err => tracing::debug!(error.code = err.code(), error.msg = %err, "controller_error"),
+        }
+
+        (self.status(), axum::Json(self.response())).into_response()
+    }
+}
+
+impl From<DbErr> for AppError {
+    fn from(err: DbErr) -> Self {
+        match err {
+            DbErr::RecordNotFound(message) => Self::not_found(message),
+            // validation errors of `before_save` hooks are serialized into the
+            // message, see `loco_rs::validation::into_db_error`
+            DbErr::Custom(message) => {
+                match serde_json::from_str::<BTreeMap<String, Vec<ModelValidationMessage>>>(
+                    &message,
+                ) {
+                    Ok(errors) => {
+                        let fields = errors
+                            .into_iter()
+                            .flat_map(|(field, messages)| {
+                                messages.into_iter().map(move |message| {
+                                    FieldError::new(&field, message.message.unwrap_or(message.code))
+                                })
+                            })
+                            .collect();
+                        Self::validation("validation_failed", "Some values are invalid", fields)
+                    },
+                    Err(_) => Self::internal(DbErr::Custom(message)),
+                }
+            },
+            err => Self::internal(err),
+        }
+    }
+}
+
+impl From<ModelError> for AppError {
+    fn from(err: ModelError) -> Self {
+        match err {
+            ModelError::EntityAlreadyExists => {
+                Self::conflict("already_exists", "Entity already exists")
+            },
+            ModelError::EntityNotFound => Self::not_found("Entity not found"),
+            ModelError::ModelValidation { errors } => {
+                Self::validation("validation_failed", "Some values are invalid", vec![
+                    FieldError::new(&errors.code, errors.message.unwrap_or_default()),
+                ])
+            },
+            ModelError::Jwt(_) => Self::unauthorized("invalid_token", "The token is invalid"),
+            ModelError::DbErr(err) => err.into(),
+            ModelError::Any(err) => Self::Internal(err),
+        }
+    }
+}
+
+impl From<loco_rs::Error> for AppError {
+    fn from(err: loco_rs::Error) -> Self {
+        match err {
+            loco_rs::Error::NotFound => Self::not_found("Resource was not found"),
+            loco_rs::Error::Unauthorized(message) => Self::unauthorized("unauthorized", message),
+            loco_rs::Error::BadRequest(message) => Self::bad_request("bad_request", message),
+            loco_rs::Error::JsonRejection(rejection) => rejection.into(),
+            loco_rs::Error::Model(err) => err.into(),
+            loco_rs::Error::DB(err) => err.into(),
+            err => Self::internal(err),
+        }
+    }
+}
+
+impl From<AppError> for loco_rs::Error {
+    fn from(err: AppError) -> Self {
+        match err {
+            AppError::Internal(err) => Self::Any(err),
+            err => {
+                Self::CustomError(
+                    err.status(),
+                    ErrorDetail::new(err.code().to_string(), err.to_string()),
+                )
+            },
+        }
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            // the body is a valid JSON, but doesn't match the expected type
+            JsonRejection::JsonDataError(err) => {
+                Self::validation("invalid_json", err.body_text(), Vec::new())
+            },
+            rejection => Self::bad_request("invalid_json", rejection.body_text()),
+        }
+    }
+}
+
+impl From<MultipartError> for AppError {
+    fn from(err: MultipartError) -> Self {
+        Self::bad_request("invalid_multipart", err.body_text())
+    }
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{middleware, Extension, Router as AxumRouter};
+use loco_rs::app::{AppContext, Initializer};
+use loco_rs::Result;
+
+use crate::middlewares::rate_limit::rate_limit;
+use crate::rate_limit::RateLimiter;
+use crate::settings::Settings;
+
+/// Wires the [`RateLimiter`] of the settings: the middleware limits the
+/// configured routes, the handlers get it as an extension for the lockout.
+pub struct RateLimitInitializer;
+
+#[async_trait]
+impl Initializer for RateLimitInitializer {
+    fn name(&self) -> String {
+        "rate-limit".to_string()
+    }
+
+    async fn after_routes(&self, router: AxumRouter, ctx: &AppContext) -> Result<AxumRouter> {
+        let settings = Settings::from_config(&ctx.config)?;
+        let limiter = Arc::new(RateLimiter::from_settings(&settings).await?);
+
+        Ok(router
+            .layer(middleware::from_fn_with_state(limiter.clone(), rate_limit))
+            .layer(Extension(limiter)))
+    }
+}
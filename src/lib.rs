@@ -1,9 +1,19 @@
 pub mod app;
+pub mod bundle;
+pub mod conditional;
 pub mod controllers;
+pub mod docx;
+pub mod errors;
 pub mod initializers;
 pub mod mailers;
 pub mod middlewares;
 pub mod models;
+pub mod oidc;
+pub mod openapi;
+pub mod rate_limit;
+pub mod settings;
+pub mod spreadsheet;
 pub mod tasks;
+pub mod totp;
 pub mod views;
 pub mod workers;
@@ -8,6 +8,7 @@ use crate::models::users;
 
 static welcome: Dir<'_> = include_dir!("src/mailers/auth/welcome");
 static forgot: Dir<'_> = include_dir!("src/mailers/auth/forgot");
+static email_change: Dir<'_> = include_dir!("src/mailers/auth/email_change");
 // #[derive(Mailer)] // -- disabled for faster build speed. it works. but lets
 // move on for now.
 
@@ -20,12 +21,12 @@ impl AuthMailer {
     /// # Errors
     ///
     /// When email sending is failed
-    pub async fn send_welcome(ctx: &AppContext, user: &users::Model) -> Result<()> {
+    pub async fn send_welcome(ctx: &AppContext, user: &users::Model, token: &str) -> Result<()> {
         Self::mail_template(ctx, &welcome, mailer::Args {
             to: user.email.to_string(),
             locals: json!({
               "name": user.name,
-              "verifyToken": user.email_verification_token,
+              "verifyToken": token,
               "domain": ctx.config.server.full_url()
             }),
             ..Default::default()
@@ -40,12 +41,41 @@ impl AuthMailer {
     /// # Errors
     ///
     /// When email sending is failed
-    pub async fn forgot_password(ctx: &AppContext, user: &users::Model) -> Result<()> {
+    pub async fn forgot_password(ctx: &AppContext, user: &users::Model, token: &str) -> Result<()> {
         Self::mail_template(ctx, &forgot, mailer::Args {
             to: user.email.to_string(),
             locals: json!({
               "name": user.name,
-              "resetToken": user.reset_token,
+              "resetToken": token,
+              "domain": ctx.config.server.full_url()
+            }),
+            ..Default::default()
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sending the verification of the new email to the new address, the
+    /// email is changed once it's verified
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_email_change(
+        ctx: &AppContext,
+        user: &users::Model,
+        token: &str,
+    ) -> Result<()> {
+        let Some(pending_email) = &user.pending_email else {
+            return Ok(());
+        };
+
+        Self::mail_template(ctx, &email_change, mailer::Args {
+            to: pending_email.to_string(),
+            locals: json!({
+              "name": user.name,
+              "verifyToken": token,
               "domain": ctx.config.server.full_url()
             }),
             ..Default::default()
@@ -0,0 +1,12 @@
+<html>
+
+<body>
+  Пользователь {{name}},
+  Вы изменили адрес электронной почты в Цицероне. Чтобы начать пользоваться новым адресом, пожалуйста, подтвердите его, пройдя по ссылке ниже:
+  <a href="{{domain}}/verify?token={{verifyToken}}">
+    Подтвердить адрес электронной почты
+  </a>
+  <p>Проект Цицерон</p>
+</body>
+
+</html>
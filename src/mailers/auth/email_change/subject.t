@@ -0,0 +1 @@
+Подтверждение нового адреса электронной почты
@@ -0,0 +1,4 @@
+Пользователь {{name}},
+Вы изменили адрес электронной почты в Цицероне. Чтобы начать пользоваться новым адресом, пожалуйста, подтвердите его, пройдя по ссылке ниже:
+
+    {{domain}}/verify?token={{verifyToken}}
\ No newline at end of file
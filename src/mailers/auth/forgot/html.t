@@ -1,11 +1,11 @@
-;<html>
+<html>
 
 <body>
-  Hey {{name}},
-  Forgot your password? No worries! You can reset it by clicking the link below:
-  <a href="http://{{domain}}/reset#{{resetToken}}">Reset Your Password</a>
-  If you didn't request a password reset, please ignore this email.
-  Best regards,<br>The Loco Team</br>
+  Пользователь {{name}},
+  Забыли пароль? Вы можете задать новый, пройдя по ссылке ниже:
+  <a href="{{domain}}/reset#{{resetToken}}">Задать новый пароль</a>
+  Если вы не запрашивали восстановление пароля, просто проигнорируйте это письмо.
+  <p>Проект Цицерон</p>
 </body>
 
 </html>
@@ -1 +1 @@
-Your reset password link
+Восстановление пароля
@@ -1,3 +1,6 @@
-Reset your password with this link:
+Пользователь {{name}},
+Забыли пароль? Вы можете задать новый, пройдя по ссылке ниже:
 
-http://localhost/reset#{{resetToken}}
+    {{domain}}/reset#{{resetToken}}
+
+Если вы не запрашивали восстановление пароля, просто проигнорируйте это письмо.
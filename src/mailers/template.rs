@@ -0,0 +1,41 @@
+// template mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde_json::json;
+
+use crate::models::{template_invitations, templates, users};
+
+static invite: Dir<'_> = include_dir!("src/mailers/template/invite");
+
+#[allow(clippy::module_name_repetitions)]
+pub struct TemplateMailer {}
+impl Mailer for TemplateMailer {}
+impl TemplateMailer {
+    /// Sending invitation to see a private template to an email without an
+    /// account
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_invitation(
+        ctx: &AppContext,
+        invitation: &template_invitations::Model,
+        template: &templates::Model,
+        inviter: &users::Model,
+    ) -> Result<()> {
+        Self::mail_template(ctx, &invite, mailer::Args {
+            to: invitation.email.to_string(),
+            locals: json!({
+              "inviter": inviter.name,
+              "template": template.name,
+              "email": invitation.email,
+              "domain": ctx.config.server.full_url()
+            }),
+            ..Default::default()
+        })
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+<html>
+
+<body>
+  Здравствуйте!
+  Пользователь {{inviter}} открыл вам доступ к шаблону «{{template}}» в Цицероне.
+  Чтобы увидеть шаблон, зарегистрируйтесь с этим адресом электронной почты и подтвердите его:
+  <a href="{{domain}}/register?email={{email}}">
+    Зарегистрироваться
+  </a>
+  <p>Проект Цицерон</p>
+</body>
+
+</html>
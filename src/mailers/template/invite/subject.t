@@ -0,0 +1 @@
+Вам открыт доступ к шаблону
@@ -0,0 +1,6 @@
+
+Здравствуйте!
+Пользователь {{inviter}} открыл вам доступ к шаблону «{{template}}» в Цицероне.
+Чтобы увидеть шаблон, зарегистрируйтесь с этим адресом электронной почты и подтвердите его:
+
+    {{domain}}/register?email={{email}}
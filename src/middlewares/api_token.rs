@@ -0,0 +1,140 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use loco_rs::prelude::*;
+
+use super::{JwtWithUser, MaybeJwtWithUser};
+use crate::errors::{AppError, AppResult};
+use crate::models::api_tokens::{self, ApiScope, TOKEN_PREFIX};
+use crate::models::users;
+
+/// Scope, that is checked by [`TokenWithScope`].
+pub trait RequiredScope {
+    const SCOPE: ApiScope;
+}
+
+macro_rules! required_scopes {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[derive(Debug)]
+            pub struct $name;
+
+            impl RequiredScope for $name {
+                const SCOPE: ApiScope = ApiScope::$name;
+            }
+        )*
+    };
+}
+
+required_scopes!(TemplatesRead, TemplatesWrite, Render);
+
+/// User of the access token or of the API token with the scope `S`, so
+/// machine clients can call the route without logging in.
+#[derive(Debug)]
+pub struct TokenWithScope<S: RequiredScope> {
+    pub user: users::Model,
+    /// `None` for access tokens.
+    pub api_token: Option<api_tokens::Model>,
+    scope: PhantomData<S>,
+}
+
+impl<S: RequiredScope> TokenWithScope<S> {
+    /// The API token of the `Authorization` header, access tokens are left
+    /// to [`JwtWithUser`].
+    fn api_token(parts: &Parts) -> Option<&str> {
+        parts
+            .headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+            .map(str::trim)
+            .filter(|token| token.starts_with(TOKEN_PREFIX))
+    }
+
+    async fn from_api_token(ctx: &AppContext, token: &str) -> AppResult<Self> {
+        let invalid =
+            || AppError::unauthorized("invalid_api_token", "The API token is invalid or expired");
+
+        let api_token = api_tokens::Model::find_active_by_token(&ctx.db, token)
+            .await?
+            .ok_or_else(invalid)?;
+        if !api_token.allows(S::SCOPE) {
+            return Err(AppError::Forbidden {
+                code: "insufficient_scope",
+                message: format!("The API token doesn't have the scope {}", S::SCOPE.as_str()),
+            });
+        }
+
+        let user = users::Entity::find_by_id(api_token.user_id)
+            .one(&ctx.db)
+            .await?
+            .ok_or_else(invalid)?;
+        let api_token = api_token.into_active_model().set_used(&ctx.db).await?;
+
+        Ok(Self {
+            user,
+            api_token: Some(api_token),
+            scope: PhantomData,
+        })
+    }
+
+    fn from_jwt(jwt: JwtWithUser) -> Self {
+        Self {
+            user: jwt.user,
+            api_token: None,
+            scope: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for TokenWithScope<R>
+where
+    AppContext: FromRef<S>,
+    S: Send + Sync,
+    R: RequiredScope,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        if let Some(token) = Self::api_token(parts) {
+            let ctx = AppContext::from_ref(state);
+            return Self::from_api_token(&ctx, token).await;
+        }
+
+        let jwt = JwtWithUser::from_request_parts(parts, state).await?;
+        Ok(Self::from_jwt(jwt))
+    }
+}
+
+/// Like [`MaybeJwtWithUser`], but accepts API tokens with the scope `S`.
+/// Unlike access tokens, invalid API tokens are rejected, so a broken
+/// integration doesn't silently see only public templates.
+#[derive(Debug)]
+pub struct MaybeTokenWithScope<S: RequiredScope>(pub Option<TokenWithScope<S>>);
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for MaybeTokenWithScope<R>
+where
+    AppContext: FromRef<S>,
+    S: Send + Sync,
+    R: RequiredScope,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        if let Some(token) = TokenWithScope::<R>::api_token(parts) {
+            let ctx = AppContext::from_ref(state);
+            return Ok(Self(Some(
+                TokenWithScope::from_api_token(&ctx, token).await?,
+            )));
+        }
+
+        let MaybeJwtWithUser(jwt) = MaybeJwtWithUser::from_request_parts(parts, state).await?;
+        Ok(Self(jwt.map(TokenWithScope::from_jwt)))
+    }
+}
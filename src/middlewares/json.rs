@@ -0,0 +1,16 @@
+use axum::extract::FromRequest;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::errors::AppError;
+
+/// JSON body, that is rejected with an [`AppError`] instead of loco's error.
+#[derive(Debug, FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
@@ -1,46 +1,38 @@
 use async_trait::async_trait;
 use axum::extract::{FromRef, FromRequestParts};
 use axum::http::request::Parts;
-use loco_rs::prelude::auth::{JWTWithUser, JWT};
+use loco_rs::prelude::auth::JWT;
 use loco_rs::prelude::*;
-use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct MaybeJwt(pub Option<JWT>);
+use super::session::reject_legacy_api_key;
+use super::JwtWithUser;
+use crate::errors::AppError;
 
-#[async_trait]
-impl<S> FromRequestParts<S> for MaybeJwt
-where
-    AppContext: FromRef<S>,
-    S: Send + Sync,
-{
-    type Rejection = Error;
-
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Error> {
-        match JWT::from_request_parts(parts, state).await {
-            Ok(jwt) => Ok(Self(Some(jwt))),
-            Err(Error::Unauthorized(_)) => Ok(Self(None)),
-            Err(err) => Err(err),
-        }
-    }
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-pub struct MaybeJwtWithUser<T: Authenticable>(pub Option<JWTWithUser<T>>);
+/// Logged in user, if the request has a valid token. Requests with invalid
+/// tokens, e.g. of revoked sessions, are treated as anonymous, but legacy
+/// API keys are rejected.
+#[derive(Debug)]
+pub struct MaybeJwtWithUser(pub Option<JwtWithUser>);
 
 #[async_trait]
-impl<S, T> FromRequestParts<S> for MaybeJwtWithUser<T>
+impl<S> FromRequestParts<S> for MaybeJwtWithUser
 where
     AppContext: FromRef<S>,
     S: Send + Sync,
-    T: Authenticable,
 {
-    type Rejection = Error;
+    type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Error> {
-        match JWTWithUser::<T>::from_request_parts(parts, state).await {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        reject_legacy_api_key(parts)?;
+        let jwt = match JWT::from_request_parts(parts, state).await {
+            Ok(jwt) => jwt,
+            Err(Error::Unauthorized(_)) => return Ok(Self(None)),
+            Err(err) => return Err(err.into()),
+        };
+        let ctx = AppContext::from_ref(state);
+        match JwtWithUser::from_claims(&ctx, jwt.claims).await {
             Ok(jwt) => Ok(Self(Some(jwt))),
-            Err(Error::Unauthorized(_)) => Ok(Self(None)),
+            Err(AppError::Unauthorized { .. }) => Ok(Self(None)),
             Err(err) => Err(err),
         }
     }
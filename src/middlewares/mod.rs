@@ -1,3 +1,12 @@
+pub mod api_token;
+pub mod json;
 pub mod maybe_auth;
+pub mod permission;
+pub mod rate_limit;
+pub mod session;
 
-pub use maybe_auth::{MaybeJwt, MaybeJwtWithUser};
+pub use api_token::{MaybeTokenWithScope, TokenWithScope};
+pub use json::Json;
+pub use maybe_auth::MaybeJwtWithUser;
+pub use permission::JwtWithPermission;
+pub use session::JwtWithUser;
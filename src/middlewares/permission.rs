@@ -0,0 +1,69 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::request::Parts;
+use loco_rs::prelude::*;
+
+use super::JwtWithUser;
+use crate::errors::AppError;
+use crate::models::roles::Permission;
+use crate::models::users;
+
+/// Permission, that is checked by [`JwtWithPermission`].
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! required_permissions {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[derive(Debug)]
+            pub struct $name;
+
+            impl RequiredPermission for $name {
+                const PERMISSION: Permission = Permission::$name;
+            }
+        )*
+    };
+}
+
+required_permissions!(
+    EditTemplates,
+    ModerateTemplates,
+    ManageCategories,
+    ManageRoles
+);
+
+/// Logged in user, whose role has the permission `P`.
+#[derive(Debug)]
+pub struct JwtWithPermission<P: RequiredPermission> {
+    pub user: users::Model,
+    permission: PhantomData<P>,
+}
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for JwtWithPermission<P>
+where
+    AppContext: FromRef<S>,
+    S: Send + Sync,
+    P: RequiredPermission,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        let jwt = JwtWithUser::from_request_parts(parts, state).await?;
+
+        if !jwt.user.role.can(P::PERMISSION) {
+            return Err(AppError::forbidden(format!(
+                "The role doesn't allow to {}",
+                P::PERMISSION.description()
+            )));
+        }
+
+        Ok(Self {
+            user: jwt.user,
+            permission: PhantomData,
+        })
+    }
+}
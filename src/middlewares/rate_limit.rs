@@ -0,0 +1,154 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{to_bytes, Body};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::errors::{AppError, AppResult};
+use crate::rate_limit::{normalize_account, RateLimiter};
+
+/// Larger bodies are not read for the account, the limited routes only
+/// accept small JSON.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// The rate limiting error with the time to wait in `Retry-After`.
+#[must_use]
+pub fn too_many_requests(code: &'static str, message: &str, retry_after: Duration) -> Response {
+    (
+        [(
+            header::RETRY_AFTER,
+            retry_after.as_secs().max(1).to_string(),
+        )],
+        AppError::too_many_requests(code, message),
+    )
+        .into_response()
+}
+
+/// Limits the requests to the routes of `settings.rate_limit.routes` by the
+/// IP address and by the `email` of the JSON body.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match check(&limiter, request).await {
+        Ok(Ok(request)) => next.run(request).await,
+        Ok(Err(retry_after)) => {
+            too_many_requests(
+                "rate_limited",
+                "Too many requests, try again later",
+                retry_after,
+            )
+        },
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn check(limiter: &RateLimiter, request: Request) -> AppResult<Result<Request, Duration>> {
+    let path = request.uri().path().to_string();
+    let Some(limits) = limiter.route(&path) else {
+        return Ok(Ok(request));
+    };
+
+    if let Some(limit) = &limits.per_ip {
+        // without the address all clients would share a single limit
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or_else(|| AppError::internal("the address of the client is unknown"))?
+            .0
+            .ip();
+        let ip = client_ip(limiter, peer, request.headers());
+        if let Some(retry_after) = limiter.hit(&format!("ip:{path}:{ip}"), limit).await? {
+            return Ok(Err(retry_after));
+        }
+    }
+
+    let Some(limit) = &limits.per_account else {
+        return Ok(Ok(request));
+    };
+    // the body is read for the email and passed on to the handler
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| AppError::bad_request("body_too_large", "The body is too large"))?;
+    let account = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|json| json.get("email")?.as_str().map(normalize_account));
+    if let Some(account) = account {
+        if let Some(retry_after) = limiter
+            .hit(&format!("account:{path}:{account}"), limit)
+            .await?
+        {
+            return Ok(Err(retry_after));
+        }
+    }
+
+    Ok(Ok(Request::from_parts(parts, Body::from(bytes))))
+}
+
+/// Address of the client. Behind trusted proxies it's the last address in
+/// the forwarding headers, that is not of a proxy: the earlier ones are sent
+/// by the client and can be anything.
+fn client_ip(limiter: &RateLimiter, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    let mut client = peer;
+
+    for hop in forwarded_for(headers).into_iter().rev() {
+        if !limiter.is_trusted_proxy(client) {
+            break;
+        }
+        // an obfuscated or unknown address, the proxy is the closest known
+        let Some(hop) = hop else {
+            break;
+        };
+        client = hop;
+    }
+
+    client
+}
+
+/// Addresses of `Forwarded` (RFC 7239), or of `X-Forwarded-For`, if it's
+/// not sent. `None` for the ones, that are not IP addresses.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let forwarded = headers
+        .get_all(header::FORWARDED)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("for")
+                    .then(|| parse_node(value))
+            })
+        })
+        .collect::<Vec<_>>();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(parse_node)
+        .collect()
+}
+
+/// Parses `192.0.2.1`, `"192.0.2.1:80"` or `"[2001:db8::1]:80"`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    node.strip_prefix('[')?.split_once(']')?.0.parse().ok()
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use loco_rs::auth::jwt::UserClaims;
+use loco_rs::prelude::auth::JWT;
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+use crate::errors::{AppError, AppResult};
+use crate::models::{sessions, users};
+
+/// Logged in user with the session of the access token. Unlike
+/// `JWTWithUser` of loco, the token is rejected, once its session is revoked
+/// or expired.
+#[derive(Debug)]
+pub struct JwtWithUser {
+    pub claims: UserClaims,
+    pub user: users::Model,
+    pub session: sessions::Model,
+}
+
+/// Prefix of the API keys of users, that were replaced by API tokens.
+const LEGACY_API_KEY_PREFIX: &str = "lo-";
+
+/// Rejects the legacy API key of the `Authorization` header, so it isn't
+/// mistaken for an invalid access token and treated as anonymous.
+pub(super) fn reject_legacy_api_key(parts: &Parts) -> AppResult<()> {
+    let is_legacy = parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token.trim().starts_with(LEGACY_API_KEY_PREFIX));
+    if is_legacy {
+        return Err(AppError::unauthorized(
+            "legacy_api_key",
+            "API keys are not accepted anymore, use an API token instead",
+        ));
+    }
+    Ok(())
+}
+
+impl JwtWithUser {
+    pub(super) async fn from_claims(ctx: &AppContext, claims: UserClaims) -> AppResult<Self> {
+        let revoked =
+            || AppError::unauthorized("session_revoked", "The session is revoked or expired");
+
+        let session_pid = claims
+            .claims
+            .as_ref()
+            .and_then(|claims| claims.get(sessions::SESSION_CLAIM))
+            .and_then(|pid| pid.as_str())
+            .and_then(|pid| Uuid::parse_str(pid).ok())
+            .ok_or_else(revoked)?;
+        let session = sessions::Model::find_active_by_pid(&ctx.db, session_pid)
+            .await?
+            .ok_or_else(revoked)?;
+
+        let user = users::Entity::find_by_id(session.user_id)
+            .one(&ctx.db)
+            .await?
+            .filter(|user| user.pid.to_string() == claims.pid)
+            .ok_or_else(revoked)?;
+
+        Ok(Self {
+            claims,
+            user,
+            session,
+        })
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for JwtWithUser
+where
+    AppContext: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        reject_legacy_api_key(parts)?;
+        let jwt = JWT::from_request_parts(parts, state).await?;
+        let ctx = AppContext::from_ref(state);
+        Self::from_claims(&ctx, jwt.claims).await
+    }
+}
@@ -2,8 +2,20 @@
 
 pub mod prelude;
 
+pub mod api_tokens;
 pub mod categories;
+pub mod documents;
+pub mod merge_jobs;
+pub mod oidc_logins;
+pub mod organization_members;
+pub mod organizations;
+pub mod sea_orm_active_enums;
+pub mod sessions;
+pub mod template_invitations;
 pub mod templates;
 pub mod templates_categories;
+pub mod templates_organizations;
+pub mod used_refresh_tokens;
+pub mod user_identities;
 pub mod users;
 pub mod users_visible_templates;
@@ -1,7 +1,18 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::api_tokens::Entity as ApiTokens;
 pub use super::categories::Entity as Categories;
+pub use super::documents::Entity as Documents;
+pub use super::merge_jobs::Entity as MergeJobs;
+pub use super::oidc_logins::Entity as OidcLogins;
+pub use super::organization_members::Entity as OrganizationMembers;
+pub use super::organizations::Entity as Organizations;
+pub use super::sessions::Entity as Sessions;
+pub use super::template_invitations::Entity as TemplateInvitations;
 pub use super::templates::Entity as Templates;
 pub use super::templates_categories::Entity as TemplatesCategories;
+pub use super::templates_organizations::Entity as TemplatesOrganizations;
+pub use super::used_refresh_tokens::Entity as UsedRefreshTokens;
+pub use super::user_identities::Entity as UserIdentities;
 pub use super::users::Entity as Users;
 pub use super::users_visible_templates::Entity as UsersVisibleTemplates;
@@ -14,12 +14,30 @@ pub struct Model {
     pub description: String,
     pub user_id: i32,
     pub is_public: bool,
+    pub downloads: i32,
+    #[sea_orm(column_type = "Text")]
+    pub search_text: String,
+    pub version: i32,
+    pub dsl_types: Option<Json>,
+    pub dsl_compiler_version: Option<i32>,
+    pub dsl_hash: Option<String>,
+    pub docx_hash: Option<String>,
+    pub files_updated_at: Option<DateTimeWithTimeZone>,
+    pub revision: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::documents::Entity")]
+    Documents,
+    #[sea_orm(has_many = "super::merge_jobs::Entity")]
+    MergeJobs,
+    #[sea_orm(has_many = "super::template_invitations::Entity")]
+    TemplateInvitations,
     #[sea_orm(has_many = "super::templates_categories::Entity")]
     TemplatesCategories,
+    #[sea_orm(has_many = "super::templates_organizations::Entity")]
+    TemplatesOrganizations,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -32,12 +50,36 @@ pub enum Relation {
     UsersVisibleTemplates,
 }
 
+impl Related<super::documents::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Documents.def()
+    }
+}
+
+impl Related<super::merge_jobs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MergeJobs.def()
+    }
+}
+
+impl Related<super::template_invitations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TemplateInvitations.def()
+    }
+}
+
 impl Related<super::templates_categories::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::TemplatesCategories.def()
     }
 }
 
+impl Related<super::templates_organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TemplatesOrganizations.def()
+    }
+}
+
 impl Related<super::users_visible_templates::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UsersVisibleTemplates.def()
@@ -3,6 +3,8 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::sea_orm_active_enums::UserRole;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "users")]
 pub struct Model {
@@ -22,24 +24,78 @@ pub struct Model {
     pub email_verification_token: Option<String>,
     pub email_verification_sent_at: Option<DateTimeWithTimeZone>,
     pub email_verified_at: Option<DateTimeWithTimeZone>,
+    pub role: UserRole,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTimeWithTimeZone>,
+    pub totp_last_step: Option<i64>,
+    pub totp_recovery_codes: Option<Json>,
+    pub pending_email: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::api_tokens::Entity")]
+    ApiTokens,
     #[sea_orm(has_many = "super::categories::Entity")]
     Categories,
+    #[sea_orm(has_many = "super::documents::Entity")]
+    Documents,
+    #[sea_orm(has_many = "super::merge_jobs::Entity")]
+    MergeJobs,
+    #[sea_orm(has_many = "super::organization_members::Entity")]
+    OrganizationMembers,
+    #[sea_orm(has_many = "super::sessions::Entity")]
+    Sessions,
     #[sea_orm(has_many = "super::templates::Entity")]
     Templates,
+    #[sea_orm(has_many = "super::user_identities::Entity")]
+    UserIdentities,
     #[sea_orm(has_many = "super::users_visible_templates::Entity")]
     UsersVisibleTemplates,
 }
 
+impl Related<super::api_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiTokens.def()
+    }
+}
+
 impl Related<super::categories::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Categories.def()
     }
 }
 
+impl Related<super::documents::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Documents.def()
+    }
+}
+
+impl Related<super::merge_jobs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MergeJobs.def()
+    }
+}
+
+impl Related<super::organization_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrganizationMembers.def()
+    }
+}
+
+impl Related<super::sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sessions.def()
+    }
+}
+
+impl Related<super::user_identities::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserIdentities.def()
+    }
+}
+
 impl Related<super::users_visible_templates::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UsersVisibleTemplates.def()
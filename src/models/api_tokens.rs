@@ -0,0 +1,212 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub use super::_entities::api_tokens::{self, ActiveModel, Entity, Model};
+use super::users::hash_token;
+use crate::errors::{AppError, AppResult};
+use crate::views::error::FieldError;
+
+/// Tokens start with the prefix, so they are told apart from access tokens
+/// and can be found by secret scanners.
+pub const TOKEN_PREFIX: &str = "ct_";
+
+const MAX_NAME_LENGTH: usize = 100;
+
+/// What a token allows. Access tokens of the logged in user allow
+/// everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+pub enum ApiScope {
+    /// List, view and download templates.
+    #[serde(rename = "templates:read")]
+    TemplatesRead,
+    /// Create, update and remove templates.
+    #[serde(rename = "templates:write")]
+    TemplatesWrite,
+    /// Start mail merges and download the results.
+    #[serde(rename = "render")]
+    Render,
+}
+
+impl ApiScope {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::TemplatesRead => "templates:read",
+            Self::TemplatesWrite => "templates:write",
+            Self::Render => "render",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            this.pid = ActiveValue::Set(Uuid::new_v4());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn generate_token() -> String {
+    format!("{TOKEN_PREFIX}{}", Uuid::new_v4().simple())
+}
+
+impl Model {
+    /// Issues a token of the user. Only the hash is stored, the token itself
+    /// is returned once.
+    ///
+    /// # Errors
+    ///
+    /// When the name, the scopes or the expiration are invalid or DB query
+    /// error
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: i32,
+        name: &str,
+        scopes: &[ApiScope],
+        expires_at: Option<DateTimeWithTimeZone>,
+    ) -> AppResult<(Self, String)> {
+        let name = name.trim();
+        let mut fields = Vec::new();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            fields.push(FieldError::new(
+                "name",
+                format!("must be from 1 to {MAX_NAME_LENGTH} characters"),
+            ));
+        }
+        if scopes.is_empty() {
+            fields.push(FieldError::new("scopes", "must not be empty"));
+        }
+        if expires_at.is_some_and(|expires_at| expires_at <= Local::now().fixed_offset()) {
+            fields.push(FieldError::new("expiresAt", "must be in the future"));
+        }
+        if !fields.is_empty() {
+            return Err(AppError::validation(
+                "invalid_token",
+                "The token is invalid",
+                fields,
+            ));
+        }
+
+        let mut scopes = scopes.to_vec();
+        scopes.sort_unstable();
+        scopes.dedup();
+
+        let token = generate_token();
+        let api_token = ActiveModel {
+            user_id: ActiveValue::set(user_id),
+            name: ActiveValue::set(name.to_string()),
+            token: ActiveValue::set(hash_token(&token)),
+            scopes: ActiveValue::set(serde_json::to_value(scopes).map_err(AppError::internal)?),
+            expires_at: ActiveValue::set(expires_at),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((api_token, token))
+    }
+
+    /// Finds the token, expired tokens are skipped.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_active_by_token(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> AppResult<Option<Self>> {
+        let api_token = Entity::find()
+            .filter(api_tokens::Column::Token.eq(hash_token(token)))
+            .one(db)
+            .await?;
+        Ok(api_token.filter(Self::is_active))
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_user(db: &DatabaseConnection, user_id: i32) -> AppResult<Vec<Self>> {
+        let api_tokens = Entity::find()
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .order_by_asc(api_tokens::Column::Id)
+            .all(db)
+            .await?;
+        Ok(api_tokens)
+    }
+
+    /// # Errors
+    ///
+    /// When the token is not found or belongs to another user or DB query
+    /// error
+    pub async fn find_by_pid_for_user(
+        db: &DatabaseConnection,
+        pid: Uuid,
+        user_id: i32,
+    ) -> AppResult<Self> {
+        Entity::find()
+            .filter(api_tokens::Column::Pid.eq(pid))
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Token not found"))
+    }
+
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| expires_at > Local::now().fixed_offset())
+    }
+
+    /// The scopes, unknown ones are skipped.
+    #[must_use]
+    pub fn scopes(&self) -> Vec<ApiScope> {
+        self.scopes
+            .as_array()
+            .map(|scopes| {
+                scopes
+                    .iter()
+                    .filter_map(|scope| serde_json::from_value(scope.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes().contains(&scope)
+    }
+}
+
+impl ActiveModel {
+    /// Replaces the token, e.g. when it leaked. The name, the scopes and the
+    /// expiration are kept.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn rotate(mut self, db: &DatabaseConnection) -> AppResult<(Model, String)> {
+        let token = generate_token();
+        self.token = ActiveValue::set(hash_token(&token));
+        self.last_used_at = ActiveValue::set(None);
+        Ok((self.update(db).await?, token))
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn set_used(mut self, db: &DatabaseConnection) -> AppResult<Model> {
+        self.last_used_at = ActiveValue::set(Some(Local::now().fixed_offset()));
+        Ok(self.update(db).await?)
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use cicero_dsl::compiler::compile_types;
+use cicero_dsl::validation::{self, Mode};
+use loco_rs::prelude::*;
+use sea_orm::{DatabaseTransaction, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::_entities::{categories, documents, templates, users};
+use super::template_invitations;
+use crate::bundle::{Bundle, BundlePublicity, BundleTemplate, TemplateManifest};
+use crate::controllers::categories::CategoryParams;
+use crate::controllers::documents::CreateDocumentParams;
+use crate::controllers::templates::{CreateTemplateParams, PublicityParams, UpdateTemplateParams};
+use crate::errors::{AppError, AppResult};
+use crate::models::loaders::TemplateRelations;
+use crate::models::roles::Permission;
+use crate::views::error::FieldError;
+use crate::views::template::PublicityResponse;
+
+/// What happens to a bundled template, when the importing user already has
+/// a template with the same name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    /// The existing template is kept as is.
+    #[default]
+    Skip,
+    /// The existing template is updated from the bundle.
+    Overwrite,
+    /// The bundled template is imported under a free name, e.g. `Договор
+    /// (2)`.
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStatus {
+    Created,
+    Updated,
+    Skipped,
+}
+
+/// Outcome of [`import`], in the order of the bundle.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub templates: Vec<(templates::Model, ImportStatus)>,
+    /// Categories, that didn't exist and were created by name.
+    pub created_categories: Vec<categories::Model>,
+}
+
+/// Bundles the templates visible to the user. The latest document of the
+/// user for a template becomes its sample data. The viewers of private
+/// templates are bundled only for their authors and editors.
+///
+/// # Errors
+///
+/// When no templates are given, a template is not found or not visible, its
+/// files are missing or DB query error
+pub async fn export(
+    db: &DatabaseConnection,
+    ids: &[i32],
+    user: &users::Model,
+) -> AppResult<Bundle> {
+    if ids.is_empty() {
+        return Err(AppError::validation(
+            "invalid_bundle",
+            "The bundle must contain templates",
+            vec![FieldError::new("ids", "must not be empty")],
+        ));
+    }
+
+    let mut found = Vec::with_capacity(ids.len());
+    for &id in ids {
+        found.push(templates::Model::find_by_id_for_user(db, id, user.id).await?);
+    }
+    let responses = TemplateRelations::load_responses(db, &found).await?;
+    let ids = found.iter().map(|template| template.id).collect::<Vec<_>>();
+    let invitations = template_invitations::Model::find_for_templates(db, &ids).await?;
+    let all_categories = categories::Model::find_all(db)
+        .await?
+        .into_iter()
+        .map(|category| (category.id, category))
+        .collect::<HashMap<_, _>>();
+
+    let mut bundle = Bundle::default();
+    for (template, response) in found.iter().zip(responses) {
+        let publicity = match response.publicity {
+            PublicityResponse::Public => BundlePublicity::Public,
+            // only those, who can edit the template, see whom it's shared with
+            PublicityResponse::Private { .. }
+                if template.user_id != user.id && !user.role.can(Permission::EditTemplates) =>
+            {
+                BundlePublicity::Private {
+                    viewers: Vec::new(),
+                }
+            },
+            // invited viewers without an account are viewers too
+            PublicityResponse::Private { viewers, .. } => {
+                BundlePublicity::Private {
+                    viewers: viewers
+                        .into_iter()
+                        .map(|viewer| viewer.email)
+                        .chain(
+                            invitations
+                                .iter()
+                                .filter(|invitation| invitation.template_id == template.id)
+                                .map(|invitation| invitation.email.clone()),
+                        )
+                        .collect(),
+                }
+            },
+        };
+
+        let sample = documents::Entity::find()
+            .filter(documents::Column::UserId.eq(user.id))
+            .filter(documents::Column::TemplateId.eq(template.id))
+            .order_by_desc(documents::Column::UpdatedAt)
+            .one(db)
+            .await?;
+
+        bundle.templates.push(BundleTemplate {
+            manifest: TemplateManifest {
+                name: template.name.clone(),
+                description: template.description.clone(),
+                categories: response
+                    .categories
+                    .iter()
+                    .map(|category| category_path(&all_categories, category.id))
+                    .collect(),
+                publicity,
+                version: template.version,
+                sample_data: sample.map(|document| document.data),
+            },
+            docx: templates::Model::find_docx(template.id).await?,
+            dsl: templates::Model::find_dsl(template.id).await?,
+        });
+    }
+
+    Ok(bundle)
+}
+
+/// Imports the templates of the bundle as templates of the user. Missing
+/// categories are created under their parents, and the sample data becomes
+/// a document of the user for every created template.
+///
+/// The import is done in a single transaction, so either every template is
+/// imported or none. The files of the templates are restored on failure too.
+///
+/// # Errors
+///
+/// When a DSL or sample data is invalid, a viewer email is invalid, error
+/// writing files, or DB query error
+pub async fn import(
+    db: &DatabaseConnection,
+    bundle: &Bundle,
+    user: &users::Model,
+    conflicts: ConflictPolicy,
+) -> AppResult<ImportReport> {
+    for (i, template) in bundle.templates.iter().enumerate() {
+        let env = compile_types(&template.dsl)
+            .map_err(|err| AppError::invalid_dsl(&format!("templates[{i}].dsl"), err))?;
+
+        for (j, path) in template.manifest.categories.iter().enumerate() {
+            if path.is_empty() {
+                return Err(AppError::validation(
+                    "invalid_bundle",
+                    "The bundle can't be read",
+                    vec![FieldError::new(
+                        &format!("templates[{i}].categories[{j}]"),
+                        "must not be empty",
+                    )],
+                ));
+            }
+        }
+
+        if let Some(data) = &template.manifest.sample_data {
+            validation::validate(&env, data, Mode::Draft).map_err(|errors| {
+                AppError::invalid_data(&format!("templates[{i}].sampleData"), &errors)
+            })?;
+        }
+    }
+
+    let txn = db.begin().await?;
+    let mut written = Vec::new();
+    match import_templates(&txn, bundle, user, conflicts, &mut written).await {
+        Ok(report) => {
+            txn.commit().await?;
+            Ok(report)
+        },
+        Err(err) => {
+            txn.rollback().await?;
+            restore_files(written).await;
+            Err(err)
+        },
+    }
+}
+
+/// Files of a template, that the import has written. The previous files are
+/// kept for the updated templates.
+struct WrittenFiles {
+    template_id: i32,
+    previous: Option<(Vec<u8>, String)>,
+}
+
+async fn import_templates(
+    txn: &DatabaseTransaction,
+    bundle: &Bundle,
+    user: &users::Model,
+    conflicts: ConflictPolicy,
+    written: &mut Vec<WrittenFiles>,
+) -> AppResult<ImportReport> {
+    let mut report = ImportReport::default();
+    for template in &bundle.templates {
+        let manifest = &template.manifest;
+
+        let mut category_ids = Vec::with_capacity(manifest.categories.len());
+        for path in &manifest.categories {
+            category_ids.push(find_or_create_category(txn, path, user, &mut report).await?);
+        }
+
+        let mut params = CreateTemplateParams {
+            name: manifest.name.clone(),
+            description: manifest.description.clone(),
+            categories: category_ids,
+            publicity: match &manifest.publicity {
+                BundlePublicity::Public => PublicityParams::Public,
+                BundlePublicity::Private { viewers } => {
+                    PublicityParams::Private {
+                        viewers: viewers.clone(),
+                        organizations: Vec::new(),
+                    }
+                },
+            },
+        };
+
+        let existing = find_by_name(txn, user.id, &params.name).await?;
+        let imported = match (existing, conflicts) {
+            (Some(existing), ConflictPolicy::Skip) => (existing, ImportStatus::Skipped),
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                let id = existing.id;
+                written.push(WrittenFiles {
+                    template_id: id,
+                    previous: Some((
+                        templates::Model::find_docx(id).await?,
+                        templates::Model::find_dsl(id).await?,
+                    )),
+                });
+                // the import replaces whatever revision the template has
+                let params = UpdateTemplateParams {
+                    template: params,
+                    revision: None,
+                };
+                let updated = existing
+                    .into_active_model()
+                    .update_template(txn, &params, id, user, &template.docx, &template.dsl)
+                    .await?;
+                (updated, ImportStatus::Updated)
+            },
+            (existing, _) => {
+                if existing.is_some() {
+                    params.name = free_name(txn, user.id, &params.name).await?;
+                }
+
+                let created =
+                    templates::Model::create(txn, &params, user.id, &template.docx, &template.dsl)
+                        .await?;
+                written.push(WrittenFiles {
+                    template_id: created.id,
+                    previous: None,
+                });
+
+                if let Some(data) = &manifest.sample_data {
+                    let params = CreateDocumentParams {
+                        template_id: created.id,
+                        name: None,
+                        data: data.clone(),
+                    };
+                    documents::Model::create(txn, &params, user.id).await?;
+                }
+
+                (created, ImportStatus::Created)
+            },
+        };
+        report.templates.push(imported);
+    }
+
+    Ok(report)
+}
+
+/// Puts back the files, that a failed import has overwritten, and removes
+/// the files of the templates, that it has created.
+async fn restore_files(written: Vec<WrittenFiles>) {
+    for files in written.into_iter().rev() {
+        let docx_path = format!("./data/templates/{}.docx", files.template_id);
+        let dsl_path = format!("./data/templates/{}.dsl", files.template_id);
+        let result = match files.previous {
+            Some((docx, dsl)) => {
+                match fs::write(&docx_path, docx).await {
+                    Ok(()) => fs::write(&dsl_path, dsl).await,
+                    Err(err) => Err(err),
+                }
+            },
+            None => {
+                let _ = fs::remove_file(&docx_path).await;
+                fs::remove_file(&dsl_path).await
+            },
+        };
+        if let Err(err) = result {
+            tracing::error!(
+                template_id = files.template_id,
+                error = %err,
+                "can't restore the files of the template after a failed import"
+            );
+        }
+    }
+}
+
+/// Names of the category and its parents, from the root down.
+fn category_path(all: &HashMap<i32, categories::Model>, id: i32) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut next = all.get(&id);
+    // the length guards against a cycle in the tree
+    while let Some(category) = next.filter(|_| path.len() <= all.len()) {
+        path.push(category.name.clone());
+        next = category.parent_id.and_then(|parent_id| all.get(&parent_id));
+    }
+    path.reverse();
+    path
+}
+
+/// The ID of the last category of the path. Names of categories are unique,
+/// so an existing category is used wherever it's in the tree, and the missing
+/// ones are created under the previous category of the path.
+async fn find_or_create_category(
+    txn: &DatabaseTransaction,
+    path: &[String],
+    user: &users::Model,
+    report: &mut ImportReport,
+) -> AppResult<i32> {
+    let mut parent_id = None;
+    for name in path {
+        let category = categories::Entity::find()
+            .filter(categories::Column::Name.eq(name.as_str()))
+            .order_by_asc(categories::Column::Id)
+            .one(txn)
+            .await?;
+        let category = match category {
+            Some(category) => category,
+            None => {
+                let params = CategoryParams {
+                    name: name.clone(),
+                    parent_id,
+                };
+                let category = categories::Model::create(txn, &params, user).await?;
+                report.created_categories.push(category.clone());
+                category
+            },
+        };
+        parent_id = Some(category.id);
+    }
+
+    parent_id.ok_or_else(|| AppError::internal("the category path is empty"))
+}
+
+async fn find_by_name<C>(db: &C, user_id: i32, name: &str) -> AppResult<Option<templates::Model>>
+where
+    C: ConnectionTrait,
+{
+    let template = templates::Entity::find()
+        .filter(templates::Column::UserId.eq(user_id))
+        .filter(templates::Column::Name.eq(name))
+        .order_by_asc(templates::Column::Id)
+        .one(db)
+        .await?;
+    Ok(template)
+}
+
+/// The first of `name (2)`, `name (3)`, ..., that the user doesn't have.
+async fn free_name<C>(db: &C, user_id: i32, name: &str) -> AppResult<String>
+where
+    C: ConnectionTrait,
+{
+    let mut number = 2;
+    loop {
+        let candidate = format!("{name} ({number})");
+        if find_by_name(db, user_id, &candidate).await?.is_none() {
+            return Ok(candidate);
+        }
+        number += 1;
+    }
+}
@@ -1,8 +1,15 @@
+use std::collections::HashMap;
+
 use loco_rs::prelude::*;
+use sea_orm::{JoinType, PaginatorTrait, QueryOrder, QuerySelect, RelationTrait};
 
 // use sea_orm::entity::prelude::*;
 pub use super::_entities::categories::{self, ActiveModel, Entity, Model};
-use super::_entities::templates;
+use super::_entities::{templates, templates_categories, users};
+use crate::controllers::categories::CategoryParams;
+use crate::errors::{AppError, AppResult};
+use crate::models::roles::Permission;
+use crate::views::error::FieldError;
 
 impl ActiveModelBehavior for ActiveModel {
     // extend active model below (keep comment for generators)
@@ -12,21 +19,300 @@ impl Model {
     /// # Errors
     ///
     /// When entity is not found
-    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> ModelResult<Self> {
+    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> AppResult<Self> {
         let category = Entity::find_by_id(id).one(db).await?;
-        category.ok_or_else(|| ModelError::EntityNotFound)
+        category.ok_or_else(|| AppError::not_found("Category not found"))
     }
 
     /// # Errors
     ///
-    /// When entity is not found
-    pub async fn find_for_template(
-        db: &DatabaseConnection,
-        template_id: i32,
-    ) -> ModelResult<Vec<Self>> {
-        let template = templates::Model::find_by_id(db, template_id).await?;
-        let categories = template.find_related(Entity).all(db).await?;
+    /// When DB query error
+    pub async fn find_all(db: &DatabaseConnection) -> AppResult<Vec<Self>> {
+        let categories = Entity::find()
+            .order_by_asc(categories::Column::Name)
+            .all(db)
+            .await?;
+        Ok(categories)
+    }
 
+    /// Categories, that the user created.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_user(db: &DatabaseConnection, user_id: i32) -> AppResult<Vec<Self>> {
+        let categories = Entity::find()
+            .filter(categories::Column::UserId.eq(user_id))
+            .order_by_asc(categories::Column::Id)
+            .all(db)
+            .await?;
         Ok(categories)
     }
+
+    /// Counts templates visible to the user (or public ones, if there is no
+    /// user) directly in each category. Categories without templates are
+    /// absent from the map.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn count_visible_templates(
+        db: &DatabaseConnection,
+        user_id: Option<i32>,
+    ) -> AppResult<HashMap<i32, u64>> {
+        let counts: Vec<(i32, i64)> = templates_categories::Entity::find()
+            .select_only()
+            .column(templates_categories::Column::CategoryId)
+            .column_as(templates_categories::Column::TemplateId.count(), "count")
+            .join(
+                JoinType::InnerJoin,
+                templates_categories::Relation::Templates.def(),
+            )
+            .filter(templates::Model::visibility_condition(user_id))
+            .group_by(templates_categories::Column::CategoryId)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        Ok(counts
+            .into_iter()
+            .map(|(category_id, count)| (category_id, count.unsigned_abs()))
+            .collect())
+    }
+
+    /// Finds IDs of the category and all of its descendants.
+    ///
+    /// # Errors
+    ///
+    /// When entity is not found or DB query error
+    pub async fn find_subtree_ids<C>(db: &C, id: i32) -> AppResult<Vec<i32>>
+    where
+        C: ConnectionTrait,
+    {
+        let edges: Vec<(i32, Option<i32>)> = Entity::find()
+            .select_only()
+            .column(categories::Column::Id)
+            .column(categories::Column::ParentId)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        if !edges.iter().any(|&(category_id, _)| category_id == id) {
+            return Err(AppError::not_found("Category not found"));
+        }
+
+        let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (category_id, parent_id) in edges {
+            if let Some(parent_id) = parent_id {
+                children.entry(parent_id).or_default().push(category_id);
+            }
+        }
+
+        let mut subtree = vec![id];
+        let mut next = 0;
+        while let Some(&category_id) = subtree.get(next) {
+            if let Some(children) = children.get(&category_id) {
+                subtree.extend(children);
+            }
+            next += 1;
+        }
+
+        Ok(subtree)
+    }
+
+    /// Subcategories can be added only to the own categories, unless the
+    /// user is allowed to manage categories.
+    ///
+    /// # Errors
+    ///
+    /// When parent is not found or belongs to another user, name is already
+    /// taken or DB query error
+    pub async fn create<C>(db: &C, params: &CategoryParams, user: &users::Model) -> AppResult<Self>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let txn = db.begin().await?;
+
+        Self::check_name_is_free(&txn, &params.name, None).await?;
+
+        if let Some(parent_id) = params.parent_id {
+            Self::check_parent(&txn, parent_id, user).await?;
+        }
+
+        let category = ActiveModel {
+            name: Set(params.name.clone()),
+            parent_id: Set(params.parent_id),
+            user_id: Set(user.id),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(category)
+    }
+
+    /// Deletes the category together with its subcategories. Subcategories of
+    /// other users are deleted only by those, who can manage categories.
+    ///
+    /// # Errors
+    ///
+    /// When category is not found, user is neither the owner nor allowed to
+    /// manage categories, the subtree has categories of other users, or DB
+    /// query error
+    pub async fn delete_category(
+        db: &DatabaseConnection,
+        id: i32,
+        user: &users::Model,
+    ) -> AppResult<()> {
+        let txn = db.begin().await?;
+
+        let category = Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Category not found"))?;
+
+        if category.user_id != user.id && !user.role.can(Permission::ManageCategories) {
+            txn.rollback().await?;
+            return Err(AppError::forbidden(
+                "Only the author or an editor can change the category",
+            ));
+        }
+
+        let subtree = Self::find_subtree_ids(&txn, id).await?;
+
+        if !user.role.can(Permission::ManageCategories) {
+            let foreign = Entity::find()
+                .filter(categories::Column::Id.is_in(subtree.clone()))
+                .filter(categories::Column::UserId.ne(user.id))
+                .count(&txn)
+                .await?;
+            if foreign > 0 {
+                txn.rollback().await?;
+                return Err(AppError::conflict(
+                    "category_not_empty",
+                    "The category has subcategories of other users",
+                ));
+            }
+        }
+
+        Entity::delete_many()
+            .filter(categories::Column::Id.is_in(subtree))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Checks, that the parent exists and the user can add subcategories to
+    /// it.
+    async fn check_parent<C>(db: &C, parent_id: i32, user: &users::Model) -> AppResult<()>
+    where
+        C: ConnectionTrait,
+    {
+        let parent = Entity::find_by_id(parent_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| {
+                AppError::validation(
+                    "invalid_references",
+                    "The parent category is not found",
+                    vec![FieldError::new("parentId", "category is not found")],
+                )
+            })?;
+
+        if parent.user_id != user.id && !user.role.can(Permission::ManageCategories) {
+            return Err(AppError::forbidden(
+                "Only the author or an editor can add subcategories to the category",
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn check_name_is_free<C>(db: &C, name: &str, except_id: Option<i32>) -> AppResult<()>
+    where
+        C: ConnectionTrait,
+    {
+        let existing = Entity::find()
+            .filter(categories::Column::Name.eq(name))
+            .one(db)
+            .await?;
+
+        match existing {
+            Some(existing) if Some(existing.id) != except_id => {
+                Err(AppError::conflict(
+                    "name_taken",
+                    format!("Category `{name}` already exists"),
+                ))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ActiveModel {
+    /// Renames the category and moves it under another parent, that the user
+    /// can add subcategories to, see [`Model::create`].
+    ///
+    /// # Errors
+    ///
+    /// When category or parent is not found, user is neither the owner nor
+    /// allowed to manage categories, the parent belongs to another user, name
+    /// is already taken, the parent is inside the category's subtree, or DB
+    /// query error
+    pub async fn update_category(
+        self,
+        db: &DatabaseConnection,
+        params: &CategoryParams,
+        user: &users::Model,
+    ) -> AppResult<Model> {
+        let txn = db.begin().await?;
+
+        let id = *self.id.as_ref();
+
+        let category = Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Category not found"))?;
+
+        if category.user_id != user.id && !user.role.can(Permission::ManageCategories) {
+            txn.rollback().await?;
+            return Err(AppError::forbidden(
+                "Only the author or an editor can change the category",
+            ));
+        }
+
+        Model::check_name_is_free(&txn, &params.name, Some(id)).await?;
+
+        if let Some(parent_id) = params.parent_id {
+            let subtree = Model::find_subtree_ids(&txn, id).await?;
+
+            if subtree.contains(&parent_id) {
+                txn.rollback().await?;
+                return Err(AppError::validation(
+                    "category_cycle",
+                    "Category can't be moved inside itself",
+                    vec![FieldError::new("parentId", "is inside of the category")],
+                ));
+            }
+
+            // an unchanged parent stays, even if it belongs to another user
+            if category.parent_id != Some(parent_id) {
+                Model::check_parent(&txn, parent_id, user).await?;
+            }
+        }
+
+        let mut category = category.into_active_model();
+        category.name = Set(params.name.clone());
+        category.parent_id = Set(params.parent_id);
+        let category = category.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(category)
+    }
 }
@@ -0,0 +1,201 @@
+use cicero_dsl::compiler::VarEnv;
+use cicero_dsl::validation::{self, Mode};
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+
+pub use super::_entities::documents::{self, ActiveModel, Entity, Model};
+use super::_entities::templates;
+use crate::controllers::documents::{CreateDocumentParams, SaveDocumentParams};
+use crate::errors::{AppError, AppResult};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            return Ok(self);
+        }
+
+        // drafts are listed by the time of the last autosave
+        let mut this = self;
+        this.updated_at = ActiveValue::Set(chrono::Utc::now().into());
+        Ok(this)
+    }
+}
+
+/// Checks the data of a draft, so missing values are allowed.
+fn validate_data(env: &VarEnv, data: &serde_json::Value) -> AppResult<()> {
+    validation::validate(env, data, Mode::Draft)
+        .map_err(|errors| AppError::invalid_data("data", &errors))
+}
+
+impl Model {
+    /// # Errors
+    ///
+    /// When entity is not found
+    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> AppResult<Self> {
+        let document = Entity::find_by_id(id).one(db).await?;
+        document.ok_or_else(|| AppError::not_found("Document not found"))
+    }
+
+    /// # Errors
+    ///
+    /// When entity is not found or the user is not the owner
+    pub async fn find_by_id_for_user(
+        db: &DatabaseConnection,
+        id: i32,
+        user_id: i32,
+    ) -> AppResult<Self> {
+        let document = Self::find_by_id(db, id).await?;
+
+        if document.user_id != user_id {
+            return Err(AppError::forbidden("The document belongs to another user"));
+        }
+
+        Ok(document)
+    }
+
+    /// Finds documents of the user together with their templates, the most
+    /// recently saved first.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_with_templates_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> AppResult<Vec<(Self, templates::Model)>> {
+        let documents = Entity::find()
+            .filter(documents::Column::UserId.eq(user_id))
+            .find_also_related(templates::Entity)
+            .order_by_desc(documents::Column::UpdatedAt)
+            .order_by_desc(documents::Column::Id)
+            .all(db)
+            .await?;
+
+        Ok(documents
+            .into_iter()
+            .filter_map(|(document, template)| Some((document, template?)))
+            .collect())
+    }
+
+    /// # Errors
+    ///
+    /// When template is not found or not visible to the user, the data
+    /// doesn't match the DSL, or error reading the DSL
+    pub async fn create<C>(db: &C, params: &CreateDocumentParams, user_id: i32) -> AppResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        let template =
+            templates::Model::find_by_id_for_user(db, params.template_id, user_id).await?;
+
+        let env = template.find_var_env(db).await?;
+        validate_data(&env, &params.data)?;
+
+        let document = ActiveModel {
+            name: Set(params.name.clone().unwrap_or_else(|| template.name.clone())),
+            user_id: Set(user_id),
+            template_id: Set(template.id),
+            template_version: Set(template.version),
+            data: Set(params.data.clone()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(document)
+    }
+
+    /// Finds the document to continue filling it. If the template was changed
+    /// since the last save, the values that don't match the new DSL are
+    /// dropped and the rest are kept. The migrated data is only returned, it's
+    /// stored by the next [`ActiveModel::save_document`].
+    ///
+    /// # Errors
+    ///
+    /// When document is not found, user is not the owner, error reading the
+    /// DSL, or DB query error
+    pub async fn reopen(db: &DatabaseConnection, id: i32, user_id: i32) -> AppResult<Self> {
+        let mut document = Self::find_by_id_for_user(db, id, user_id).await?;
+        let template = templates::Model::find_by_id(db, document.template_id).await?;
+
+        if document.template_version == template.version {
+            return Ok(document);
+        }
+
+        let env = template.find_var_env(db).await?;
+        document.data = validation::retain_compatible(&env, &document.data);
+        document.template_version = template.version;
+
+        Ok(document)
+    }
+
+    /// Creates a copy of the document owned by the same user.
+    ///
+    /// # Errors
+    ///
+    /// When document is not found, user is not the owner, or DB query error
+    pub async fn duplicate(db: &DatabaseConnection, id: i32, user_id: i32) -> AppResult<Self> {
+        let document = Self::find_by_id_for_user(db, id, user_id).await?;
+
+        let copy = ActiveModel {
+            name: Set(format!("{} (copy)", document.name)),
+            user_id: Set(document.user_id),
+            template_id: Set(document.template_id),
+            template_version: Set(document.template_version),
+            data: Set(document.data),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(copy)
+    }
+
+    /// # Errors
+    ///
+    /// When document is not found, user is not the owner, or DB query error
+    pub async fn delete_document(db: &DatabaseConnection, id: i32, user_id: i32) -> AppResult<()> {
+        let document = Self::find_by_id_for_user(db, id, user_id).await?;
+
+        Entity::delete(document.into_active_model())
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl ActiveModel {
+    /// Replaces the name and the data of the document, so saving the same
+    /// state again is harmless. The data is checked against the current DSL
+    /// of the template.
+    ///
+    /// # Errors
+    ///
+    /// When document is not found, user is not the owner, the data doesn't
+    /// match the DSL, error reading the DSL, or DB query error
+    pub async fn save_document(
+        self,
+        db: &DatabaseConnection,
+        params: &SaveDocumentParams,
+        user_id: i32,
+    ) -> AppResult<Model> {
+        let document = Model::find_by_id_for_user(db, *self.id.as_ref(), user_id).await?;
+        let template = templates::Model::find_by_id(db, document.template_id).await?;
+
+        let env = template.find_var_env(db).await?;
+        validate_data(&env, &params.data)?;
+
+        let mut document = document.into_active_model();
+        document.name = Set(params.name.clone());
+        document.data = Set(params.data.clone());
+        document.template_version = Set(template.version);
+        let document = document.update(db).await?;
+
+        Ok(document)
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+
+use super::_entities::{
+    categories,
+    organizations,
+    templates,
+    templates_categories,
+    templates_organizations,
+    users,
+    users_visible_templates,
+};
+use crate::views::template::WithCategoriesResponse;
+
+/// Authors, categories, viewers and organizations of a batch of templates,
+/// fetched in a constant number of queries regardless of the batch size.
+#[derive(Debug, Default)]
+pub struct TemplateRelations {
+    authors: HashMap<i32, users::Model>,
+    categories: HashMap<i32, Vec<categories::Model>>,
+    viewers: HashMap<i32, Vec<users::Model>>,
+    organizations: HashMap<i32, Vec<organizations::Model>>,
+}
+
+impl TemplateRelations {
+    /// Loads relations of the given templates in at most four queries: one
+    /// for authors, one for categories, and one for viewers and one for
+    /// organizations of private templates.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn load(
+        db: &DatabaseConnection,
+        templates: &[templates::Model],
+    ) -> ModelResult<Self> {
+        if templates.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let template_ids = templates.iter().map(|template| template.id);
+        let author_ids = templates
+            .iter()
+            .map(|template| template.user_id)
+            .collect::<HashSet<_>>();
+        let private_ids = templates
+            .iter()
+            .filter(|template| !template.is_public)
+            .map(|template| template.id)
+            .collect::<Vec<_>>();
+
+        let authors = users::Entity::find()
+            .filter(users::Column::Id.is_in(author_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|author| (author.id, author))
+            .collect();
+
+        let mut categories: HashMap<i32, Vec<categories::Model>> = HashMap::new();
+        for (link, category) in templates_categories::Entity::find()
+            .filter(templates_categories::Column::TemplateId.is_in(template_ids))
+            .find_also_related(categories::Entity)
+            .order_by_asc(templates_categories::Column::CategoryId)
+            .all(db)
+            .await?
+        {
+            if let Some(category) = category {
+                categories
+                    .entry(link.template_id)
+                    .or_default()
+                    .push(category);
+            }
+        }
+
+        let mut viewers: HashMap<i32, Vec<users::Model>> = HashMap::new();
+        let mut organizations: HashMap<i32, Vec<organizations::Model>> = HashMap::new();
+        if !private_ids.is_empty() {
+            for (link, viewer) in users_visible_templates::Entity::find()
+                .filter(users_visible_templates::Column::TemplateId.is_in(private_ids.clone()))
+                .find_also_related(users::Entity)
+                .order_by_asc(users_visible_templates::Column::UserId)
+                .all(db)
+                .await?
+            {
+                if let Some(viewer) = viewer {
+                    viewers.entry(link.template_id).or_default().push(viewer);
+                }
+            }
+
+            for (link, organization) in templates_organizations::Entity::find()
+                .filter(templates_organizations::Column::TemplateId.is_in(private_ids))
+                .find_also_related(organizations::Entity)
+                .order_by_asc(templates_organizations::Column::OrganizationId)
+                .all(db)
+                .await?
+            {
+                if let Some(organization) = organization {
+                    organizations
+                        .entry(link.template_id)
+                        .or_default()
+                        .push(organization);
+                }
+            }
+        }
+
+        Ok(Self {
+            authors,
+            categories,
+            viewers,
+            organizations,
+        })
+    }
+
+    /// Assembles the response for a template from the loaded relations.
+    ///
+    /// # Errors
+    ///
+    /// When the template was not part of the loaded batch
+    pub fn response(&self, template: &templates::Model) -> ModelResult<WithCategoriesResponse> {
+        let author = self
+            .authors
+            .get(&template.user_id)
+            .ok_or(ModelError::EntityNotFound)?;
+        let categories = self
+            .categories
+            .get(&template.id)
+            .map_or(&[][..], Vec::as_slice);
+        let sharing = (!template.is_public).then(|| {
+            (
+                self.viewers
+                    .get(&template.id)
+                    .map_or(&[][..], Vec::as_slice),
+                self.organizations
+                    .get(&template.id)
+                    .map_or(&[][..], Vec::as_slice),
+            )
+        });
+
+        Ok(WithCategoriesResponse::new(
+            template, author, categories, sharing,
+        ))
+    }
+
+    /// Loads relations of the templates and assembles their responses,
+    /// preserving the order of the templates.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn load_responses(
+        db: &DatabaseConnection,
+        templates: &[templates::Model],
+    ) -> ModelResult<Vec<WithCategoriesResponse>> {
+        let relations = Self::load(db, templates).await?;
+
+        templates
+            .iter()
+            .map(|template| relations.response(template))
+            .collect()
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use cicero_dsl::compiler::VarEnv;
+use cicero_dsl::path;
+use cicero_dsl::validation::{self, Mode, ValidationError};
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+pub use super::_entities::merge_jobs::{self, ActiveModel, Entity, Model};
+pub use super::_entities::sea_orm_active_enums::MergeJobStatus;
+use super::_entities::templates;
+use crate::controllers::merges::MergeParams;
+use crate::errors::{AppError, AppResult};
+use crate::spreadsheet::Table;
+use crate::views::error::FieldError;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            return Ok(self);
+        }
+
+        // shows when the progress was reported last time
+        let mut this = self;
+        this.updated_at = ActiveValue::Set(chrono::Utc::now().into());
+        Ok(this)
+    }
+}
+
+/// Problems with a single row of the table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RowErrors {
+    /// Number of the row in the spreadsheet, the header is the first row.
+    pub row: usize,
+    pub errors: Vec<ValidationError>,
+}
+
+fn to_json<T: Serialize>(value: &T) -> AppResult<serde_json::Value> {
+    serde_json::to_value(value).map_err(AppError::internal)
+}
+
+/// Column headers mapped to paths of values, see [`path`].
+pub type Mapping = BTreeMap<String, String>;
+
+impl Model {
+    /// Jobs, that the user started, the oldest first.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_user(db: &DatabaseConnection, user_id: i32) -> AppResult<Vec<Self>> {
+        let jobs = Entity::find()
+            .filter(merge_jobs::Column::UserId.eq(user_id))
+            .order_by_asc(merge_jobs::Column::Id)
+            .all(db)
+            .await?;
+        Ok(jobs)
+    }
+
+    /// # Errors
+    ///
+    /// When entity is not found
+    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> AppResult<Self> {
+        let job = Entity::find_by_id(id).one(db).await?;
+        job.ok_or_else(|| AppError::not_found("Merge job not found"))
+    }
+
+    /// # Errors
+    ///
+    /// When entity is not found or the user is not the owner
+    pub async fn find_by_id_for_user(
+        db: &DatabaseConnection,
+        id: i32,
+        user_id: i32,
+    ) -> AppResult<Self> {
+        let job = Self::find_by_id(db, id).await?;
+
+        if job.user_id != user_id {
+            return Err(AppError::forbidden("The merge job belongs to another user"));
+        }
+
+        Ok(job)
+    }
+
+    /// Creates a pending job, that generates a document for every row of the
+    /// table.
+    ///
+    /// # Errors
+    ///
+    /// When template is not found or not visible to the user, the mapping
+    /// refers to missing columns or values, the table is empty, error reading
+    /// the DSL, or DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        params: &MergeParams,
+        user_id: i32,
+        table: &Table,
+    ) -> AppResult<Self> {
+        let template =
+            templates::Model::find_by_id_for_user(db, params.template_id, user_id).await?;
+
+        let env = template.find_var_env(db).await?;
+
+        let fields = params
+            .mapping
+            .iter()
+            .filter_map(|(column, path)| {
+                let field = format!("mapping.{column}");
+                if table.column(column).is_none() {
+                    return Some(FieldError::new(&field, "no such column"));
+                }
+                path::check(&env, path)
+                    .err()
+                    .map(|error| FieldError::new(&field, error))
+            })
+            .collect::<Vec<_>>();
+
+        if !fields.is_empty() {
+            return Err(AppError::validation(
+                "invalid_mapping",
+                "The mapping is invalid",
+                fields,
+            ));
+        }
+
+        if table.rows.is_empty() {
+            return Err(AppError::validation(
+                "empty_table",
+                "The table has no rows",
+                vec![FieldError::new("file", "has no rows")],
+            ));
+        }
+
+        let total_rows = i32::try_from(table.rows.len()).map_err(|_| {
+            AppError::validation("table_too_large", "The table is too large", vec![
+                FieldError::new("file", "has too many rows"),
+            ])
+        })?;
+
+        let job = ActiveModel {
+            user_id: Set(user_id),
+            template_id: Set(template.id),
+            status: Set(MergeJobStatus::Pending),
+            mapping: Set(to_json(&params.mapping)?),
+            headers: Set(to_json(&table.headers)?),
+            rows: Set(to_json(&table.rows)?),
+            row_numbers: Set(Some(to_json(&table.row_numbers)?)),
+            total_rows: Set(total_rows),
+            row_errors: Set(serde_json::Value::Array(Vec::new())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Path of the ZIP archive with generated documents.
+    #[must_use]
+    pub fn result_path(&self) -> PathBuf {
+        PathBuf::from(format!("./data/merges/{}.zip", self.id))
+    }
+
+    /// # Errors
+    ///
+    /// When the job is not completed or error reading file
+    pub async fn find_result(&self) -> AppResult<Vec<u8>> {
+        if self.status != MergeJobStatus::Completed {
+            return Err(AppError::conflict(
+                "job_not_completed",
+                "The job is not completed",
+            ));
+        }
+
+        fs::read(self.result_path())
+            .await
+            .map_err(AppError::internal)
+    }
+
+    /// # Errors
+    ///
+    /// When the stored row errors are malformed
+    pub fn row_errors(&self) -> AppResult<Vec<RowErrors>> {
+        serde_json::from_value(self.row_errors.clone()).map_err(AppError::internal)
+    }
+
+    /// Fills the data of the document from a row of the table and checks,
+    /// that all required values are present.
+    ///
+    /// # Errors
+    ///
+    /// Returns all problems with the row
+    pub fn row_data(
+        env: &VarEnv,
+        mapping: &Mapping,
+        headers: &[String],
+        row: &[String],
+    ) -> Result<serde_json::Value, Vec<ValidationError>> {
+        let mut data = serde_json::Value::Object(serde_json::Map::new());
+        let mut errors = Vec::new();
+
+        for (column, path) in mapping {
+            let text = headers
+                .iter()
+                .position(|header| header == column)
+                .and_then(|index| row.get(index))
+                .map_or("", String::as_str);
+
+            if let Err(error) = path::set(env, &mut data, path, text) {
+                errors.push(error);
+            }
+        }
+
+        if let Err(validation_errors) = validation::validate(env, &data, Mode::Complete) {
+            // values, that couldn't be parsed, are already reported
+            let validation_errors = validation_errors
+                .into_iter()
+                .filter(|error| !errors.iter().any(|parsed| parsed.path == error.path))
+                .collect::<Vec<_>>();
+            errors.extend(validation_errors);
+        }
+
+        if errors.is_empty() {
+            Ok(data)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn start(self, db: &DatabaseConnection) -> AppResult<Self> {
+        let mut job = self.into_active_model();
+        job.status = Set(MergeJobStatus::Running);
+        Ok(job.update(db).await?)
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn report_progress(
+        self,
+        db: &DatabaseConnection,
+        processed_rows: i32,
+        failed_rows: i32,
+    ) -> AppResult<Self> {
+        let mut job = self.into_active_model();
+        job.processed_rows = Set(processed_rows);
+        job.failed_rows = Set(failed_rows);
+        Ok(job.update(db).await?)
+    }
+
+    /// Marks the job as completed and drops the uploaded rows, that are not
+    /// needed anymore.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn complete(
+        self,
+        db: &DatabaseConnection,
+        row_errors: &[RowErrors],
+    ) -> AppResult<Self> {
+        let failed_rows = i32::try_from(row_errors.len()).unwrap_or(i32::MAX);
+
+        let mut job = self.into_active_model();
+        job.status = Set(MergeJobStatus::Completed);
+        job.processed_rows = Set(*job.total_rows.as_ref());
+        job.failed_rows = Set(failed_rows);
+        job.row_errors = Set(to_json(&row_errors)?);
+        job.rows = Set(serde_json::Value::Array(Vec::new()));
+        Ok(job.update(db).await?)
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn fail(self, db: &DatabaseConnection, error: String) -> AppResult<Self> {
+        let mut job = self.into_active_model();
+        job.status = Set(MergeJobStatus::Failed);
+        job.error = Set(Some(error));
+        job.rows = Set(serde_json::Value::Array(Vec::new()));
+        Ok(job.update(db).await?)
+    }
+}
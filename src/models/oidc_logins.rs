@@ -0,0 +1,69 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+
+pub use super::_entities::oidc_logins::{self, ActiveModel, Entity, Model};
+use super::users::hash_token;
+use crate::errors::AppResult;
+use crate::oidc;
+
+impl ActiveModelBehavior for ActiveModel {
+    // extend active model below (keep comment for generators)
+}
+
+impl Model {
+    /// Starts the login at the provider with a new state, PKCE code verifier
+    /// and nonce. Only the hash of the state is stored, the state itself is
+    /// returned to be sent to the provider. Logins, that were never finished,
+    /// are cleaned up.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn start(
+        db: &DatabaseConnection,
+        provider: &str,
+        ttl: chrono::Duration,
+    ) -> AppResult<(Self, String)> {
+        Entity::delete_many()
+            .filter(oidc_logins::Column::CreatedAt.lt(Local::now().fixed_offset() - ttl))
+            .exec(db)
+            .await?;
+
+        let state = oidc::generate_secret();
+        let login = ActiveModel {
+            provider: ActiveValue::set(provider.to_string()),
+            state: ActiveValue::set(hash_token(&state)),
+            code_verifier: ActiveValue::set(oidc::generate_secret()),
+            nonce: ActiveValue::set(oidc::generate_secret()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((login, state))
+    }
+
+    /// Finishes the login of the state, so the state can't be used again.
+    /// Expired logins and logins at other providers are not found.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn finish(
+        db: &DatabaseConnection,
+        provider: &str,
+        state: &str,
+        ttl: chrono::Duration,
+    ) -> AppResult<Option<Self>> {
+        let Some(login) = Entity::find()
+            .filter(oidc_logins::Column::State.eq(hash_token(state)))
+            .filter(oidc_logins::Column::Provider.eq(provider))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        login.clone().delete(db).await?;
+        Ok(Some(login).filter(|login| Local::now().fixed_offset() - login.created_at <= ttl))
+    }
+}
@@ -0,0 +1,7 @@
+use sea_orm::entity::prelude::*;
+
+pub use super::_entities::organization_members::{self, ActiveModel, Entity, Model};
+
+impl ActiveModelBehavior for ActiveModel {
+    // extend active model below (keep comment for generators)
+}
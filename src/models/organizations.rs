@@ -0,0 +1,530 @@
+use loco_rs::prelude::*;
+use sea_orm::{Condition, DatabaseTransaction, QueryOrder};
+
+pub use super::_entities::organizations::{self, ActiveModel, Entity, Model};
+use super::_entities::sea_orm_active_enums::OrganizationRole;
+use super::_entities::{organization_members, users};
+use crate::controllers::organizations::{MemberParams, OrganizationParams, RenameParams};
+use crate::errors::{AppError, AppResult};
+use crate::views::error::FieldError;
+
+impl ActiveModelBehavior for ActiveModel {
+    // extend active model below (keep comment for generators)
+}
+
+impl OrganizationRole {
+    /// Owners and admins manage members and teams, only owners can remove
+    /// the organization.
+    #[must_use]
+    pub const fn can_manage(self) -> bool {
+        matches!(self, Self::Owner | Self::Admin)
+    }
+
+    const fn rank(self) -> u8 {
+        match self {
+            Self::Owner => 2,
+            Self::Admin => 1,
+            Self::Member => 0,
+        }
+    }
+}
+
+impl Model {
+    /// Teams are organizations with a parent.
+    #[must_use]
+    pub const fn is_team(&self) -> bool {
+        self.parent_id.is_some()
+    }
+
+    /// # Errors
+    ///
+    /// When entity is not found
+    pub async fn find_by_id<C>(db: &C, id: i32) -> AppResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        let organization = Entity::find_by_id(id).one(db).await?;
+        organization.ok_or_else(|| AppError::not_found("Organization not found"))
+    }
+
+    /// Organizations and teams the user is a member of, with the user's role.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> AppResult<Vec<(Self, OrganizationRole)>> {
+        let memberships = organization_members::Entity::find()
+            .filter(organization_members::Column::UserId.eq(user_id))
+            .find_also_related(Entity)
+            .order_by_asc(organization_members::Column::OrganizationId)
+            .all(db)
+            .await?;
+
+        Ok(memberships
+            .into_iter()
+            .filter_map(|(member, organization)| Some((organization?, member.role)))
+            .collect())
+    }
+
+    /// Role of the user in the organization. Owners and admins of an
+    /// organization are owners of all of its teams.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn role_of<C>(&self, db: &C, user_id: i32) -> AppResult<Option<OrganizationRole>>
+    where
+        C: ConnectionTrait,
+    {
+        let ids = std::iter::once(self.id).chain(self.parent_id);
+        let memberships = organization_members::Entity::find()
+            .filter(organization_members::Column::OrganizationId.is_in(ids))
+            .filter(organization_members::Column::UserId.eq(user_id))
+            .all(db)
+            .await?;
+
+        Ok(memberships
+            .into_iter()
+            .filter_map(|member| {
+                if member.organization_id == self.id {
+                    Some(member.role)
+                } else {
+                    // plain members of the parent organization aren't members
+                    // of its teams
+                    member.role.can_manage().then_some(OrganizationRole::Owner)
+                }
+            })
+            .max_by_key(|role| role.rank()))
+    }
+
+    async fn require_role<C>(
+        &self,
+        db: &C,
+        user_id: i32,
+        is_allowed: impl Fn(OrganizationRole) -> bool + Send,
+        message: &str,
+    ) -> AppResult<OrganizationRole>
+    where
+        C: ConnectionTrait,
+    {
+        match self.role_of(db, user_id).await? {
+            Some(role) if is_allowed(role) => Ok(role),
+            _ => Err(AppError::forbidden(message)),
+        }
+    }
+
+    /// Finds the organization, if the user is a member of it.
+    ///
+    /// # Errors
+    ///
+    /// When entity is not found, the user is not a member or DB query error
+    pub async fn find_by_id_for_member(
+        db: &DatabaseConnection,
+        id: i32,
+        user_id: i32,
+    ) -> AppResult<Self> {
+        let organization = Self::find_by_id(db, id).await?;
+        organization
+            .require_role(
+                db,
+                user_id,
+                |_| true,
+                "Only members can see the organization",
+            )
+            .await?;
+        Ok(organization)
+    }
+
+    /// Members of the organization with their users.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_members(
+        &self,
+        db: &DatabaseConnection,
+    ) -> AppResult<Vec<(organization_members::Model, users::Model)>> {
+        let members = organization_members::Entity::find()
+            .filter(organization_members::Column::OrganizationId.eq(self.id))
+            .find_also_related(users::Entity)
+            .order_by_asc(organization_members::Column::UserId)
+            .all(db)
+            .await?;
+
+        Ok(members
+            .into_iter()
+            .filter_map(|(member, user)| Some((member, user?)))
+            .collect())
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_teams(&self, db: &DatabaseConnection) -> AppResult<Vec<Self>> {
+        let teams = Entity::find()
+            .filter(organizations::Column::ParentId.eq(self.id))
+            .order_by_asc(organizations::Column::Name)
+            .all(db)
+            .await?;
+        Ok(teams)
+    }
+
+    /// Finds the organizations and teams, that the user can share templates
+    /// with, i.e. is a member of. Unknown ones are reported in `fields` as
+    /// `organizations[i]`.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_sharing(
+        txn: &DatabaseTransaction,
+        ids: &[i32],
+        user_id: i32,
+        fields: &mut Vec<FieldError>,
+    ) -> AppResult<Vec<Self>> {
+        let mut organizations = Vec::with_capacity(ids.len());
+
+        for (i, organization_id) in ids.iter().copied().enumerate() {
+            let membership = organization_members::Entity::find_by_id((organization_id, user_id))
+                .find_also_related(Entity)
+                .one(txn)
+                .await?;
+
+            match membership {
+                Some((_, Some(organization))) => organizations.push(organization),
+                // not leaking organizations of other users
+                _ => {
+                    fields.push(FieldError::new(
+                        &format!("organizations[{i}]"),
+                        format!("organization {organization_id} is not found"),
+                    ));
+                },
+            }
+        }
+
+        Ok(organizations)
+    }
+
+    /// Creates an organization owned by the user, or a team of the parent
+    /// organization, that the user manages.
+    ///
+    /// # Errors
+    ///
+    /// When parent is not found or is a team itself, the user doesn't manage
+    /// the parent, or DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        params: &OrganizationParams,
+        user_id: i32,
+    ) -> AppResult<Self> {
+        let txn = db.begin().await?;
+
+        if let Some(parent_id) = params.parent_id {
+            let parent = Entity::find_by_id(parent_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| {
+                    AppError::validation(
+                        "invalid_references",
+                        "The parent organization is not found",
+                        vec![FieldError::new("parentId", "organization is not found")],
+                    )
+                })?;
+
+            if parent.is_team() {
+                txn.rollback().await?;
+                return Err(AppError::validation(
+                    "nested_team",
+                    "Teams can't have teams",
+                    vec![FieldError::new("parentId", "is a team")],
+                ));
+            }
+
+            parent
+                .require_role(
+                    &txn,
+                    user_id,
+                    OrganizationRole::can_manage,
+                    "Only owners and admins can create teams",
+                )
+                .await?;
+        }
+
+        let organization = ActiveModel {
+            name: Set(params.name.clone()),
+            parent_id: Set(params.parent_id),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        organization_members::ActiveModel {
+            organization_id: Set(organization.id),
+            user_id: Set(user_id),
+            role: Set(OrganizationRole::Owner),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(organization)
+    }
+
+    /// Deletes the organization together with its teams. Templates shared
+    /// with them stay visible to their authors and viewers only.
+    ///
+    /// # Errors
+    ///
+    /// When organization is not found, the user is not its owner, or DB
+    /// query error
+    pub async fn delete_organization(
+        db: &DatabaseConnection,
+        id: i32,
+        user_id: i32,
+    ) -> AppResult<()> {
+        let txn = db.begin().await?;
+
+        let organization = Self::find_by_id(&txn, id).await?;
+        organization
+            .require_role(
+                &txn,
+                user_id,
+                |role| role == OrganizationRole::Owner,
+                "Only owners can delete the organization",
+            )
+            .await?;
+
+        Entity::delete_many()
+            .filter(
+                Condition::any()
+                    .add(organizations::Column::Id.eq(id))
+                    .add(organizations::Column::ParentId.eq(id)),
+            )
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Adds the user with the email to the organization, or changes the role
+    /// of an existing member. Members of teams must be members of the parent
+    /// organization, only owners can grant or revoke the owner role.
+    ///
+    /// # Errors
+    ///
+    /// When organization or user is not found, the user is not allowed to
+    /// manage members, the last owner is demoted, or DB query error
+    pub async fn set_member(
+        &self,
+        db: &DatabaseConnection,
+        params: &MemberParams,
+        user_id: i32,
+    ) -> AppResult<(organization_members::Model, users::Model)> {
+        let txn = db.begin().await?;
+
+        let role = self
+            .require_role(
+                &txn,
+                user_id,
+                OrganizationRole::can_manage,
+                "Only owners and admins can manage members",
+            )
+            .await?;
+
+        let member_user = users::Entity::find()
+            .filter(users::Column::Email.eq(&params.email))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                AppError::validation("invalid_references", "The user is not found", vec![
+                    FieldError::new("email", format!("user {} is not found", params.email)),
+                ])
+            })?;
+
+        if let Some(parent_id) = self.parent_id {
+            organization_members::Entity::find_by_id((parent_id, member_user.id))
+                .one(&txn)
+                .await?
+                .ok_or_else(|| {
+                    AppError::validation(
+                        "not_a_member",
+                        "Only members of the organization can join its teams",
+                        vec![FieldError::new(
+                            "email",
+                            "is not a member of the organization",
+                        )],
+                    )
+                })?;
+        }
+
+        let existing = organization_members::Entity::find_by_id((self.id, member_user.id))
+            .one(&txn)
+            .await?;
+
+        let changes_owner = params.role == OrganizationRole::Owner
+            || existing
+                .as_ref()
+                .is_some_and(|member| member.role == OrganizationRole::Owner);
+        if changes_owner && role != OrganizationRole::Owner {
+            txn.rollback().await?;
+            return Err(AppError::forbidden(
+                "Only owners can grant or revoke the owner role",
+            ));
+        }
+
+        let member = match existing {
+            Some(member) => {
+                if member.role == OrganizationRole::Owner && params.role != OrganizationRole::Owner
+                {
+                    self.check_other_owners(&txn, member.user_id).await?;
+                }
+
+                let mut member = member.into_active_model();
+                member.role = Set(params.role);
+                member.update(&txn).await?
+            },
+            None => {
+                organization_members::ActiveModel {
+                    organization_id: Set(self.id),
+                    user_id: Set(member_user.id),
+                    role: Set(params.role),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?
+            },
+        };
+
+        txn.commit().await?;
+
+        Ok((member, member_user))
+    }
+
+    /// Removes the member with the `pid` from the organization and its
+    /// teams. Members can leave by themselves.
+    ///
+    /// # Errors
+    ///
+    /// When the member is not found, the user is not allowed to manage
+    /// members, the last owner is removed, or DB query error
+    pub async fn remove_member(
+        &self,
+        db: &DatabaseConnection,
+        pid: &str,
+        user_id: i32,
+    ) -> AppResult<()> {
+        let txn = db.begin().await?;
+
+        let pid = Uuid::parse_str(pid).map_err(|_| AppError::not_found("Member not found"))?;
+        let member = organization_members::Entity::find()
+            .inner_join(users::Entity)
+            .filter(organization_members::Column::OrganizationId.eq(self.id))
+            .filter(users::Column::Pid.eq(pid))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Member not found"))?;
+
+        if member.user_id != user_id {
+            let role = self
+                .require_role(
+                    &txn,
+                    user_id,
+                    OrganizationRole::can_manage,
+                    "Only owners and admins can manage members",
+                )
+                .await?;
+
+            if member.role == OrganizationRole::Owner && role != OrganizationRole::Owner {
+                txn.rollback().await?;
+                return Err(AppError::forbidden(
+                    "Only owners can grant or revoke the owner role",
+                ));
+            }
+        }
+
+        if member.role == OrganizationRole::Owner {
+            self.check_other_owners(&txn, member.user_id).await?;
+        }
+
+        let teams = Entity::find()
+            .filter(organizations::Column::ParentId.eq(self.id))
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|team| team.id);
+
+        organization_members::Entity::delete_many()
+            .filter(
+                organization_members::Column::OrganizationId
+                    .is_in(std::iter::once(self.id).chain(teams)),
+            )
+            .filter(organization_members::Column::UserId.eq(member.user_id))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Checks that the organization has an owner besides the user, so it
+    /// isn't left unmanaged.
+    async fn check_other_owners(&self, txn: &DatabaseTransaction, user_id: i32) -> AppResult<()> {
+        let other_owner = organization_members::Entity::find()
+            .filter(organization_members::Column::OrganizationId.eq(self.id))
+            .filter(organization_members::Column::Role.eq(OrganizationRole::Owner))
+            .filter(organization_members::Column::UserId.ne(user_id))
+            .one(txn)
+            .await?;
+
+        if other_owner.is_none() {
+            return Err(AppError::conflict(
+                "last_owner",
+                "The organization must have at least one owner",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl ActiveModel {
+    /// # Errors
+    ///
+    /// When the user is not allowed to manage the organization or DB query
+    /// error
+    pub async fn rename(
+        self,
+        db: &DatabaseConnection,
+        params: &RenameParams,
+        user_id: i32,
+    ) -> AppResult<Model> {
+        let txn = db.begin().await?;
+
+        let id = *self.id.as_ref();
+        let organization = Model::find_by_id(&txn, id).await?;
+        organization
+            .require_role(
+                &txn,
+                user_id,
+                OrganizationRole::can_manage,
+                "Only owners and admins can rename the organization",
+            )
+            .await?;
+
+        let mut organization = organization.into_active_model();
+        organization.name = Set(params.name.clone());
+        let organization = organization.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(organization)
+    }
+}
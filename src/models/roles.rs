@@ -0,0 +1,57 @@
+pub use super::_entities::sea_orm_active_enums::UserRole;
+
+/// Actions on entities of other users, that are allowed to some roles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Edit templates of any author.
+    EditTemplates,
+    /// Remove public templates of any author, e.g. abusive ones.
+    ModerateTemplates,
+    /// Create, edit and remove categories of any user.
+    ManageCategories,
+    /// Change roles of users.
+    ManageRoles,
+}
+
+impl Permission {
+    /// What the permission allows, e.g. for error messages.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::EditTemplates => "edit templates of other users",
+            Self::ModerateTemplates => "remove public templates of other users",
+            Self::ManageCategories => "manage categories of other users",
+            Self::ManageRoles => "change roles of users",
+        }
+    }
+}
+
+impl UserRole {
+    #[must_use]
+    pub const fn permissions(self) -> &'static [Permission] {
+        match self {
+            Self::User => &[],
+            Self::Editor => &[Permission::EditTemplates, Permission::ManageCategories],
+            Self::Moderator => {
+                &[
+                    Permission::EditTemplates,
+                    Permission::ManageCategories,
+                    Permission::ModerateTemplates,
+                ]
+            },
+            Self::Admin => {
+                &[
+                    Permission::EditTemplates,
+                    Permission::ManageCategories,
+                    Permission::ModerateTemplates,
+                    Permission::ManageRoles,
+                ]
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn can(self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::QueryOrder;
+use uuid::Uuid;
+
+pub use super::_entities::sessions::{self, ActiveModel, Entity, Model};
+use super::_entities::used_refresh_tokens;
+use super::users::hash_token;
+use crate::errors::AppResult;
+
+/// Claim of the access token with the pid of its session.
+pub const SESSION_CLAIM: &str = "sid";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            this.pid = ActiveValue::Set(Uuid::new_v4());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Starts a session of the user, e.g. on login. Only the hash of the
+    /// refresh token is stored, the token itself is returned to the client.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: i32,
+        user_agent: Option<String>,
+        ttl: chrono::Duration,
+    ) -> AppResult<(Self, String)> {
+        let token = Uuid::new_v4().to_string();
+        let now = Local::now().fixed_offset();
+        let session = ActiveModel {
+            user_id: ActiveValue::set(user_id),
+            refresh_token: ActiveValue::set(hash_token(&token)),
+            user_agent: ActiveValue::set(user_agent),
+            last_used_at: ActiveValue::set(now),
+            expires_at: ActiveValue::set(now + ttl),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((session, token))
+    }
+
+    /// Finds the session, that can be refreshed with the token.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_active_by_refresh_token(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> AppResult<Option<Self>> {
+        let session = Entity::find()
+            .filter(sessions::Column::RefreshToken.eq(hash_token(token)))
+            .one(db)
+            .await?;
+        Ok(session.filter(Self::is_active))
+    }
+
+    /// Finds the session, whose refresh token was already replaced with the
+    /// token. The token is presented again, so someone else has a copy of it.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_by_used_refresh_token(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> AppResult<Option<Self>> {
+        let session = Entity::find()
+            .inner_join(used_refresh_tokens::Entity)
+            .filter(used_refresh_tokens::Column::RefreshToken.eq(hash_token(token)))
+            .one(db)
+            .await?;
+        Ok(session)
+    }
+
+    /// Finds the session of the access token, revoked and expired sessions
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_active_by_pid(db: &DatabaseConnection, pid: Uuid) -> AppResult<Option<Self>> {
+        let session = Entity::find()
+            .filter(sessions::Column::Pid.eq(pid))
+            .one(db)
+            .await?;
+        Ok(session.filter(Self::is_active))
+    }
+
+    /// Finds the sessions, that the user is logged in with, the recently used
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_active_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> AppResult<Vec<Self>> {
+        let sessions = Entity::find()
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .filter(sessions::Column::ExpiresAt.gt(Local::now().fixed_offset()))
+            .order_by_desc(sessions::Column::LastUsedAt)
+            .order_by_desc(sessions::Column::Id)
+            .all(db)
+            .await?;
+        Ok(sessions)
+    }
+
+    /// Logs the user out everywhere, e.g. after the password was reset.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn revoke_all_for_user(db: &DatabaseConnection, user_id: i32) -> AppResult<()> {
+        Entity::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(Local::now().fixed_offset()),
+            )
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Logs the user out on the other devices, e.g. after the password was
+    /// changed, the current session is kept.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn revoke_others(&self, db: &DatabaseConnection) -> AppResult<()> {
+        Entity::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(Local::now().fixed_offset()),
+            )
+            .filter(sessions::Column::UserId.eq(self.user_id))
+            .filter(sessions::Column::Id.ne(self.id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > Local::now().fixed_offset()
+    }
+}
+
+impl ActiveModel {
+    /// Replaces the refresh token and prolongs the session. The used token
+    /// is kept, so using it again is detected, see
+    /// [`Model::find_by_used_refresh_token`].
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn rotate(
+        mut self,
+        db: &DatabaseConnection,
+        ttl: chrono::Duration,
+    ) -> AppResult<(Model, String)> {
+        let txn = db.begin().await?;
+
+        used_refresh_tokens::ActiveModel {
+            session_id: self.id.clone(),
+            refresh_token: self.refresh_token.clone(),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let token = Uuid::new_v4().to_string();
+        let now = Local::now().fixed_offset();
+        self.refresh_token = ActiveValue::set(hash_token(&token));
+        self.last_used_at = ActiveValue::set(now);
+        self.expires_at = ActiveValue::set(now + ttl);
+        let session = self.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok((session, token))
+    }
+
+    /// Logs out of the session, its access token is rejected too.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn revoke(mut self, db: &DatabaseConnection) -> AppResult<Model> {
+        self.revoked_at = ActiveValue::set(Some(Local::now().into()));
+        Ok(self.update(db).await?)
+    }
+}
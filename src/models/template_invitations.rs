@@ -0,0 +1,135 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+
+pub use super::_entities::template_invitations::{self, ActiveModel, Entity, Model};
+use super::_entities::{users, users_visible_templates};
+use crate::errors::AppResult;
+
+impl ActiveModelBehavior for ActiveModel {
+    // extend active model below (keep comment for generators)
+}
+
+impl Model {
+    /// Makes the pending invitations of the template match the emails:
+    /// invitations of other emails are removed, the missing ones are added
+    /// as not sent yet. Existing invitations are kept, so they aren't sent
+    /// again.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn sync<C>(db: &C, template_id: i32, emails: &[String]) -> AppResult<()>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(template_invitations::Column::TemplateId.eq(template_id))
+            .filter(template_invitations::Column::Email.is_not_in(emails))
+            .exec(db)
+            .await?;
+
+        let existing = Entity::find()
+            .filter(template_invitations::Column::TemplateId.eq(template_id))
+            .all(db)
+            .await?;
+
+        for email in emails {
+            if existing.iter().any(|invitation| &invitation.email == email) {
+                continue;
+            }
+
+            ActiveModel {
+                template_id: Set(template_id),
+                email: Set(email.clone()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_unsent(db: &DatabaseConnection, template_id: i32) -> AppResult<Vec<Self>> {
+        let invitations = Entity::find()
+            .filter(template_invitations::Column::TemplateId.eq(template_id))
+            .filter(template_invitations::Column::SentAt.is_null())
+            .order_by_asc(template_invitations::Column::Id)
+            .all(db)
+            .await?;
+        Ok(invitations)
+    }
+
+    /// Pending invitations to the templates.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_templates(
+        db: &DatabaseConnection,
+        template_ids: &[i32],
+    ) -> AppResult<Vec<Self>> {
+        let invitations = Entity::find()
+            .filter(template_invitations::Column::TemplateId.is_in(template_ids.iter().copied()))
+            .order_by_asc(template_invitations::Column::Id)
+            .all(db)
+            .await?;
+        Ok(invitations)
+    }
+
+    /// Turns invitations to the email of the user into grants to see the
+    /// templates. Called once the user has verified the email, so nobody can
+    /// claim invitations to a foreign address.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn accept_for_user(db: &DatabaseConnection, user: &users::Model) -> AppResult<()> {
+        let txn = db.begin().await?;
+
+        let invitations = Entity::find()
+            .filter(template_invitations::Column::Email.eq(&user.email))
+            .all(&txn)
+            .await?;
+
+        for invitation in &invitations {
+            let is_visible =
+                users_visible_templates::Entity::find_by_id((invitation.template_id, user.id))
+                    .one(&txn)
+                    .await?;
+
+            if is_visible.is_none() {
+                users_visible_templates::ActiveModel {
+                    template_id: Set(invitation.template_id),
+                    user_id: Set(user.id),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?;
+            }
+        }
+
+        Entity::delete_many()
+            .filter(template_invitations::Column::Email.eq(&user.email))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl ActiveModel {
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn set_sent(mut self, db: &DatabaseConnection) -> AppResult<Model> {
+        self.sent_at = Set(Some(Local::now().into()));
+        Ok(self.update(db).await?)
+    }
+}
@@ -1,124 +1,518 @@
+use std::collections::BTreeSet;
+use std::io::SeekFrom;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
-use cicero_dsl::compiler::compile_types;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use cicero_dsl::compiler::{self, compile_types, VarEnv};
+use cicero_dsl::types::Var;
 use loco_rs::prelude::*;
 // use sea_orm::entity::prelude::*;
-use sea_orm::sea_query::Query;
-use sea_orm::Condition;
+use sea_orm::sea_query::{Expr, LikeExpr, Query};
+use sea_orm::{Condition, DatabaseTransaction, PaginatorTrait, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 pub use super::_entities::templates::{self, ActiveModel, Entity, Model};
-use super::_entities::{templates_categories, users, users_visible_templates};
-use crate::controllers::templates::{CreateTemplateParams, PublicityParams};
-
+use super::_entities::{
+    categories,
+    organization_members,
+    organizations,
+    template_invitations,
+    templates_categories,
+    templates_organizations,
+    users,
+    users_visible_templates,
+};
+use crate::conditional::revision_conflict;
+use crate::controllers::templates::{
+    CreateTemplateParams,
+    ListParams,
+    PatchTemplateParams,
+    PublicityFilter,
+    PublicityParams,
+    SortOrder,
+    UpdateTemplateParams,
+};
+use crate::errors::{AppError, AppResult};
+use crate::models::roles::Permission;
+use crate::views::error::FieldError;
+
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 100;
+
+#[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
-    // extend active model below (keep comment for generators)
+    async fn before_save<C>(self, _db: &C, _insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !self.name.is_set() && !self.description.is_set() {
+            return Ok(self);
+        }
+
+        let mut this = self;
+        if let (Some(name), Some(description)) =
+            (this.name.try_as_ref(), this.description.try_as_ref())
+        {
+            this.search_text = ActiveValue::Set(search_text(name, description));
+        }
+        Ok(this)
+    }
+}
+
+/// Lowercased name and description, that are searched by
+/// [`Model::find_visible_page`]. Lowercasing is done here and not in SQL,
+/// because SQLite's `LOWER` ignores non-ASCII letters.
+fn search_text(name: &str, description: &str) -> String {
+    format!("{name}\n{description}").to_lowercase()
+}
+
+/// Hex of the SHA-256 of a file, that serves as its `ETag`.
+fn sha256(contents: &[u8]) -> String {
+    hex::encode(Sha256::digest(contents))
+}
+
+/// The DSL compiled on upload. The types are cached with the template in the
+/// order of the DSL, as they are served to the constructor.
+struct CompiledDsl {
+    env: VarEnv,
+    types: serde_json::Value,
+    hash: String,
+}
+
+impl CompiledDsl {
+    fn new(dsl: &str) -> Result<Self, String> {
+        let env = compile_types(dsl)?;
+        let types = serde_json::to_value(env.values().collect::<Vec<_>>())
+            .map_err(|err| err.to_string())?;
+
+        Ok(Self {
+            env,
+            types,
+            hash: sha256(dsl.as_bytes()),
+        })
+    }
+
+    fn set(self, template: &mut ActiveModel) {
+        template.dsl_types = Set(Some(self.types));
+        template.dsl_compiler_version = Set(Some(compiler::VERSION));
+        template.dsl_hash = Set(Some(self.hash));
+    }
+}
+
+/// Checks, that the variables used in the docx are declared in the DSL. The
+/// errors are reported for the `field`, that was uploaded.
+fn check_variables(field: &str, used: &BTreeSet<String>, env: &VarEnv) -> AppResult<()> {
+    let fields = used
+        .iter()
+        .filter(|name| !env.contains_key(*name))
+        .map(|name| {
+            FieldError::new(
+                field,
+                format!("`{name}` is used in the docx, but not declared in the DSL"),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    Err(AppError::validation(
+        "undeclared_variables",
+        "The docx uses variables, that the DSL doesn't declare",
+        fields,
+    ))
+}
+
+/// The cached types keyed by the names of the variables.
+fn var_env(types: serde_json::Value) -> AppResult<VarEnv> {
+    let vars: Vec<Var> = serde_json::from_value(types).map_err(AppError::internal)?;
+
+    Ok(vars
+        .into_iter()
+        .map(|var| (var.name.clone(), var))
+        .collect())
+}
+
+/// Users and organizations, that a private template is shared with, and
+/// emails of viewers without an account.
+/// A file, that is written next to the template file and replaces it only
+/// after the transaction is committed, so the file never gets ahead of the
+/// revision and hash in the database.
+struct StagedFile {
+    path: String,
+    temp: String,
+}
+
+impl StagedFile {
+    async fn write(path: String, contents: &[u8]) -> AppResult<Self> {
+        let temp = format!("{path}.{}.tmp", uuid::Uuid::new_v4());
+        fs::write(&temp, contents)
+            .await
+            .map_err(AppError::internal)?;
+        Ok(Self { path, temp })
+    }
+
+    /// Commits the transaction and moves the file into place, or removes it,
+    /// when the commit fails.
+    async fn commit(self, txn: DatabaseTransaction) -> AppResult<()> {
+        if let Err(err) = txn.commit().await {
+            let _ = fs::remove_file(&self.temp).await;
+            return Err(err.into());
+        }
+        fs::rename(&self.temp, &self.path)
+            .await
+            .map_err(AppError::internal)
+    }
+
+    async fn discard(self) {
+        let _ = fs::remove_file(&self.temp).await;
+    }
+}
+
+struct Sharing {
+    viewers: Vec<users::Model>,
+    organizations: Vec<organizations::Model>,
+    invited: Vec<String>,
+}
+
+async fn find_categories(
+    txn: &DatabaseTransaction,
+    category_ids: &[i32],
+    fields: &mut Vec<FieldError>,
+) -> AppResult<Vec<categories::Model>> {
+    let mut categories = Vec::with_capacity(category_ids.len());
+
+    for (i, category_id) in category_ids.iter().copied().enumerate() {
+        match categories::Entity::find_by_id(category_id).one(txn).await? {
+            Some(category) => categories.push(category),
+            None => {
+                fields.push(FieldError::new(
+                    &format!("categories[{i}]"),
+                    format!("category {category_id} is not found"),
+                ));
+            },
+        }
+    }
+
+    Ok(categories)
+}
+
+/// Finds the viewers of a private template, `None` for a public one. The
+/// organizations must be the ones of the author.
+async fn find_sharing(
+    txn: &DatabaseTransaction,
+    publicity: &PublicityParams,
+    author_id: i32,
+    fields: &mut Vec<FieldError>,
+) -> AppResult<Option<Sharing>> {
+    let PublicityParams::Private {
+        viewers: visible_to,
+        organizations: organization_ids,
+    } = publicity
+    else {
+        return Ok(None);
+    };
+
+    let mut viewers = Vec::with_capacity(visible_to.len());
+    let mut invited = Vec::new();
+
+    for (i, viewer_email) in visible_to.iter().map(String::as_str).enumerate() {
+        let viewer = users::Entity::find()
+            .filter(
+                query::condition()
+                    .eq(users::Column::Email, viewer_email)
+                    .build(),
+            )
+            .one(txn)
+            .await?;
+
+        match viewer {
+            Some(viewer) => viewers.push(viewer),
+            // the template becomes visible once the user registers
+            None if validation::is_valid_email(viewer_email).is_ok() => {
+                invited.push(viewer_email.to_string());
+            },
+            None => {
+                fields.push(FieldError::new(
+                    &format!("viewers[{i}]"),
+                    format!("{viewer_email} is not a valid email"),
+                ));
+            },
+        }
+    }
+
+    let organizations =
+        organizations::Model::find_for_sharing(txn, organization_ids, author_id, fields).await?;
+
+    Ok(Some(Sharing {
+        viewers,
+        organizations,
+        invited,
+    }))
+}
+
+fn invalid_references(fields: Vec<FieldError>) -> AppError {
+    AppError::validation(
+        "invalid_references",
+        "Some categories, viewers or organizations are not found",
+        fields,
+    )
+}
+
+/// Replaces the categories of the template.
+async fn set_categories(
+    txn: &DatabaseTransaction,
+    template_id: i32,
+    categories: &[categories::Model],
+) -> AppResult<()> {
+    templates_categories::Entity::delete_many()
+        .filter(templates_categories::Column::TemplateId.eq(template_id))
+        .exec(txn)
+        .await?;
+
+    for category in categories {
+        let template_category = templates_categories::ActiveModel {
+            template_id: Set(template_id),
+            category_id: Set(category.id),
+            ..Default::default()
+        };
+
+        templates_categories::Entity::insert(template_category)
+            .exec(txn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the viewers and organizations of the template. Viewers without
+/// an account are invited, see [`template_invitations::Model::sync`].
+async fn set_sharing(
+    txn: &DatabaseTransaction,
+    template_id: i32,
+    sharing: Option<&Sharing>,
+) -> AppResult<()> {
+    users_visible_templates::Entity::delete_many()
+        .filter(users_visible_templates::Column::TemplateId.eq(template_id))
+        .exec(txn)
+        .await?;
+
+    templates_organizations::Entity::delete_many()
+        .filter(templates_organizations::Column::TemplateId.eq(template_id))
+        .exec(txn)
+        .await?;
+
+    let Some(sharing) = sharing else {
+        template_invitations::Model::sync(txn, template_id, &[]).await?;
+        return Ok(());
+    };
+
+    for viewer in &sharing.viewers {
+        let viewer_template = users_visible_templates::ActiveModel {
+            user_id: Set(viewer.id),
+            template_id: Set(template_id),
+            ..Default::default()
+        };
+
+        users_visible_templates::Entity::insert(viewer_template)
+            .exec(txn)
+            .await?;
+    }
+
+    for organization in &sharing.organizations {
+        let template_organization = templates_organizations::ActiveModel {
+            template_id: Set(template_id),
+            organization_id: Set(organization.id),
+            ..Default::default()
+        };
+
+        templates_organizations::Entity::insert(template_organization)
+            .exec(txn)
+            .await?;
+    }
+
+    template_invitations::Model::sync(txn, template_id, &sharing.invited).await
+}
+
+/// Position of the last template of a page in the chosen sort order.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "sort")]
+pub enum Cursor {
+    Newest {
+        created_at: DateTimeWithTimeZone,
+        id: i32,
+    },
+    Name {
+        name: String,
+        id: i32,
+    },
+    Popularity {
+        downloads: i32,
+        id: i32,
+    },
+}
+
+impl Cursor {
+    fn new(template: &Model, sort: SortOrder) -> Self {
+        match sort {
+            SortOrder::Newest => {
+                Self::Newest {
+                    created_at: template.created_at,
+                    id: template.id,
+                }
+            },
+            SortOrder::Name => {
+                Self::Name {
+                    name: template.name.clone(),
+                    id: template.id,
+                }
+            },
+            SortOrder::Popularity => {
+                Self::Popularity {
+                    downloads: template.downloads,
+                    id: template.id,
+                }
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> String {
+        // serializing a plain enum of strings and numbers can't fail
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decodes the cursor, returns `None` if it is malformed or was issued for
+    /// another sort order.
+    #[must_use]
+    pub fn decode(cursor: &str, sort: SortOrder) -> Option<Self> {
+        let json = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let cursor: Self = serde_json::from_slice(&json).ok()?;
+
+        let matches = matches!(
+            (&cursor, sort),
+            (Self::Newest { .. }, SortOrder::Newest)
+                | (Self::Name { .. }, SortOrder::Name)
+                | (Self::Popularity { .. }, SortOrder::Popularity)
+        );
+
+        matches.then_some(cursor)
+    }
+
+    fn condition(&self) -> Condition {
+        match self {
+            Self::Newest { created_at, id } => {
+                Condition::any()
+                    .add(templates::Column::CreatedAt.lt(*created_at))
+                    .add(
+                        Condition::all()
+                            .add(templates::Column::CreatedAt.eq(*created_at))
+                            .add(templates::Column::Id.lt(*id)),
+                    )
+            },
+            Self::Name { name, id } => {
+                Condition::any()
+                    .add(templates::Column::Name.gt(name.as_str()))
+                    .add(
+                        Condition::all()
+                            .add(templates::Column::Name.eq(name.as_str()))
+                            .add(templates::Column::Id.gt(*id)),
+                    )
+            },
+            Self::Popularity { downloads, id } => {
+                Condition::any()
+                    .add(templates::Column::Downloads.lt(*downloads))
+                    .add(
+                        Condition::all()
+                            .add(templates::Column::Downloads.eq(*downloads))
+                            .add(templates::Column::Id.lt(*id)),
+                    )
+            },
+        }
+    }
+}
+
+/// A single page of templates, see [`Model::find_visible_page`].
+#[derive(Debug)]
+pub struct Page {
+    pub templates: Vec<Model>,
+    pub total: u64,
+    pub next_cursor: Option<String>,
 }
 
 impl Model {
     /// # Errors
     ///
-    /// When author is not found, categories are not found, viewers are not
-    /// found, or error writing files.
-    pub async fn create(
-        db: &DatabaseConnection,
+    /// When the DSL doesn't compile, the docx can't be read or uses undeclared
+    /// variables, author is not found, categories are not found, emails of
+    /// viewers are invalid, or error writing files. Viewers without an account
+    /// are invited, see [`template_invitations::Model::sync`].
+    pub async fn create<C>(
+        db: &C,
         params: &CreateTemplateParams,
         author_id: i32,
         docx: &[u8],
         dsl: &str,
-    ) -> ModelResult<Self> {
-        let txn = db.begin().await?;
+    ) -> AppResult<Self>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let compiled = CompiledDsl::new(dsl).map_err(|err| AppError::invalid_dsl("dsl", err))?;
+        let used = crate::docx::variables(docx)
+            .map_err(|err| AppError::invalid_docx("docx", err.to_string()))?;
+        check_variables("docx", &used, &compiled.env)?;
 
-        compile_types(dsl).map_err(|_| ModelError::Any("Invalid DSL".into()))?;
+        let txn = db.begin().await?;
 
-        let author = Entity::find_by_id(author_id)
+        let author = users::Entity::find_by_id(author_id)
             .one(&txn)
             .await?
-            .ok_or(ModelError::EntityNotFound)?;
-
-        let mut categories = Vec::with_capacity(params.categories.len());
-
-        for category_id in params.categories.iter().copied() {
-            let category = Entity::find_by_id(category_id)
-                .one(&txn)
-                .await?
-                .ok_or(ModelError::EntityNotFound)?;
-            categories.push(category);
-        }
-
-        let viewers = match &params.publicity {
-            PublicityParams::Public => None,
-            PublicityParams::Private {
-                viewers: visible_to,
-            } => {
-                let mut viewers = Vec::with_capacity(visible_to.len());
-
-                for viewer_email in visible_to.iter().map(String::as_str) {
-                    let viewer = users::Entity::find()
-                        .filter(
-                            query::condition()
-                                .eq(users::Column::Email, viewer_email)
-                                .build(),
-                        )
-                        .one(&txn)
-                        .await?
-                        .ok_or(ModelError::EntityNotFound)?;
-                    viewers.push(viewer);
-                }
+            .ok_or_else(|| AppError::not_found("Author not found"))?;
 
-                Some(viewers)
-            },
-        };
+        let mut fields = Vec::new();
+        let categories = find_categories(&txn, &params.categories, &mut fields).await?;
+        let sharing = find_sharing(&txn, &params.publicity, author.id, &mut fields).await?;
 
-        let template = ActiveModel {
+        if !fields.is_empty() {
+            txn.rollback().await?;
+            return Err(invalid_references(fields));
+        }
+
+        let mut template = ActiveModel {
             name: Set(params.name.clone()),
             description: Set(params.description.clone()),
-            is_public: Set(viewers.is_none()),
+            is_public: Set(sharing.is_none()),
             user_id: Set(author.id),
             ..Default::default()
         };
+        template.set_docx(docx);
+        template.set_dsl(compiled);
 
-        let template = Entity::insert(template).exec(&txn).await?;
-        let template_id = template.last_insert_id;
+        let template = template.insert(&txn).await?;
+        let template_id = template.id;
 
         fs::write(format!("./data/templates/{template_id}.docx"), docx)
             .await
-            .map_err(|_| ModelError::Any("Error writing file".into()))?;
+            .map_err(AppError::internal)?;
 
         fs::write(format!("./data/templates/{template_id}.dsl"), dsl)
             .await
-            .map_err(|_| ModelError::Any("Error writing file".into()))?;
-
-        for category in &categories {
-            let template_category = templates_categories::ActiveModel {
-                template_id: Set(template_id),
-                category_id: Set(category.id),
-                ..Default::default()
-            };
-
-            templates_categories::Entity::insert(template_category)
-                .exec(&txn)
-                .await?;
-        }
-
-        if let Some(viewers) = viewers {
-            for viewer in &viewers {
-                let viewer_template = users_visible_templates::ActiveModel {
-                    user_id: Set(viewer.id),
-                    template_id: Set(template_id),
-                    ..Default::default()
-                };
-
-                users_visible_templates::Entity::insert(viewer_template)
-                    .exec(&txn)
-                    .await?;
-            }
-        }
+            .map_err(AppError::internal)?;
+
+        set_categories(&txn, template_id, &categories).await?;
+        set_sharing(&txn, template_id, sharing.as_ref()).await?;
 
         let template = Entity::find_by_id(template_id)
             .one(&txn)
             .await?
-            .ok_or(ModelError::EntityNotFound);
+            .ok_or_else(|| AppError::not_found("Template not found"));
 
         txn.commit().await?;
 
@@ -128,15 +522,18 @@ impl Model {
     /// # Errors
     ///
     /// When entity is not found
-    pub async fn find_by_id(db: &DatabaseConnection, id: i32) -> ModelResult<Self> {
+    pub async fn find_by_id<C>(db: &C, id: i32) -> AppResult<Self>
+    where
+        C: ConnectionTrait,
+    {
         let template = Entity::find_by_id(id).one(db).await?;
-        template.ok_or_else(|| ModelError::EntityNotFound)
+        template.ok_or_else(|| AppError::not_found("Template not found"))
     }
 
     /// # Errors
     ///
     /// When entity is not found
-    pub async fn find_public(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+    pub async fn find_public(db: &DatabaseConnection) -> AppResult<Vec<Self>> {
         let templates = templates::Entity::find()
             .filter(templates::Column::IsPublic.eq(true))
             .all(db)
@@ -144,28 +541,96 @@ impl Model {
         Ok(templates)
     }
 
+    /// Templates, that the user is the author of.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_author(db: &DatabaseConnection, user_id: i32) -> AppResult<Vec<Self>> {
+        let templates = Entity::find()
+            .filter(templates::Column::UserId.eq(user_id))
+            .order_by_asc(templates::Column::Id)
+            .all(db)
+            .await?;
+        Ok(templates)
+    }
+
+    /// Private templates of other users, that are shared with the user
+    /// directly and not through an organization.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_shared_with_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> AppResult<Vec<Self>> {
+        let templates = Entity::find()
+            .filter(templates::Column::UserId.ne(user_id))
+            .filter(
+                templates::Column::Id.in_subquery(
+                    Query::select()
+                        .column(users_visible_templates::Column::TemplateId)
+                        .from(users_visible_templates::Entity)
+                        .and_where(users_visible_templates::Column::UserId.eq(user_id))
+                        .to_owned(),
+                ),
+            )
+            .order_by_asc(templates::Column::Id)
+            .all(db)
+            .await?;
+        Ok(templates)
+    }
+
+    /// Condition that matches templates visible to the user, or only public
+    /// ones for anonymous visitors. Private templates are visible to their
+    /// authors, viewers and members of the organizations and teams they are
+    /// shared with.
+    pub fn visibility_condition(user_id: Option<i32>) -> Condition {
+        let Some(user_id) = user_id else {
+            return Condition::all().add(templates::Column::IsPublic.eq(true));
+        };
+
+        Condition::any()
+            .add(templates::Column::IsPublic.eq(true))
+            .add(templates::Column::UserId.eq(user_id))
+            .add(
+                templates::Column::Id.in_subquery(
+                    Query::select()
+                        .column(users_visible_templates::Column::TemplateId)
+                        .from(users_visible_templates::Entity)
+                        .and_where(users_visible_templates::Column::UserId.eq(user_id))
+                        .to_owned(),
+                ),
+            )
+            .add(
+                templates::Column::Id.in_subquery(
+                    Query::select()
+                        .column(templates_organizations::Column::TemplateId)
+                        .from(templates_organizations::Entity)
+                        .and_where(
+                            templates_organizations::Column::OrganizationId.in_subquery(
+                                Query::select()
+                                    .column(organization_members::Column::OrganizationId)
+                                    .from(organization_members::Entity)
+                                    .and_where(organization_members::Column::UserId.eq(user_id))
+                                    .to_owned(),
+                            ),
+                        )
+                        .to_owned(),
+                ),
+            )
+    }
+
     /// # Errors
     ///
     /// When entity is not found
     pub async fn find_visible_to_user(
         db: &DatabaseConnection,
         user_id: i32,
-    ) -> ModelResult<Vec<Self>> {
+    ) -> AppResult<Vec<Self>> {
         let templates = templates::Entity::find()
-            .filter(
-                Condition::any()
-                    .add(templates::Column::IsPublic.eq(true))
-                    .add(templates::Column::UserId.eq(user_id))
-                    .add(
-                        templates::Column::Id.in_subquery(
-                            Query::select()
-                                .column(users_visible_templates::Column::TemplateId)
-                                .from(users_visible_templates::Entity)
-                                .and_where(users_visible_templates::Column::UserId.eq(user_id))
-                                .to_owned(),
-                        ),
-                    ),
-            )
+            .filter(Self::visibility_condition(Some(user_id)))
             .all(db)
             .await?;
 
@@ -178,52 +643,190 @@ impl Model {
     pub async fn find_visible(
         db: &DatabaseConnection,
         user_id: Option<i32>,
-    ) -> ModelResult<Vec<Self>> {
+    ) -> AppResult<Vec<Self>> {
         match user_id {
             Some(user_id) => Self::find_visible_to_user(db, user_id).await,
             None => Self::find_public(db).await,
         }
     }
 
+    /// Finds a page of templates visible to the user (or public ones, if
+    /// there is no user), that match the filters of `params`, starting after
+    /// the `cursor`.
+    ///
     /// # Errors
     ///
-    /// When entity is not found
-    pub async fn find_by_id_for_user(
+    /// When DB query error
+    pub async fn find_visible_page(
         db: &DatabaseConnection,
-        id: i32,
-        user_id: i32,
-    ) -> ModelResult<Self> {
-        let txn = db.begin().await?;
+        user_id: Option<i32>,
+        params: &ListParams,
+        cursor: Option<Cursor>,
+    ) -> AppResult<Page> {
+        let mut condition = Condition::all().add(Self::visibility_condition(user_id));
+
+        if let Some(search) = params.search.as_deref().map(str::trim) {
+            if !search.is_empty() {
+                let escaped = search
+                    .to_lowercase()
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+
+                condition = condition.add(
+                    Expr::col((templates::Entity, templates::Column::SearchText))
+                        .like(LikeExpr::new(format!("%{escaped}%")).escape('\\')),
+                );
+            }
+        }
+
+        if !params.categories.is_empty() {
+            condition = condition.add(
+                templates::Column::Id.in_subquery(
+                    Query::select()
+                        .column(templates_categories::Column::TemplateId)
+                        .from(templates_categories::Entity)
+                        .and_where(
+                            templates_categories::Column::CategoryId
+                                .is_in(params.categories.iter().copied()),
+                        )
+                        .to_owned(),
+                ),
+            );
+        }
+
+        if let Some(author) = params.author {
+            condition = condition.add(
+                templates::Column::UserId.in_subquery(
+                    Query::select()
+                        .column(users::Column::Id)
+                        .from(users::Entity)
+                        .and_where(users::Column::Pid.eq(author))
+                        .to_owned(),
+                ),
+            );
+        }
+
+        if let Some(publicity) = params.publicity {
+            condition =
+                condition.add(templates::Column::IsPublic.eq(publicity == PublicityFilter::Public));
+        }
+
+        if let Some(from) = params.created_from {
+            condition = condition.add(templates::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = params.created_to {
+            condition = condition.add(templates::Column::CreatedAt.lte(to));
+        }
+        if let Some(from) = params.updated_from {
+            condition = condition.add(templates::Column::UpdatedAt.gte(from));
+        }
+        if let Some(to) = params.updated_to {
+            condition = condition.add(templates::Column::UpdatedAt.lte(to));
+        }
+
+        let query = templates::Entity::find().filter(condition);
+
+        let total = query.clone().count(db).await?;
+
+        let mut query = match params.sort {
+            SortOrder::Newest => {
+                query
+                    .order_by_desc(templates::Column::CreatedAt)
+                    .order_by_desc(templates::Column::Id)
+            },
+            SortOrder::Name => {
+                query
+                    .order_by_asc(templates::Column::Name)
+                    .order_by_asc(templates::Column::Id)
+            },
+            SortOrder::Popularity => {
+                query
+                    .order_by_desc(templates::Column::Downloads)
+                    .order_by_desc(templates::Column::Id)
+            },
+        };
+
+        if let Some(cursor) = cursor {
+            query = query.filter(cursor.condition());
+        }
+
+        let limit = params
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        // one more template to know whether there is a next page
+        let mut templates = query.limit(limit + 1).all(db).await?;
+
+        let next_cursor = if templates.len() as u64 > limit {
+            templates.truncate(limit as usize);
+            templates
+                .last()
+                .map(|template| Cursor::new(template, params.sort).encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            templates,
+            total,
+            next_cursor,
+        })
+    }
+
+    /// Counts a download of the template for the popularity sort order.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn increment_downloads(db: &DatabaseConnection, id: i32) -> AppResult<()> {
+        templates::Entity::update_many()
+            .col_expr(
+                templates::Column::Downloads,
+                Expr::col(templates::Column::Downloads).add(1),
+            )
+            .filter(templates::Column::Id.eq(id))
+            .exec(db)
+            .await?;
 
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// When entity is not found
+    pub async fn find_by_id_for_user<C>(db: &C, id: i32, user_id: i32) -> AppResult<Self>
+    where
+        C: ConnectionTrait,
+    {
         let template = Self::find_by_id(db, id).await?;
         if template.is_public || template.user_id == user_id {
-            txn.commit().await?;
             return Ok(template);
         }
 
-        let is_visible = users_visible_templates::Entity::find_by_id((id, user_id))
-            .one(db)
+        let is_visible = Entity::find_by_id(id)
+            .filter(Self::visibility_condition(Some(user_id)))
+            .count(db)
             .await?;
 
-        if is_visible.is_some() {
-            txn.commit().await?;
+        if is_visible > 0 {
             return Ok(template);
         };
 
-        txn.commit().await?;
-        Err(ModelError::Any("Unauthorized".into()))
+        Err(AppError::forbidden("The template is private"))
     }
 
     /// # Errors
     ///
     /// When entity is not found
-    pub async fn find_public_by_id(db: &DatabaseConnection, id: i32) -> ModelResult<Self> {
+    pub async fn find_public_by_id(db: &DatabaseConnection, id: i32) -> AppResult<Self> {
         let template = templates::Entity::find()
             .filter(templates::Column::IsPublic.eq(true))
             .filter(templates::Column::Id.eq(id))
             .one(db)
             .await?;
-        template.ok_or_else(|| ModelError::EntityNotFound)
+        template.ok_or_else(|| AppError::not_found("Template not found"))
     }
 
     /// # Errors
@@ -233,7 +836,7 @@ impl Model {
         db: &DatabaseConnection,
         id: i32,
         user_id: Option<i32>,
-    ) -> ModelResult<Self> {
+    ) -> AppResult<Self> {
         match user_id {
             Some(user_id) => Self::find_by_id_for_user(db, id, user_id).await,
             None => Self::find_public_by_id(db, id).await,
@@ -243,16 +846,14 @@ impl Model {
     /// # Errors
     ///
     /// When file is not found or error reading file
-    pub async fn find_docx(id: i32) -> ModelResult<Vec<u8>> {
+    pub async fn find_docx(id: i32) -> AppResult<Vec<u8>> {
         let file_path = PathBuf::from(format!("./data/templates/{id}.docx"));
 
         if !file_path.exists() {
-            return Err(ModelError::EntityNotFound);
+            return Err(AppError::not_found("Template file not found"));
         }
 
-        let buffer = fs::read(file_path)
-            .await
-            .map_err(|_| ModelError::Any("Error reading file".into()))?;
+        let buffer = fs::read(file_path).await.map_err(AppError::internal)?;
 
         Ok(buffer)
     }
@@ -260,41 +861,256 @@ impl Model {
     /// # Errors
     ///
     /// When file is not found or error reading file
-    pub async fn find_dsl(id: i32) -> ModelResult<String> {
+    pub async fn find_dsl(id: i32) -> AppResult<String> {
         let file_path = PathBuf::from(format!("./data/templates/{id}.dsl"));
 
         if !file_path.exists() {
-            return Err(ModelError::EntityNotFound);
+            return Err(AppError::not_found("Template file not found"));
         }
 
         let buffer = fs::read_to_string(file_path)
             .await
-            .map_err(|_| ModelError::Any("Error reading file".into()))?;
+            .map_err(AppError::internal)?;
 
         Ok(buffer)
     }
 
+    /// Size of the docx file, without reading it.
+    ///
+    /// # Errors
+    ///
+    /// When file is not found
+    pub async fn find_docx_size(id: i32) -> AppResult<u64> {
+        let metadata = fs::metadata(format!("./data/templates/{id}.docx"))
+            .await
+            .map_err(|_| AppError::not_found("Template file not found"))?;
+
+        Ok(metadata.len())
+    }
+
+    /// Reads only the bytes of the docx file in the range, e.g. to resume a
+    /// download.
+    ///
+    /// # Errors
+    ///
+    /// When file is not found, or the range is out of the file
+    pub async fn find_docx_range(id: i32, range: RangeInclusive<u64>) -> AppResult<Vec<u8>> {
+        let mut file = fs::File::open(format!("./data/templates/{id}.docx"))
+            .await
+            .map_err(|_| AppError::not_found("Template file not found"))?;
+        file.seek(SeekFrom::Start(*range.start()))
+            .await
+            .map_err(AppError::internal)?;
+
+        let len = usize::try_from(range.end() - range.start() + 1).map_err(AppError::internal)?;
+        let mut buffer = vec![0; len];
+        file.read_exact(&mut buffer)
+            .await
+            .map_err(AppError::internal)?;
+
+        Ok(buffer)
+    }
+
+    /// When the docx or the DSL was uploaded last. Templates from before it
+    /// was stored fall back to the time of the last change.
+    #[must_use]
+    pub fn files_updated_at(&self) -> DateTimeWithTimeZone {
+        self.files_updated_at.unwrap_or(self.updated_at)
+    }
+
+    /// SHA-256 of the docx file. It's stored on upload, templates from before
+    /// that get it on the first request.
+    ///
+    /// # Errors
+    ///
+    /// When file is not found, or DB query error
+    pub async fn find_docx_hash(&self, db: &DatabaseConnection) -> AppResult<String> {
+        if let Some(hash) = &self.docx_hash {
+            return Ok(hash.clone());
+        }
+
+        let hash = sha256(&Self::find_docx(self.id).await?);
+        let mut template = self.clone().into_active_model();
+        template.docx_hash = Set(Some(hash.clone()));
+        template.update(db).await?;
+
+        Ok(hash)
+    }
+
+    /// SHA-256 of the DSL file, see [`Self::find_docx_hash`].
+    ///
+    /// # Errors
+    ///
+    /// When file is not found, or DB query error
+    pub async fn find_dsl_hash(&self, db: &DatabaseConnection) -> AppResult<String> {
+        if let Some(hash) = &self.dsl_hash {
+            return Ok(hash.clone());
+        }
+
+        let hash = sha256(Self::find_dsl(self.id).await?.as_bytes());
+        let mut template = self.clone().into_active_model();
+        template.dsl_hash = Set(Some(hash.clone()));
+        template.update(db).await?;
+
+        Ok(hash)
+    }
+
+    /// Types of the DSL, as they are served to the constructor. They are
+    /// cached on upload and compiled again, if another version of the
+    /// compiler cached them.
+    ///
+    /// # Errors
+    ///
+    /// When the DSL doesn't compile anymore, error reading the file, or DB
+    /// query error
+    pub async fn find_dsl_types<C>(&self, db: &C) -> AppResult<serde_json::Value>
+    where
+        C: ConnectionTrait,
+    {
+        // the DSL was compiled on upload, so it's a server problem now
+        self.find_dsl_types_or(db, AppError::internal).await
+    }
+
+    /// Same as [`Self::find_dsl_types`], but the error of the compiler is
+    /// turned into the one of the caller.
+    async fn find_dsl_types_or<C>(
+        &self,
+        db: &C,
+        compile_error: impl FnOnce(String) -> AppError,
+    ) -> AppResult<serde_json::Value>
+    where
+        C: ConnectionTrait,
+    {
+        if let (Some(types), Some(compiler::VERSION)) = (&self.dsl_types, self.dsl_compiler_version)
+        {
+            return Ok(types.clone());
+        }
+
+        let dsl = Self::find_dsl(self.id).await?;
+        let compiled = CompiledDsl::new(&dsl).map_err(compile_error)?;
+        let types = compiled.types.clone();
+
+        let mut template = self.clone().into_active_model();
+        compiled.set(&mut template);
+        template.update(db).await?;
+
+        Ok(types)
+    }
+
+    /// Same as [`Self::find_dsl_types`], but keyed by the names of the
+    /// variables to validate documents against.
+    ///
     /// # Errors
     ///
-    /// When author is not found, categories are not found, viewers are not
-    /// found, or error writing files.
+    /// See [`Self::find_dsl_types`]
+    pub async fn find_var_env<C>(&self, db: &C) -> AppResult<VarEnv>
+    where
+        C: ConnectionTrait,
+    {
+        var_env(self.find_dsl_types(db).await?)
+    }
+
+    /// Compiles the DSL file again and caches the types, e.g. after the
+    /// compiler was updated.
+    ///
+    /// # Errors
+    ///
+    /// When the DSL doesn't compile anymore, error reading the file, or DB
+    /// query error. The previously cached types are kept then.
+    pub async fn recompile_dsl(self, db: &DatabaseConnection) -> AppResult<Self> {
+        let dsl = Self::find_dsl(self.id).await?;
+        let compiled = CompiledDsl::new(&dsl).map_err(|err| AppError::invalid_dsl("dsl", err))?;
+
+        let mut template = self.into_active_model();
+        compiled.set(&mut template);
+        Ok(template.update(db).await?)
+    }
+
+    /// Moves the template to the next revision, unless it was changed since
+    /// the `expected` one. Postgres keeps the row locked until the transaction
+    /// ends, so a concurrent writer waits and then sees the new revision.
+    async fn next_revision(
+        self,
+        txn: &DatabaseTransaction,
+        expected: Option<i32>,
+    ) -> AppResult<Self> {
+        let mut update = Entity::update_many()
+            .col_expr(
+                templates::Column::Revision,
+                Expr::col(templates::Column::Revision).add(1),
+            )
+            .filter(templates::Column::Id.eq(self.id));
+        if let Some(expected) = expected {
+            update = update.filter(templates::Column::Revision.eq(expected));
+        }
+
+        if update.exec(txn).await?.rows_affected == 0 {
+            return Err(revision_conflict());
+        }
+
+        Entity::find_by_id(self.id)
+            .one(txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Template not found"))
+    }
+
+    /// The template, that the user is about to change, moved to the next
+    /// revision. Editors can change templates of other users too, but only
+    /// those they can see.
+    async fn find_for_editing(
+        txn: &DatabaseTransaction,
+        id: i32,
+        user: &users::Model,
+        revision: Option<i32>,
+    ) -> AppResult<Self> {
+        let template = Self::find_by_id_for_user(txn, id, user.id).await?;
+
+        if template.user_id != user.id && !user.role.can(Permission::EditTemplates) {
+            return Err(AppError::forbidden(
+                "Only the author or an editor can edit the template",
+            ));
+        }
+
+        template.next_revision(txn, revision).await
+    }
+
+    /// Removes the template of the user. Moderators can remove public
+    /// templates of other users too. Unless the `revision` is `None`, the
+    /// template must not be changed since it.
+    ///
+    /// # Errors
+    ///
+    /// When template is not found, the user is not allowed to remove it, the
+    /// revision is outdated, or DB query error
     pub async fn delete_template(
         db: &DatabaseConnection,
         id: i32,
-        user_id: i32,
-    ) -> ModelResult<()> {
+        revision: Option<i32>,
+        user: &users::Model,
+    ) -> AppResult<()> {
         let txn = db.begin().await?;
 
         let template = Entity::find_by_id(id)
             .one(&txn)
             .await?
-            .ok_or(ModelError::EntityNotFound)?;
+            .ok_or_else(|| AppError::not_found("Template not found"))?;
 
-        if template.user_id != user_id {
+        let is_moderated = template.is_public && user.role.can(Permission::ModerateTemplates);
+        if template.user_id != user.id && !is_moderated {
             txn.rollback().await?;
-            return Err(ModelError::Any("Unauthorized".into()));
+            return Err(AppError::forbidden(
+                "Only the author or a moderator can delete the template",
+            ));
         }
 
+        let template = match template.next_revision(&txn, revision).await {
+            Ok(template) => template,
+            Err(err) => {
+                txn.rollback().await?;
+                return Err(err);
+            },
+        };
+
         // if files are missing... they are already deleted, so ignore errors
         let _ = fs::remove_file(format!("./data/templates/{}.docx", template.id)).await;
         let _ = fs::remove_file(format!("./data/templates/{}.dsl", template.id)).await;
@@ -310,117 +1126,283 @@ impl Model {
 }
 
 impl ActiveModel {
+    fn set_docx(&mut self, docx: &[u8]) {
+        self.docx_hash = Set(Some(sha256(docx)));
+        self.files_updated_at = Set(Some(chrono::Utc::now().into()));
+    }
+
+    fn set_dsl(&mut self, compiled: CompiledDsl) {
+        self.files_updated_at = Set(Some(chrono::Utc::now().into()));
+        compiled.set(self);
+    }
+
+    /// Replaces the template. Editors can change templates of other users
+    /// too. Unless the `revision` of the params is `None`, the template must
+    /// not be changed since it.
+    ///
     /// # Errors
     ///
-    /// When author is not found, categories are not found, viewers are not
-    /// found, or error writing files.
-    pub async fn update_template(
+    /// When the DSL doesn't compile, the docx can't be read or uses undeclared
+    /// variables, template is not found, the user is not allowed to edit it,
+    /// the revision is outdated, categories are not found, emails of viewers
+    /// are invalid, or error writing files.
+    pub async fn update_template<C>(
         self,
-        db: &DatabaseConnection,
-        params: &CreateTemplateParams,
+        db: &C,
+        params: &UpdateTemplateParams,
         id: i32,
-        author_id: i32,
+        user: &users::Model,
         docx: &[u8],
         dsl: &str,
-    ) -> ModelResult<Model> {
+    ) -> AppResult<Model>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let (params, revision) = (&params.template, params.revision);
+        let compiled = CompiledDsl::new(dsl).map_err(|err| AppError::invalid_dsl("dsl", err))?;
+        let used = crate::docx::variables(docx)
+            .map_err(|err| AppError::invalid_docx("docx", err.to_string()))?;
+        check_variables("docx", &used, &compiled.env)?;
+
         let txn = db.begin().await?;
 
-        compile_types(dsl).map_err(|_| ModelError::Any("Invalid DSL".into()))?;
+        let template = match Model::find_for_editing(&txn, id, user, revision).await {
+            Ok(template) => template,
+            Err(err) => {
+                txn.rollback().await?;
+                return Err(err);
+            },
+        };
 
-        let template = Entity::find_by_id(id)
-            .one(&txn)
-            .await?
-            .ok_or(ModelError::EntityNotFound)?;
+        let mut fields = Vec::new();
+        let categories = find_categories(&txn, &params.categories, &mut fields).await?;
+        // shared on behalf of the author, whoever edits the template
+        let sharing = find_sharing(&txn, &params.publicity, template.user_id, &mut fields).await?;
 
-        if template.user_id != author_id {
+        if !fields.is_empty() {
             txn.rollback().await?;
-            return Err(ModelError::Any("Unauthorized".into()));
+            return Err(invalid_references(fields));
         }
 
-        let mut categories = Vec::with_capacity(params.categories.len());
+        fs::write(format!("./data/templates/{}.docx", template.id), docx)
+            .await
+            .map_err(AppError::internal)?;
 
-        for category_id in params.categories.iter().copied() {
-            let category = Entity::find_by_id(category_id)
-                .one(&txn)
-                .await?
-                .ok_or(ModelError::EntityNotFound)?;
-            categories.push(category);
-        }
+        fs::write(format!("./data/templates/{}.dsl", template.id), dsl)
+            .await
+            .map_err(AppError::internal)?;
 
-        let viewers = match &params.publicity {
-            PublicityParams::Public => None,
-            PublicityParams::Private {
-                viewers: visible_to,
-            } => {
-                let mut viewers = Vec::with_capacity(visible_to.len());
+        set_categories(&txn, template.id, &categories).await?;
+        set_sharing(&txn, template.id, sharing.as_ref()).await?;
 
-                for viewer_email in visible_to.iter().map(String::as_str) {
-                    let viewer = users::Entity::find()
-                        .filter(
-                            query::condition()
-                                .eq(users::Column::Email, viewer_email)
-                                .build(),
-                        )
-                        .one(&txn)
-                        .await?
-                        .ok_or(ModelError::EntityNotFound)?;
-                    viewers.push(viewer);
-                }
+        let version = template.version;
+        let mut template = template.into_active_model();
+        template.name = Set(params.name.clone());
+        template.description = Set(params.description.clone());
+        template.is_public = Set(sharing.is_none());
+        // drafts of documents are migrated to the new DSL on reopening
+        template.version = Set(version + 1);
+        template.set_docx(docx);
+        template.set_dsl(compiled);
+        let template = template.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(template)
+    }
 
-                Some(viewers)
+    /// Changes only the metadata, that is sent. The files and the version of
+    /// the template stay the same, so drafts of documents are kept as is.
+    ///
+    /// # Errors
+    ///
+    /// When template is not found, the user is not allowed to edit it, the
+    /// revision is outdated, categories are not found, or emails of viewers
+    /// are invalid
+    pub async fn patch_template(
+        self,
+        db: &DatabaseConnection,
+        params: &PatchTemplateParams,
+        id: i32,
+        user: &users::Model,
+    ) -> AppResult<Model> {
+        let txn = db.begin().await?;
+
+        let template = match Model::find_for_editing(&txn, id, user, params.revision).await {
+            Ok(template) => template,
+            Err(err) => {
+                txn.rollback().await?;
+                return Err(err);
             },
         };
 
-        Entity::delete_many()
-            .filter(templates_categories::Column::TemplateId.eq(id))
-            .exec(&txn)
-            .await?;
+        let mut fields = Vec::new();
+        let categories = match &params.categories {
+            Some(category_ids) => Some(find_categories(&txn, category_ids, &mut fields).await?),
+            None => None,
+        };
+        let sharing = match &params.publicity {
+            Some(publicity) => {
+                Some(find_sharing(&txn, publicity, template.user_id, &mut fields).await?)
+            },
+            None => None,
+        };
 
-        Entity::delete_many()
-            .filter(users_visible_templates::Column::TemplateId.eq(id))
-            .exec(&txn)
-            .await?;
+        if !fields.is_empty() {
+            txn.rollback().await?;
+            return Err(invalid_references(fields));
+        }
 
-        fs::write(format!("./data/templates/{}.docx", template.id), docx)
+        if let Some(categories) = &categories {
+            set_categories(&txn, template.id, categories).await?;
+        }
+        if let Some(sharing) = &sharing {
+            set_sharing(&txn, template.id, sharing.as_ref()).await?;
+        }
+
+        let mut template = template.into_active_model();
+        if let Some(name) = &params.name {
+            template.name = Set(name.clone());
+        }
+        if let Some(description) = &params.description {
+            template.description = Set(description.clone());
+        }
+        if let Some(sharing) = &sharing {
+            template.is_public = Set(sharing.is_none());
+        }
+        let template = template.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(template)
+    }
+
+    /// Replaces only the docx. Its tags must refer to the variables of the
+    /// current DSL.
+    ///
+    /// # Errors
+    ///
+    /// When the docx can't be read or uses undeclared variables, template is
+    /// not found, the user is not allowed to edit it, the revision is
+    /// outdated, or error reading and writing files
+    pub async fn replace_docx(
+        self,
+        db: &DatabaseConnection,
+        id: i32,
+        revision: Option<i32>,
+        user: &users::Model,
+        docx: &[u8],
+    ) -> AppResult<Model> {
+        let used = crate::docx::variables(docx)
+            .map_err(|err| AppError::invalid_docx("docx", err.to_string()))?;
+
+        let txn = db.begin().await?;
+
+        let template = match Model::find_for_editing(&txn, id, user, revision).await {
+            Ok(template) => template,
+            Err(err) => {
+                txn.rollback().await?;
+                return Err(err);
+            },
+        };
+
+        // the types are read after the row is locked, so they can't change
+        // meanwhile. A DSL, that doesn't compile anymore, must be fixed first.
+        let checked = template
+            .find_dsl_types_or(&txn, |err| AppError::invalid_dsl("dsl", err))
             .await
-            .map_err(|_| ModelError::Any("Error writing file".into()))?;
+            .and_then(var_env)
+            .and_then(|env| check_variables("docx", &used, &env));
+        if let Err(err) = checked {
+            txn.rollback().await?;
+            return Err(err);
+        }
 
-        fs::write(format!("./data/templates/{}.dsl", template.id), dsl)
+        let staged =
+            StagedFile::write(format!("./data/templates/{}.docx", template.id), docx).await?;
+
+        let mut template = template.into_active_model();
+        template.set_docx(docx);
+        let template = match template.update(&txn).await {
+            Ok(template) => template,
+            Err(err) => {
+                staged.discard().await;
+                return Err(err.into());
+            },
+        };
+
+        staged.commit(txn).await?;
+
+        Ok(template)
+    }
+
+    /// Replaces only the DSL. The variables, that the docx uses, must stay
+    /// declared.
+    ///
+    /// # Errors
+    ///
+    /// When the DSL doesn't compile or misses variables of the docx, template
+    /// is not found, the user is not allowed to edit it, the revision is
+    /// outdated, or error writing the file
+    pub async fn replace_dsl(
+        self,
+        db: &DatabaseConnection,
+        id: i32,
+        revision: Option<i32>,
+        user: &users::Model,
+        dsl: &str,
+    ) -> AppResult<Model> {
+        let compiled = CompiledDsl::new(dsl).map_err(|err| AppError::invalid_dsl("dsl", err))?;
+
+        let txn = db.begin().await?;
+
+        let template = match Model::find_for_editing(&txn, id, user, revision).await {
+            Ok(template) => template,
+            Err(err) => {
+                txn.rollback().await?;
+                return Err(err);
+            },
+        };
+
+        // docx files were not checked before, an unreadable one doesn't block
+        // fixing the DSL
+        match Model::find_docx(template.id)
             .await
-            .map_err(|_| ModelError::Any("Error writing file".into()))?;
-
-        for category in &categories {
-            let template_category = templates_categories::ActiveModel {
-                template_id: Set(template.id),
-                category_id: Set(category.id),
-                ..Default::default()
-            };
-
-            templates_categories::Entity::insert(template_category)
-                .exec(&txn)
-                .await?;
-        }
-
-        if let Some(viewers) = viewers.as_ref() {
-            for viewer in viewers {
-                let viewer_template = users_visible_templates::ActiveModel {
-                    user_id: Set(viewer.id),
-                    template_id: Set(template.id),
-                    ..Default::default()
-                };
-
-                users_visible_templates::Entity::insert(viewer_template)
-                    .exec(&txn)
-                    .await?;
-            }
+            .map(|docx| crate::docx::variables(&docx))
+        {
+            Ok(Ok(used)) => {
+                if let Err(err) = check_variables("dsl", &used, &compiled.env) {
+                    txn.rollback().await?;
+                    return Err(err);
+                }
+            },
+            _ => {
+                tracing::warn!(
+                    template_id = template.id,
+                    "docx can't be checked against the DSL"
+                )
+            },
         }
 
+        let staged = StagedFile::write(
+            format!("./data/templates/{}.dsl", template.id),
+            dsl.as_bytes(),
+        )
+        .await?;
+
+        let version = template.version;
         let mut template = template.into_active_model();
-        template.name = Set(params.name.clone());
-        template.is_public = Set(viewers.is_none());
-        let template = template.update(&txn).await?;
+        template.version = Set(version + 1);
+        template.set_dsl(compiled);
+        let template = match template.update(&txn).await {
+            Ok(template) => template,
+            Err(err) => {
+                staged.discard().await;
+                return Err(err.into());
+            },
+        };
 
-        txn.commit().await?;
+        staged.commit(txn).await?;
 
         Ok(template)
     }
@@ -0,0 +1,7 @@
+use sea_orm::entity::prelude::*;
+
+pub use super::_entities::used_refresh_tokens::{self, ActiveModel, Entity, Model};
+
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+}
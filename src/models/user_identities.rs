@@ -0,0 +1,68 @@
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+
+pub use super::_entities::user_identities::{self, ActiveModel, Entity, Model};
+use super::_entities::users;
+use crate::errors::AppResult;
+
+impl ActiveModelBehavior for ActiveModel {
+    // extend active model below (keep comment for generators)
+}
+
+impl Model {
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_user(db: &DatabaseConnection, user_id: i32) -> AppResult<Vec<Self>> {
+        let identities = Entity::find()
+            .filter(user_identities::Column::UserId.eq(user_id))
+            .order_by_asc(user_identities::Column::Id)
+            .all(db)
+            .await?;
+        Ok(identities)
+    }
+
+    /// Finds the user, that the identity at the provider is linked to.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_user(
+        db: &DatabaseConnection,
+        provider: &str,
+        subject: &str,
+    ) -> AppResult<Option<users::Model>> {
+        let user = users::Entity::find()
+            .inner_join(Entity)
+            .filter(user_identities::Column::Provider.eq(provider))
+            .filter(user_identities::Column::Subject.eq(subject))
+            .one(db)
+            .await?;
+        Ok(user)
+    }
+
+    /// Links the identity at the provider to the user, so the user can log in
+    /// with the provider.
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn link(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        provider: &str,
+        subject: &str,
+        email: Option<String>,
+    ) -> AppResult<Self> {
+        let identity = ActiveModel {
+            user_id: ActiveValue::set(user.id),
+            provider: ActiveValue::set(provider.to_string()),
+            subject: ActiveValue::set(subject.to_string()),
+            email: ActiveValue::set(email),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(identity)
+    }
+}
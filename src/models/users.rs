@@ -1,22 +1,72 @@
-use async_trait::async_trait;
+use std::sync::LazyLock;
+
 use chrono::offset::Local;
 use loco_rs::auth::jwt;
 use loco_rs::hash;
 use loco_rs::prelude::*;
-use migration::Query;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sea_orm::sea_query::Expr;
+use sea_orm::{Condition, QueryOrder, QuerySelect};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::_entities::sea_orm_active_enums::OrganizationRole;
 pub use super::_entities::users::{self, ActiveModel, Entity, Model};
-use super::_entities::{templates, users_visible_templates};
+use super::_entities::{categories, merge_jobs, organization_members, organizations, templates};
+use super::roles::UserRole;
+use super::sessions;
+use crate::controllers::user::TemplatesDisposal;
+use crate::errors::{AppError, AppResult};
+use crate::totp;
+use crate::views::error::FieldError;
+
+/// How long to wait before the verification email can be sent again.
+const VERIFICATION_RESEND_INTERVAL: chrono::Duration = chrono::Duration::minutes(1);
+
+/// How many recovery codes are given, when the two-factor authentication is
+/// enabled. Each of them can be used once instead of the one-time code.
+const RECOVERY_CODES_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// Only hashes of the verification, reset and refresh tokens are stored, so a
+/// leaked database doesn't leak usable tokens.
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_recovery_code() -> String {
+    let code = rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(RECOVERY_CODE_LENGTH)
+        .map(|char| char::from(char).to_ascii_lowercase())
+        .collect::<String>();
+    let (head, tail) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+    format!("{head}-{tail}")
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    hash_token(&code.trim().to_lowercase())
+}
+
+fn is_expired(sent_at: Option<DateTimeWithTimeZone>, ttl: chrono::Duration) -> bool {
+    sent_at.is_none_or(|sent_at| Local::now().fixed_offset() - sent_at > ttl)
+}
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct LoginParams {
     pub email: String,
     pub password: String,
+    /// The one-time or a recovery code, if the two-factor authentication is
+    /// enabled.
+    #[serde(default)]
+    pub code: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct RegisterParams {
     pub email: String,
     pub password: String,
@@ -50,6 +100,8 @@ impl ActiveModelBehavior for super::_entities::users::ActiveModel {
         if insert {
             let mut this = self;
             this.pid = ActiveValue::Set(Uuid::new_v4());
+            // API keys were replaced by API tokens and are rejected, the
+            // column stays only because SQLite can't drop unique columns
             this.api_key = ActiveValue::Set(format!("lo-{}", Uuid::new_v4()));
             Ok(this)
         } else {
@@ -58,25 +110,6 @@ impl ActiveModelBehavior for super::_entities::users::ActiveModel {
     }
 }
 
-#[async_trait]
-impl Authenticable for super::_entities::users::Model {
-    async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
-        let user = users::Entity::find()
-            .filter(
-                model::query::condition()
-                    .eq(users::Column::ApiKey, api_key)
-                    .build(),
-            )
-            .one(db)
-            .await?;
-        user.ok_or_else(|| ModelError::EntityNotFound)
-    }
-
-    async fn find_by_claims_key(db: &DatabaseConnection, claims_key: &str) -> ModelResult<Self> {
-        Self::find_by_pid(db, claims_key).await
-    }
-}
-
 impl super::_entities::users::Model {
     /// finds a user by the provided email
     ///
@@ -107,7 +140,7 @@ impl super::_entities::users::Model {
         let user = users::Entity::find()
             .filter(
                 model::query::condition()
-                    .eq(users::Column::EmailVerificationToken, token)
+                    .eq(users::Column::EmailVerificationToken, hash_token(token))
                     .build(),
             )
             .one(db)
@@ -115,7 +148,7 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    /// /// finds a user by the provided reset token
+    /// finds a user by the provided reset token
     ///
     /// # Errors
     ///
@@ -124,7 +157,7 @@ impl super::_entities::users::Model {
         let user = users::Entity::find()
             .filter(
                 model::query::condition()
-                    .eq(users::Column::ResetToken, token)
+                    .eq(users::Column::ResetToken, hash_token(token))
                     .build(),
             )
             .one(db)
@@ -150,21 +183,25 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    /// finds a user by the provided api key
+    /// Finds all users, e.g. to manage their roles.
     ///
     /// # Errors
     ///
-    /// When could not find user by the given token or DB query error
-    pub async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
-        let user = users::Entity::find()
-            .filter(
-                model::query::condition()
-                    .eq(users::Column::ApiKey, api_key)
-                    .build(),
-            )
-            .one(db)
+    /// When DB query error
+    pub async fn find_all(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let users = users::Entity::find()
+            .order_by_asc(users::Column::Id)
+            .all(db)
             .await?;
-        user.ok_or_else(|| ModelError::EntityNotFound)
+        Ok(users)
+    }
+
+    /// Checks the password against the hash of a random one, so the login of
+    /// an unknown email takes as long as of a registered one.
+    pub fn verify_dummy_password(password: &str) {
+        static DUMMY_HASH: LazyLock<String> =
+            LazyLock::new(|| hash::hash_password(&Uuid::new_v4().to_string()).unwrap_or_default());
+        let _ = hash::verify_password(password, &DUMMY_HASH);
     }
 
     /// Verifies whether the provided plain password matches the hashed password
@@ -218,75 +255,423 @@ impl super::_entities::users::Model {
         Ok(user)
     }
 
-    /// Creates a JWT
+    /// Creates the user of an identity provider, that vouches for the email,
+    /// so the email is verified. The password is random, the user logs in
+    /// with the provider or resets the password.
+    ///
+    /// # Errors
+    ///
+    /// When the email is registered already or could not save the user into
+    /// the DB
+    pub async fn create_from_identity(
+        db: &DatabaseConnection,
+        email: &str,
+        name: Option<&str>,
+    ) -> ModelResult<Self> {
+        let name = name
+            .map(str::trim)
+            .filter(|name| name.chars().count() >= 2)
+            .unwrap_or(email);
+        let user = Self::create_with_password(db, &RegisterParams {
+            email: email.to_string(),
+            password: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+        })
+        .await?;
+        user.into_active_model().verified(db).await
+    }
+
+    /// Creates a JWT of the session, so the token is rejected, once the
+    /// session is revoked.
     ///
     /// # Errors
     ///
     /// when could not convert user claims to jwt token
-    pub fn generate_jwt(&self, secret: &str, expiration: &u64) -> ModelResult<String> {
-        Ok(jwt::JWT::new(secret).generate_token(expiration, self.pid.to_string(), None)?)
+    pub fn generate_jwt(
+        &self,
+        secret: &str,
+        expiration: &u64,
+        session: &sessions::Model,
+    ) -> ModelResult<String> {
+        Ok(jwt::JWT::new(secret).generate_token(
+            expiration,
+            self.pid.to_string(),
+            Some(serde_json::json!({ sessions::SESSION_CLAIM: session.pid })),
+        )?)
     }
 
+    /// Rejects users, that haven't verified their email yet.
+    ///
+    /// # Errors
+    ///
+    /// When the email is not verified
+    pub fn require_verified(&self) -> AppResult<()> {
+        if self.email_verified_at.is_none() {
+            return Err(AppError::Forbidden {
+                code: "email_not_verified",
+                message: "The email is not verified".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the verification email can be sent again, so the mailbox
+    /// isn't flooded.
+    #[must_use]
+    pub fn can_resend_verification(&self) -> bool {
+        self.email_verification_sent_at.is_none_or(|sent_at| {
+            Local::now().fixed_offset() - sent_at >= VERIFICATION_RESEND_INTERVAL
+        })
+    }
+
+    /// Whether the verification token was sent longer than `ttl` ago.
+    #[must_use]
+    pub fn is_verification_token_expired(&self, ttl: chrono::Duration) -> bool {
+        is_expired(self.email_verification_sent_at, ttl)
+    }
+
+    /// Whether the reset token was sent longer than `ttl` ago.
+    #[must_use]
+    pub fn is_reset_token_expired(&self, ttl: chrono::Duration) -> bool {
+        is_expired(self.reset_sent_at, ttl)
+    }
+
+    /// Whether the login requires the one-time code.
+    #[must_use]
+    pub fn has_totp(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    /// Starts the enrollment into the two-factor authentication with a new
+    /// secret. It isn't required at login until it's confirmed with a code.
+    ///
     /// # Errors
     ///
-    /// When could not find the template author or DB query error
-    pub async fn find_template_author(
+    /// When the two-factor authentication is already enabled or DB query error
+    pub async fn start_totp(self, db: &DatabaseConnection) -> AppResult<Self> {
+        if self.has_totp() {
+            return Err(totp_enabled());
+        }
+
+        let mut user = self.into_active_model();
+        user.totp_secret = ActiveValue::set(Some(totp::generate_secret()));
+        user.totp_last_step = ActiveValue::set(None);
+        Ok(user.update(db).await?)
+    }
+
+    /// Enables the two-factor authentication, once the user proves, that the
+    /// authenticator app has the secret. Returns the recovery codes, only
+    /// their hashes are stored.
+    ///
+    /// # Errors
+    ///
+    /// When the enrollment isn't started, the code is wrong or DB query error
+    pub async fn confirm_totp(
+        self,
         db: &DatabaseConnection,
-        template_id: i32,
-    ) -> ModelResult<Self> {
-        let author = users::Entity::find()
-            .filter(
-                users::Column::Id.in_subquery(
-                    Query::select()
-                        .column(templates::Column::UserId)
-                        .from(templates::Entity)
-                        .and_where(templates::Column::Id.eq(template_id))
-                        .to_owned(),
-                ),
-            )
-            .one(db)
-            .await?;
-        author.ok_or_else(|| ModelError::EntityNotFound)
+        code: &str,
+        time: u64,
+    ) -> AppResult<(Self, Vec<String>)> {
+        if self.has_totp() {
+            return Err(totp_enabled());
+        }
+        if self.totp_secret.is_none() {
+            return Err(AppError::conflict(
+                "totp_not_started",
+                "The two-factor authentication enrollment is not started",
+            ));
+        }
+        let Some(step) = self.verify_totp(code, time) else {
+            return Err(AppError::validation(
+                "invalid_totp_code",
+                "The one-time code is wrong",
+                vec![FieldError::new("code", "is wrong")],
+            ));
+        };
+
+        let recovery_codes = (0..RECOVERY_CODES_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect::<Vec<_>>();
+        let hashes = recovery_codes
+            .iter()
+            .map(|code| hash_recovery_code(code))
+            .collect::<Vec<_>>();
+
+        let mut user = self.into_active_model();
+        user.totp_enabled_at = ActiveValue::set(Some(Local::now().into()));
+        user.totp_last_step = ActiveValue::set(Some(step));
+        user.totp_recovery_codes = ActiveValue::set(Some(serde_json::json!(hashes)));
+        Ok((user.update(db).await?, recovery_codes))
     }
 
+    /// Checks the second factor at login: either the one-time code or one of
+    /// the recovery codes. Both can't be used again.
+    ///
     /// # Errors
     ///
-    /// When could not find the template viewers or DB query error
-    pub async fn find_template_viewers(
+    /// When the code is wrong or DB query error
+    pub async fn check_second_factor(
+        self,
         db: &DatabaseConnection,
-        template_id: i32,
-    ) -> ModelResult<Option<Vec<Self>>> {
-        let template = templates::Entity::find_by_id(template_id)
-            .one(db)
+        code: &str,
+        time: u64,
+    ) -> AppResult<Self> {
+        if let Some(step) = self.verify_totp(code, time) {
+            let mut user = self.into_active_model();
+            user.totp_last_step = ActiveValue::set(Some(step));
+            return Ok(user.update(db).await?);
+        }
+
+        let hash = hash_recovery_code(code);
+        let mut hashes = self
+            .totp_recovery_codes
+            .as_ref()
+            .and_then(|codes| serde_json::from_value::<Vec<String>>(codes.clone()).ok())
+            .unwrap_or_default();
+        let Some(index) = hashes.iter().position(|recovery| recovery == &hash) else {
+            return Err(AppError::unauthorized(
+                "invalid_totp_code",
+                "The one-time code is wrong",
+            ));
+        };
+        hashes.remove(index);
+
+        let mut user = self.into_active_model();
+        user.totp_recovery_codes = ActiveValue::set(Some(serde_json::json!(hashes)));
+        Ok(user.update(db).await?)
+    }
+
+    /// Turns the two-factor authentication off, the code is required, so a
+    /// stolen session isn't enough.
+    ///
+    /// # Errors
+    ///
+    /// When the code is wrong or DB query error
+    pub async fn disable_totp(
+        self,
+        db: &DatabaseConnection,
+        code: &str,
+        time: u64,
+    ) -> AppResult<Self> {
+        if !self.has_totp() {
+            return Err(AppError::conflict(
+                "totp_disabled",
+                "The two-factor authentication is not enabled",
+            ));
+        }
+        let user = self.check_second_factor(db, code, time).await?;
+
+        let mut user = user.into_active_model();
+        user.totp_secret = ActiveValue::set(None);
+        user.totp_enabled_at = ActiveValue::set(None);
+        user.totp_last_step = ActiveValue::set(None);
+        user.totp_recovery_codes = ActiveValue::set(None);
+        Ok(user.update(db).await?)
+    }
+
+    /// Checks the password again before changes, that a stolen session
+    /// shouldn't be enough for.
+    ///
+    /// # Errors
+    ///
+    /// When the password is wrong
+    pub fn confirm_password(&self, password: &str) -> AppResult<()> {
+        if !self.verify_password(password) {
+            return Err(AppError::validation(
+                "invalid_password",
+                "The password is wrong",
+                vec![FieldError::new("password", "is wrong")],
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts changing the email. The current email is kept, until the new
+    /// one is verified with the returned token, see
+    /// [`ActiveModel::change_email`].
+    ///
+    /// # Errors
+    ///
+    /// When the email is invalid or taken, or DB query error
+    pub async fn request_email_change(
+        self,
+        db: &DatabaseConnection,
+        email: &str,
+    ) -> AppResult<(Self, String)> {
+        let invalid = |message: &str| {
+            AppError::validation("invalid_email", "The email is invalid", vec![
+                FieldError::new("email", message),
+            ])
+        };
+        if validation::is_valid_email(email).is_err() {
+            return Err(invalid("is not a valid email"));
+        }
+        if email == self.email {
+            return Err(invalid("is the current email"));
+        }
+        check_email_is_free(db, email).await?;
+
+        let token = Uuid::new_v4().to_string();
+        let mut user = self.into_active_model();
+        user.pending_email = ActiveValue::set(Some(email.to_string()));
+        user.email_verification_sent_at = ActiveValue::set(Some(Local::now().into()));
+        user.email_verification_token = ActiveValue::Set(Some(hash_token(&token)));
+        Ok((user.update(db).await?, token))
+    }
+
+    /// Deletes the account with everything of the user. The templates are
+    /// either transferred to another user together with the categories or
+    /// deleted, the documents of other users from the deleted templates are
+    /// deleted too.
+    ///
+    /// # Errors
+    ///
+    /// When the user is the last owner of an organization, the recipient of
+    /// the templates is not found, or DB query error
+    pub async fn delete_account(
+        self,
+        db: &DatabaseConnection,
+        templates: &TemplatesDisposal,
+    ) -> AppResult<()> {
+        let txn = db.begin().await?;
+
+        // teams are managed by the owners of the organization anyway
+        for (membership, organization) in organization_members::Entity::find()
+            .filter(organization_members::Column::UserId.eq(self.id))
+            .filter(organization_members::Column::Role.eq(OrganizationRole::Owner))
+            .find_also_related(organizations::Entity)
+            .all(&txn)
             .await?
-            .ok_or(ModelError::EntityNotFound)?;
+        {
+            if organization.is_none_or(|organization| organization.parent_id.is_some()) {
+                continue;
+            }
 
-        if template.is_public {
-            return Ok(None);
+            let other_owner = organization_members::Entity::find()
+                .filter(organization_members::Column::OrganizationId.eq(membership.organization_id))
+                .filter(organization_members::Column::Role.eq(OrganizationRole::Owner))
+                .filter(organization_members::Column::UserId.ne(self.id))
+                .one(&txn)
+                .await?;
+            if other_owner.is_none() {
+                txn.rollback().await?;
+                return Err(AppError::conflict(
+                    "last_owner",
+                    "Transfer the ownership of the organizations before deleting the account",
+                ));
+            }
         }
 
-        let viewers = users::Entity::find()
-            .filter(
-                users::Column::Id.in_subquery(
-                    Query::select()
-                        .column(users_visible_templates::Column::UserId)
-                        .from(users_visible_templates::Entity)
-                        .and_where(users_visible_templates::Column::TemplateId.eq(template_id))
-                        .to_owned(),
-                ),
-            )
-            .all(db)
+        let template_ids: Vec<i32> = templates::Entity::find()
+            .select_only()
+            .column(templates::Column::Id)
+            .filter(templates::Column::UserId.eq(self.id))
+            .into_tuple()
+            .all(&txn)
             .await?;
-        Ok(Some(viewers))
+
+        // merges of other users from the deleted templates go with them
+        let mut merges = Condition::any().add(merge_jobs::Column::UserId.eq(self.id));
+        if matches!(templates, TemplatesDisposal::Delete) {
+            merges = merges.add(merge_jobs::Column::TemplateId.is_in(template_ids.clone()));
+        }
+        let merges = merge_jobs::Entity::find().filter(merges).all(&txn).await?;
+
+        match templates {
+            TemplatesDisposal::Transfer { transfer_to } => {
+                let recipient = users::Entity::find()
+                    .filter(users::Column::Email.eq(transfer_to))
+                    .filter(users::Column::Id.ne(self.id))
+                    .filter(users::Column::EmailVerifiedAt.is_not_null())
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::validation(
+                            "invalid_references",
+                            "The user to transfer the templates to is not found",
+                            vec![FieldError::new("transferTo", "user is not found")],
+                        )
+                    })?;
+
+                templates::Entity::update_many()
+                    .col_expr(templates::Column::UserId, Expr::value(recipient.id))
+                    .filter(templates::Column::UserId.eq(self.id))
+                    .exec(&txn)
+                    .await?;
+                categories::Entity::update_many()
+                    .col_expr(categories::Column::UserId, Expr::value(recipient.id))
+                    .filter(categories::Column::UserId.eq(self.id))
+                    .exec(&txn)
+                    .await?;
+            },
+            TemplatesDisposal::Delete => {
+                templates::Entity::delete_many()
+                    .filter(templates::Column::UserId.eq(self.id))
+                    .exec(&txn)
+                    .await?;
+            },
+        }
+
+        // sessions, drafts, categories, memberships and grants are deleted
+        // by the cascade
+        users::Entity::delete_by_id(self.id).exec(&txn).await?;
+
+        txn.commit().await?;
+
+        // if files are missing... they are already deleted, so ignore errors
+        if matches!(templates, TemplatesDisposal::Delete) {
+            for id in template_ids {
+                let _ = fs::remove_file(format!("./data/templates/{id}.docx")).await;
+                let _ = fs::remove_file(format!("./data/templates/{id}.dsl")).await;
+            }
+        }
+        for merge in merges {
+            let _ = fs::remove_file(merge.result_path()).await;
+        }
+
+        Ok(())
+    }
+
+    /// The step of the valid one-time code, unless the code was already used.
+    fn verify_totp(&self, code: &str, time: u64) -> Option<i64> {
+        let secret = self.totp_secret.as_deref()?;
+        let step = i64::try_from(totp::verify(secret, code, time)?).ok()?;
+        self.totp_last_step
+            .is_none_or(|last_step| step > last_step)
+            .then_some(step)
     }
 }
 
+/// Emails are unique, so the email can't be taken by another account.
+async fn check_email_is_free(db: &DatabaseConnection, email: &str) -> AppResult<()> {
+    let taken = users::Entity::find()
+        .filter(users::Column::Email.eq(email))
+        .one(db)
+        .await?;
+    if taken.is_some() {
+        return Err(AppError::conflict(
+            "email_taken",
+            "The email is used by another account",
+        ));
+    }
+    Ok(())
+}
+
+fn totp_enabled() -> AppError {
+    AppError::conflict(
+        "totp_enabled",
+        "The two-factor authentication is already enabled",
+    )
+}
+
 impl super::_entities::users::ActiveModel {
     /// Sets the email verification information for the user and
     /// updates it in the database.
     ///
     /// This method is used to record the timestamp when the email verification
-    /// was sent and generate a unique verification token for the user.
+    /// was sent and generate a unique verification token for the user. Only
+    /// the hash of the token is stored, the token itself is returned to be
+    /// mailed.
     ///
     /// # Errors
     ///
@@ -294,10 +679,11 @@ impl super::_entities::users::ActiveModel {
     pub async fn set_email_verification_sent(
         mut self,
         db: &DatabaseConnection,
-    ) -> ModelResult<Model> {
+    ) -> ModelResult<(Model, String)> {
+        let token = Uuid::new_v4().to_string();
         self.email_verification_sent_at = ActiveValue::set(Some(Local::now().into()));
-        self.email_verification_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
-        Ok(self.update(db).await?)
+        self.email_verification_token = ActiveValue::Set(Some(hash_token(&token)));
+        Ok((self.update(db).await?, token))
     }
 
     /// Sets the information for a reset password request,
@@ -305,16 +691,31 @@ impl super::_entities::users::ActiveModel {
     /// database.
     ///
     /// This method records the timestamp when the reset password token is sent
-    /// and generates a unique token for the user.
-    ///
-    /// # Arguments
+    /// and generates a unique token for the user. Like the verification token,
+    /// only its hash is stored.
     ///
     /// # Errors
     ///
     /// when has DB query error
-    pub async fn set_forgot_password_sent(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+    pub async fn set_forgot_password_sent(
+        mut self,
+        db: &DatabaseConnection,
+    ) -> ModelResult<(Model, String)> {
+        let token = Uuid::new_v4().to_string();
         self.reset_sent_at = ActiveValue::set(Some(Local::now().into()));
-        self.reset_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
+        self.reset_token = ActiveValue::Set(Some(hash_token(&token)));
+        Ok((self.update(db).await?, token))
+    }
+
+    /// Invalidates the outstanding reset token, e.g. after the user logged
+    /// in with the password.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn clear_reset_token(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.reset_token = ActiveValue::Set(None);
+        self.reset_sent_at = ActiveValue::Set(None);
         Ok(self.update(db).await?)
     }
 
@@ -322,13 +723,56 @@ impl super::_entities::users::ActiveModel {
     /// email and updates it in the database.
     ///
     /// This method sets the timestamp when the user successfully verifies their
-    /// email.
+    /// email. The token can't be used again.
     ///
     /// # Errors
     ///
     /// when has DB query error
     pub async fn verified(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
         self.email_verified_at = ActiveValue::set(Some(Local::now().into()));
+        self.email_verification_token = ActiveValue::Set(None);
+        Ok(self.update(db).await?)
+    }
+
+    /// Changes the email to the verified pending one, invitations to the new
+    /// email can be accepted then.
+    ///
+    /// # Errors
+    ///
+    /// When the email was taken meanwhile or DB query error
+    pub async fn change_email(mut self, db: &DatabaseConnection) -> AppResult<Model> {
+        let Some(Some(email)) = self.pending_email.clone().take() else {
+            return Err(AppError::conflict(
+                "email_change_not_requested",
+                "The change of the email is not requested",
+            ));
+        };
+        check_email_is_free(db, &email).await?;
+
+        self.email = ActiveValue::set(email);
+        self.pending_email = ActiveValue::set(None);
+        self.email_verified_at = ActiveValue::set(Some(Local::now().into()));
+        self.email_verification_token = ActiveValue::Set(None);
+        Ok(self.update(db).await?)
+    }
+
+    /// Changes the name, it's validated like at the registration.
+    ///
+    /// # Errors
+    ///
+    /// When the name is invalid or DB query error
+    pub async fn set_name(mut self, db: &DatabaseConnection, name: &str) -> ModelResult<Model> {
+        self.name = ActiveValue::set(name.trim().to_string());
+        Ok(self.update(db).await?)
+    }
+
+    /// Changes the role of the user, which grants the permissions of the role.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_role(mut self, db: &DatabaseConnection, role: UserRole) -> ModelResult<Model> {
+        self.role = ActiveValue::set(role);
         Ok(self.update(db).await?)
     }
 
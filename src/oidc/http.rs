@@ -0,0 +1,131 @@
+//! Requests to the identity provider: the discovery document, the keys and
+//! the token exchange. The discovery document and the keys change rarely, so
+//! they are cached.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use reqwest::header;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::{AppError, AppResult};
+
+/// The provider is considered unavailable after that.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Of the connection alone, that is shorter, so a down provider fails fast.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest response, that is read from the provider.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// How long the cached documents are used.
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .user_agent(concat!("cicero/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("the TLS backend is compiled in")
+});
+
+/// A body of a document with the time, when it was fetched.
+type Cached = (Instant, Vec<u8>);
+
+/// Cached documents by their URLs.
+static CACHE: LazyLock<Mutex<HashMap<String, Cached>>> = LazyLock::new(Mutex::default);
+
+/// Fetches the JSON document, or takes it from the cache, if it was fetched
+/// recently.
+///
+/// # Errors
+///
+/// When the provider is not available or responds with an error
+pub async fn get_cached_json<T: DeserializeOwned>(url: &str) -> AppResult<T> {
+    let cached = CACHE
+        .lock()
+        .expect("the cache is not poisoned")
+        .get(url)
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+        .map(|(_, body)| body.clone());
+
+    match cached {
+        Some(body) => serde_json::from_slice(&body).map_err(AppError::internal),
+        None => get_json(url).await,
+    }
+}
+
+/// Fetches the JSON document and caches it for [`get_cached_json`].
+///
+/// # Errors
+///
+/// When the provider is not available or responds with an error
+pub async fn get_json<T: DeserializeOwned>(url: &str) -> AppResult<T> {
+    let request = CLIENT.get(url).header(header::ACCEPT, "application/json");
+    let (status, body) = send(request, url).await?;
+    if !status.is_success() {
+        return Err(AppError::internal(format!(
+            "GET {url} responded with {status}"
+        )));
+    }
+
+    let document = serde_json::from_slice(&body).map_err(AppError::internal)?;
+    CACHE
+        .lock()
+        .expect("the cache is not poisoned")
+        .insert(url.to_string(), (Instant::now(), body));
+    Ok(document)
+}
+
+/// Posts the form and returns the status with the response, that is
+/// deserialized from JSON even for errors, as OAuth error responses are JSON
+/// too.
+///
+/// # Errors
+///
+/// When the provider is not available or the response is not the JSON
+pub async fn post_form<F: Serialize, T: DeserializeOwned>(
+    url: &str,
+    form: &F,
+) -> AppResult<(StatusCode, T)> {
+    let request = CLIENT
+        .post(url)
+        .header(header::ACCEPT, "application/json")
+        .form(form);
+    let (status, body) = send(request, url).await?;
+    let response = serde_json::from_slice(&body)
+        .map_err(|err| AppError::internal(format!("POST {url} responded with {status}: {err}")))?;
+    Ok((status, response))
+}
+
+/// Sends the request and reads the body up to [`MAX_BODY_SIZE`].
+async fn send(request: reqwest::RequestBuilder, url: &str) -> AppResult<(StatusCode, Vec<u8>)> {
+    let too_large = || {
+        AppError::internal(format!(
+            "{url} responded with more than {MAX_BODY_SIZE} bytes"
+        ))
+    };
+
+    let mut response = request.send().await.map_err(AppError::internal)?;
+    let status = response.status();
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_BODY_SIZE as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(AppError::internal)? {
+        if body.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((status, body))
+}
@@ -0,0 +1,200 @@
+//! Single sign-on with OpenID Connect identity providers, e.g. the corporate
+//! IdP of a customer.
+//!
+//! The login is the authorization code flow with PKCE: the client is sent to
+//! [`Client::authorization_url`], the provider redirects back with a code,
+//! that is exchanged for the ID token by [`Client::exchange_code`]. The
+//! endpoints and the signing keys are taken from the discovery document of
+//! the issuer, both are cached for a while.
+
+mod http;
+
+use axum::http::StatusCode;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::errors::{AppError, AppResult};
+use crate::settings::OidcProvider;
+
+/// Endpoints of the provider from `/.well-known/openid-configuration`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Metadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Claims of the ID token, that identify the user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    /// The id of the user at the provider, that never changes.
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+}
+
+/// A random secret for the login, e.g. the state or the PKCE code verifier.
+#[must_use]
+pub fn generate_secret() -> String {
+    let mut secret = [0; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    URL_SAFE_NO_PAD.encode(secret)
+}
+
+/// The `S256` code challenge of the PKCE code verifier.
+#[must_use]
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// The configured provider with its discovered endpoints.
+pub struct Client<'a> {
+    provider: &'a OidcProvider,
+    metadata: Metadata,
+}
+
+impl<'a> Client<'a> {
+    /// Fetches the discovery document of the issuer, unless it's cached.
+    ///
+    /// # Errors
+    ///
+    /// When the provider is not available or is not the configured issuer
+    pub async fn discover(provider: &'a OidcProvider) -> AppResult<Self> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/')
+        );
+        let metadata: Metadata = http::get_cached_json(&url).await?;
+        if metadata.issuer.trim_end_matches('/') != provider.issuer.trim_end_matches('/') {
+            return Err(AppError::internal(format!(
+                "the discovery document of {} is of the issuer {}",
+                provider.issuer, metadata.issuer
+            )));
+        }
+        Ok(Self { provider, metadata })
+    }
+
+    /// Where the user logs in at the provider.
+    ///
+    /// # Errors
+    ///
+    /// When the authorization endpoint is not a valid URL
+    pub fn authorization_url(&self, state: &str, nonce: &str, verifier: &str) -> AppResult<String> {
+        let mut url =
+            Url::parse(&self.metadata.authorization_endpoint).map_err(AppError::internal)?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.provider.client_id)
+            .append_pair("redirect_uri", &self.provider.redirect_uri)
+            .append_pair("scope", &self.provider.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", &code_challenge(verifier))
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.into())
+    }
+
+    /// Exchanges the authorization code for the ID token and returns its
+    /// verified claims.
+    ///
+    /// # Errors
+    ///
+    /// When the code is rejected by the provider or the ID token is invalid
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        verifier: &str,
+        nonce: &str,
+    ) -> AppResult<IdTokenClaims> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.provider.redirect_uri.as_str()),
+            ("client_id", self.provider.client_id.as_str()),
+            ("code_verifier", verifier),
+        ];
+        if let Some(secret) = &self.provider.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let (status, response): (StatusCode, TokenResponse) =
+            http::post_form(&self.metadata.token_endpoint, &form).await?;
+        let id_token = match response.id_token {
+            Some(id_token) if status.is_success() => id_token,
+            _ => {
+                tracing::info!(
+                    status = status.as_u16(),
+                    error = response.error,
+                    "authorization code rejected"
+                );
+                return Err(AppError::unauthorized(
+                    "invalid_authorization_code",
+                    "The authorization code is invalid or expired",
+                ));
+            },
+        };
+
+        self.verify_id_token(&id_token, nonce).await
+    }
+
+    /// Checks the signature with the keys of the provider, the issuer, the
+    /// audience, the expiration and the nonce of the login.
+    async fn verify_id_token(&self, id_token: &str, nonce: &str) -> AppResult<IdTokenClaims> {
+        let invalid = || AppError::unauthorized("invalid_id_token", "The ID token is invalid");
+
+        let header = jsonwebtoken::decode_header(id_token).map_err(|_| invalid())?;
+        // the keys are public, so tokens signed with a shared secret are not
+        // accepted
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(invalid());
+        }
+
+        let mut keys: JwkSet = http::get_cached_json(&self.metadata.jwks_uri).await?;
+        if let Some(kid) = &header.kid {
+            // the provider may have rotated the keys since they were cached
+            if keys.find(kid).is_none() {
+                keys = http::get_json(&self.metadata.jwks_uri).await?;
+            }
+        }
+        let jwk = match &header.kid {
+            Some(kid) => keys.find(kid),
+            None => keys.keys.first(),
+        }
+        .ok_or_else(invalid)?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|_| invalid())?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.metadata.issuer]);
+        validation.set_audience(&[&self.provider.client_id]);
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|err| {
+                tracing::info!(error = err.to_string(), "invalid ID token");
+                invalid()
+            })?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid());
+        }
+        Ok(claims)
+    }
+}
@@ -0,0 +1,63 @@
+//! The OpenAPI document of the API for integrations. The routes of each
+//! controller are listed by its `ApiDoc`, the schemas are derived from the
+//! request and response types.
+
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::OpenApi as Document;
+use utoipa::{Modify, OpenApi};
+
+use crate::controllers::{api_tokens, auth, bundles, templates, user};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Cicero API"),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "auth", description = "Registration, login and sessions"),
+        (name = "user", description = "Account of the logged in user"),
+        (name = "api tokens", description = "Personal tokens for machine clients"),
+        (name = "templates", description = "Templates with their docx and DSL"),
+    )
+)]
+struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut Document) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "jwt",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some("Access token from the login"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "Personal API token, the scopes of the route are required",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// The document of the documented controllers.
+#[must_use]
+pub fn document() -> Document {
+    let mut document = ApiDoc::openapi();
+    document.merge(auth::ApiDoc::openapi());
+    document.merge(user::ApiDoc::openapi());
+    document.merge(api_tokens::ApiDoc::openapi());
+    document.merge(templates::ApiDoc::openapi());
+    document.merge(bundles::ApiDoc::openapi());
+    document
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use super::{Hit, Storage};
+use crate::errors::AppResult;
+
+/// Expired counters are removed, once there are more counters than this.
+const MAX_COUNTERS: usize = 10_000;
+
+/// Counters in memory, for a single instance.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    counters: Mutex<HashMap<String, (u64, Instant)>>,
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn increment(&self, key: &str, window: Duration) -> AppResult<Hit> {
+        let now = Instant::now();
+        let mut counters = self
+            .counters
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if counters.len() > MAX_COUNTERS {
+            counters.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+
+        let (count, expires_at) = counters
+            .entry(key.to_string())
+            .and_modify(|counter| {
+                if counter.1 <= now {
+                    *counter = (0, now + window);
+                }
+            })
+            .or_insert((0, now + window));
+        *count += 1;
+
+        Ok(Hit {
+            count: *count,
+            expires_in: *expires_at - now,
+        })
+    }
+
+    async fn get(&self, key: &str) -> AppResult<Option<Hit>> {
+        let now = Instant::now();
+        let counters = self
+            .counters
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        Ok(counters
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(count, expires_at)| {
+                Hit {
+                    count: *count,
+                    expires_in: *expires_at - now,
+                }
+            }))
+    }
+
+    async fn remove(&self, key: &str) -> AppResult<()> {
+        self.counters
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .remove(key);
+        Ok(())
+    }
+}
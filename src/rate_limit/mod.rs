@@ -0,0 +1,178 @@
+//! Throttling of requests, e.g. against brute force of passwords, and the
+//! lockout of accounts after failed logins.
+//!
+//! Counters are kept in a [`Storage`]: in memory of the instance or in Redis,
+//! so that the instances behind a load balancer share them. Behind a load
+//! balancer its address must be in `settings.rate_limit.trusted_proxies`, or
+//! all clients share the limits by IP.
+
+mod memory;
+mod redis;
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ipnet::IpNet;
+
+pub use self::memory::MemoryStorage;
+pub use self::redis::RedisStorage;
+use crate::errors::{AppError, AppResult};
+use crate::settings::{Limit, LockoutSettings, RateLimitStorage, RouteLimits, Settings};
+
+/// A counter in the storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub count: u64,
+    /// Time until the counter is reset.
+    pub expires_in: Duration,
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Increments the counter of the key. The counter expires after the
+    /// window since its first increment.
+    async fn increment(&self, key: &str, window: Duration) -> AppResult<Hit>;
+
+    /// The counter of the key, unless it's expired.
+    async fn get(&self, key: &str) -> AppResult<Option<Hit>>;
+
+    async fn remove(&self, key: &str) -> AppResult<()>;
+}
+
+pub struct RateLimiter {
+    storage: Box<dyn Storage>,
+    routes: BTreeMap<String, RouteLimits>,
+    lockout: LockoutSettings,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl RateLimiter {
+    #[must_use]
+    pub fn new(
+        storage: Box<dyn Storage>,
+        routes: BTreeMap<String, RouteLimits>,
+        lockout: LockoutSettings,
+        trusted_proxies: Vec<IpNet>,
+    ) -> Self {
+        Self {
+            storage,
+            routes,
+            lockout,
+            trusted_proxies,
+        }
+    }
+
+    /// Creates the limiter with the storage of the settings.
+    ///
+    /// # Errors
+    ///
+    /// When Redis is not configured or can't be connected
+    pub async fn from_settings(settings: &Settings) -> AppResult<Self> {
+        let storage: Box<dyn Storage> = match settings.rate_limit.storage {
+            RateLimitStorage::Memory => Box::new(MemoryStorage::default()),
+            RateLimitStorage::Redis => {
+                let uri = settings.rate_limit.redis_uri.as_deref().ok_or_else(|| {
+                    AppError::internal("`settings.rate_limit.redis_uri` is required for Redis")
+                })?;
+                Box::new(RedisStorage::connect(uri).await?)
+            },
+        };
+        Ok(Self::new(
+            storage,
+            settings.rate_limit.routes.clone(),
+            settings.auth.lockout.clone(),
+            settings.rate_limit.trusted_proxies.clone(),
+        ))
+    }
+
+    /// The limits of the route by its path.
+    #[must_use]
+    pub fn route(&self, path: &str) -> Option<&RouteLimits> {
+        self.routes.get(path)
+    }
+
+    /// Whether the address is of a proxy, whose forwarding headers are
+    /// trusted.
+    #[must_use]
+    pub fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Counts the request. Returns the time to wait, if the limit is
+    /// exceeded.
+    ///
+    /// # Errors
+    ///
+    /// When the storage fails
+    pub async fn hit(&self, key: &str, limit: &Limit) -> AppResult<Option<Duration>> {
+        let hit = self
+            .storage
+            .increment(&format!("hits:{key}"), limit.window())
+            .await?;
+        Ok((hit.count > limit.limit).then_some(hit.expires_in))
+    }
+
+    /// How long the account is locked for, if it is.
+    ///
+    /// # Errors
+    ///
+    /// When the storage fails
+    pub async fn locked_for(&self, account: &str) -> AppResult<Option<Duration>> {
+        let lock = self.storage.get(&lock_key(account)).await?;
+        Ok(lock.map(|lock| lock.expires_in))
+    }
+
+    /// Counts the failed login. Once there are too many of them, the account
+    /// is locked, for longer with each next failure.
+    ///
+    /// # Errors
+    ///
+    /// When the storage fails
+    pub async fn record_failure(&self, account: &str) -> AppResult<()> {
+        let failures = self
+            .storage
+            .increment(
+                &failures_key(account),
+                Duration::from_secs(self.lockout.window),
+            )
+            .await?;
+        if let Some(delay) = self.lockout.lock_after(failures.count) {
+            tracing::info!(
+                account,
+                failures = failures.count,
+                delay = delay.as_secs(),
+                "account locked"
+            );
+            let key = lock_key(account);
+            self.storage.remove(&key).await?;
+            self.storage.increment(&key, delay).await?;
+        }
+        Ok(())
+    }
+
+    /// Forgets the failed logins after the successful one.
+    ///
+    /// # Errors
+    ///
+    /// When the storage fails
+    pub async fn reset_failures(&self, account: &str) -> AppResult<()> {
+        self.storage.remove(&failures_key(account)).await
+    }
+}
+
+/// Emails are compared case-insensitively, so the limits can't be bypassed
+/// by changing the case.
+#[must_use]
+pub fn normalize_account(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn failures_key(account: &str) -> String {
+    format!("failures:{}", normalize_account(account))
+}
+
+fn lock_key(account: &str) -> String {
+    format!("lock:{}", normalize_account(account))
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use redis::{Client, Script};
+
+use super::{Hit, Storage};
+use crate::errors::{AppError, AppResult};
+
+/// Keys are prefixed, so the Redis can be shared with other apps.
+const KEY_PREFIX: &str = "cicero:rate_limit:";
+
+/// The window starts with the first increment, so the expiration is only set
+/// for the new counter. Returns the counter and its TTL in milliseconds.
+const INCREMENT: &str = r"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('PEXPIRE', KEYS[1], ARGV[1])
+end
+return {count, redis.call('PTTL', KEYS[1])}
+";
+
+/// Counters in Redis, that are shared between the instances.
+#[derive(Clone)]
+pub struct RedisStorage {
+    connection: MultiplexedConnection,
+}
+
+impl RedisStorage {
+    /// Connects to Redis by the URI, e.g. `redis://127.0.0.1/`.
+    ///
+    /// # Errors
+    ///
+    /// When the URI is invalid or Redis is not available
+    pub async fn connect(uri: &str) -> AppResult<Self> {
+        let client = Client::open(uri).map_err(AppError::internal)?;
+        let connection = client
+            .get_multiplexed_tokio_connection()
+            .await
+            .map_err(AppError::internal)?;
+        Ok(Self { connection })
+    }
+}
+
+fn hit(count: u64, ttl: i64) -> Hit {
+    Hit {
+        count,
+        expires_in: Duration::from_millis(u64::try_from(ttl).unwrap_or_default()),
+    }
+}
+
+#[async_trait]
+impl Storage for RedisStorage {
+    async fn increment(&self, key: &str, window: Duration) -> AppResult<Hit> {
+        let (count, ttl): (u64, i64) = Script::new(INCREMENT)
+            .key(format!("{KEY_PREFIX}{key}"))
+            .arg(u64::try_from(window.as_millis()).unwrap_or(u64::MAX))
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(AppError::internal)?;
+        Ok(hit(count, ttl))
+    }
+
+    async fn get(&self, key: &str) -> AppResult<Option<Hit>> {
+        let key = format!("{KEY_PREFIX}{key}");
+        let (count, ttl): (Option<u64>, i64) = redis::pipe()
+            .get(&key)
+            .pttl(&key)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(AppError::internal)?;
+        Ok(count.map(|count| hit(count, ttl)))
+    }
+
+    async fn remove(&self, key: &str) -> AppResult<()> {
+        redis::cmd("DEL")
+            .arg(format!("{KEY_PREFIX}{key}"))
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .map_err(AppError::internal)
+    }
+}
@@ -0,0 +1,272 @@
+//! Application specific settings from the `settings` section of the config.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use ipnet::IpNet;
+use loco_rs::config::Config;
+use serde::Deserialize;
+
+use crate::errors::{AppError, AppResult};
+
+/// The longest TTL, 100 years. Longer ones are cut to it, so adding a TTL to
+/// a date can't overflow.
+const MAX_TTL: u64 = 100 * 365 * 24 * 60 * 60;
+
+/// A TTL of the config in seconds.
+fn ttl(seconds: u64) -> chrono::Duration {
+    chrono::Duration::seconds(seconds.min(MAX_TTL).cast_signed())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    #[serde(default)]
+    pub oidc: OidcSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthSettings {
+    /// How long the email verification token is valid, in seconds.
+    #[serde(default = "AuthSettings::default_verification_token_ttl")]
+    pub verification_token_ttl: u64,
+    /// How long the password reset token is valid, in seconds.
+    #[serde(default = "AuthSettings::default_reset_token_ttl")]
+    pub reset_token_ttl: u64,
+    /// How long the session can be refreshed since the last refresh, in
+    /// seconds. Access tokens are short-lived, see `auth.jwt.expiration`.
+    #[serde(default = "AuthSettings::default_refresh_token_ttl")]
+    pub refresh_token_ttl: u64,
+    /// Minimal time of the responses, that could tell by their timing,
+    /// whether the email is registered, in milliseconds.
+    #[serde(default = "AuthSettings::default_min_response_time")]
+    pub min_response_time: u64,
+    #[serde(default)]
+    pub lockout: LockoutSettings,
+}
+
+impl AuthSettings {
+    const fn default_verification_token_ttl() -> u64 {
+        // 2 days
+        172_800
+    }
+
+    const fn default_reset_token_ttl() -> u64 {
+        // 1 hour
+        3600
+    }
+
+    const fn default_refresh_token_ttl() -> u64 {
+        // 30 days
+        2_592_000
+    }
+
+    const fn default_min_response_time() -> u64 {
+        500
+    }
+
+    #[must_use]
+    pub fn verification_token_ttl(&self) -> chrono::Duration {
+        ttl(self.verification_token_ttl)
+    }
+
+    #[must_use]
+    pub fn reset_token_ttl(&self) -> chrono::Duration {
+        ttl(self.reset_token_ttl)
+    }
+
+    #[must_use]
+    pub fn refresh_token_ttl(&self) -> chrono::Duration {
+        ttl(self.refresh_token_ttl)
+    }
+
+    #[must_use]
+    pub const fn min_response_time(&self) -> Duration {
+        Duration::from_millis(self.min_response_time)
+    }
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            verification_token_ttl: Self::default_verification_token_ttl(),
+            reset_token_ttl: Self::default_reset_token_ttl(),
+            refresh_token_ttl: Self::default_refresh_token_ttl(),
+            min_response_time: Self::default_min_response_time(),
+            lockout: LockoutSettings::default(),
+        }
+    }
+}
+
+/// Progressive lockout of accounts after failed logins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockoutSettings {
+    /// Failed logins in a row, after that the account is locked.
+    pub threshold: u64,
+    /// The first lock in seconds, every next failure doubles it.
+    pub delay: u64,
+    /// The longest lock in seconds.
+    pub max_delay: u64,
+    /// Seconds since the first failure, after that failures are forgotten.
+    pub window: u64,
+}
+
+impl LockoutSettings {
+    /// How long the account is locked after the failure, if at all.
+    #[must_use]
+    pub fn lock_after(&self, failures: u64) -> Option<Duration> {
+        let extra = failures.checked_sub(self.threshold)?;
+        let delay = self
+            .delay
+            .saturating_mul(2u64.saturating_pow(u32::try_from(extra).unwrap_or(u32::MAX)));
+        Some(Duration::from_secs(delay.min(self.max_delay)))
+    }
+}
+
+impl Default for LockoutSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            delay: 30,
+            max_delay: 3600,
+            window: 3600,
+        }
+    }
+}
+
+/// Limits of requests to the routes, e.g. against brute force of passwords.
+#[derive(Debug, Default, Deserialize)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    pub storage: RateLimitStorage,
+    /// URI of Redis for the `redis` storage.
+    pub redis_uri: Option<String>,
+    /// Limits by the path of the route, e.g. `/api/auth/login`.
+    #[serde(default)]
+    pub routes: BTreeMap<String, RouteLimits>,
+    /// Networks of the proxies in front of the app, e.g. the load balancer.
+    /// Requests from them are limited by the client address, that they pass
+    /// in `Forwarded` or `X-Forwarded-For`.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+/// Where the counters of requests are kept.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStorage {
+    /// In memory of the instance.
+    #[default]
+    Memory,
+    /// In Redis, so the instances share them.
+    Redis,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct RouteLimits {
+    /// Requests from the same IP address.
+    pub per_ip: Option<Limit>,
+    /// Requests with the same `email` in the JSON body.
+    pub per_account: Option<Limit>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Limit {
+    /// Requests, that are allowed in the window.
+    pub limit: u64,
+    /// The window in seconds.
+    pub window: u64,
+}
+
+impl Limit {
+    #[must_use]
+    pub const fn window(&self) -> Duration {
+        Duration::from_secs(self.window)
+    }
+}
+
+/// Single sign-on with OpenID Connect identity providers.
+#[derive(Debug, Deserialize)]
+pub struct OidcSettings {
+    /// Providers by the name in the routes, e.g. `corp` of
+    /// `/api/auth/oidc/corp/authorize`.
+    #[serde(default)]
+    pub providers: BTreeMap<String, OidcProvider>,
+    /// How long the login at the provider can take, in seconds.
+    #[serde(default = "OidcSettings::default_login_ttl")]
+    pub login_ttl: u64,
+}
+
+impl OidcSettings {
+    const fn default_login_ttl() -> u64 {
+        // 10 minutes
+        600
+    }
+
+    #[must_use]
+    pub fn login_ttl(&self) -> chrono::Duration {
+        ttl(self.login_ttl)
+    }
+}
+
+impl Default for OidcSettings {
+    fn default() -> Self {
+        Self {
+            providers: BTreeMap::new(),
+            login_ttl: Self::default_login_ttl(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProvider {
+    /// The issuer, its discovery document is at
+    /// `{issuer}/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    /// Only for confidential clients, public clients rely on PKCE.
+    pub client_secret: Option<String>,
+    /// Where the provider redirects back to, usually a page of the frontend,
+    /// that passes the `code` and the `state` to the callback.
+    pub redirect_uri: String,
+    #[serde(default = "OidcProvider::default_scopes")]
+    pub scopes: String,
+    /// Accounts with verified emails of these domains are created on the
+    /// first login, existing accounts of the emails are linked.
+    #[serde(default)]
+    pub auto_provision_domains: Vec<String>,
+}
+
+impl OidcProvider {
+    fn default_scopes() -> String {
+        "openid email profile".to_string()
+    }
+
+    /// Whether the provider is trusted with the accounts of the email.
+    #[must_use]
+    pub fn can_provision(&self, email: &str) -> bool {
+        email.rsplit_once('@').is_some_and(|(_, domain)| {
+            self.auto_provision_domains
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+        })
+    }
+}
+
+impl Settings {
+    /// Reads the settings, falling back to the defaults when the section is
+    /// missing.
+    ///
+    /// # Errors
+    ///
+    /// When the section has invalid values
+    pub fn from_config(config: &Config) -> AppResult<Self> {
+        config.settings.as_ref().map_or_else(
+            || Ok(Self::default()),
+            |settings| Self::deserialize(settings).map_err(AppError::internal),
+        )
+    }
+}
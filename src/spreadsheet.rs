@@ -0,0 +1,175 @@
+//! Reading of tables, that are uploaded as CSV or XLSX files.
+
+use std::io::Cursor;
+
+use calamine::{open_workbook_from_rs, Data, Reader, Xlsx};
+
+/// Date format for date cells of XLSX files.
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Xlsx,
+}
+
+impl Format {
+    /// Detects the format by the extension of the file name.
+    #[must_use]
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+
+        match extension.to_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "xlsx" => Some(Self::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpreadsheetError {
+    #[error("invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("invalid XLSX: {0}")]
+    Xlsx(#[from] calamine::XlsxError),
+    #[error("the file has no sheets")]
+    NoSheets,
+    #[error("the file has no header row")]
+    NoHeader,
+}
+
+/// A table with a header row. All cells are converted to text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Numbers of the rows in the file, starting with 1. Blank rows are
+    /// skipped, so they tell the rows apart in messages to the user.
+    pub row_numbers: Vec<usize>,
+}
+
+impl Table {
+    /// # Errors
+    ///
+    /// When the file can't be parsed or has no header row
+    pub fn parse(bytes: &[u8], format: Format) -> Result<Self, SpreadsheetError> {
+        let mut rows = match format {
+            Format::Csv => read_csv(bytes)?,
+            Format::Xlsx => read_xlsx(bytes)?,
+        }
+        .into_iter()
+        .filter(|(_, row)| row.iter().any(|cell| !cell.trim().is_empty()));
+
+        let (_, headers) = rows.next().ok_or(SpreadsheetError::NoHeader)?;
+        let headers = headers
+            .into_iter()
+            .map(|header| header.trim().to_string())
+            .collect();
+
+        let (row_numbers, rows) = rows.unzip();
+
+        Ok(Self {
+            headers,
+            rows,
+            row_numbers,
+        })
+    }
+
+    /// Index of the column with the header.
+    #[must_use]
+    pub fn column(&self, header: &str) -> Option<usize> {
+        self.headers.iter().position(|name| name == header)
+    }
+}
+
+/// Rows of the file with their numbers.
+type Rows = Vec<(usize, Vec<String>)>;
+
+fn read_csv(bytes: &[u8]) -> Result<Rows, SpreadsheetError> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+
+    // Excel with the Russian locale exports CSV with semicolons
+    let first_line = bytes
+        .split(|&byte| byte == b'\n')
+        .find(|line| !line.trim_ascii().is_empty())
+        .unwrap_or_default();
+    let count = |delimiter| first_line.iter().filter(|&&byte| byte == delimiter).count();
+    let delimiter = if count(b';') > count(b',') {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(bytes);
+
+    reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            let record = record?;
+            let number = record
+                .position()
+                .and_then(|position| usize::try_from(position.byte()).ok())
+                .map_or(i + 1, |offset| line_number(bytes, offset));
+            Ok((number, record.iter().map(ToString::to_string).collect()))
+        })
+        .collect()
+}
+
+/// Line of the record at the offset. The reader skips blank lines, but the
+/// offset of the next record points at them.
+fn line_number(bytes: &[u8], offset: usize) -> usize {
+    let (before, after) = bytes.split_at(offset.min(bytes.len()));
+    let blank = after
+        .iter()
+        .take_while(|&&byte| matches!(byte, b'\r' | b'\n'))
+        .filter(|&&byte| byte == b'\n');
+
+    before
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .chain(blank)
+        .count()
+        + 1
+}
+
+fn read_xlsx(bytes: &[u8]) -> Result<Rows, SpreadsheetError> {
+    let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes))?;
+
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or(SpreadsheetError::NoSheets)??;
+
+    // the range starts at the first used row of the sheet
+    let first = range
+        .start()
+        .map_or(0, |(row, _)| usize::try_from(row).unwrap_or_default());
+
+    Ok(range
+        .rows()
+        .enumerate()
+        .map(|(i, row)| (first + i + 1, row.iter().map(cell_to_string).collect()))
+        .collect())
+}
+
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        #[allow(clippy::cast_possible_truncation)]
+        Data::Float(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+            (*number as i64).to_string()
+        },
+        Data::DateTime(date) => {
+            date.as_datetime().map_or_else(
+                || cell.to_string(),
+                |date| date.format(DATE_FORMAT).to_string(),
+            )
+        },
+        cell => cell.to_string(),
+    }
+}
@@ -0,0 +1,50 @@
+//! Writes a bundle of templates to a file, e.g. to import it on another
+//! instance with [`super::import_templates`].
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo run task export_templates email:admin@example.com ids:1,2 path:templates.zip
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::models::{bundles, users};
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ExportTemplates;
+#[async_trait]
+impl Task for ExportTemplates {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "export_templates".to_string(),
+            detail: "Export templates visible to a user into a bundle file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let email = vars.cli_arg("email")?;
+        let ids = vars
+            .cli_arg("ids")?
+            .split(',')
+            .map(|id| {
+                id.trim()
+                    .parse()
+                    .map_err(|_| Error::string(&format!("invalid template ID `{id}`")))
+            })
+            .collect::<Result<Vec<i32>>>()?;
+        let path = vars.cli_arg("path")?;
+
+        let user = users::Model::find_by_email(&app_context.db, email).await?;
+        let bundle = bundles::export(&app_context.db, &ids, &user).await?;
+        std::fs::write(path, bundle.write().map_err(Error::wrap)?)?;
+
+        tracing::info!(
+            path,
+            templates = bundle.templates.len(),
+            "templates exported"
+        );
+
+        Ok(())
+    }
+}
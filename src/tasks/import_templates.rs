@@ -0,0 +1,56 @@
+//! Imports a bundle file as templates of a user. `conflicts` is `skip` (the
+//! default), `overwrite` or `rename`, see [`ConflictPolicy`].
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo run task import_templates email:admin@example.com path:templates.zip conflicts:rename
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::bundle::Bundle;
+use crate::models::bundles::{self, ConflictPolicy};
+use crate::models::users;
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ImportTemplates;
+#[async_trait]
+impl Task for ImportTemplates {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "import_templates".to_string(),
+            detail: "Import a bundle file as templates of a user".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let email = vars.cli_arg("email")?;
+        let path = vars.cli_arg("path")?;
+        let conflicts = match vars.cli.get("conflicts") {
+            Some(conflicts) => {
+                serde_json::from_value(serde_json::Value::String(conflicts.clone()))
+                    .map_err(|_| Error::string(&format!("unknown conflict policy `{conflicts}`")))?
+            },
+            None => ConflictPolicy::default(),
+        };
+
+        let user = users::Model::find_by_email(&app_context.db, email).await?;
+        let bundle = Bundle::read(&std::fs::read(path)?).map_err(Error::wrap)?;
+        let report = bundles::import(&app_context.db, &bundle, &user, conflicts).await?;
+
+        for (template, status) in &report.templates {
+            tracing::info!(
+                id = template.id,
+                name = template.name,
+                ?status,
+                "template imported"
+            );
+        }
+        for category in &report.created_categories {
+            tracing::info!(name = category.name, "category created");
+        }
+
+        Ok(())
+    }
+}
@@ -1 +1,5 @@
+pub mod export_templates;
+pub mod import_templates;
+pub mod recompile_templates;
 pub mod seed;
+pub mod set_role;
@@ -0,0 +1,54 @@
+//! Compiles the DSL of every template again and caches the types, e.g. after
+//! an upgrade of the compiler. Templates, that don't compile anymore, are
+//! reported and keep their previous types.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo run task recompile_templates
+//! ```
+
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+
+use crate::models::_entities::templates;
+
+#[allow(clippy::module_name_repetitions)]
+pub struct RecompileTemplates;
+#[async_trait]
+impl Task for RecompileTemplates {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "recompile_templates".to_string(),
+            detail: "Compile the DSL of every template again and report failures".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let all = templates::Entity::find()
+            .order_by_asc(templates::Column::Id)
+            .all(&app_context.db)
+            .await?;
+        let total = all.len();
+
+        let mut failed = Vec::new();
+        for template in all {
+            let (id, name) = (template.id, template.name.clone());
+            if let Err(err) = template.recompile_dsl(&app_context.db).await {
+                tracing::error!(id, name, error = %err, "template doesn't compile");
+                failed.push(id);
+            }
+        }
+
+        tracing::info!(total, failed = failed.len(), "templates recompiled");
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::string(&format!(
+                "{} of {total} templates don't compile: {failed:?}",
+                failed.len()
+            )))
+        }
+    }
+}
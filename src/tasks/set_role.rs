@@ -0,0 +1,43 @@
+//! Changes the role of a user, e.g. to appoint the first admin, who can
+//! manage roles through the API afterwards.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo run task set_role email:admin@example.com role:admin
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::models::roles::UserRole;
+use crate::models::users;
+
+#[allow(clippy::module_name_repetitions)]
+pub struct SetRole;
+#[async_trait]
+impl Task for SetRole {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "set_role".to_string(),
+            detail: "Change the role of a user: user, editor, moderator or admin".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let email = vars.cli_arg("email")?;
+        let role = vars.cli_arg("role")?;
+
+        let role: UserRole = serde_json::from_value(serde_json::Value::String(role.clone()))
+            .map_err(|_| Error::string(&format!("unknown role `{role}`")))?;
+
+        let user = users::Model::find_by_email(&app_context.db, email).await?;
+        let user = user
+            .into_active_model()
+            .set_role(&app_context.db, role)
+            .await?;
+
+        tracing::info!(pid = user.pid.to_string(), role = ?user.role, "role changed");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Time-based one-time passwords (RFC 6238) for the two-factor
+//! authentication with authenticator apps.
+//!
+//! Codes have 6 digits and change every 30 seconds, the secret is HMAC-SHA1
+//! key encoded with base32, as authenticator apps expect. The time is passed
+//! explicitly, so the codes can be checked with a fixed clock.
+
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::RngCore;
+use sha1::Sha1;
+
+/// Seconds, that a code is valid for.
+pub const STEP: u64 = 30;
+
+const DIGITS: u32 = 6;
+const SECRET_LENGTH: usize = 20;
+/// Codes of the neighbouring steps are accepted too, since the clock of the
+/// phone can be a little off.
+const SKEW: u64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The current time in seconds since the Unix epoch.
+#[must_use]
+pub fn now() -> u64 {
+    u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default()
+}
+
+/// Generates a random secret, encoded with base32.
+#[must_use]
+pub fn generate_secret() -> String {
+    let mut secret = [0; SECRET_LENGTH];
+    rand::thread_rng().fill_bytes(&mut secret);
+    encode_base32(&secret)
+}
+
+/// URI of the secret for authenticator apps, that the client shows as a
+/// QR code.
+#[must_use]
+pub fn provisioning_uri(secret: &str, issuer: &str, account: &str) -> String {
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC);
+    let account = utf8_percent_encode(account, NON_ALPHANUMERIC);
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP}"
+    )
+}
+
+/// The code at the time in seconds since the Unix epoch, `None` if the secret
+/// is not valid base32.
+#[must_use]
+pub fn code(secret: &str, time: u64) -> Option<String> {
+    let key = decode_base32(secret)?;
+    Some(code_at_step(&key, time / STEP))
+}
+
+/// Finds the step of the time, that the code belongs to. The step is
+/// remembered, so the same code can't be used twice.
+#[must_use]
+pub fn verify(secret: &str, code: &str, time: u64) -> Option<u64> {
+    let key = decode_base32(secret)?;
+    let step = time / STEP;
+    (step.saturating_sub(SKEW)..=step + SKEW).find(|&step| code_at_step(&key, step) == code.trim())
+}
+
+fn code_at_step(key: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // dynamic truncation of RFC 4226
+    let offset = usize::from(hash[hash.len() - 1] & 0x0F);
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7F,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(char::from(
+                BASE32_ALPHABET[(buffer >> bits) as usize & 0x1F],
+            ));
+        }
+    }
+    if bits > 0 {
+        encoded.push(char::from(
+            BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 0x1F],
+        ));
+    }
+    encoded
+}
+
+fn decode_base32(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for char in encoded.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&letter| letter == char.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | u32::try_from(value).ok()?;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    (!bytes.is_empty()).then_some(bytes)
+}
@@ -0,0 +1,54 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::api_tokens::{self, ApiScope};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = ApiTokenResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub pid: String,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub last_used_at: Option<DateTimeWithTimeZone>,
+}
+
+impl Response {
+    #[must_use]
+    pub fn new(api_token: &api_tokens::Model) -> Self {
+        Self {
+            pid: api_token.pid.to_string(),
+            name: api_token.name.clone(),
+            scopes: api_token.scopes(),
+            created_at: api_token.created_at,
+            expires_at: api_token.expires_at,
+            last_used_at: api_token.last_used_at,
+        }
+    }
+}
+
+/// The issued token, that is shown only once.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = ApiTokenWithSecretResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct WithSecretResponse {
+    #[serde(flatten)]
+    pub api_token: Response,
+    pub token: String,
+}
+
+impl WithSecretResponse {
+    #[must_use]
+    pub fn new(api_token: &api_tokens::Model, token: String) -> Self {
+        Self {
+            api_token: Response::new(api_token),
+            token,
+        }
+    }
+}
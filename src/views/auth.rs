@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::models::_entities::users;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub pid: String,
     pub name: String,
     pub email: String,
@@ -14,9 +16,10 @@ pub struct LoginResponse {
 
 impl LoginResponse {
     #[must_use]
-    pub fn new(user: &users::Model, token: &str) -> Self {
+    pub fn new(user: &users::Model, token: &str, refresh_token: &str) -> Self {
         Self {
             token: token.to_string(),
+            refresh_token: refresh_token.to_string(),
             pid: user.pid.to_string(),
             name: user.name.clone(),
             email: user.email.clone(),
@@ -24,3 +27,12 @@ impl LoginResponse {
         }
     }
 }
+
+/// Where the client sends the user to log in at the identity provider.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct OidcAuthorizeResponse {
+    pub url: String,
+    /// Comes back with the redirect of the provider and is passed to the
+    /// callback.
+    pub state: String,
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::bundles::{ImportReport, ImportStatus};
+use crate::views::category::Response as CategoryResponse;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResponse {
+    pub templates: Vec<ImportedTemplateResponse>,
+    pub created_categories: Vec<CategoryResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedTemplateResponse {
+    /// The created or the existing template.
+    pub id: i32,
+    pub name: String,
+    pub status: ImportStatus,
+}
+
+impl ImportResponse {
+    #[must_use]
+    pub fn new(report: &ImportReport) -> Self {
+        Self {
+            templates: report
+                .templates
+                .iter()
+                .map(|(template, status)| {
+                    ImportedTemplateResponse {
+                        id: template.id,
+                        name: template.name.clone(),
+                        status: *status,
+                    }
+                })
+                .collect(),
+            created_categories: report
+                .created_categories
+                .iter()
+                .map(CategoryResponse::new)
+                .collect(),
+        }
+    }
+}
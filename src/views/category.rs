@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::models::_entities::categories;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = CategoryResponse)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
     pub id: i32,
@@ -18,3 +20,46 @@ impl Response {
         }
     }
 }
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = CategoryDetailsResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailsResponse {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+impl DetailsResponse {
+    #[must_use]
+    pub fn new(category: &categories::Model) -> Self {
+        Self {
+            id: category.id,
+            name: category.name.clone(),
+            parent_id: category.parent_id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithCountResponse {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+    /// Number of visible templates directly in this category, not counting
+    /// subcategories.
+    pub template_count: u64,
+}
+
+impl WithCountResponse {
+    #[must_use]
+    pub fn new(category: &categories::Model, template_count: u64) -> Self {
+        Self {
+            id: category.id,
+            name: category.name.clone(),
+            parent_id: category.parent_id,
+            template_count,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::models::{documents, templates};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = DocumentResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailsResponse {
+    pub id: i32,
+    pub name: String,
+    pub template_id: i32,
+    /// Version of the template, that the data was last checked against.
+    pub template_version: i32,
+    pub data: Value,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+impl DetailsResponse {
+    #[must_use]
+    pub fn new(document: &documents::Model) -> Self {
+        Self {
+            id: document.id,
+            name: document.name.clone(),
+            template_id: document.template_id,
+            template_version: document.template_version,
+            data: document.data.clone(),
+            created_at: document.created_at,
+            updated_at: document.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateResponse {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResponse {
+    pub id: i32,
+    pub name: String,
+    pub template: TemplateResponse,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+impl ListResponse {
+    #[must_use]
+    pub fn new(document: &documents::Model, template: &templates::Model) -> Self {
+        Self {
+            id: document.id,
+            name: document.name.clone(),
+            template: TemplateResponse {
+                id: template.id,
+                name: template.name.clone(),
+            },
+            updated_at: document.updated_at,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::roles::UserRole;
+use crate::models::{template_invitations, templates, user_identities, users};
+use crate::views::api_token::Response as ApiTokenResponse;
+use crate::views::category::DetailsResponse as CategoryResponse;
+use crate::views::document::DetailsResponse as DocumentResponse;
+use crate::views::merge::DetailsResponse as MergeResponse;
+use crate::views::organization::WithRoleResponse as OrganizationResponse;
+use crate::views::session::Response as SessionResponse;
+use crate::views::template::WithCategoriesResponse;
+
+/// Everything stored about the user, for the download of the personal data.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResponse {
+    pub account: AccountResponse,
+    pub sessions: Vec<SessionResponse>,
+    pub api_tokens: Vec<ApiTokenResponse>,
+    pub identities: Vec<IdentityResponse>,
+    pub organizations: Vec<OrganizationResponse>,
+    pub categories: Vec<CategoryResponse>,
+    /// Templates of the user with their files and sharing.
+    pub templates: Vec<TemplateResponse>,
+    /// Private templates of other users, that are shared with the user.
+    pub shared_with_me: Vec<WithCategoriesResponse>,
+    /// Drafts of documents.
+    pub documents: Vec<DocumentResponse>,
+    pub merges: Vec<MergeResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountResponse {
+    pub pid: String,
+    pub name: String,
+    pub email: String,
+    /// The new email, that is not verified yet.
+    pub pending_email: Option<String>,
+    pub role: UserRole,
+    pub is_verified: bool,
+    pub has_totp: bool,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+impl AccountResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            pid: user.pid.to_string(),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            pending_email: user.pending_email.clone(),
+            role: user.role,
+            is_verified: user.email_verified_at.is_some(),
+            has_totp: user.has_totp(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// Identity at a single sign-on provider, that the user logs in with.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityResponse {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl IdentityResponse {
+    #[must_use]
+    pub fn new(identity: &user_identities::Model) -> Self {
+        Self {
+            provider: identity.provider.clone(),
+            subject: identity.subject.clone(),
+            email: identity.email.clone(),
+            created_at: identity.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = ExportTemplateResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateResponse {
+    #[serde(flatten)]
+    pub template: WithCategoriesResponse,
+    /// Emails without an account, that the template is shared with.
+    pub invitations: Vec<String>,
+    pub downloads: i32,
+    pub version: i32,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    pub updated_at: DateTimeWithTimeZone,
+    /// `None`, when the file is missing.
+    pub dsl: Option<String>,
+    /// The `docx` file, encoded with base64.
+    pub docx: Option<String>,
+}
+
+impl TemplateResponse {
+    #[must_use]
+    pub fn new(
+        template: &templates::Model,
+        response: WithCategoriesResponse,
+        invitations: &[template_invitations::Model],
+        dsl: Option<String>,
+        docx: Option<&[u8]>,
+    ) -> Self {
+        Self {
+            template: response,
+            invitations: invitations
+                .iter()
+                .filter(|invitation| invitation.template_id == template.id)
+                .map(|invitation| invitation.email.clone())
+                .collect(),
+            downloads: template.downloads,
+            version: template.version,
+            created_at: template.created_at,
+            updated_at: template.updated_at,
+            dsl,
+            docx: docx.map(|docx| STANDARD.encode(docx)),
+        }
+    }
+}
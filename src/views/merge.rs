@@ -0,0 +1,47 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::errors::AppResult;
+use crate::models::merge_jobs::{self, MergeJobStatus, RowErrors};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = MergeResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailsResponse {
+    pub id: i32,
+    pub template_id: i32,
+    pub status: MergeJobStatus,
+    pub total_rows: i32,
+    pub processed_rows: i32,
+    pub failed_rows: i32,
+    /// Rows, that were skipped, with the reasons.
+    #[schema(value_type = Vec<Object>)]
+    pub row_errors: Vec<RowErrors>,
+    /// Reason of the failure of the whole job.
+    pub error: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+impl DetailsResponse {
+    /// # Errors
+    ///
+    /// When the stored row errors are malformed
+    pub fn new(job: &merge_jobs::Model) -> AppResult<Self> {
+        Ok(Self {
+            id: job.id,
+            template_id: job.template_id,
+            status: job.status,
+            total_rows: job.total_rows,
+            processed_rows: job.processed_rows,
+            failed_rows: job.failed_rows,
+            row_errors: job.row_errors()?,
+            error: job.error.clone(),
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+        })
+    }
+}
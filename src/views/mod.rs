@@ -1,4 +1,12 @@
+pub mod api_token;
 pub mod auth;
+pub mod bundle;
 pub mod category;
+pub mod document;
+pub mod error;
+pub mod export;
+pub mod merge;
+pub mod organization;
+pub mod session;
 pub mod template;
 pub mod user;
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::_entities::sea_orm_active_enums::OrganizationRole;
+use crate::models::_entities::{organization_members, organizations, users};
+use crate::views::user::Response as UserResponse;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = OrganizationResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub id: i32,
+    pub name: String,
+    /// The organization of a team, `None` for an organization.
+    pub parent_id: Option<i32>,
+}
+
+impl Response {
+    #[must_use]
+    pub fn new(organization: &organizations::Model) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name.clone(),
+            parent_id: organization.parent_id,
+        }
+    }
+}
+
+/// The organization with the role of the current user in it.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = OrganizationWithRoleResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct WithRoleResponse {
+    #[serde(flatten)]
+    pub organization: Response,
+    pub role: OrganizationRole,
+}
+
+impl WithRoleResponse {
+    #[must_use]
+    pub fn new(organization: &organizations::Model, role: OrganizationRole) -> Self {
+        Self {
+            organization: Response::new(organization),
+            role,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub role: OrganizationRole,
+}
+
+impl MemberResponse {
+    #[must_use]
+    pub fn new(member: &organization_members::Model, user: &users::Model) -> Self {
+        assert_eq!(member.user_id, user.id);
+        Self {
+            user: UserResponse::new(user),
+            role: member.role,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailsResponse {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub members: Vec<MemberResponse>,
+    /// Teams of the organization, always empty for a team.
+    pub teams: Vec<Response>,
+}
+
+impl DetailsResponse {
+    #[must_use]
+    pub fn new(
+        organization: &organizations::Model,
+        members: &[(organization_members::Model, users::Model)],
+        teams: &[organizations::Model],
+    ) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name.clone(),
+            parent_id: organization.parent_id,
+            members: members
+                .iter()
+                .map(|(member, user)| MemberResponse::new(member, user))
+                .collect(),
+            teams: teams.iter().map(Response::new).collect(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::sessions;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = SessionResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub pid: String,
+    pub user_agent: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    pub last_used_at: DateTimeWithTimeZone,
+    /// Whether the list is requested with the access token of the session.
+    pub current: bool,
+}
+
+impl Response {
+    #[must_use]
+    pub fn new(session: &sessions::Model, current: &sessions::Model) -> Self {
+        Self {
+            pid: session.pid.to_string(),
+            user_agent: session.user_agent.clone(),
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            current: session.id == current.id,
+        }
+    }
+}
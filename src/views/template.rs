@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::models::{categories, templates, users};
+use crate::models::{categories, organizations, templates, users};
 use crate::views::category::Response as CategoryResponse;
+use crate::views::organization::Response as OrganizationResponse;
 use crate::views::user::Response;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct WithCategoriesResponse {
     pub id: i32,
@@ -13,19 +15,35 @@ pub struct WithCategoriesResponse {
     pub author: Response,
     pub publicity: PublicityResponse,
     pub categories: Vec<CategoryResponse>,
+    /// Moves with every change of the template. Updates and deletes send it
+    /// back in `If-Match`, so they don't overwrite changes of others.
+    pub revision: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum PublicityResponse {
     Public,
-    Private { viewers: Vec<Response> },
+    Private {
+        viewers: Vec<Response>,
+        organizations: Vec<OrganizationResponse>,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageResponse {
+    pub templates: Vec<WithCategoriesResponse>,
+    /// Number of templates matching the filters across all pages.
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateResponse {
     pub id: i32,
+    pub revision: i32,
 }
 
 impl WithCategoriesResponse {
@@ -33,18 +51,20 @@ impl WithCategoriesResponse {
     ///
     /// Panics in the following cases:
     ///
-    /// 1. if the template is public and has initialized viewers,
+    /// 1. if the template is public and has initialized viewers and
+    ///    organizations,
     ///
-    /// 2. if the template is private and doesn't have initialized viewers.
+    /// 2. if the template is private and doesn't have initialized viewers and
+    ///    organizations.
     #[must_use]
     pub fn new(
         template: &templates::Model,
         author: &users::Model,
         categories: &[categories::Model],
-        viewers: Option<&Vec<users::Model>>,
+        sharing: Option<(&[users::Model], &[organizations::Model])>,
     ) -> Self {
         assert_eq!(template.user_id, author.id);
-        let publicity = viewers.map_or_else(
+        let publicity = sharing.map_or_else(
             || {
                 assert!(
                     template.is_public,
@@ -53,13 +73,17 @@ impl WithCategoriesResponse {
 
                 PublicityResponse::Public
             },
-            |users| {
+            |(users, organizations)| {
                 assert!(
                     !template.is_public,
                     "Template is public, but it has a list of users that it is visible to"
                 );
                 PublicityResponse::Private {
                     viewers: users.iter().map(Response::new).collect(),
+                    organizations: organizations
+                        .iter()
+                        .map(OrganizationResponse::new)
+                        .collect(),
                 }
             },
         );
@@ -71,6 +95,7 @@ impl WithCategoriesResponse {
             author: Response::new(author),
             publicity,
             categories: categories.iter().map(CategoryResponse::new).collect(),
+            revision: template.revision,
         }
     }
 }
@@ -78,6 +103,9 @@ impl WithCategoriesResponse {
 impl CreateResponse {
     #[must_use]
     pub const fn new(template: &templates::Model) -> Self {
-        Self { id: template.id }
+        Self {
+            id: template.id,
+            revision: template.revision,
+        }
     }
 }
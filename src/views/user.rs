@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::models::_entities::users;
+use crate::models::roles::UserRole;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = UserResponse)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
     pub pid: String,
@@ -20,3 +23,49 @@ impl Response {
         }
     }
 }
+
+/// The user with the role, for the user themselves and admins.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(as = UserWithRoleResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct WithRoleResponse {
+    #[serde(flatten)]
+    pub user: Response,
+    pub role: UserRole,
+}
+
+impl WithRoleResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            user: Response::new(user),
+            role: user.role,
+        }
+    }
+}
+
+/// The secret of the two-factor authentication, that is being enabled.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    /// `otpauth://` URI for the QR code, that authenticator apps scan.
+    pub provisioning_uri: String,
+}
+
+impl TotpEnrollmentResponse {
+    #[must_use]
+    pub fn new(secret: &str, provisioning_uri: String) -> Self {
+        Self {
+            secret: secret.to_string(),
+            provisioning_uri,
+        }
+    }
+}
+
+/// Recovery codes are shown once, only their hashes are stored.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
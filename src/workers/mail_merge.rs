@@ -0,0 +1,141 @@
+use std::io::{Cursor, Write};
+
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::docx;
+use crate::models::merge_jobs::{self, Mapping, RowErrors};
+use crate::models::templates;
+
+/// Progress is saved after every this number of rows.
+const PROGRESS_STEP: usize = 10;
+
+pub struct MailMergeWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct MailMergeWorkerArgs {
+    pub job_id: i32,
+}
+
+#[async_trait]
+impl BackgroundWorker<MailMergeWorkerArgs> for MailMergeWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    async fn perform(&self, args: MailMergeWorkerArgs) -> Result<()> {
+        let db = &self.ctx.db;
+
+        let job = merge_jobs::Model::find_by_id(db, args.job_id)
+            .await?
+            .start(db)
+            .await?;
+
+        match merge(db, job.clone()).await {
+            Ok((job, row_errors)) => {
+                job.complete(db, &row_errors).await?;
+            },
+            Err(err) => {
+                tracing::error!(job_id = job.id, err = err.to_string(), "mail merge failed");
+                job.fail(db, err.to_string()).await?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders a document for every valid row into a ZIP archive. Problems with
+/// the rows are listed in `errors.csv` inside of the archive.
+async fn merge(
+    db: &DatabaseConnection,
+    mut job: merge_jobs::Model,
+) -> Result<(merge_jobs::Model, Vec<RowErrors>)> {
+    let template = templates::Model::find_by_id(db, job.template_id).await?;
+    let docx = templates::Model::find_docx(template.id).await?;
+    let env = template.find_var_env(db).await?;
+
+    let mapping: Mapping = serde_json::from_value(job.mapping.clone())?;
+    let headers: Vec<String> = serde_json::from_value(job.headers.clone())?;
+    let rows: Vec<Vec<String>> = serde_json::from_value(job.rows.clone())?;
+    let row_numbers: Vec<usize> = job
+        .row_numbers
+        .clone()
+        .map(serde_json::from_value)
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut row_errors = Vec::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        // jobs from before the numbers were kept have the header in the first
+        // row and no blank rows
+        let number = row_numbers.get(index).copied().unwrap_or(index + 2);
+
+        match merge_jobs::Model::row_data(&env, &mapping, &headers, row) {
+            Ok(data) => {
+                let document = docx::render(&docx, &data).map_err(Error::wrap)?;
+                archive
+                    .start_file(format!("{number}.docx"), SimpleFileOptions::default())
+                    .map_err(Error::wrap)?;
+                archive.write_all(&document)?;
+            },
+            Err(errors) => {
+                row_errors.push(RowErrors {
+                    row: number,
+                    errors,
+                });
+            },
+        }
+
+        let processed = index + 1;
+        if processed % PROGRESS_STEP == 0 {
+            job = job
+                .report_progress(
+                    db,
+                    i32::try_from(processed).unwrap_or(i32::MAX),
+                    i32::try_from(row_errors.len()).unwrap_or(i32::MAX),
+                )
+                .await?;
+        }
+    }
+
+    if !row_errors.is_empty() {
+        archive
+            .start_file("errors.csv", SimpleFileOptions::default())
+            .map_err(Error::wrap)?;
+        archive.write_all(&errors_csv(&row_errors)?)?;
+    }
+
+    let archive = archive.finish().map_err(Error::wrap)?.into_inner();
+
+    fs::create_dir_all("./data/merges").await?;
+    fs::write(job.result_path(), archive).await?;
+
+    Ok((job, row_errors))
+}
+
+fn errors_csv(row_errors: &[RowErrors]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer
+        .write_record(["row", "path", "message"])
+        .map_err(Error::wrap)?;
+    for row in row_errors {
+        for error in &row.errors {
+            writer
+                .write_record([&row.row.to_string(), &error.path, &error.message])
+                .map_err(Error::wrap)?;
+        }
+    }
+
+    writer
+        .into_inner()
+        .map_err(|err| Error::wrap(err.into_error()))
+}
@@ -1 +1 @@
-
+pub mod mail_merge;
@@ -1 +1,2 @@
+mod templates;
 mod users;
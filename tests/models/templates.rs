@@ -0,0 +1,199 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use cicero::app::App;
+use cicero::controllers::templates::{CreateTemplateParams, PublicityParams};
+use cicero::models::loaders::TemplateRelations;
+use cicero::models::users::{self, RegisterParams};
+use cicero::models::{
+    organizations,
+    templates,
+    templates_categories,
+    templates_organizations,
+    users_visible_templates,
+};
+use cicero::views::template::PublicityResponse;
+use cicero_dsl::compiler;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+use serial_test::serial;
+use tokio::fs;
+use zip::ZipWriter;
+
+#[tokio::test]
+#[serial]
+async fn can_load_relations_in_constant_queries() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    App::seed(db, Path::new("src/fixtures/test")).await.unwrap();
+
+    let viewer = users::Model::create_with_password(db, &RegisterParams {
+        email: "viewer@example.com".to_string(),
+        password: "1234".to_string(),
+        name: "viewer".to_string(),
+    })
+    .await
+    .unwrap();
+    let organization = organizations::ActiveModel {
+        name: ActiveValue::set("Юристы".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    for i in 0..20 {
+        let template = templates::ActiveModel {
+            name: ActiveValue::set(format!("Шаблон {i}")),
+            description: ActiveValue::set(String::new()),
+            user_id: ActiveValue::set(if i % 2 == 0 { 1 } else { viewer.id }),
+            is_public: ActiveValue::set(i % 3 != 0),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+
+        templates_categories::ActiveModel {
+            template_id: ActiveValue::set(template.id),
+            category_id: ActiveValue::set(i % 2 + 1),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+
+        if !template.is_public {
+            users_visible_templates::ActiveModel {
+                template_id: ActiveValue::set(template.id),
+                user_id: ActiveValue::set(viewer.id),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .unwrap();
+
+            templates_organizations::ActiveModel {
+                template_id: ActiveValue::set(template.id),
+                organization_id: ActiveValue::set(organization.id),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .unwrap();
+        }
+    }
+
+    let all_templates = templates::Entity::find().all(db).await.unwrap();
+    assert_eq!(all_templates.len(), 22);
+
+    let queries = Arc::new(AtomicUsize::new(0));
+    let mut counted_db = db.clone();
+    counted_db.set_metric_callback({
+        let queries = queries.clone();
+        move |_| {
+            queries.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let responses = TemplateRelations::load_responses(&counted_db, &all_templates)
+        .await
+        .unwrap();
+
+    assert_eq!(queries.load(Ordering::SeqCst), 4);
+    assert_eq!(responses.len(), all_templates.len());
+
+    for (template, response) in all_templates.iter().zip(&responses) {
+        assert_eq!(template.id, response.id);
+        assert_eq!(response.categories.len(), 1);
+        match &response.publicity {
+            PublicityResponse::Public => assert!(template.is_public),
+            PublicityResponse::Private {
+                viewers,
+                organizations,
+            } => {
+                assert!(!template.is_public);
+                assert_eq!(viewers.len(), 1);
+                assert_eq!(viewers[0].email, viewer.email);
+                assert_eq!(organizations.len(), 1);
+                assert_eq!(organizations[0].id, organization.id);
+            },
+        }
+    }
+}
+
+/// A docx without a document, so without variables.
+fn docx() -> Vec<u8> {
+    ZipWriter::new(Cursor::new(Vec::new()))
+        .finish()
+        .unwrap()
+        .into_inner()
+}
+
+#[tokio::test]
+#[serial]
+async fn caches_compiled_dsl_types() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    App::seed(db, Path::new("src/fixtures/test")).await.unwrap();
+
+    fs::create_dir_all("./data/templates").await.unwrap();
+    let dsl = "
+        /// Арендатор
+        let tenant: String;
+        /// Арендодатель
+        let landlord: String;
+    ";
+    let template = templates::Model::create(
+        db,
+        &CreateTemplateParams {
+            name: "Договор аренды".to_string(),
+            description: String::new(),
+            categories: Vec::new(),
+            publicity: PublicityParams::Public,
+        },
+        1,
+        &docx(),
+        dsl,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(template.dsl_compiler_version, Some(compiler::VERSION));
+    assert_eq!(template.dsl_hash.as_ref().map(String::len), Some(64));
+    let types = template.find_dsl_types(db).await.unwrap();
+    let names = types
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|var| var["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["tenant", "landlord"]);
+    let env = template.find_var_env(db).await.unwrap();
+    assert_eq!(env.keys().collect::<Vec<_>>(), ["tenant", "landlord"]);
+
+    // types cached by an older compiler are compiled again
+    let stale = templates::ActiveModel {
+        id: ActiveValue::unchanged(template.id),
+        dsl_types: ActiveValue::set(Some(serde_json::json!([]))),
+        dsl_compiler_version: ActiveValue::set(Some(compiler::VERSION - 1)),
+        ..Default::default()
+    }
+    .update(db)
+    .await
+    .unwrap();
+    assert_eq!(stale.find_dsl_types(db).await.unwrap(), types);
+    let recompiled = templates::Entity::find_by_id(template.id)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(recompiled.dsl_compiler_version, Some(compiler::VERSION));
+    assert_eq!(recompiled.dsl_types, Some(types));
+
+    let _ = fs::remove_file(format!("./data/templates/{}.docx", template.id)).await;
+    let _ = fs::remove_file(format!("./data/templates/{}.dsl", template.id)).await;
+}
@@ -1,5 +1,6 @@
 use cicero::app::App;
 use cicero::models::users::{self, Model, RegisterParams};
+use cicero::totp;
 use insta::assert_debug_snapshot;
 use loco_rs::app::Hooks;
 use loco_rs::model::ModelError;
@@ -226,3 +227,75 @@ async fn can_reset_password() {
             .verify_password("new-password")
     );
 }
+
+#[test]
+fn totp_codes_match_rfc_6238() {
+    // the SHA1 secret of the RFC, "12345678901234567890" in base32
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    assert_eq!(totp::code(secret, 59).as_deref(), Some("287082"));
+    assert_eq!(totp::code(secret, 1_111_111_109).as_deref(), Some("081804"));
+    assert_eq!(totp::code(secret, 2_000_000_000).as_deref(), Some("279037"));
+    assert_eq!(
+        totp::verify(secret, "081804", 1_111_111_109 + 30),
+        Some(37_037_036)
+    );
+    assert_eq!(totp::verify(secret, "081804", 1_111_111_109 + 60), None);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_enable_totp() {
+    configure_insta!();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    App::seed(db, std::path::Path::new("src/fixtures/test"))
+        .await
+        .unwrap();
+    // a fixed clock
+    let now = 1_700_000_000;
+
+    let user = Model::find_by_pid(db, "11111111-1111-1111-1111-111111111111")
+        .await
+        .unwrap();
+    let user = user.start_totp(db).await.unwrap();
+    assert!(!user.has_totp());
+    let secret = user.totp_secret.clone().unwrap();
+
+    let wrong = user.clone().confirm_totp(db, "000000", now).await;
+    assert!(wrong.is_err());
+
+    let code = totp::code(&secret, now).unwrap();
+    let (user, recovery_codes) = user.confirm_totp(db, &code, now).await.unwrap();
+    assert!(user.has_totp());
+    assert_eq!(recovery_codes.len(), 10);
+
+    // the code can't be used twice
+    let replayed = user.clone().check_second_factor(db, &code, now).await;
+    assert!(replayed.is_err());
+    let next = totp::code(&secret, now + totp::STEP).unwrap();
+    let user = user
+        .check_second_factor(db, &next, now + totp::STEP)
+        .await
+        .unwrap();
+
+    // so can't the recovery codes, but any of them works
+    let later = now + 10 * totp::STEP;
+    let user = user
+        .check_second_factor(db, &recovery_codes[3], later)
+        .await
+        .unwrap();
+    let reused = user
+        .clone()
+        .check_second_factor(db, &recovery_codes[3], later)
+        .await;
+    assert!(reused.is_err());
+
+    let user = user
+        .disable_totp(db, &recovery_codes[0], later)
+        .await
+        .unwrap();
+    assert!(!user.has_totp());
+    assert!(user.totp_secret.is_none());
+    assert!(user.totp_recovery_codes.is_none());
+}
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use cicero::app::App;
+use cicero::models::roles::UserRole;
+use cicero::models::{templates, users};
+use cicero::views::user::WithRoleResponse;
+use insta::assert_debug_snapshot;
+use loco_rs::app::Hooks;
+use loco_rs::boot::run_task;
+use loco_rs::{task, testing};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("admin_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_manage_roles() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let forbidden = request
+                .get("/api/admin/users")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_debug_snapshot!(
+                "users_without_permission",
+                (forbidden.status_code(), forbidden.text())
+            );
+
+            run_task::<App>(
+                &ctx,
+                Some(&"set_role".to_string()),
+                &task::Vars::from_cli_args(vec![
+                    ("email".to_string(), user.user.email.clone()),
+                    ("role".to_string(), "admin".to_string()),
+                ]),
+            )
+            .await
+            .unwrap();
+
+            let list: Vec<WithRoleResponse> = request
+                .get("/api/admin/users")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(
+                list.iter()
+                    .map(|user| (user.user.email.as_str(), user.role))
+                    .collect::<Vec<_>>(),
+                [
+                    ("user1@example.com", UserRole::User),
+                    ("test@loco.com", UserRole::Admin)
+                ]
+            );
+
+            let promoted: WithRoleResponse = request
+                .put("/api/admin/users/11111111-1111-1111-1111-111111111111/role")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "role": "moderator" }))
+                .await
+                .json();
+            assert_eq!(promoted.role, UserRole::Moderator);
+
+            let own = request
+                .put(&format!("/api/admin/users/{}/role", user.user.pid))
+                .add_header(auth_key, auth_value)
+                .json(&serde_json::json!({ "role": "user" }))
+                .await;
+            assert_debug_snapshot!("change_own_role", (own.status_code(), own.text()));
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn moderator_can_remove_public_templates() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // owned by the user from the fixtures
+            let private = templates::ActiveModel {
+                name: ActiveValue::set("Личный договор".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(1),
+                is_public: ActiveValue::set(false),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let response = request
+                .delete("/api/admin/templates/1")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_eq!(response.status_code(), 403);
+
+            users::Model::find_by_email(&ctx.db, &user.user.email)
+                .await
+                .unwrap()
+                .into_active_model()
+                .set_role(&ctx.db, UserRole::Moderator)
+                .await
+                .unwrap();
+
+            request
+                .delete("/api/admin/templates/1")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+            let response = request
+                .delete(&format!("/api/admin/templates/{}", private.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_eq!(response.status_code(), 403);
+
+            // moderators are editors too
+            let response = request
+                .put("/api/admin/categories/1")
+                .add_header(auth_key, auth_value)
+                .json(&serde_json::json!({ "name": "Купля-продажа" }))
+                .await;
+            assert_eq!(response.status_code(), 200);
+
+            assert!(templates::Model::find_by_id(&ctx.db, 1).await.is_err());
+            assert!(templates::Model::find_by_id(&ctx.db, private.id)
+                .await
+                .is_ok());
+        }
+    })
+    .await;
+}
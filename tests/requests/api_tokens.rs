@@ -0,0 +1,206 @@
+use axum::http::StatusCode;
+use cicero::app::App;
+use cicero::models::{api_tokens, templates};
+use cicero::views::api_token::{Response, WithSecretResponse};
+use insta::assert_debug_snapshot;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("api_tokens_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_use_scoped_tokens() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let template = templates::ActiveModel {
+                name: ActiveValue::set("Договор".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(user.user.id),
+                is_public: ActiveValue::set(false),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let created: WithSecretResponse = request
+                .post("/api/user/tokens")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({
+                    "name": "CI",
+                    "scopes": ["templates:read", "templates:read"],
+                }))
+                .await
+                .json();
+            assert!(created.token.starts_with(api_tokens::TOKEN_PREFIX));
+            assert_eq!(created.api_token.scopes, vec![
+                api_tokens::ApiScope::TemplatesRead
+            ]);
+
+            // the private template is visible with the token
+            let (token_key, token_value) = prepare_data::auth_header(&created.token);
+            request
+                .get(&format!("/api/templates/{}", template.id))
+                .add_header(token_key.clone(), token_value.clone())
+                .await
+                .assert_status_ok();
+            request
+                .get(&format!("/api/templates/{}", template.id))
+                .await
+                .assert_status_not_found();
+
+            let response = request
+                .delete(&format!("/api/templates/{}", template.id))
+                .add_header(token_key.clone(), token_value.clone())
+                .await;
+            assert_debug_snapshot!(
+                "insufficient_scope",
+                (response.status_code(), response.text())
+            );
+            request
+                .post("/api/merges")
+                .add_header(token_key.clone(), token_value.clone())
+                .await
+                .assert_status(StatusCode::FORBIDDEN);
+
+            // tokens can't manage tokens
+            request
+                .get("/api/user/tokens")
+                .add_header(token_key.clone(), token_value.clone())
+                .await
+                .assert_status_unauthorized();
+
+            let list: Vec<Response> = request
+                .get("/api/user/tokens")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(list.len(), 1);
+            assert_eq!(list[0].name, "CI");
+            assert!(list[0].last_used_at.is_some());
+
+            // the old token stops working, once it's rotated
+            let rotated: WithSecretResponse = request
+                .post(&format!(
+                    "/api/user/tokens/{}/rotate",
+                    created.api_token.pid
+                ))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(rotated.api_token.pid, created.api_token.pid);
+            let response = request
+                .get(&format!("/api/templates/{}", template.id))
+                .add_header(token_key, token_value)
+                .await;
+            assert_debug_snapshot!(
+                "invalid_api_token",
+                (response.status_code(), response.text())
+            );
+
+            let (rotated_key, rotated_value) = prepare_data::auth_header(&rotated.token);
+            request
+                .get(&format!("/api/templates/{}", template.id))
+                .add_header(rotated_key.clone(), rotated_value.clone())
+                .await
+                .assert_status_ok();
+
+            request
+                .delete(&format!("/api/user/tokens/{}", rotated.api_token.pid))
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_ok();
+            request
+                .get("/api/templates")
+                .add_header(rotated_key, rotated_value)
+                .await
+                .assert_status_unauthorized();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_tokens() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let response = request
+                .post("/api/user/tokens")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({
+                    "name": " ",
+                    "scopes": [],
+                    "expiresAt": "2020-01-01T00:00:00Z",
+                }))
+                .await;
+            assert_debug_snapshot!("invalid_token", (response.status_code(), response.text()));
+
+            request
+                .post("/api/user/tokens")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "CI", "scopes": ["admin"] }))
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            // expired tokens are rejected
+            let created: WithSecretResponse = request
+                .post("/api/user/tokens")
+                .add_header(auth_key, auth_value)
+                .json(&serde_json::json!({ "name": "CI", "scopes": ["render"] }))
+                .await
+                .json();
+            let api_token = api_tokens::Model::find_active_by_token(&ctx.db, &created.token)
+                .await
+                .unwrap()
+                .unwrap();
+            let mut api_token = api_token.into_active_model();
+            api_token.expires_at = ActiveValue::set(Some(
+                chrono::Utc::now().fixed_offset() - chrono::Duration::hours(1),
+            ));
+            api_token.update(&ctx.db).await.unwrap();
+
+            let (token_key, token_value) = prepare_data::auth_header(&created.token);
+            request
+                .get("/api/merges/1")
+                .add_header(token_key, token_value)
+                .await
+                .assert_status_unauthorized();
+
+            // the legacy API keys are rejected, even by the public routes
+            let (key_key, key_value) = prepare_data::auth_header(&user.user.api_key);
+            let response = request
+                .get("/api/user/current")
+                .add_header(key_key.clone(), key_value.clone())
+                .await;
+            assert_debug_snapshot!("legacy_api_key", (response.status_code(), response.text()));
+            request
+                .get("/api/templates")
+                .add_header(key_key, key_value)
+                .await
+                .assert_status_unauthorized();
+        }
+    })
+    .await;
+}
@@ -1,8 +1,15 @@
+use axum::http::header::USER_AGENT;
+use axum::http::{HeaderValue, StatusCode};
 use cicero::app::App;
 use cicero::models::users;
+use cicero::totp;
+use cicero::views::auth::LoginResponse;
+use cicero::views::session::Response as SessionResponse;
+use cicero::views::user::{RecoveryCodesResponse, TotpEnrollmentResponse};
 use insta::{assert_debug_snapshot, with_settings};
 use loco_rs::testing;
 use rstest::rstest;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
 use serial_test::serial;
 
 use super::prepare_data;
@@ -35,17 +42,20 @@ async fn can_register() {
             let _response = request.post("/api/auth/register").json(&payload).await;
             let saved_user = users::Model::find_by_email(&ctx.db, email).await;
 
+            // the stored token is a hash
+            let mut filters = testing::cleanup_user_model();
+            filters.push((r"[0-9a-f]{64}", "TOKEN_HASH"));
             with_settings!({
-                filters => testing::cleanup_user_model()
+                filters => filters
             }, {
                 assert_debug_snapshot!(saved_user);
             });
 
-            with_settings!({
-                filters => testing::cleanup_email()
-            }, {
-                assert_debug_snapshot!(ctx.mailer.unwrap().deliveries());
-            });
+            // bodies are base64 encoded with the token inside, so only the
+            // recipients are checked
+            let deliveries = ctx.mailer.unwrap().deliveries();
+            assert_eq!(deliveries.count, 1);
+            assert!(deliveries.messages[0].contains("To: test@loco.com"));
         }
     })
     .await;
@@ -74,9 +84,8 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
                 .json(&register_payload)
                 .await;
 
-            let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
             let verify_payload = serde_json::json!({
-                "token": user.email_verification_token,
+                "token": prepare_data::verification_token(&ctx, email).await,
             });
             request.post("/api/auth/verify").json(&verify_payload).await;
 
@@ -108,7 +117,7 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
 
 #[tokio::test]
 #[serial]
-async fn can_login_without_verify() {
+async fn cannot_login_without_verify() {
     configure_insta!();
 
     testing::request::<App, _, _>(|request, _ctx| {
@@ -127,7 +136,6 @@ async fn can_login_without_verify() {
                 .json(&register_payload)
                 .await;
 
-            //verify user request
             let response = request
                 .post("/api/auth/login")
                 .json(&serde_json::json!({
@@ -136,11 +144,7 @@ async fn can_login_without_verify() {
                 }))
                 .await;
 
-            with_settings!({
-                filters => testing::cleanup_user_model()
-            }, {
-                assert_debug_snapshot!((response.status_code(), response.text()));
-            });
+            assert_debug_snapshot!((response.status_code(), response.text()));
         }
     })
     .await;
@@ -166,9 +170,16 @@ async fn can_reset_password() {
             assert!(user.reset_token.is_some());
             assert!(user.reset_sent_at.is_some());
 
+            let token = prepare_data::reset_token(&ctx, &user.email).await;
+            let user = users::Model::find_by_email(&ctx.db, &user.email)
+                .await
+                .unwrap();
+            // only the hash is stored
+            assert_ne!(user.reset_token.as_deref(), Some(token.as_str()));
+
             let new_password = "new-password";
             let reset_payload = serde_json::json!({
-                "token": user.reset_token,
+                "token": token,
                 "password": new_password,
             });
 
@@ -193,11 +204,87 @@ async fn can_reset_password() {
 
             assert_eq!(response.status_code(), 200);
 
-            with_settings!({
-                filters => testing::cleanup_email()
-            }, {
-                assert_debug_snapshot!(ctx.mailer.unwrap().deliveries());
-            });
+            // the token is used up
+            let reused = request
+                .post("/api/auth/reset")
+                .json(&serde_json::json!({ "token": token, "password": "other-password" }))
+                .await;
+            assert_eq!(reused.status_code(), 200);
+            let user = users::Model::find_by_email(&ctx.db, &user.email)
+                .await
+                .unwrap();
+            assert!(user.verify_password(new_password));
+
+            // the welcome email and the reset one
+            let deliveries = ctx.mailer.unwrap().deliveries();
+            assert_eq!(deliveries.count, 2);
+            assert!(deliveries.messages[1].contains("To: test@loco.com"));
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn auth_errors() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            prepare_data::init_user_login(&request, &ctx).await;
+
+            let cases = [
+                (
+                    "login_with_unknown_email",
+                    request
+                        .post("/api/auth/login")
+                        .json(&serde_json::json!({
+                            "email": "nobody@loco.com",
+                            "password": "12341234"
+                        }))
+                        .await,
+                ),
+                (
+                    "register_with_invalid_values",
+                    request
+                        .post("/api/auth/register")
+                        .json(&serde_json::json!({
+                            "name": "l",
+                            "email": "invalid-email",
+                            "password": "12341234"
+                        }))
+                        .await,
+                ),
+                (
+                    "register_without_password",
+                    request
+                        .post("/api/auth/register")
+                        .json(&serde_json::json!({
+                            "name": "loco",
+                            "email": "new@loco.com"
+                        }))
+                        .await,
+                ),
+                (
+                    "verify_with_invalid_token",
+                    request
+                        .post("/api/auth/verify")
+                        .json(&serde_json::json!({ "token": "invalid" }))
+                        .await,
+                ),
+                (
+                    "login_with_malformed_json",
+                    request
+                        .post("/api/auth/login")
+                        .bytes("{".into())
+                        .content_type("application/json")
+                        .await,
+                ),
+            ];
+
+            for (name, response) in cases {
+                assert_debug_snapshot!(name, (response.status_code(), response.text()));
+            }
         }
     })
     .await;
@@ -227,3 +314,465 @@ async fn can_get_current_user() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn can_resend_verification() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let email = "test@loco.com";
+            request
+                .post("/api/auth/register")
+                .json(&serde_json::json!({
+                    "name": "loco",
+                    "email": email,
+                    "password": "12341234"
+                }))
+                .await
+                .assert_status_ok();
+            let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+
+            // a throttled email gets the same response as an unknown one
+            let too_soon = request
+                .post("/api/auth/resend-verification")
+                .json(&serde_json::json!({ "email": email }))
+                .await;
+            let unknown = request
+                .post("/api/auth/resend-verification")
+                .json(&serde_json::json!({ "email": "nobody@loco.com" }))
+                .await;
+            assert_eq!(
+                (too_soon.status_code(), too_soon.text()),
+                (unknown.status_code(), unknown.text())
+            );
+            too_soon.assert_status_ok();
+            let throttled = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+            assert_eq!(
+                throttled.email_verification_token,
+                user.email_verification_token
+            );
+
+            let mut sent_earlier = user.clone().into_active_model();
+            sent_earlier.email_verification_sent_at = ActiveValue::set(
+                user.email_verification_sent_at
+                    .map(|sent_at| sent_at - chrono::Duration::minutes(2)),
+            );
+            sent_earlier.update(&ctx.db).await.unwrap();
+
+            request
+                .post("/api/auth/resend-verification")
+                .json(&serde_json::json!({ "email": email }))
+                .await
+                .assert_status_ok();
+            let resent = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+            assert_ne!(
+                resent.email_verification_token,
+                user.email_verification_token
+            );
+
+            // unknown emails aren't exposed
+            request
+                .post("/api/auth/resend-verification")
+                .json(&serde_json::json!({ "email": "nobody@loco.com" }))
+                .await
+                .assert_status_ok();
+
+            // the welcome email and the resent one
+            assert_eq!(ctx.mailer.unwrap().deliveries().count, 2);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn expired_tokens_are_rejected() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let email = "test@loco.com";
+            let password = "12341234";
+            request
+                .post("/api/auth/register")
+                .json(&serde_json::json!({
+                    "name": "loco",
+                    "email": email,
+                    "password": password
+                }))
+                .await
+                .assert_status_ok();
+
+            let token = prepare_data::verification_token(&ctx, email).await;
+            let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+            let mut sent_earlier = user.clone().into_active_model();
+            sent_earlier.email_verification_sent_at = ActiveValue::set(
+                user.email_verification_sent_at
+                    .map(|sent_at| sent_at - chrono::Duration::days(3)),
+            );
+            sent_earlier.update(&ctx.db).await.unwrap();
+
+            let expired = request
+                .post("/api/auth/verify")
+                .json(&serde_json::json!({ "token": token }))
+                .await;
+            assert_debug_snapshot!(
+                "verify_with_expired_token",
+                (expired.status_code(), expired.text())
+            );
+            assert!(users::Model::find_by_email(&ctx.db, email)
+                .await
+                .unwrap()
+                .email_verified_at
+                .is_none());
+
+            let token = prepare_data::verification_token(&ctx, email).await;
+            request
+                .post("/api/auth/verify")
+                .json(&serde_json::json!({ "token": token }))
+                .await
+                .assert_status_ok();
+
+            let token = prepare_data::reset_token(&ctx, email).await;
+            let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+            let mut sent_earlier = user.clone().into_active_model();
+            sent_earlier.reset_sent_at = ActiveValue::set(
+                user.reset_sent_at
+                    .map(|sent_at| sent_at - chrono::Duration::hours(2)),
+            );
+            sent_earlier.update(&ctx.db).await.unwrap();
+
+            let expired = request
+                .post("/api/auth/reset")
+                .json(&serde_json::json!({ "token": token, "password": "new-password" }))
+                .await;
+            assert_debug_snapshot!(
+                "reset_with_expired_token",
+                (expired.status_code(), expired.text())
+            );
+            let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+            assert!(user.verify_password(password));
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn login_invalidates_reset_token() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let login_data = prepare_data::init_user_login(&request, &ctx).await;
+            let token = prepare_data::reset_token(&ctx, &login_data.user.email).await;
+
+            request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({
+                    "email": login_data.user.email,
+                    "password": "1234"
+                }))
+                .await
+                .assert_status_ok();
+
+            let user = users::Model::find_by_email(&ctx.db, &login_data.user.email)
+                .await
+                .unwrap();
+            assert!(user.reset_token.is_none());
+            assert!(user.reset_sent_at.is_none());
+
+            request
+                .post("/api/auth/reset")
+                .json(&serde_json::json!({ "token": token, "password": "new-password" }))
+                .await
+                .assert_status_ok();
+            let user = users::Model::find_by_email(&ctx.db, &login_data.user.email)
+                .await
+                .unwrap();
+            assert!(user.verify_password("1234"));
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn reused_refresh_token_revokes_the_session() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let login: LoginResponse = request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({
+                    "email": user.user.email,
+                    "password": "1234"
+                }))
+                .await
+                .json();
+
+            // a thief refreshes with a copy of the token first
+            let stolen: LoginResponse = request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": login.refresh_token }))
+                .await
+                .json();
+            let stolen: LoginResponse = request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": stolen.refresh_token }))
+                .await
+                .json();
+
+            // the used refresh token is rotated away
+            let reused = request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": login.refresh_token }))
+                .await;
+            assert_debug_snapshot!(
+                "refresh_with_used_token",
+                (reused.status_code(), reused.text())
+            );
+
+            // and the session of the thief is revoked
+            request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": stolen.refresh_token }))
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+            let (auth_key, auth_value) = prepare_data::auth_header(&stolen.token);
+            request
+                .get("/api/user/current")
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+
+            // other sessions are kept
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            request
+                .get("/api/user/current")
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_refresh_and_logout() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let login: LoginResponse = request
+                .post("/api/auth/login")
+                .add_header(USER_AGENT, HeaderValue::from_static("cicero-test"))
+                .json(&serde_json::json!({
+                    "email": user.user.email,
+                    "password": "1234"
+                }))
+                .await
+                .json();
+
+            let refreshed: LoginResponse = request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": login.refresh_token }))
+                .await
+                .json();
+            assert_ne!(refreshed.refresh_token, login.refresh_token);
+
+            let (auth_key, auth_value) = prepare_data::auth_header(&refreshed.token);
+            let sessions: Vec<SessionResponse> = request
+                .get("/api/user/sessions")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(
+                sessions
+                    .iter()
+                    .map(|session| (session.user_agent.as_deref(), session.current))
+                    .collect::<Vec<_>>(),
+                [(Some("cicero-test"), true), (None, false)]
+            );
+
+            request
+                .post("/api/auth/logout")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+
+            // the access token is still valid, but its session is revoked
+            let revoked = request
+                .get("/api/user/current")
+                .add_header(auth_key, auth_value)
+                .await;
+            assert_debug_snapshot!(
+                "access_with_revoked_session",
+                (revoked.status_code(), revoked.text())
+            );
+            let revoked = request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": refreshed.refresh_token }))
+                .await;
+            assert_eq!(revoked.status_code(), 401);
+
+            // the other session is kept
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            request
+                .get("/api/user/current")
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_logout_everywhere() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let other: LoginResponse = request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({
+                    "email": user.user.email,
+                    "password": "1234"
+                }))
+                .await
+                .json();
+
+            // logging out on the other device
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+            let sessions: Vec<SessionResponse> = request
+                .get("/api/user/sessions")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            let other_session = sessions.iter().find(|session| !session.current).unwrap();
+            request
+                .delete(&format!("/api/user/sessions/{}", other_session.pid))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+            let (other_key, other_value) = prepare_data::auth_header(&other.token);
+            request
+                .get("/api/user/current")
+                .add_header(other_key, other_value)
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+
+            let other: LoginResponse = request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({
+                    "email": user.user.email,
+                    "password": "1234"
+                }))
+                .await
+                .json();
+            request
+                .post("/api/auth/logout-all")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+
+            for token in [&user.token, &other.token] {
+                let (auth_key, auth_value) = prepare_data::auth_header(token);
+                request
+                    .get("/api/user/current")
+                    .add_header(auth_key, auth_value)
+                    .await
+                    .assert_status(StatusCode::UNAUTHORIZED);
+            }
+            request
+                .post("/api/auth/refresh")
+                .json(&serde_json::json!({ "refreshToken": other.refresh_token }))
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_login_with_totp() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let enrollment: TotpEnrollmentResponse = request
+                .post("/api/user/totp")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(
+                enrollment.provisioning_uri,
+                format!(
+                    "otpauth://totp/Cicero:test%40loco%2Ecom?secret={}&issuer=Cicero&algorithm=SHA1&digits=6&period=30",
+                    enrollment.secret
+                )
+            );
+
+            let now = totp::now();
+            let confirmed: RecoveryCodesResponse = request
+                .post("/api/user/totp/confirm")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({
+                    "code": totp::code(&enrollment.secret, now).unwrap(),
+                }))
+                .await
+                .json();
+
+            let login = |code: Option<String>| {
+                request.post("/api/auth/login").json(&serde_json::json!({
+                    "email": user.user.email,
+                    "password": "1234",
+                    "code": code,
+                }))
+            };
+
+            let without_code = login(None).await;
+            assert_debug_snapshot!(
+                "login_without_totp_code",
+                (without_code.status_code(), without_code.text())
+            );
+            let wrong_code = login(Some("000000".to_string())).await;
+            assert_debug_snapshot!(
+                "login_with_wrong_totp_code",
+                (wrong_code.status_code(), wrong_code.text())
+            );
+
+            // the code of the confirmation is used, the next one is accepted
+            let next = totp::code(&enrollment.secret, now + totp::STEP).unwrap();
+            login(Some(next)).await.assert_status_ok();
+
+            let recovery_code = confirmed.recovery_codes[0].clone();
+            login(Some(recovery_code.clone())).await.assert_status_ok();
+            login(Some(recovery_code))
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+
+            request
+                .post("/api/user/totp/disable")
+                .add_header(auth_key, auth_value)
+                .json(&serde_json::json!({ "code": confirmed.recovery_codes[1] }))
+                .await
+                .assert_status_ok();
+            login(None).await.assert_status_ok();
+        }
+    })
+    .await;
+}
@@ -0,0 +1,333 @@
+use axum::http::StatusCode;
+use axum_test::multipart::{MultipartForm, Part};
+use cicero::app::App;
+use cicero::bundle::{Bundle, BundlePublicity, BundleTemplate, TemplateManifest, MAX_FILE_SIZE};
+use cicero::controllers::categories::CategoryParams;
+use cicero::controllers::documents::CreateDocumentParams;
+use cicero::controllers::templates::{CreateTemplateParams, PublicityParams};
+use cicero::models::users::{self, RegisterParams};
+use cicero::models::{categories, documents, templates};
+use cicero::views::bundle::ImportResponse;
+use insta::assert_debug_snapshot;
+use loco_rs::testing;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serial_test::serial;
+use tokio::fs;
+
+use super::prepare_data;
+
+const DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+";
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("bundles_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+fn form(bundle: Vec<u8>) -> MultipartForm {
+    MultipartForm::new().add_part("bundle", Part::bytes(bundle).file_name("templates.zip"))
+}
+
+async fn remove_template_files(db: &sea_orm::DatabaseConnection) {
+    for template in templates::Entity::find().all(db).await.unwrap() {
+        let _ = fs::remove_file(format!("./data/templates/{}.docx", template.id)).await;
+        let _ = fs::remove_file(format!("./data/templates/{}.dsl", template.id)).await;
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_and_import_bundles() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let parent = categories::Model::create(
+                &ctx.db,
+                &CategoryParams {
+                    name: "Недвижимость".to_string(),
+                    parent_id: None,
+                },
+                &user.user,
+            )
+            .await
+            .unwrap();
+            let category = categories::Model::create(
+                &ctx.db,
+                &CategoryParams {
+                    name: "Аренда".to_string(),
+                    parent_id: Some(parent.id),
+                },
+                &user.user,
+            )
+            .await
+            .unwrap();
+            fs::create_dir_all("./data/templates").await.unwrap();
+            let template = templates::Model::create(
+                &ctx.db,
+                &CreateTemplateParams {
+                    name: "Договор аренды".to_string(),
+                    description: "Квартиры".to_string(),
+                    categories: vec![category.id],
+                    publicity: PublicityParams::Private {
+                        viewers: vec!["viewer@example.com".to_string()],
+                        organizations: Vec::new(),
+                    },
+                },
+                user.user.id,
+                &prepare_data::docx("{{tenant}}"),
+                DSL,
+            )
+            .await
+            .unwrap();
+            documents::Model::create(
+                &ctx.db,
+                &CreateDocumentParams {
+                    template_id: template.id,
+                    name: None,
+                    data: serde_json::json!({ "tenant": "Иван" }),
+                },
+                user.user.id,
+            )
+            .await
+            .unwrap();
+
+            let response = request
+                .get(&format!("/api/templates/export?ids={}", template.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            response.assert_status_ok();
+            let exported = response.as_bytes().to_vec();
+
+            let mut bundle = Bundle::read(&exported).unwrap();
+            let manifest = &bundle.templates[0].manifest;
+            assert_eq!(manifest.name, "Договор аренды");
+            assert_eq!(manifest.categories, [["Недвижимость", "Аренда"]]);
+            assert_eq!(manifest.publicity, BundlePublicity::Private {
+                viewers: vec!["viewer@example.com".to_string()]
+            });
+            assert_eq!(
+                manifest.sample_data,
+                Some(serde_json::json!({ "tenant": "Иван" }))
+            );
+            assert_eq!(bundle.templates[0].docx, prepare_data::docx("{{tenant}}"));
+            assert_eq!(bundle.templates[0].dsl, DSL);
+
+            // the user already has the template
+            let skipped: ImportResponse = request
+                .post("/api/templates/import")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(exported.clone()))
+                .await
+                .json();
+            assert_eq!(skipped.templates[0].id, template.id);
+
+            let overwritten: ImportResponse = request
+                .post("/api/templates/import?conflicts=overwrite")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(exported))
+                .await
+                .json();
+            assert_eq!(overwritten.templates[0].id, template.id);
+            assert_eq!(
+                templates::Model::find_by_id(&ctx.db, template.id)
+                    .await
+                    .unwrap()
+                    .version,
+                template.version + 1
+            );
+
+            // missing categories are created under their parents
+            bundle.templates[0].manifest.categories = vec![
+                vec!["Недвижимость".to_string(), "Аренда".to_string()],
+                vec![
+                    "Недвижимость".to_string(),
+                    "Жильё".to_string(),
+                    "Дома".to_string(),
+                ],
+            ];
+            let renamed: ImportResponse = request
+                .post("/api/templates/import?conflicts=rename")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(bundle.write().unwrap()))
+                .await
+                .json();
+            assert_debug_snapshot!((
+                skipped.templates[0].status,
+                overwritten.templates[0].status,
+                &renamed.templates[0].name,
+                renamed.templates[0].status,
+                renamed
+                    .created_categories
+                    .iter()
+                    .map(|category| category.name.as_str())
+                    .collect::<Vec<_>>(),
+            ));
+
+            let houses = categories::Model::find_by_id(&ctx.db, renamed.created_categories[1].id)
+                .await
+                .unwrap();
+            let housing = categories::Model::find_by_id(&ctx.db, houses.parent_id.unwrap())
+                .await
+                .unwrap();
+            assert_eq!(housing.name, "Жильё");
+            assert_eq!(housing.parent_id, Some(parent.id));
+
+            let imported = templates::Model::find_by_id(&ctx.db, renamed.templates[0].id)
+                .await
+                .unwrap();
+            assert!(!imported.is_public);
+            assert_eq!(templates::Model::find_dsl(imported.id).await.unwrap(), DSL);
+            let samples = documents::Entity::find()
+                .filter(documents::documents::Column::TemplateId.eq(imported.id))
+                .count(&ctx.db)
+                .await
+                .unwrap();
+            assert_eq!(samples, 1);
+
+            remove_template_files(&ctx.db).await;
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn bundles_viewers_only_for_editors() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let author = users::Model::create_with_password(&ctx.db, &RegisterParams {
+                email: "author@example.com".to_string(),
+                password: "1234".to_string(),
+                name: "author".to_string(),
+            })
+            .await
+            .unwrap();
+            fs::create_dir_all("./data/templates").await.unwrap();
+            let template = templates::Model::create(
+                &ctx.db,
+                &CreateTemplateParams {
+                    name: "Договор аренды".to_string(),
+                    description: String::new(),
+                    categories: Vec::new(),
+                    publicity: PublicityParams::Private {
+                        viewers: vec![user.user.email.clone(), "invited@example.com".to_string()],
+                        organizations: Vec::new(),
+                    },
+                },
+                author.id,
+                &prepare_data::docx("{{tenant}}"),
+                DSL,
+            )
+            .await
+            .unwrap();
+
+            // a viewer doesn't get the emails of the other viewers
+            let response = request
+                .get(&format!("/api/templates/export?ids={}", template.id))
+                .add_header(auth_key, auth_value)
+                .await;
+            response.assert_status_ok();
+            let bundle = Bundle::read(response.as_bytes()).unwrap();
+            assert_eq!(
+                bundle.templates[0].manifest.publicity,
+                BundlePublicity::Private {
+                    viewers: Vec::new()
+                }
+            );
+
+            remove_template_files(&ctx.db).await;
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_bundles() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let response = request
+                .post("/api/templates/import")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(b"not a zip".to_vec()))
+                .await;
+            assert_debug_snapshot!("invalid_bundle", (response.status_code(), response.text()));
+
+            request
+                .get("/api/templates/export")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            // nothing is imported, unless every template is valid
+            let mut bundle = Bundle::default();
+            for (name, dsl) in [("Договор", DSL), ("Акт", "let tenant: Strin;")] {
+                bundle.templates.push(BundleTemplate {
+                    manifest: TemplateManifest {
+                        name: name.to_string(),
+                        description: String::new(),
+                        categories: Vec::new(),
+                        publicity: BundlePublicity::Public,
+                        version: 1,
+                        sample_data: None,
+                    },
+                    docx: Vec::new(),
+                    dsl: dsl.to_string(),
+                });
+            }
+            let response = request
+                .post("/api/templates/import")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(bundle.write().unwrap()))
+                .await;
+            assert_debug_snapshot!("invalid_dsl", (response.status_code(), response.text()));
+            assert_eq!(templates::Entity::find().count(&ctx.db).await.unwrap(), 0);
+
+            // a file, that is too large, when decompressed
+            bundle.templates.truncate(1);
+            bundle.templates[0].docx = vec![0; usize::try_from(MAX_FILE_SIZE).unwrap() + 1];
+            let response = request
+                .post("/api/templates/import")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(bundle.write().unwrap()))
+                .await;
+            assert_debug_snapshot!("too_large_file", (response.status_code(), response.text()));
+
+            // a template, that fails on import, rolls back the ones before it
+            bundle.templates[0].docx = prepare_data::docx("{{tenant}}");
+            let mut invalid = bundle.templates[0].clone();
+            invalid.manifest.name = "Акт".to_string();
+            invalid.manifest.publicity = BundlePublicity::Private {
+                viewers: vec!["not an email".to_string()],
+            };
+            bundle.templates.push(invalid);
+            let response = request
+                .post("/api/templates/import")
+                .add_header(auth_key, auth_value)
+                .multipart(form(bundle.write().unwrap()))
+                .await;
+            assert_debug_snapshot!("partly_invalid", (response.status_code(), response.text()));
+            assert_eq!(templates::Entity::find().count(&ctx.db).await.unwrap(), 0);
+            assert!(fs::metadata("./data/templates/1.dsl").await.is_err());
+        }
+    })
+    .await;
+}
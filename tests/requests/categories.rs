@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use cicero::app::App;
+use cicero::models::{categories, templates, templates_categories};
+use cicero::views::category::{DetailsResponse, WithCountResponse};
+use cicero::views::template::PageResponse;
+use insta::assert_debug_snapshot;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("categories_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_manage_nested_categories() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let contracts: DetailsResponse = request
+                .post("/api/categories")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Договоры" }))
+                .await
+                .json();
+            let lease: DetailsResponse = request
+                .post("/api/categories")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Аренда", "parentId": contracts.id }))
+                .await
+                .json();
+            let housing: DetailsResponse = request
+                .post("/api/categories")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Жилая", "parentId": lease.id }))
+                .await
+                .json();
+
+            for (name, category_id) in [("Аренда квартиры", housing.id), ("Аренда офиса", lease.id)]
+            {
+                let template = templates::ActiveModel {
+                    name: ActiveValue::set(name.to_string()),
+                    description: ActiveValue::set(String::new()),
+                    user_id: ActiveValue::set(user.user.id),
+                    is_public: ActiveValue::set(true),
+                    ..Default::default()
+                }
+                .insert(&ctx.db)
+                .await
+                .unwrap();
+
+                templates_categories::ActiveModel {
+                    template_id: ActiveValue::set(template.id),
+                    category_id: ActiveValue::set(category_id),
+                    ..Default::default()
+                }
+                .insert(&ctx.db)
+                .await
+                .unwrap();
+            }
+
+            let list: Vec<WithCountResponse> = request.get("/api/categories").await.json();
+            let counts = list
+                .iter()
+                .map(|category| {
+                    (
+                        category.name.as_str(),
+                        category.parent_id,
+                        category.template_count,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let subtree: PageResponse = request
+                .get(&format!(
+                    "/api/categories/{}/templates?sort=name",
+                    contracts.id
+                ))
+                .await
+                .json();
+            let leaf: PageResponse = request
+                .get(&format!("/api/categories/{}/templates", housing.id))
+                .await
+                .json();
+
+            // a category can't become its own descendant
+            let cycle = request
+                .put(&format!("/api/categories/{}", contracts.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Договоры", "parentId": housing.id }))
+                .await;
+
+            let renamed: DetailsResponse = request
+                .put(&format!("/api/categories/{}", housing.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Жилая недвижимость", "parentId": lease.id }))
+                .await
+                .json();
+
+            assert_debug_snapshot!((
+                counts,
+                subtree
+                    .templates
+                    .iter()
+                    .map(|template| template.name.as_str())
+                    .collect::<Vec<_>>(),
+                leaf.total,
+                cycle.status_code(),
+                renamed.name,
+            ));
+
+            request
+                .delete(&format!("/api/categories/{}", lease.id))
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_ok();
+
+            let list: Vec<WithCountResponse> = request.get("/api/categories").await.json();
+            assert_eq!(list.len(), 3);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_change_foreign_category() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // seeded categories belong to another user
+            let update = request
+                .put("/api/categories/1")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Чужая" }))
+                .await;
+            let remove = request
+                .delete("/api/categories/1")
+                .add_header(auth_key, auth_value)
+                .await;
+            let anonymous = request
+                .post("/api/categories")
+                .json(&serde_json::json!({ "name": "Анонимная" }))
+                .await;
+
+            assert_eq!(update.status_code(), 403);
+            assert_eq!(remove.status_code(), 403);
+            assert_eq!(anonymous.status_code(), 401);
+
+            let category: DetailsResponse = request.get("/api/categories/1").await.json();
+            assert_eq!(category.name, "Договоры купли-продажи");
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_nest_under_foreign_category() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // seeded categories belong to another user
+            let create = request
+                .post("/api/categories")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Вложенная", "parentId": 1 }))
+                .await;
+            let own: DetailsResponse = request
+                .post("/api/categories")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Своя" }))
+                .await
+                .json();
+            let update = request
+                .put(&format!("/api/categories/{}", own.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Своя", "parentId": 1 }))
+                .await;
+
+            // a subcategory of another user, e.g. added by an editor
+            categories::ActiveModel {
+                name: ActiveValue::set("Чужая".to_string()),
+                parent_id: ActiveValue::set(Some(own.id)),
+                user_id: ActiveValue::set(1),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+            let remove = request
+                .delete(&format!("/api/categories/{}", own.id))
+                .add_header(auth_key, auth_value)
+                .await;
+
+            assert_eq!(create.status_code(), 403);
+            assert_eq!(update.status_code(), 403);
+            assert_debug_snapshot!((remove.status_code(), remove.text()));
+
+            let own: DetailsResponse = request
+                .get(&format!("/api/categories/{}", own.id))
+                .await
+                .json();
+            assert_eq!(own.parent_id, None);
+        }
+    })
+    .await;
+}
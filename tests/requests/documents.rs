@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use cicero::app::App;
+use cicero::controllers::templates::{CreateTemplateParams, PublicityParams, UpdateTemplateParams};
+use cicero::models::{documents, templates};
+use cicero::views::document::{DetailsResponse, ListResponse};
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+use tokio::fs;
+
+use super::prepare_data;
+
+const DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+    /// Арендная плата
+    let rent: Integer;
+";
+
+const UPDATED_DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+    /// Арендная плата
+    let rent: String;
+";
+
+async fn create_template(db: &sea_orm::DatabaseConnection, user_id: i32) -> templates::Model {
+    let template = templates::ActiveModel {
+        name: ActiveValue::set("Договор аренды".to_string()),
+        description: ActiveValue::set(String::new()),
+        user_id: ActiveValue::set(user_id),
+        is_public: ActiveValue::set(true),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    fs::create_dir_all("./data/templates").await.unwrap();
+    fs::write(format!("./data/templates/{}.dsl", template.id), DSL)
+        .await
+        .unwrap();
+
+    template
+}
+
+async fn remove_template_files(template_id: i32) {
+    let _ = fs::remove_file(format!("./data/templates/{template_id}.docx")).await;
+    let _ = fs::remove_file(format!("./data/templates/{template_id}.dsl")).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_save_and_resume_documents() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let template = create_template(&ctx.db, user.user.id).await;
+
+            let document: DetailsResponse = request
+                .post("/api/documents")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({
+                    "templateId": template.id,
+                    "data": { "tenant": "Иван" },
+                }))
+                .await
+                .json();
+            assert_eq!(document.name, "Договор аренды");
+            assert_eq!(document.template_version, 1);
+
+            let invalid = request
+                .put(&format!("/api/documents/{}", document.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Аренда", "data": { "rent": "много" } }))
+                .await;
+            assert_eq!(invalid.status_code(), 422);
+
+            let saved = serde_json::json!({
+                "name": "Аренда квартиры",
+                "data": { "tenant": "Иван", "rent": 1000 },
+            });
+            for _ in 0..2 {
+                let response = request
+                    .put(&format!("/api/documents/{}", document.id))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .json(&saved)
+                    .await;
+                assert_eq!(response.status_code(), 200);
+            }
+
+            let copy: DetailsResponse = request
+                .post(&format!("/api/documents/{}/duplicate", document.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_ne!(copy.id, document.id);
+            assert_eq!(copy.name, "Аренда квартиры (copy)");
+            assert_eq!(copy.data, saved["data"]);
+
+            let list: Vec<ListResponse> = request
+                .get("/api/documents")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            let mut names = list
+                .iter()
+                .map(|document| (document.name.as_str(), document.template.id))
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            assert_eq!(names, [
+                ("Аренда квартиры", template.id),
+                ("Аренда квартиры (copy)", template.id),
+            ]);
+
+            let params = UpdateTemplateParams {
+                template: CreateTemplateParams {
+                    name: template.name.clone(),
+                    description: template.description.clone(),
+                    categories: vec![],
+                    publicity: PublicityParams::Public,
+                },
+                revision: Some(template.revision),
+            };
+            template
+                .clone()
+                .into_active_model()
+                .update_template(
+                    &ctx.db,
+                    &params,
+                    template.id,
+                    &user.user,
+                    &prepare_data::docx(""),
+                    UPDATED_DSL,
+                )
+                .await
+                .unwrap();
+
+            let reopened: DetailsResponse = request
+                .get(&format!("/api/documents/{}", document.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(reopened.template_version, 2);
+            assert_eq!(reopened.data, serde_json::json!({ "tenant": "Иван" }));
+
+            // reopening doesn't change the stored document, the next save does
+            let stored = documents::Model::find_by_id(&ctx.db, document.id)
+                .await
+                .unwrap();
+            assert_eq!(stored.template_version, 1);
+            assert_eq!(stored.data, saved["data"]);
+
+            let resaved: DetailsResponse = request
+                .put(&format!("/api/documents/{}", document.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": reopened.name, "data": reopened.data }))
+                .await
+                .json();
+            assert_eq!(resaved.template_version, 2);
+
+            let response = request
+                .delete(&format!("/api/documents/{}", copy.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_eq!(response.status_code(), 200);
+
+            let list: Vec<ListResponse> = request
+                .get("/api/documents")
+                .add_header(auth_key, auth_value)
+                .await
+                .json();
+            assert_eq!(list.len(), 1);
+            assert_eq!(list[0].id, document.id);
+
+            remove_template_files(template.id).await;
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_open_foreign_document() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // owned by the user from the fixtures
+            let document = documents::ActiveModel {
+                name: ActiveValue::set("Чужой договор".to_string()),
+                user_id: ActiveValue::set(1),
+                template_id: ActiveValue::set(1),
+                template_version: ActiveValue::set(1),
+                data: ActiveValue::set(serde_json::json!({})),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            for response in [
+                request
+                    .get(&format!("/api/documents/{}", document.id))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .await,
+                request
+                    .post(&format!("/api/documents/{}/duplicate", document.id))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .await,
+                request
+                    .delete(&format!("/api/documents/{}", document.id))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .await,
+            ] {
+                assert_eq!(response.status_code(), 403);
+            }
+
+            let list: Vec<ListResponse> = request
+                .get("/api/documents")
+                .add_header(auth_key, auth_value)
+                .await
+                .json();
+            assert!(list.is_empty());
+        }
+    })
+    .await;
+}
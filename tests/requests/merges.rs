@@ -0,0 +1,245 @@
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use axum_test::multipart::{MultipartForm, Part};
+use cicero::app::App;
+use cicero::models::merge_jobs::MergeJobStatus;
+use cicero::models::templates;
+use cicero::views::merge::DetailsResponse;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+use tokio::fs;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use super::prepare_data;
+
+const DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+    /// Арендная плата
+    let rent: Integer;
+    /// Город
+    let city: Place?;
+";
+
+/// The tag `{{tenant}}` is split over two runs, as Word often does.
+const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body><w:p><w:r><w:t>Арендатор: {{ten</w:t></w:r><w:r><w:t>ant}}, плата {{rent}}, город {{city}}</w:t></w:r></w:p></w:body></w:document>"#;
+
+fn docx() -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("[Content_Types].xml", SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(b"<Types/>").unwrap();
+    writer
+        .start_file("word/document.xml", SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(DOCUMENT.as_bytes()).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+fn read_entry(archive: &[u8], name: &str) -> Vec<u8> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut entry = archive.by_name(name).unwrap();
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).unwrap();
+    content
+}
+
+fn form(template_id: i32, mapping: &serde_json::Value, csv: &str) -> MultipartForm {
+    let json = serde_json::json!({ "templateId": template_id, "mapping": mapping });
+
+    MultipartForm::new()
+        .add_text("json", json.to_string())
+        .add_part(
+            "file",
+            Part::bytes(csv.as_bytes().to_vec()).file_name("tenants.csv"),
+        )
+}
+
+#[tokio::test]
+#[serial]
+async fn can_merge_table_into_documents() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let template = templates::ActiveModel {
+                name: ActiveValue::set("Договор аренды".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(user.user.id),
+                is_public: ActiveValue::set(true),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            fs::create_dir_all("./data/templates").await.unwrap();
+            fs::write(format!("./data/templates/{}.dsl", template.id), DSL)
+                .await
+                .unwrap();
+            fs::write(format!("./data/templates/{}.docx", template.id), docx())
+                .await
+                .unwrap();
+
+            let csv = "ФИО;Плата\nИванов;1000\nПетров;много\n;2000\n";
+            let mapping = serde_json::json!({ "ФИО": "tenant", "Плата": "rent" });
+
+            let invalid = request
+                .post("/api/merges")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(
+                    template.id,
+                    &serde_json::json!({ "Адрес": "city", "Плата": "landlord" }),
+                    csv,
+                ))
+                .await;
+            assert_eq!(invalid.status_code(), 422);
+
+            let job: DetailsResponse = request
+                .post("/api/merges")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(template.id, &mapping, csv))
+                .await
+                .json();
+            assert_eq!(job.total_rows, 3);
+
+            let mut job = job;
+            for _ in 0..100 {
+                if matches!(
+                    job.status,
+                    MergeJobStatus::Completed | MergeJobStatus::Failed
+                ) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                job = request
+                    .get(&format!("/api/merges/{}", job.id))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .await
+                    .json();
+            }
+
+            assert_eq!(job.status, MergeJobStatus::Completed, "{:?}", job.error);
+            assert_eq!(job.processed_rows, 3);
+            assert_eq!(job.failed_rows, 2);
+            assert_eq!(
+                job.row_errors
+                    .iter()
+                    .map(|row| (row.row, row.errors[0].path.as_str()))
+                    .collect::<Vec<_>>(),
+                [(3, "rent"), (4, "tenant")]
+            );
+
+            let archive = request
+                .get(&format!("/api/merges/{}/result", job.id))
+                .add_header(auth_key, auth_value)
+                .await
+                .into_bytes();
+
+            let document = read_entry(&archive, "2.docx");
+            let xml = String::from_utf8(read_entry(&document, "word/document.xml")).unwrap();
+            assert!(xml.contains(
+                r#"<w:t xml:space="preserve">Арендатор: Иванов</w:t></w:r><w:r><w:t xml:space="preserve">, плата 1000, город _______</w:t>"#
+            ));
+
+            assert_eq!(
+                String::from_utf8(read_entry(&archive, "errors.csv")).unwrap(),
+                "row,path,message\n3,rent,`много` is not an integer\n4,tenant,value is \
+                 required\n"
+            );
+
+            let _ = fs::remove_file(format!("./data/templates/{}.dsl", template.id)).await;
+            let _ = fs::remove_file(format!("./data/templates/{}.docx", template.id)).await;
+            let _ = fs::remove_file(format!("./data/merges/{}.zip", job.id)).await;
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn numbers_rows_as_in_the_file() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let template = templates::ActiveModel {
+                name: ActiveValue::set("Договор аренды".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(user.user.id),
+                is_public: ActiveValue::set(true),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            fs::create_dir_all("./data/templates").await.unwrap();
+            fs::write(format!("./data/templates/{}.dsl", template.id), DSL)
+                .await
+                .unwrap();
+            fs::write(format!("./data/templates/{}.docx", template.id), docx())
+                .await
+                .unwrap();
+
+            // blank lines above the header and between the rows
+            let csv = "\nФИО;Плата\nИванов;1000\n;\n\nПетров;много\n";
+            let mapping = serde_json::json!({ "ФИО": "tenant", "Плата": "rent" });
+
+            let mut job: DetailsResponse = request
+                .post("/api/merges")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(template.id, &mapping, csv))
+                .await
+                .json();
+            assert_eq!(job.total_rows, 2);
+
+            for _ in 0..100 {
+                if matches!(
+                    job.status,
+                    MergeJobStatus::Completed | MergeJobStatus::Failed
+                ) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                job = request
+                    .get(&format!("/api/merges/{}", job.id))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .await
+                    .json();
+            }
+            assert_eq!(job.status, MergeJobStatus::Completed, "{:?}", job.error);
+
+            let archive = request
+                .get(&format!("/api/merges/{}/result", job.id))
+                .add_header(auth_key, auth_value)
+                .await
+                .into_bytes();
+
+            let document = read_entry(&archive, "3.docx");
+            let xml = String::from_utf8(read_entry(&document, "word/document.xml")).unwrap();
+            assert!(xml.contains("Иванов"));
+            assert_eq!(
+                String::from_utf8(read_entry(&archive, "errors.csv")).unwrap(),
+                "row,path,message\n6,rent,`много` is not an integer\n"
+            );
+
+            let _ = fs::remove_file(format!("./data/templates/{}.dsl", template.id)).await;
+            let _ = fs::remove_file(format!("./data/templates/{}.docx", template.id)).await;
+            let _ = fs::remove_file(format!("./data/merges/{}.zip", job.id)).await;
+        }
+    })
+    .await;
+}
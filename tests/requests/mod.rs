@@ -1,2 +1,14 @@
+mod admin;
+mod api_tokens;
 mod auth;
+mod bundles;
+mod categories;
+mod documents;
+mod merges;
+mod oidc;
+mod openapi;
+mod organizations;
 mod prepare_data;
+mod rate_limit;
+mod templates;
+mod user;
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use axum_test::TestResponse;
+use cicero::app::App;
+use cicero::models::{user_identities, users};
+use cicero::views::auth::{LoginResponse, OidcAuthorizeResponse};
+use cicero::{oidc, totp};
+use insta::assert_debug_snapshot;
+use jsonwebtoken::{EncodingKey, Header};
+use loco_rs::app::Hooks;
+use loco_rs::{testing, TestServer};
+use sea_orm::EntityTrait;
+use serial_test::serial;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use url::Url;
+
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("oidc_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+/// The issuer of the `mock` provider in `config/test.yaml`.
+const ISSUER: &str = "http://127.0.0.1:5591";
+const CLIENT_ID: &str = "cicero";
+const KEY_ID: &str = "test";
+/// The signing key of the mock provider and its public modulus.
+const PRIVATE_KEY: &str = include_str!("idp.pem");
+const MODULUS: &str = "zka5wi7Y6A5re755ziYH89ye4JrXB5goPaj1PaXFha3J4lNWZNeJ-reF-ENMQ5vRi5Mb-RUrLDT8mWXmagPJzNRLyqxQTqQRhN_O8Tim8xVSvI438syLc-BPhUag5RqpAQvPLm5EsDGr5zRV066GPntpGYagg2w6_FF2QkvYBYh6D4Yufg8-JBumHYGiBoEMLMgw45IackluEmAkJamD2TruiTyHYz9jqBO05FeII4DjhlCDwSskETECxTY1uy0y-Jz-92nmT0LLzzCf8XK50IkReP0suhtt_kgb5guY6LOjQiFl8Q3Y-8qdiEYRPibDHrbrofuC-s8Y8hUwHdVspQ";
+
+/// A code, that the provider issued after the login of the user.
+struct Grant {
+    code_challenge: String,
+    claims: serde_json::Value,
+}
+
+/// An identity provider, that serves the discovery document, the keys and
+/// the token endpoint. The login of the user is skipped, the test issues the
+/// codes directly.
+#[derive(Clone, Default)]
+struct MockIdp {
+    grants: Arc<Mutex<HashMap<String, Grant>>>,
+}
+
+impl MockIdp {
+    async fn start() -> (Self, JoinHandle<()>) {
+        let idp = Self::default();
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(idp.clone());
+        let listener = TcpListener::bind("127.0.0.1:5591").await.unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        (idp, server)
+    }
+
+    /// Logs the user in like the provider would at the authorization URL
+    /// and returns the code of the redirect. The claims are added to the
+    /// standard claims of the ID token.
+    fn authorize(&self, url: &str, claims: serde_json::Value) -> String {
+        let url = Url::parse(url).unwrap();
+        assert!(url.as_str().starts_with(&format!("{ISSUER}/authorize")));
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["response_type"], "code");
+        assert_eq!(query["client_id"], CLIENT_ID);
+        assert_eq!(query["code_challenge_method"], "S256");
+
+        let mut id_token = serde_json::json!({
+            "iss": ISSUER,
+            "aud": CLIENT_ID,
+            "iat": chrono::Utc::now().timestamp(),
+            "exp": chrono::Utc::now().timestamp() + 300,
+            "nonce": query["nonce"],
+        });
+        id_token
+            .as_object_mut()
+            .unwrap()
+            .extend(claims.as_object().unwrap().clone());
+
+        let code = uuid::Uuid::new_v4().to_string();
+        self.grants.lock().unwrap().insert(code.clone(), Grant {
+            code_challenge: query["code_challenge"].clone(),
+            claims: id_token,
+        });
+        code
+    }
+}
+
+async fn discovery() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "issuer": ISSUER,
+        "authorization_endpoint": format!("{ISSUER}/authorize"),
+        "token_endpoint": format!("{ISSUER}/token"),
+        "jwks_uri": format!("{ISSUER}/jwks"),
+    }))
+}
+
+async fn jwks() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "keys": [{
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "kid": KEY_ID,
+            "n": MODULUS,
+            "e": "AQAB",
+        }],
+    }))
+}
+
+async fn token(
+    State(idp): State<MockIdp>,
+    Form(form): Form<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let grant = idp.grants.lock().unwrap().remove(&form["code"]);
+    let Some(grant) = grant.filter(|grant| {
+        form["grant_type"] == "authorization_code"
+            && oidc::code_challenge(&form["code_verifier"]) == grant.code_challenge
+    }) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "invalid_grant" })),
+        );
+    };
+
+    let mut header = Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = Some(KEY_ID.to_string());
+    let key = EncodingKey::from_rsa_pem(PRIVATE_KEY.as_bytes()).unwrap();
+    let id_token = jsonwebtoken::encode(&header, &grant.claims, &key).unwrap();
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "access_token": "access-token",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })),
+    )
+}
+
+async fn authorize(request: &TestServer) -> OidcAuthorizeResponse {
+    let response = request.get("/api/auth/oidc/mock/authorize").await;
+    response.assert_status_ok();
+    serde_json::from_str(&response.text()).unwrap()
+}
+
+async fn callback(request: &TestServer, code: &str, state: &str) -> TestResponse {
+    request
+        .post("/api/auth/oidc/mock/callback")
+        .json(&serde_json::json!({ "code": code, "state": state }))
+        .await
+}
+
+/// The whole single sign-on of the user with the claims.
+async fn login(request: &TestServer, idp: &MockIdp, claims: serde_json::Value) -> TestResponse {
+    let authorization = authorize(request).await;
+    let code = idp.authorize(&authorization.url, claims);
+    callback(request, &code, &authorization.state).await
+}
+
+/// The single sign-on, that confirms the one-time code of the account.
+async fn login_with_totp(
+    request: &TestServer,
+    idp: &MockIdp,
+    claims: serde_json::Value,
+    totp_code: &str,
+) -> TestResponse {
+    let authorization = authorize(request).await;
+    let code = idp.authorize(&authorization.url, claims);
+    request
+        .post("/api/auth/oidc/mock/callback")
+        .json(&serde_json::json!({
+            "code": code,
+            "state": authorization.state,
+            "totpCode": totp_code,
+        }))
+        .await
+}
+
+#[tokio::test]
+#[serial]
+async fn can_login_with_provider() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let (idp, server) = MockIdp::start().await;
+
+            let authorization = authorize(&request).await;
+            let code = idp.authorize(
+                &authorization.url,
+                serde_json::json!({
+                    "sub": "alice",
+                    "email": "alice@example.com",
+                    "email_verified": true,
+                    "name": "Alice",
+                }),
+            );
+            let response = callback(&request, &code, &authorization.state).await;
+            response.assert_status_ok();
+
+            // the account is provisioned with the verified email
+            let login: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+            assert_eq!(login.email, "alice@example.com");
+            assert_eq!(login.name, "Alice");
+            assert!(login.is_verified);
+            let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+            request
+                .get("/api/user/current")
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_ok();
+
+            // the state can't be used again
+            callback(&request, &code, &authorization.state)
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            // the identity is linked, even when the email changes
+            let authorization = authorize(&request).await;
+            let code = idp.authorize(
+                &authorization.url,
+                serde_json::json!({ "sub": "alice", "email": "alice@other.com" }),
+            );
+            let response = callback(&request, &code, &authorization.state).await;
+            response.assert_status_ok();
+            let second_login: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+            assert_eq!(second_login.pid, login.pid);
+
+            let identities = user_identities::Entity::find().all(&ctx.db).await.unwrap();
+            assert_eq!(identities.len(), 1);
+
+            server.abort();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn links_accounts_of_trusted_domains() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let (idp, server) = MockIdp::start().await;
+            // the provider is not trusted with other domains
+            let response = login(
+                &request,
+                &idp,
+                serde_json::json!({
+                    "sub": "mallory",
+                    "email": "mallory@other.com",
+                    "email_verified": true,
+                }),
+            )
+            .await;
+            assert_debug_snapshot!(
+                "identity_not_linked",
+                (response.status_code(), response.text())
+            );
+
+            // nor with unverified emails
+            login(
+                &request,
+                &idp,
+                serde_json::json!({
+                    "sub": "user1",
+                    "email": "user1@example.com",
+                    "email_verified": false,
+                }),
+            )
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+            // the existing account of the email is linked
+            let response = login(
+                &request,
+                &idp,
+                serde_json::json!({
+                    "sub": "user1",
+                    "email": "user1@example.com",
+                    "email_verified": true,
+                }),
+            )
+            .await;
+            response.assert_status_ok();
+            let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+            assert_eq!(login_response.pid, "11111111-1111-1111-1111-111111111111");
+            assert!(login_response.is_verified);
+
+            let user = users::Model::find_by_email(&ctx.db, "user1@example.com")
+                .await
+                .unwrap();
+            let identity = user_identities::Entity::find()
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(identity.user_id, user.id);
+            assert_eq!(identity.provider, "mock");
+            assert_eq!(identity.subject, "user1");
+
+            server.abort();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn links_accounts_with_totp_by_code() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let (idp, server) = MockIdp::start().await;
+
+            let user = users::Model::find_by_email(&ctx.db, "user1@example.com")
+                .await
+                .unwrap()
+                .start_totp(&ctx.db)
+                .await
+                .unwrap();
+            let secret = user.totp_secret.clone().unwrap();
+            let (_, recovery_codes) = user
+                .confirm_totp(
+                    &ctx.db,
+                    &totp::code(&secret, totp::now()).unwrap(),
+                    totp::now(),
+                )
+                .await
+                .unwrap();
+
+            let claims = serde_json::json!({
+                "sub": "user1",
+                "email": "user1@example.com",
+                "email_verified": true,
+            });
+            let response = login(&request, &idp, claims.clone()).await;
+            assert_debug_snapshot!(
+                "identity_totp_required",
+                (response.status_code(), response.text())
+            );
+            login_with_totp(&request, &idp, claims.clone(), "000000")
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+            assert!(user_identities::Entity::find()
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .is_none());
+
+            login_with_totp(&request, &idp, claims.clone(), &recovery_codes[0])
+                .await
+                .assert_status_ok();
+            let identity = user_identities::Entity::find()
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(identity.subject, "user1");
+
+            // the linked identity logs in without the code
+            login(&request, &idp, claims).await.assert_status_ok();
+
+            server.abort();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_logins() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, _ctx| {
+        async move {
+            let (idp, server) = MockIdp::start().await;
+            let claims = serde_json::json!({
+                "sub": "alice",
+                "email": "alice@example.com",
+                "email_verified": true,
+            });
+
+            // the ID token is issued for another client
+            let authorization = authorize(&request).await;
+            let mut wrong_audience = claims.clone();
+            wrong_audience["aud"] = "other".into();
+            let code = idp.authorize(&authorization.url, wrong_audience);
+            let response = callback(&request, &code, &authorization.state).await;
+            assert_debug_snapshot!(
+                "invalid_id_token",
+                (response.status_code(), response.text())
+            );
+
+            // the ID token is of another login
+            let authorization = authorize(&request).await;
+            let mut wrong_nonce = claims.clone();
+            wrong_nonce["nonce"] = "other".into();
+            let code = idp.authorize(&authorization.url, wrong_nonce);
+            callback(&request, &code, &authorization.state)
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+
+            // the code is of another login, so the code verifier doesn't match
+            let first = authorize(&request).await;
+            let second = authorize(&request).await;
+            let code = idp.authorize(&first.url, claims.clone());
+            let response = callback(&request, &code, &second.state).await;
+            assert_debug_snapshot!(
+                "invalid_authorization_code",
+                (response.status_code(), response.text())
+            );
+
+            request
+                .post("/api/auth/oidc/mock/callback")
+                .json(&serde_json::json!({ "code": "code", "state": "unknown" }))
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            request
+                .get("/api/auth/oidc/unknown/authorize")
+                .await
+                .assert_status_not_found();
+
+            server.abort();
+        }
+    })
+    .await;
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use cicero::app::App;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use serial_test::serial;
+
+/// Controllers, whose routes are documented.
+const DOCUMENTED_PREFIXES: [&str; 3] = ["/api/auth/", "/api/user/", "/api/templates"];
+
+#[tokio::test]
+#[serial]
+async fn documents_all_routes() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let response = request.get("/api/openapi.json").await;
+            response.assert_status_ok();
+            let document: serde_json::Value = response.json();
+            assert_eq!(document["openapi"], "3.1.0");
+
+            let documented = document["paths"]
+                .as_object()
+                .unwrap()
+                .iter()
+                .flat_map(|(path, operations)| {
+                    operations
+                        .as_object()
+                        .unwrap()
+                        .keys()
+                        .map(move |method| (method.to_uppercase(), path.clone()))
+                })
+                .collect::<BTreeSet<_>>();
+
+            // `:id` of axum is `{id}` in OpenAPI
+            let routes = App::routes(&ctx)
+                .collect()
+                .into_iter()
+                .filter(|route| {
+                    DOCUMENTED_PREFIXES
+                        .iter()
+                        .any(|prefix| route.uri.starts_with(prefix))
+                })
+                .flat_map(|route| {
+                    let path = route
+                        .uri
+                        .split('/')
+                        .map(|part| {
+                            part.strip_prefix(':')
+                                .map_or_else(|| part.to_string(), |param| format!("{{{param}}}"))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    route
+                        .actions
+                        .into_iter()
+                        .map(move |method| (method.to_string(), path.clone()))
+                })
+                .collect::<BTreeSet<_>>();
+
+            // query parameters only refer to their schemas
+            let schemas = document["components"]["schemas"].as_object().unwrap();
+            let text = document.to_string();
+            for reference in text.split("\"#/components/schemas/").skip(1) {
+                let name = reference.split('"').next().unwrap();
+                assert!(
+                    schemas.contains_key(name),
+                    "schema {name} is not in the document"
+                );
+            }
+
+            assert_eq!(
+                routes, documented,
+                "routes and the OpenAPI document differ, update the `ApiDoc` of the controller"
+            );
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn serves_api_reference() {
+    testing::request::<App, _, _>(|request, _ctx| {
+        async move {
+            let response = request.get("/api/docs").await;
+            response.assert_status_ok();
+            assert!(response.text().contains("\"openapi\":\"3.1.0\""));
+        }
+    })
+    .await;
+}
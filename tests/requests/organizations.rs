@@ -0,0 +1,290 @@
+use std::path::Path;
+
+use axum_test::multipart::{MultipartForm, Part};
+use cicero::app::App;
+use cicero::controllers::organizations::{MemberParams, OrganizationParams};
+use cicero::models::_entities::sea_orm_active_enums::OrganizationRole;
+use cicero::models::{organizations, templates, templates_organizations};
+use cicero::views::organization::{DetailsResponse, MemberResponse, WithRoleResponse};
+use cicero::views::template::PageResponse;
+use insta::assert_debug_snapshot;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+
+use super::prepare_data;
+
+const FIXTURE_USER_PID: &str = "11111111-1111-1111-1111-111111111111";
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("organizations_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_manage_organizations() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let lawyers: DetailsResponse = request
+                .post("/api/organizations")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Юристы" }))
+                .await
+                .json();
+            assert_eq!(lawyers.members.len(), 1);
+            assert_eq!(lawyers.members[0].role, OrganizationRole::Owner);
+
+            let contracts: DetailsResponse = request
+                .post("/api/organizations")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Договорной отдел", "parentId": lawyers.id }))
+                .await
+                .json();
+            assert_eq!(contracts.parent_id, Some(lawyers.id));
+
+            let nested = request
+                .post("/api/organizations")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Аренда", "parentId": contracts.id }))
+                .await;
+            assert_debug_snapshot!("create_nested_team", (nested.status_code(), nested.text()));
+
+            let outsider = request
+                .put(&format!("/api/organizations/{}/members", contracts.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "email": "user1@example.com" }))
+                .await;
+            assert_debug_snapshot!(
+                "add_outsider_to_team",
+                (outsider.status_code(), outsider.text())
+            );
+
+            for id in [lawyers.id, contracts.id] {
+                let member: MemberResponse = request
+                    .put(&format!("/api/organizations/{id}/members"))
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .json(&serde_json::json!({ "email": "user1@example.com" }))
+                    .await
+                    .json();
+                assert_eq!(member.role, OrganizationRole::Member);
+            }
+
+            let demoted = request
+                .put(&format!("/api/organizations/{}/members", lawyers.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "email": user.user.email, "role": "admin" }))
+                .await;
+            assert_debug_snapshot!("demote_last_owner", (demoted.status_code(), demoted.text()));
+
+            let details: DetailsResponse = request
+                .get(&format!("/api/organizations/{}", lawyers.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(
+                details
+                    .members
+                    .iter()
+                    .map(|member| (member.user.email.as_str(), member.role))
+                    .collect::<Vec<_>>(),
+                [
+                    ("user1@example.com", OrganizationRole::Member),
+                    ("test@loco.com", OrganizationRole::Owner),
+                ]
+            );
+            assert_eq!(
+                details.teams.iter().map(|team| team.id).collect::<Vec<_>>(),
+                [contracts.id]
+            );
+
+            // leaving the organization leaves its teams too
+            request
+                .delete(&format!(
+                    "/api/organizations/{}/members/{FIXTURE_USER_PID}",
+                    lawyers.id
+                ))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+            let team: DetailsResponse = request
+                .get(&format!("/api/organizations/{}", contracts.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(team.members.len(), 1);
+
+            let list: Vec<WithRoleResponse> = request
+                .get("/api/organizations")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(
+                list.iter()
+                    .map(|organization| organization.organization.name.as_str())
+                    .collect::<Vec<_>>(),
+                ["Юристы", "Договорной отдел"]
+            );
+
+            request
+                .delete(&format!("/api/organizations/{}", lawyers.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+            let list: Vec<WithRoleResponse> = request
+                .get("/api/organizations")
+                .add_header(auth_key, auth_value)
+                .await
+                .json();
+            assert!(list.is_empty());
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_share_templates_with_teams() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // the organization of the user from the fixtures
+            let organization = organizations::Model::create(
+                &ctx.db,
+                &OrganizationParams {
+                    name: "Юристы".to_string(),
+                    parent_id: None,
+                },
+                1,
+            )
+            .await
+            .unwrap();
+            let team = organizations::Model::create(
+                &ctx.db,
+                &OrganizationParams {
+                    name: "Договорной отдел".to_string(),
+                    parent_id: Some(organization.id),
+                },
+                1,
+            )
+            .await
+            .unwrap();
+
+            let template = templates::ActiveModel {
+                name: ActiveValue::set("Договор отдела".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(1),
+                is_public: ActiveValue::set(false),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+            templates_organizations::ActiveModel {
+                template_id: ActiveValue::set(template.id),
+                organization_id: ActiveValue::set(team.id),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let response = request
+                .get(&format!("/api/templates/{}", template.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_eq!(response.status_code(), 403);
+
+            for organization in [&organization, &team] {
+                organization
+                    .set_member(
+                        &ctx.db,
+                        &MemberParams {
+                            email: user.user.email.clone(),
+                            role: OrganizationRole::Member,
+                        },
+                        1,
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            request
+                .get(&format!("/api/templates/{}", template.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+            let private: PageResponse = request
+                .get("/api/templates?publicity=private")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .json();
+            assert_eq!(
+                private
+                    .templates
+                    .iter()
+                    .map(|template| template.id)
+                    .collect::<Vec<_>>(),
+                [template.id]
+            );
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [],
+                "publicity": "private",
+                "viewers": [],
+                "organizations": [organization.id, team.id + 1],
+            });
+            let foreign = request
+                .post("/api/templates")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(
+                    MultipartForm::new()
+                        .add_text("json", json.to_string())
+                        .add_part(
+                            "docx",
+                            Part::bytes(prepare_data::docx("")).file_name("template.docx"),
+                        )
+                        .add_text("dsl", "/// Арендатор\nlet tenant: String;"),
+                )
+                .await;
+            assert_debug_snapshot!(
+                "share_with_unknown_organization",
+                (foreign.status_code(), foreign.text())
+            );
+
+            organization
+                .remove_member(&ctx.db, &user.user.pid.to_string(), 1)
+                .await
+                .unwrap();
+
+            let response = request
+                .get(&format!("/api/templates/{}", template.id))
+                .add_header(auth_key, auth_value)
+                .await;
+            assert_eq!(response.status_code(), 403);
+        }
+    })
+    .await;
+}
@@ -1,8 +1,13 @@
+use std::io::{Cursor, Write};
+
 use axum::http::{HeaderName, HeaderValue};
 use cicero::models::users;
 use cicero::views::auth::LoginResponse;
 use loco_rs::app::AppContext;
 use loco_rs::TestServer;
+use sea_orm::IntoActiveModel;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
 
 const USER_EMAIL: &str = "test@loco.com";
 const USER_PASSWORD: &str = "1234";
@@ -24,12 +29,8 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
         .post("/api/auth/register")
         .json(&register_payload)
         .await;
-    let user = users::Model::find_by_email(&ctx.db, USER_EMAIL)
-        .await
-        .unwrap();
-
     let verify_payload = serde_json::json!({
-        "token": user.email_verification_token,
+        "token": verification_token(ctx, USER_EMAIL).await,
     });
 
     request.post("/api/auth/verify").json(&verify_payload).await;
@@ -52,8 +53,44 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     }
 }
 
+/// Only hashes of the tokens are stored and the mails are encoded, so a new
+/// token is issued instead of reading the mailed one.
+pub async fn verification_token(ctx: &AppContext, email: &str) -> String {
+    let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+    let (_, token) = user
+        .into_active_model()
+        .set_email_verification_sent(&ctx.db)
+        .await
+        .unwrap();
+    token
+}
+
+pub async fn reset_token(ctx: &AppContext, email: &str) -> String {
+    let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+    let (_, token) = user
+        .into_active_model()
+        .set_forgot_password_sent(&ctx.db)
+        .await
+        .unwrap();
+    token
+}
+
 pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
     let auth_header_value = HeaderValue::from_str(&format!("Bearer {}", &token)).unwrap();
 
     (HeaderName::from_static("authorization"), auth_header_value)
 }
+
+/// A docx with a single paragraph of the text, that may contain tags.
+pub fn docx(text: &str) -> Vec<u8> {
+    let document = format!(
+        r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body><w:p><w:r><w:t>{text}</w:t></w:r></w:p></w:body></w:document>"#
+    );
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("word/document.xml", SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(document.as_bytes()).unwrap();
+    writer.finish().unwrap().into_inner()
+}
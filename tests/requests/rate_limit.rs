@@ -0,0 +1,256 @@
+use std::time::{Duration, Instant};
+
+use axum::http::header::{FORWARDED, RETRY_AFTER};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use cicero::app::App;
+use cicero::rate_limit::{RedisStorage, Storage};
+use insta::assert_debug_snapshot;
+use loco_rs::testing;
+use serial_test::serial;
+
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("rate_limit_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn limits_requests_per_account() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, _ctx| {
+        async move {
+            // the limit of `forgot` is 3 requests per email in the tests
+            for email in [
+                "user1@example.com",
+                "User1@Example.com ",
+                "user1@example.com",
+            ] {
+                request
+                    .post("/api/auth/forgot")
+                    .json(&serde_json::json!({ "email": email }))
+                    .await
+                    .assert_status_ok();
+            }
+
+            let limited = request
+                .post("/api/auth/forgot")
+                .json(&serde_json::json!({ "email": "user1@example.com" }))
+                .await;
+            let retry_after: u64 = limited
+                .header(RETRY_AFTER)
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!((1..=60).contains(&retry_after));
+            assert_debug_snapshot!("forgot_too_often", (limited.status_code(), limited.text()));
+
+            request
+                .post("/api/auth/forgot")
+                .json(&serde_json::json!({ "email": "user2@example.com" }))
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn limits_requests_per_ip() {
+    testing::request::<App, _, _>(|request, _ctx| {
+        async move {
+            // the limit of `register` is 10 requests per IP in the tests
+            for i in 0..10 {
+                request
+                    .post("/api/auth/register")
+                    .json(&serde_json::json!({
+                        "name": "loco",
+                        "email": format!("user{i}@loco.com"),
+                        "password": "1234",
+                    }))
+                    .await
+                    .assert_status_ok();
+            }
+
+            request
+                .post("/api/auth/register")
+                .json(&serde_json::json!({
+                    "name": "loco",
+                    "email": "one-more@loco.com",
+                    "password": "1234",
+                }))
+                .await
+                .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+            // other routes have their own limits
+            request
+                .post("/api/auth/forgot")
+                .json(&serde_json::json!({ "email": "user0@loco.com" }))
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn limits_requests_per_forwarded_ip() {
+    testing::request::<App, _, _>(|request, _ctx| {
+        async move {
+            let register = |i: usize, forwarded_for: &'static str| {
+                request
+                    .post("/api/auth/register")
+                    .add_header(
+                        HeaderName::from_static("x-forwarded-for"),
+                        HeaderValue::from_static(forwarded_for),
+                    )
+                    .json(&serde_json::json!({
+                        "name": "loco",
+                        "email": format!("user{i}@loco.com"),
+                        "password": "1234",
+                    }))
+            };
+
+            // the test client is a trusted proxy, addresses before the last
+            // one are sent by the client and are ignored
+            for i in 0..10 {
+                let forwarded_for = if i % 2 == 0 {
+                    "203.0.113.1"
+                } else {
+                    "198.51.100.1, 203.0.113.1"
+                };
+                register(i, forwarded_for).await.assert_status_ok();
+            }
+            register(10, "203.0.113.1")
+                .await
+                .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+            // other clients behind the proxy have their own limits
+            register(11, "203.0.113.2").await.assert_status_ok();
+            request
+                .post("/api/auth/register")
+                .add_header(
+                    FORWARDED,
+                    HeaderValue::from_static("for=\"[2001:db8::1]:4711\";proto=https"),
+                )
+                .json(&serde_json::json!({
+                    "name": "loco",
+                    "email": "user12@loco.com",
+                    "password": "1234",
+                }))
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn locks_accounts_after_failed_logins() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let login = |email: &str, password: &str| {
+                request.post("/api/auth/login").json(&serde_json::json!({
+                    "email": email,
+                    "password": password,
+                }))
+            };
+
+            // a successful login forgets the failures
+            for _ in 0..4 {
+                login(&user.user.email, "wrong")
+                    .await
+                    .assert_status(StatusCode::UNAUTHORIZED);
+            }
+            login(&user.user.email, "1234").await.assert_status_ok();
+
+            // the lockout threshold is 5 failures in the tests
+            for _ in 0..5 {
+                login(&user.user.email, "wrong")
+                    .await
+                    .assert_status(StatusCode::UNAUTHORIZED);
+            }
+            let locked = login(&user.user.email, "1234").await;
+            let retry_after: u64 = locked
+                .header(RETRY_AFTER)
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!((1..=30).contains(&retry_after));
+            assert_debug_snapshot!("login_when_locked", (locked.status_code(), locked.text()));
+
+            // unknown emails are locked the same way
+            for _ in 0..5 {
+                login("nobody@loco.com", "wrong")
+                    .await
+                    .assert_status(StatusCode::UNAUTHORIZED);
+            }
+            login("nobody@loco.com", "wrong")
+                .await
+                .assert_status(StatusCode::TOO_MANY_REQUESTS);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn responds_to_unknown_emails_in_min_time() {
+    testing::request::<App, _, _>(|request, _ctx| {
+        async move {
+            // the minimal response time is 50ms in the tests
+            for path in ["/api/auth/forgot", "/api/auth/resend-verification"] {
+                let started = Instant::now();
+                request
+                    .post(path)
+                    .json(&serde_json::json!({ "email": "nobody@loco.com" }))
+                    .await
+                    .assert_status_ok();
+                assert!(started.elapsed() >= Duration::from_millis(50));
+            }
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[ignore = "requires Redis at REDIS_URL"]
+async fn can_count_in_redis() {
+    let uri = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+    let storage = RedisStorage::connect(&uri).await.unwrap();
+    let key = format!("test:{}", uuid::Uuid::new_v4());
+
+    assert!(storage.get(&key).await.unwrap().is_none());
+    let first = storage
+        .increment(&key, Duration::from_secs(60))
+        .await
+        .unwrap();
+    let second = storage
+        .increment(&key, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!((first.count, second.count), (1, 2));
+    assert!(second.expires_in <= Duration::from_secs(60));
+    assert!(second.expires_in > Duration::from_secs(50));
+    assert_eq!(
+        storage.get(&key).await.unwrap().map(|hit| hit.count),
+        Some(2)
+    );
+
+    storage.remove(&key).await.unwrap();
+    assert!(storage.get(&key).await.unwrap().is_none());
+}
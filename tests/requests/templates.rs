@@ -0,0 +1,947 @@
+use std::path::Path;
+
+use axum::http::header::{
+    ACCEPT_RANGES,
+    CONTENT_RANGE,
+    ETAG,
+    IF_MATCH,
+    IF_MODIFIED_SINCE,
+    IF_NONE_MATCH,
+    IF_RANGE,
+    LAST_MODIFIED,
+    RANGE,
+};
+use axum::http::{HeaderValue, StatusCode};
+use axum_test::multipart::{MultipartForm, Part};
+use cicero::app::App;
+use cicero::controllers::templates::{CreateTemplateParams, PublicityParams, UpdateTemplateParams};
+use cicero::models::_entities::template_invitations;
+use cicero::models::roles::UserRole;
+use cicero::models::{categories, sessions, templates, users, users_visible_templates};
+use cicero::views::template::{CreateResponse, PageResponse, WithCategoriesResponse};
+use insta::assert_debug_snapshot;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue,
+    ColumnTrait,
+    EntityTrait,
+    IntoActiveModel,
+    QueryFilter,
+};
+use serial_test::serial;
+use tokio::fs;
+
+use super::prepare_data;
+
+const DSL: &str = "
+    /// Арендатор
+    let tenant: String;
+";
+
+const INVALID_DSL: &str = "
+    /// Арендатор
+    let tenant: Strin;
+";
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("templates_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_list_templates() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+
+            let response = request.get("/api/templates").await;
+
+            assert_debug_snapshot!((response.status_code(), response.text()));
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_paginate_templates() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+
+            let response = request.get("/api/templates?sort=name&limit=1").await;
+            let first: PageResponse = serde_json::from_str(&response.text()).unwrap();
+
+            assert_eq!(first.total, 2);
+            assert_eq!(first.templates.len(), 1);
+            let cursor = first.next_cursor.unwrap();
+
+            let response = request
+                .get(&format!("/api/templates?sort=name&limit=1&cursor={cursor}"))
+                .await;
+            let second: PageResponse = serde_json::from_str(&response.text()).unwrap();
+
+            assert_eq!(second.total, 2);
+            assert_eq!(second.templates.len(), 1);
+            assert!(second.next_cursor.is_none());
+            assert!(first.templates[0].name < second.templates[0].name);
+
+            // cursor issued for another sort order
+            let response = request
+                .get(&format!("/api/templates?sort=newest&cursor={cursor}"))
+                .await;
+            assert_eq!(response.status_code(), 400);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_filter_templates() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+
+            let by_category: PageResponse = request.get("/api/templates?categories=2").await.json();
+            let by_search: PageResponse = request
+                .get("/api/templates?search=%D0%B3%D0%B0%D0%B9%D0%B4")
+                .await
+                .json();
+            let private: PageResponse =
+                request.get("/api/templates?publicity=private").await.json();
+            let by_date: PageResponse = request
+                .get("/api/templates?createdFrom=2024-01-01T00:00:00Z")
+                .await
+                .json();
+
+            assert_debug_snapshot!((
+                by_category
+                    .templates
+                    .iter()
+                    .map(|t| t.id)
+                    .collect::<Vec<_>>(),
+                by_search.templates.iter().map(|t| t.id).collect::<Vec<_>>(),
+                private.total,
+                by_date.total,
+            ));
+        }
+    })
+    .await;
+}
+
+fn form(json: &serde_json::Value, dsl: &str) -> MultipartForm {
+    docx_form(json, prepare_data::docx(""), dsl)
+}
+
+fn docx_form(json: &serde_json::Value, docx: Vec<u8>, dsl: &str) -> MultipartForm {
+    MultipartForm::new()
+        .add_text("json", json.to_string())
+        .add_part("docx", Part::bytes(docx).file_name("template.docx"))
+        .add_text("dsl", dsl.to_string())
+}
+
+#[tokio::test]
+#[serial]
+async fn create_template_errors() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [],
+                "publicity": "public",
+            });
+            let unknown_references = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [1, 404],
+                "publicity": "private",
+                "viewers": ["nobody"],
+            });
+
+            let cases = [
+                ("invalid_dsl", form(&json, INVALID_DSL)),
+                ("invalid_docx", docx_form(&json, b"docx".to_vec(), DSL)),
+                (
+                    "undeclared_variables",
+                    docx_form(&json, prepare_data::docx("{{landlord}}"), DSL),
+                ),
+                ("invalid_json", form(&serde_json::json!({ "name": 1 }), DSL)),
+                (
+                    "missing_part",
+                    MultipartForm::new().add_text("json", json.to_string()),
+                ),
+                (
+                    "unexpected_part",
+                    form(&json, DSL).add_text("pdf", String::new()),
+                ),
+                ("unknown_references", form(&unknown_references, DSL)),
+            ];
+
+            for (name, form) in cases {
+                let response = request
+                    .post("/api/templates")
+                    .add_header(auth_key.clone(), auth_value.clone())
+                    .multipart(form)
+                    .await;
+
+                assert_debug_snapshot!(
+                    format!("create_template_{name}"),
+                    (response.status_code(), response.text())
+                );
+            }
+
+            let response = request
+                .post("/api/templates/validate")
+                .add_header(auth_key, auth_value)
+                .multipart(MultipartForm::new().add_text("dsl", INVALID_DSL))
+                .await;
+
+            assert_debug_snapshot!(
+                "validate_invalid_dsl",
+                (response.status_code(), response.text())
+            );
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn template_access_errors() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // owned by the user from the fixtures
+            let private = templates::ActiveModel {
+                name: ActiveValue::set("Личный договор".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(1),
+                is_public: ActiveValue::set(false),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let cases = [
+                (
+                    "anonymous_private",
+                    request.get(&format!("/api/templates/{}", private.id)).await,
+                ),
+                (
+                    "foreign_private",
+                    request
+                        .get(&format!("/api/templates/{}", private.id))
+                        .add_header(auth_key.clone(), auth_value.clone())
+                        .await,
+                ),
+                (
+                    "delete_foreign",
+                    request
+                        .delete("/api/templates/1")
+                        .add_header(auth_key.clone(), auth_value.clone())
+                        .add_header(IF_MATCH, HeaderValue::from_static("*"))
+                        .await,
+                ),
+                (
+                    "missing",
+                    request
+                        .get("/api/templates/404")
+                        .add_header(auth_key, auth_value)
+                        .await,
+                ),
+                (
+                    "invalid_cursor",
+                    request.get("/api/templates?cursor=invalid").await,
+                ),
+            ];
+
+            for (name, response) in cases {
+                assert_debug_snapshot!(
+                    format!("template_{name}"),
+                    (response.status_code(), response.text())
+                );
+            }
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn editors_cannot_edit_invisible_templates() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let mut editor = user.user.clone().into_active_model();
+            editor.role = ActiveValue::set(UserRole::Editor);
+            editor.update(&ctx.db).await.unwrap();
+
+            // owned by the user from the fixtures
+            let private = templates::ActiveModel {
+                name: ActiveValue::set("Личный договор".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(1),
+                is_public: ActiveValue::set(false),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let response = request
+                .patch(&format!("/api/templates/{}", private.id))
+                .add_header(auth_key, auth_value)
+                .add_header(IF_MATCH, HeaderValue::from_static("*"))
+                .json(&serde_json::json!({ "name": "Чужой договор" }))
+                .await;
+            assert_debug_snapshot!((response.status_code(), response.text()));
+
+            let template = templates::Model::find_by_id(&ctx.db, private.id)
+                .await
+                .unwrap();
+            assert_eq!(template.name, "Личный договор");
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_invite_unregistered_viewers() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [],
+                "publicity": "private",
+                "viewers": ["user1@example.com", "invited@example.com"],
+            });
+            let response = request
+                .post("/api/templates")
+                .add_header(auth_key, auth_value)
+                .multipart(form(&json, DSL))
+                .await;
+            response.assert_status_ok();
+            let template_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+            let template_id = i32::try_from(template_id).unwrap();
+
+            let invitations = template_invitations::Entity::find()
+                .filter(template_invitations::Column::TemplateId.eq(template_id))
+                .all(&ctx.db)
+                .await
+                .unwrap();
+            assert_eq!(invitations.len(), 1);
+            assert_eq!(invitations[0].email, "invited@example.com");
+            assert!(invitations[0].sent_at.is_some());
+
+            request
+                .post("/api/auth/register")
+                .json(&serde_json::json!({
+                    "name": "invited",
+                    "email": "invited@example.com",
+                    "password": "1234",
+                }))
+                .await
+                .assert_status_ok();
+            let invited = users::Model::find_by_email(&ctx.db, "invited@example.com")
+                .await
+                .unwrap();
+
+            // the grant waits for the verification of the email
+            let is_visible = users_visible_templates::Entity::find_by_id((template_id, invited.id))
+                .one(&ctx.db)
+                .await
+                .unwrap();
+            assert!(is_visible.is_none());
+
+            request
+                .post("/api/auth/verify")
+                .json(&serde_json::json!({
+                    "token": prepare_data::verification_token(&ctx, &invited.email).await,
+                }))
+                .await
+                .assert_status_ok();
+
+            let is_visible = users_visible_templates::Entity::find_by_id((template_id, invited.id))
+                .one(&ctx.db)
+                .await
+                .unwrap();
+            assert!(is_visible.is_some());
+            assert!(template_invitations::Entity::find()
+                .all(&ctx.db)
+                .await
+                .unwrap()
+                .is_empty());
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn unverified_users_cannot_create_templates() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            request
+                .post("/api/auth/register")
+                .json(&serde_json::json!({
+                    "name": "loco",
+                    "email": "unverified@loco.com",
+                    "password": "1234",
+                }))
+                .await
+                .assert_status_ok();
+            let user = users::Model::find_by_email(&ctx.db, "unverified@loco.com")
+                .await
+                .unwrap();
+
+            // e.g. a token issued before verification was required
+            let jwt = ctx.config.get_jwt_config().unwrap();
+            let (session, _) =
+                sessions::Model::create(&ctx.db, user.id, None, chrono::Duration::days(1))
+                    .await
+                    .unwrap();
+            let token = user
+                .generate_jwt(&jwt.secret, &jwt.expiration, &session)
+                .unwrap();
+            let (auth_key, auth_value) = prepare_data::auth_header(&token);
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [],
+                "publicity": "public",
+            });
+            let response = request
+                .post("/api/templates")
+                .add_header(auth_key, auth_value)
+                .multipart(form(&json, DSL))
+                .await;
+            assert_eq!(response.status_code(), 403);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn serves_template_files_conditionally() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+
+            fs::create_dir_all("./data/templates").await.unwrap();
+            let params = CreateTemplateParams {
+                name: "Договор аренды".to_string(),
+                description: String::new(),
+                categories: Vec::new(),
+                publicity: PublicityParams::Public,
+            };
+            let contents = prepare_data::docx("0123456789");
+            let len = contents.len();
+            let template = templates::Model::create(&ctx.db, &params, user.user.id, &contents, DSL)
+                .await
+                .unwrap();
+            let docx_url = format!("/api/templates/{}/docx", template.id);
+
+            let response = request.get(&docx_url).await;
+            response.assert_status_ok();
+            let etag = response.header(ETAG);
+            assert_eq!(
+                etag.to_str().unwrap(),
+                format!("\"{}\"", template.docx_hash.clone().unwrap())
+            );
+            assert_eq!(response.header(ACCEPT_RANGES), "bytes");
+            let last_modified = response.header(LAST_MODIFIED);
+
+            request
+                .get(&docx_url)
+                .add_header(IF_NONE_MATCH, etag.clone())
+                .await
+                .assert_status(StatusCode::NOT_MODIFIED);
+            request
+                .get(&docx_url)
+                .add_header(IF_MODIFIED_SINCE, last_modified)
+                .await
+                .assert_status(StatusCode::NOT_MODIFIED);
+
+            let response = request
+                .get(&docx_url)
+                .add_header(RANGE, HeaderValue::from_static("bytes=5-"))
+                .await;
+            response.assert_status(StatusCode::PARTIAL_CONTENT);
+            assert_eq!(
+                response.header(CONTENT_RANGE),
+                format!("bytes 5-{}/{len}", len - 1)
+            );
+            assert_eq!(response.as_bytes().as_ref(), &contents[5..]);
+
+            // the copy of the client is outdated, so it gets the whole file
+            let response = request
+                .get(&docx_url)
+                .add_header(RANGE, HeaderValue::from_static("bytes=5-"))
+                .add_header(IF_RANGE, HeaderValue::from_static("\"outdated\""))
+                .await;
+            response.assert_status_ok();
+            assert_eq!(response.as_bytes().as_ref(), contents);
+
+            let response = request
+                .get(&docx_url)
+                .add_header(
+                    RANGE,
+                    HeaderValue::from_str(&format!("bytes={}-", len + 10)).unwrap(),
+                )
+                .await;
+            response.assert_status(StatusCode::RANGE_NOT_SATISFIABLE);
+            assert_eq!(response.header(CONTENT_RANGE), format!("bytes */{len}"));
+
+            // neither cached copies nor resumed parts are counted
+            let downloads = templates::Model::find_by_id(&ctx.db, template.id)
+                .await
+                .unwrap()
+                .downloads;
+            assert_eq!(downloads, 2);
+
+            for url in ["dsl", "dsl/types"] {
+                let url = format!("/api/templates/{}/{url}", template.id);
+                let response = request.get(&url).await;
+                response.assert_status_ok();
+                request
+                    .get(&url)
+                    .add_header(IF_NONE_MATCH, response.header(ETAG))
+                    .await
+                    .assert_status(StatusCode::NOT_MODIFIED);
+            }
+
+            let id = template.id;
+            let params = UpdateTemplateParams {
+                template: params,
+                revision: None,
+            };
+            template
+                .into_active_model()
+                .update_template(
+                    &ctx.db,
+                    &params,
+                    id,
+                    &user.user,
+                    &prepare_data::docx("changed"),
+                    DSL,
+                )
+                .await
+                .unwrap();
+            request
+                .get(&docx_url)
+                .add_header(IF_NONE_MATCH, etag)
+                .await
+                .assert_status_ok();
+
+            let _ = fs::remove_file(format!("./data/templates/{id}.docx")).await;
+            let _ = fs::remove_file(format!("./data/templates/{id}.dsl")).await;
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_outdated_revisions() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [],
+                "publicity": "public",
+            });
+            let created: CreateResponse = request
+                .post("/api/templates")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(&json, DSL))
+                .await
+                .json();
+            assert_eq!(created.revision, 1);
+            let url = format!("/api/templates/{}", created.id);
+
+            let response = request
+                .put(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(&json, DSL))
+                .await;
+            assert_debug_snapshot!(
+                "revision_required",
+                (response.status_code(), response.text())
+            );
+
+            let updated: WithCategoriesResponse = request
+                .put(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"1\""))
+                .multipart(form(&json, DSL))
+                .await
+                .json();
+            assert_eq!(updated.revision, 2);
+
+            // a co-worker saves the revision, that was already replaced
+            let response = request
+                .put(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"1\""))
+                .multipart(form(&json, DSL))
+                .await;
+            assert_debug_snapshot!(
+                "revision_conflict",
+                (response.status_code(), response.text())
+            );
+
+            let mut with_revision = json.clone();
+            with_revision["revision"] = 2.into();
+            let updated: WithCategoriesResponse = request
+                .put(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(&with_revision, DSL))
+                .await
+                .json();
+            assert_eq!(updated.revision, 3);
+
+            request
+                .delete(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"2\""))
+                .await
+                .assert_status(StatusCode::PRECONDITION_FAILED);
+            request
+                .delete(&url)
+                .add_header(auth_key, auth_value)
+                .add_header(IF_MATCH, HeaderValue::from_static("\"3\""))
+                .await
+                .assert_status_ok();
+
+            let _ = fs::remove_file(format!("./data/templates/{}.docx", created.id)).await;
+            let _ = fs::remove_file(format!("./data/templates/{}.dsl", created.id)).await;
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_patch_template_metadata() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "Квартиры",
+                "categories": [],
+                "publicity": "public",
+            });
+            let created: CreateResponse = request
+                .post("/api/templates")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(&json, DSL))
+                .await
+                .json();
+            let url = format!("/api/templates/{}", created.id);
+
+            // the description is saved by a full update too
+            let mut replaced = json.clone();
+            replaced["description"] = "Квартиры и дома".into();
+            let updated: WithCategoriesResponse = request
+                .put(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"1\""))
+                .multipart(form(&replaced, DSL))
+                .await
+                .json();
+            assert_eq!(updated.description, "Квартиры и дома");
+            let version = templates::Model::find_by_id(&ctx.db, created.id)
+                .await
+                .unwrap()
+                .version;
+
+            request
+                .patch(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Договор найма" }))
+                .await
+                .assert_status(StatusCode::PRECONDITION_REQUIRED);
+
+            let patched: WithCategoriesResponse = request
+                .patch(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "Договор найма", "revision": 2 }))
+                .await
+                .json();
+            assert_eq!(patched.name, "Договор найма");
+            assert_eq!(patched.description, "Квартиры и дома");
+            assert_eq!(patched.revision, 3);
+
+            let template = templates::Model::find_by_id(&ctx.db, created.id)
+                .await
+                .unwrap();
+            assert_eq!(template.version, version);
+            assert_eq!(template.search_text, "договор найма\nквартиры и дома");
+
+            // viewers without the publicity are not dropped silently
+            let response = request
+                .patch(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"3\""))
+                .json(&serde_json::json!({ "publicity": "private" }))
+                .await;
+            assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+            let response = request
+                .patch(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"3\""))
+                .json(&serde_json::json!({
+                    "publicity": "private",
+                    "viewers": ["not an email"],
+                }))
+                .await;
+            assert_debug_snapshot!(
+                "patch_invalid_viewers",
+                (response.status_code(), response.text())
+            );
+
+            let category = categories::ActiveModel {
+                name: ActiveValue::Set("Аренда".to_string()),
+                user_id: ActiveValue::Set(user.user.id),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let patched: WithCategoriesResponse = request
+                .patch(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"3\""))
+                .json(&serde_json::json!({
+                    "categories": [category.id],
+                    "publicity": "private",
+                    "viewers": [],
+                }))
+                .await
+                .json();
+            assert_eq!(patched.name, "Договор найма");
+            assert_eq!(patched.categories.len(), 1);
+            assert!(matches!(
+                patched.publicity,
+                cicero::views::template::PublicityResponse::Private { .. }
+            ));
+            assert_eq!(patched.revision, 4);
+
+            let _ = fs::remove_file(format!("./data/templates/{}.docx", created.id)).await;
+            let _ = fs::remove_file(format!("./data/templates/{}.dsl", created.id)).await;
+        }
+    })
+    .await;
+}
+
+fn file_form(part: &str, contents: Vec<u8>) -> MultipartForm {
+    MultipartForm::new().add_part(part, Part::bytes(contents).file_name(part))
+}
+
+#[tokio::test]
+#[serial]
+async fn can_replace_docx_and_dsl_separately() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let json = serde_json::json!({
+                "name": "Договор аренды",
+                "description": "",
+                "categories": [],
+                "publicity": "public",
+            });
+            let created: CreateResponse = request
+                .post("/api/templates")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(form(&json, DSL))
+                .await
+                .json();
+            let id = created.id;
+            let created = templates::Model::find_by_id(&ctx.db, id).await.unwrap();
+
+            request
+                .put(&format!("/api/templates/{id}/docx"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .multipart(file_form("docx", prepare_data::docx("{{tenant}}")))
+                .await
+                .assert_status(StatusCode::PRECONDITION_REQUIRED);
+
+            let response = request
+                .put(&format!("/api/templates/{id}/docx"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("*"))
+                .multipart(file_form("docx", b"not a docx".to_vec()))
+                .await;
+            assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+            assert!(response.text().contains("invalid_docx"));
+
+            // fields of loop elements are not variables of the DSL
+            let loop_docx =
+                prepare_data::docx("{{tenant}} {{#rooms}}{{area}}{{/rooms}} {{landlord}}");
+            let response = request
+                .put(&format!("/api/templates/{id}/docx"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("*"))
+                .multipart(file_form("docx", loop_docx))
+                .await;
+            assert_debug_snapshot!(
+                "undeclared_docx_variables",
+                (response.status_code(), response.text())
+            );
+
+            let replaced: WithCategoriesResponse = request
+                .put(&format!("/api/templates/{id}/docx"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"1\""))
+                .multipart(file_form(
+                    "docx",
+                    prepare_data::docx("Арендатор: {{tenant}}"),
+                ))
+                .await
+                .json();
+            assert_eq!(replaced.revision, 2);
+
+            let template = templates::Model::find_by_id(&ctx.db, id).await.unwrap();
+            assert_ne!(template.docx_hash, created.docx_hash);
+            assert_eq!(template.dsl_hash, created.dsl_hash);
+            assert_eq!(template.version, created.version);
+
+            // the DSL must keep declaring the variables of the docx
+            let dsl = "
+                /// Арендодатель
+                let landlord: String;
+            ";
+            let response = request
+                .put(&format!("/api/templates/{id}/dsl"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"2\""))
+                .multipart(file_form("dsl", dsl.as_bytes().to_vec()))
+                .await;
+            assert_debug_snapshot!(
+                "undeclared_dsl_variables",
+                (response.status_code(), response.text())
+            );
+
+            request
+                .put(&format!("/api/templates/{id}/dsl"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"2\""))
+                .multipart(file_form("dsl", INVALID_DSL.as_bytes().to_vec()))
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            let dsl = format!(
+                "{DSL}
+                /// Арендодатель
+                let landlord: String;
+            "
+            );
+            let replaced: WithCategoriesResponse = request
+                .put(&format!("/api/templates/{id}/dsl"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .add_header(IF_MATCH, HeaderValue::from_static("\"2\""))
+                .multipart(file_form("dsl", dsl.as_bytes().to_vec()))
+                .await
+                .json();
+            assert_eq!(replaced.revision, 3);
+
+            let template = templates::Model::find_by_id(&ctx.db, id).await.unwrap();
+            assert_eq!(
+                template.docx_hash.as_deref(),
+                Some(
+                    request
+                        .get(&format!("/api/templates/{id}/docx"))
+                        .add_header(auth_key.clone(), auth_value.clone())
+                        .await
+                        .header(ETAG)
+                        .to_str()
+                        .unwrap()
+                        .trim_matches('"')
+                )
+            );
+            assert_eq!(template.version, created.version + 1);
+
+            let types = request
+                .get(&format!("/api/templates/{id}/dsl/types"))
+                .add_header(auth_key, auth_value)
+                .await
+                .text();
+            assert!(types.contains("landlord"));
+
+            let _ = fs::remove_file(format!("./data/templates/{id}.docx")).await;
+            let _ = fs::remove_file(format!("./data/templates/{id}.dsl")).await;
+        }
+    })
+    .await;
+}
@@ -0,0 +1,312 @@
+use std::path::Path;
+
+use axum::http::StatusCode;
+use cicero::app::App;
+use cicero::models::{categories, templates, users};
+use cicero::views::auth::LoginResponse;
+use cicero::views::export::ExportResponse;
+use cicero::views::user::WithRoleResponse;
+use insta::assert_debug_snapshot;
+use loco_rs::app::Hooks;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("user_request");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+#[tokio::test]
+#[serial]
+async fn can_change_name_and_password() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let response: WithRoleResponse = request
+                .put("/api/user/profile")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": "  Иван Петров " }))
+                .await
+                .json();
+            assert_eq!(response.user.name, "Иван Петров");
+
+            request
+                .put("/api/user/profile")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "name": " " }))
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            // another device is logged out by the change
+            let other_device: LoginResponse = request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({ "email": "test@loco.com", "password": "1234" }))
+                .await
+                .json();
+
+            let response = request
+                .post("/api/user/password")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "password": "wrong", "newPassword": "5678" }))
+                .await;
+            assert_debug_snapshot!(
+                "invalid_password",
+                (response.status_code(), response.text())
+            );
+
+            request
+                .post("/api/user/password")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "password": "1234", "newPassword": "5678" }))
+                .await
+                .assert_status_ok();
+
+            request
+                .get("/api/user/current")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await
+                .assert_status_ok();
+            let (other_key, other_value) = prepare_data::auth_header(&other_device.token);
+            request
+                .get("/api/user/current")
+                .add_header(other_key, other_value)
+                .await
+                .assert_status_unauthorized();
+
+            request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({ "email": "test@loco.com", "password": "5678" }))
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_change_email() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            let response = request
+                .post("/api/user/email")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "email": "user1@example.com", "password": "1234" }))
+                .await;
+            assert_debug_snapshot!("email_taken", (response.status_code(), response.text()));
+
+            request
+                .post("/api/user/email")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "email": "new@loco.com", "password": "1234" }))
+                .await
+                .assert_status_ok();
+
+            // the current email is used until the new one is verified
+            let pending = users::Model::find_by_email(&ctx.db, "test@loco.com")
+                .await
+                .unwrap();
+            assert_eq!(pending.pending_email.as_deref(), Some("new@loco.com"));
+
+            request
+                .post("/api/auth/verify")
+                .json(&serde_json::json!({
+                    "token": prepare_data::verification_token(&ctx, "test@loco.com").await,
+                }))
+                .await
+                .assert_status_ok();
+
+            let changed = users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+                .await
+                .unwrap();
+            assert_eq!(changed.email, "new@loco.com");
+            assert_eq!(changed.pending_email, None);
+            request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({ "email": "new@loco.com", "password": "1234" }))
+                .await
+                .assert_status_ok();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_data() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            templates::ActiveModel {
+                name: ActiveValue::set("Договор".to_string()),
+                description: ActiveValue::set(String::new()),
+                user_id: ActiveValue::set(user.user.id),
+                is_public: ActiveValue::set(false),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+            let export: ExportResponse = request
+                .get("/api/user/export")
+                .add_header(auth_key, auth_value)
+                .await
+                .json();
+            assert_eq!(export.account.email, "test@loco.com");
+            assert_eq!(export.sessions.len(), 1);
+            assert_eq!(export.templates.len(), 1);
+            assert_eq!(export.templates[0].template.name, "Договор");
+            assert_eq!(export.templates[0].dsl, None);
+            assert!(export.shared_with_me.is_empty());
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_delete_account() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+            let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+            // the choice of the templates is required
+            request
+                .delete("/api/user/account")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "password": "1234" }))
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            // the fixture user is not verified, so can't receive the templates
+            let response = request
+                .delete("/api/user/account")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({
+                    "password": "1234",
+                    "templates": "transfer",
+                    "transferTo": "user1@example.com",
+                }))
+                .await;
+            assert_debug_snapshot!(
+                "invalid_transfer",
+                (response.status_code(), response.text())
+            );
+
+            request
+                .delete("/api/user/account")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "password": "wrong", "templates": "delete" }))
+                .await
+                .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+            request
+                .delete("/api/user/account")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&serde_json::json!({ "password": "1234", "templates": "delete" }))
+                .await
+                .assert_status_ok();
+
+            assert!(users::Entity::find_by_id(user.user.id)
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .is_none());
+            request
+                .get("/api/user/current")
+                .add_header(auth_key, auth_value)
+                .await
+                .assert_status_unauthorized();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_transfer_templates_on_deletion() {
+    testing::request::<App, _, _>(|request, ctx| {
+        async move {
+            App::seed(&ctx.db, Path::new("src/fixtures/test"))
+                .await
+                .unwrap();
+            let user = prepare_data::init_user_login(&request, &ctx).await;
+
+            // the fixture user leaves the templates to the test user
+            let owner = users::Model::find_by_email(&ctx.db, "user1@example.com")
+                .await
+                .unwrap()
+                .into_active_model()
+                .reset_password(&ctx.db, "1234")
+                .await
+                .unwrap()
+                .into_active_model()
+                .verified(&ctx.db)
+                .await
+                .unwrap();
+            let login: LoginResponse = request
+                .post("/api/auth/login")
+                .json(&serde_json::json!({ "email": "user1@example.com", "password": "1234" }))
+                .await
+                .json();
+            let (owner_key, owner_value) = prepare_data::auth_header(&login.token);
+
+            let owned_templates = templates::Model::find_for_author(&ctx.db, owner.id)
+                .await
+                .unwrap();
+            let owned_categories = categories::Model::find_for_user(&ctx.db, owner.id)
+                .await
+                .unwrap();
+            assert!(!owned_templates.is_empty());
+
+            request
+                .delete("/api/user/account")
+                .add_header(owner_key, owner_value)
+                .json(&serde_json::json!({
+                    "password": "1234",
+                    "templates": "transfer",
+                    "transferTo": "test@loco.com",
+                }))
+                .await
+                .assert_status_ok();
+
+            let transferred = templates::Model::find_for_author(&ctx.db, user.user.id)
+                .await
+                .unwrap();
+            assert_eq!(transferred.len(), owned_templates.len());
+            let categories = categories::Model::find_for_user(&ctx.db, user.user.id)
+                .await
+                .unwrap();
+            assert_eq!(categories.len(), owned_categories.len());
+        }
+    })
+    .await;
+}
@@ -1 +1,2 @@
+pub mod recompile_templates;
 pub mod seed;
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use cicero::app::App;
+use cicero::models::templates;
+use cicero_dsl::compiler;
+use loco_rs::app::Hooks;
+use loco_rs::boot::run_task;
+use loco_rs::{task, testing};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+use serial_test::serial;
+use tokio::fs;
+
+#[tokio::test]
+#[serial]
+async fn reports_templates_that_dont_compile() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    App::seed(db, Path::new("src/fixtures/test")).await.unwrap();
+
+    fs::create_dir_all("./data/templates").await.unwrap();
+    let mut ids = Vec::new();
+    for (name, dsl) in [
+        ("Договор", "/// Арендатор\nlet tenant: String;"),
+        ("Акт", "/// Арендатор\nlet tenant: Strin;"),
+    ] {
+        let template = templates::ActiveModel {
+            name: ActiveValue::set(name.to_string()),
+            description: ActiveValue::set(String::new()),
+            user_id: ActiveValue::set(1),
+            is_public: ActiveValue::set(true),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        fs::write(format!("./data/templates/{}.dsl", template.id), dsl)
+            .await
+            .unwrap();
+        ids.push(template.id);
+    }
+
+    let result = run_task::<App>(
+        &boot.app_context,
+        Some(&"recompile_templates".to_string()),
+        &task::Vars::default(),
+    )
+    .await;
+    // the seeded templates have no files, so they fail too
+    let mut failed = templates::Entity::find()
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|template| template.id)
+        .filter(|&id| id != ids[0])
+        .collect::<Vec<_>>();
+    failed.sort_unstable();
+    assert!(failed.contains(&ids[1]));
+    let err = result.unwrap_err().to_string();
+    assert!(err.ends_with(&format!("{failed:?}")));
+
+    let valid = templates::Entity::find_by_id(ids[0])
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(valid.dsl_compiler_version, Some(compiler::VERSION));
+    assert!(valid.dsl_types.is_some());
+
+    let invalid = templates::Entity::find_by_id(ids[1])
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(invalid.dsl_types, None);
+
+    for id in ids {
+        let _ = fs::remove_file(format!("./data/templates/{id}.dsl")).await;
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use cicero::app::App;
+use cicero::controllers::merges::MergeParams;
+use cicero::models::merge_jobs::{self, MergeJobStatus};
+use cicero::models::templates;
+use cicero::spreadsheet::Table;
+use cicero::workers::mail_merge::{MailMergeWorker, MailMergeWorkerArgs};
+use loco_rs::app::Hooks;
+use loco_rs::bgworker::BackgroundWorker;
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+use tokio::fs;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const DSL: &str = "
+    /// Вид
+    enum Kind {
+        /// Физлицо
+        Individual,
+        /// Юрлицо
+        Company,
+    }
+
+    /// Вид арендатора
+    let kind: Kind;
+    /// Телефоны
+    let phones: [PhoneNumber];
+";
+
+const DOCUMENT: &str = r#"<w:document><w:body><w:p><w:r><w:t>{{#kind._discriminant == "Company"}}Юрлицо{{/}}{{^kind._discriminant == "Company"}}Физлицо{{/}}: {{#phones}}[{{.}}]{{/phones}}</w:t></w:r></w:p></w:body></w:document>"#;
+
+fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in entries {
+        writer
+            .start_file(*name, SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn read_entry(archive: &[u8], name: &str) -> Vec<u8> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut content = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    content
+}
+
+#[tokio::test]
+#[serial]
+async fn renders_sections_and_loops() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let ctx = boot.app_context;
+    App::seed(&ctx.db, Path::new("src/fixtures/test"))
+        .await
+        .unwrap();
+
+    let template = templates::ActiveModel {
+        name: ActiveValue::set("Договор аренды".to_string()),
+        description: ActiveValue::set(String::new()),
+        user_id: ActiveValue::set(1),
+        is_public: ActiveValue::set(true),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+
+    fs::create_dir_all("./data/templates").await.unwrap();
+    fs::write(format!("./data/templates/{}.dsl", template.id), DSL)
+        .await
+        .unwrap();
+    fs::write(
+        format!("./data/templates/{}.docx", template.id),
+        zip(&[("word/document.xml", DOCUMENT.as_bytes())]),
+    )
+    .await
+    .unwrap();
+
+    let table = Table {
+        headers: vec!["Вид".to_string(), "Телефоны".to_string()],
+        rows: vec![
+            vec!["Company".to_string(), "+7999; +7888".to_string()],
+            vec!["Individual".to_string(), "+7777".to_string()],
+        ],
+        row_numbers: vec![2, 3],
+    };
+    let params = MergeParams {
+        template_id: template.id,
+        mapping: [("Вид", "kind"), ("Телефоны", "phones")]
+            .into_iter()
+            .map(|(column, path)| (column.to_string(), path.to_string()))
+            .collect(),
+    };
+
+    let job = merge_jobs::Model::create(&ctx.db, &params, 1, &table)
+        .await
+        .unwrap();
+
+    MailMergeWorker::build(&ctx)
+        .perform(MailMergeWorkerArgs { job_id: job.id })
+        .await
+        .unwrap();
+
+    let job = merge_jobs::Model::find_by_id(&ctx.db, job.id)
+        .await
+        .unwrap();
+    assert_eq!(job.status, MergeJobStatus::Completed);
+    assert_eq!(job.failed_rows, 0);
+
+    let archive = job.find_result().await.unwrap();
+    for (name, text) in [
+        ("2.docx", "Юрлицо: [+7999][+7888]"),
+        ("3.docx", "Физлицо: [+7777]"),
+    ] {
+        let document = read_entry(&archive, name);
+        let xml = String::from_utf8(read_entry(&document, "word/document.xml")).unwrap();
+        assert!(xml.contains(text), "{xml}");
+    }
+
+    let _ = fs::remove_file(format!("./data/templates/{}.dsl", template.id)).await;
+    let _ = fs::remove_file(format!("./data/templates/{}.docx", template.id)).await;
+    let _ = fs::remove_file(job.result_path()).await;
+}
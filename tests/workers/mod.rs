@@ -1 +1 @@
-
+mod mail_merge;